@@ -0,0 +1,176 @@
+//! Buffering of messages for future heights and rounds.
+//!
+//! Votes and proposals for a round we have not reached yet are kept and
+//! replayed once we get there, so a validator that is slightly behind
+//! does not lose them. The engine verifies messages against the validator
+//! set for their height before buffering them, but validators can still
+//! send many; three bounds keep the buffer finite:
+//!
+//! - messages more than `max_heights_ahead` heights past the current
+//!   height are dropped on arrival;
+//! - so are messages more than `max_rounds_ahead` rounds past the current
+//!   round (at a later height, past round 0), so votes are only held for
+//!   a small window of rounds;
+//! - at most `max_messages` messages are held. When full, the messages
+//!   furthest ahead are dropped first, since they are the least likely to
+//!   be needed soon.
+//!
+//! Only the first message of each kind from each signer is kept per
+//! round, so repeats cannot crowd out other validators' votes. This relies
+//! on the signature having been checked: otherwise a forgery arriving
+//! first would shut out the signer's real message.
+//!
+//! Memory use is therefore bounded by `max_messages` times the largest
+//! accepted message (proposals are limited by `max_proposal_tx_bytes`).
+
+use crate::types::ConsensusMessage;
+use std::collections::BTreeMap;
+
+/// Messages waiting for their height and round, keyed by `(height, round)`.
+#[derive(Debug)]
+pub struct FutureBuffer {
+    messages: BTreeMap<(u64, u64), Vec<ConsensusMessage>>,
+    len: usize,
+    max_heights_ahead: u64,
+    max_rounds_ahead: u64,
+    max_messages: usize,
+}
+
+impl FutureBuffer {
+    /// Create an empty buffer with the given bounds.
+    pub fn new(max_heights_ahead: u64, max_rounds_ahead: u64, max_messages: usize) -> Self {
+        Self {
+            messages: BTreeMap::new(),
+            len: 0,
+            max_heights_ahead,
+            max_rounds_ahead,
+            max_messages,
+        }
+    }
+
+    /// Whether a message is close enough to `current_height` and
+    /// `current_round` to be buffered at all.
+    pub fn in_window(&self, current_height: u64, current_round: u64, message: &ConsensusMessage) -> bool {
+        if message.height() > current_height.saturating_add(self.max_heights_ahead) {
+            return false;
+        }
+        let base_round = if message.height() == current_height { current_round } else { 0 };
+        message.round() <= base_round.saturating_add(self.max_rounds_ahead)
+    }
+
+    /// Buffer a message while at `current_height` and `current_round`.
+    ///
+    /// Returns whether the message is held after the caps are enforced.
+    pub fn insert(&mut self, current_height: u64, current_round: u64, message: ConsensusMessage) -> bool {
+        if !self.in_window(current_height, current_round, &message) {
+            return false;
+        }
+
+        let key = (message.height(), message.round());
+        let held = self.messages.entry(key).or_default();
+        if message.signer().is_some()
+            && held.iter().any(|m| {
+                std::mem::discriminant(m) == std::mem::discriminant(&message)
+                    && m.signer() == message.signer()
+            })
+        {
+            return true; // Already holding this signer's message
+        }
+        held.push(message);
+        self.len += 1;
+
+        let mut kept = true;
+        while self.len > self.max_messages {
+            let Some(mut furthest) = self.messages.last_entry() else {
+                break;
+            };
+            if *furthest.key() == key {
+                kept = false; // The new message is the one dropped
+            }
+            furthest.get_mut().pop();
+            if furthest.get().is_empty() {
+                furthest.remove();
+            }
+            self.len -= 1;
+        }
+        kept
+    }
+
+    /// Take every message for `height` up to and including `round`.
+    ///
+    /// Messages for earlier heights are discarded.
+    pub fn take_ready(&mut self, height: u64, round: u64) -> Vec<ConsensusMessage> {
+        let later = self.messages.split_off(&(height, round.saturating_add(1)));
+        let ready: Vec<ConsensusMessage> = std::mem::replace(&mut self.messages, later)
+            .into_iter()
+            .filter(|((h, _), _)| *h == height)
+            .flat_map(|(_, messages)| messages)
+            .collect();
+        self.len = self.messages.values().map(Vec::len).sum();
+        ready
+    }
+
+    /// Messages held for rounds of `height` after `round`, latest round first.
+    pub fn later_rounds(&self, height: u64, round: u64) -> impl Iterator<Item = (u64, &[ConsensusMessage])> {
+        self.messages
+            .range((height, round.saturating_add(1))..=(height, u64::MAX))
+            .rev()
+            .map(|(&(_, round), messages)| (round, messages.as_slice()))
+    }
+
+    /// Number of buffered messages.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if nothing is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Prevote, Signature64, ValidatorId};
+
+    fn prevote(height: u64, round: u64) -> ConsensusMessage {
+        ConsensusMessage::Prevote(Prevote {
+            height,
+            round,
+            block_hash: None,
+            validator: ValidatorId([1u8; 32]),
+            signature: Signature64([0u8; 64]),
+        })
+    }
+
+    #[test]
+    fn furthest_ahead_dropped_first() {
+        let mut buffer = FutureBuffer::new(10, 4, 2);
+        assert!(buffer.insert(1, 0, prevote(3, 0)));
+        assert!(buffer.insert(1, 0, prevote(2, 0)));
+
+        // Full: the height-3 message makes way for one nearer the tip
+        assert!(buffer.insert(1, 0, prevote(2, 1)));
+        assert_eq!(buffer.len(), 2);
+
+        // A message further ahead than everything held is dropped itself
+        assert!(!buffer.insert(1, 0, prevote(5, 0)));
+        assert!(!buffer.insert(1, 0, prevote(12, 0)));
+
+        let ready = buffer.take_ready(2, 0);
+        assert_eq!(ready.len(), 1);
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn rounds_beyond_window_dropped() {
+        let mut buffer = FutureBuffer::new(10, 2, 100);
+        assert!(buffer.insert(1, 5, prevote(1, 7)));
+        assert!(!buffer.insert(1, 5, prevote(1, 8)));
+
+        // Later heights count rounds from zero
+        assert!(buffer.insert(1, 5, prevote(2, 2)));
+        assert!(!buffer.insert(1, 5, prevote(2, 3)));
+    }
+}
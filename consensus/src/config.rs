@@ -1,10 +1,16 @@
 //! Consensus engine configuration.
 
+use crate::error::{ConsensusError, Result};
+use crate::leader::LeaderSeed;
 use std::time::Duration;
 use serde::{Deserialize, Serialize};
 
 /// Configuration for the consensus engine.
+///
+/// Missing fields take their `Default` values, so a deployment only
+/// lists what it changes. Durations are in milliseconds.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ConsensusConfig {
     /// Timeout for proposal phase.
     #[serde(with = "humantime_serde")]
@@ -22,8 +28,70 @@ pub struct ConsensusConfig {
     #[serde(with = "humantime_serde")]
     pub timeout_delta: Duration,
 
+    /// Time the leader may spend executing a block via MARS before proposing.
+    #[serde(with = "humantime_serde")]
+    pub block_execution_budget: Duration,
+
     /// Maximum rounds before giving up on a height.
     pub max_rounds: u64,
+
+    /// Maximum size of a proposal's serialized transactions, in bytes.
+    pub max_proposal_tx_bytes: usize,
+
+    /// Broadcast the finality certificate on finalization, so peers that
+    /// missed some commits can finalize on receipt instead of waiting for
+    /// re-gossip.
+    #[serde(default)]
+    pub gossip_finality_certificate: bool,
+
+    /// Re-gossip each round's proposal once after accepting it, so peers
+    /// that missed the leader's broadcast still receive it. Repeats of a
+    /// proposal already held are not re-gossiped, and the network's seen
+    /// cache drops copies peers already have.
+    #[serde(default)]
+    pub rebroadcast_proposals: bool,
+
+    /// Follow consensus without voting or proposing (explorers, RPC nodes).
+    #[serde(default)]
+    pub observer: bool,
+
+    /// Chain identifier, mixed into leader seeds.
+    #[serde(default = "default_chain_id")]
+    pub chain_id: String,
+
+    /// Fixed base seed used instead of the chain id when deriving leader
+    /// seeds, for reproducible schedules in tests.
+    #[serde(default)]
+    pub leader_seed: Option<[u8; 32]>,
+
+    /// How many heights past the current one messages are buffered for.
+    #[serde(default = "default_max_future_heights")]
+    pub max_future_heights: u64,
+
+    /// How many rounds past the current one messages are buffered for,
+    /// bounding the vote history held in memory.
+    #[serde(default = "default_max_future_rounds")]
+    pub max_future_rounds: u64,
+
+    /// Maximum number of buffered future messages (see `FutureBuffer`).
+    #[serde(default = "default_max_future_messages")]
+    pub max_future_messages: usize,
+}
+
+fn default_chain_id() -> String {
+    "unykorn-devnet".to_string()
+}
+
+fn default_max_future_heights() -> u64 {
+    4
+}
+
+fn default_max_future_rounds() -> u64 {
+    4
+}
+
+fn default_max_future_messages() -> usize {
+    1024
 }
 
 impl Default for ConsensusConfig {
@@ -33,15 +101,89 @@ impl Default for ConsensusConfig {
             prevote_timeout: Duration::from_secs(2),
             commit_timeout: Duration::from_secs(2),
             timeout_delta: Duration::from_millis(500),
+            block_execution_budget: Duration::from_secs(1),
             max_rounds: 10,
+            max_proposal_tx_bytes: 4 * 1024 * 1024,
+            gossip_finality_certificate: false,
+            rebroadcast_proposals: false,
+            observer: false,
+            chain_id: default_chain_id(),
+            leader_seed: None,
+            max_future_heights: default_max_future_heights(),
+            max_future_rounds: default_max_future_rounds(),
+            max_future_messages: default_max_future_messages(),
         }
     }
 }
 
+/// The timeouts in effect for one round, after backoff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundTimeouts {
+    /// Round number.
+    pub round: u64,
+
+    /// Propose timeout (including the block execution budget).
+    #[serde(with = "humantime_serde")]
+    pub propose: Duration,
+
+    /// Prevote timeout.
+    #[serde(with = "humantime_serde")]
+    pub prevote: Duration,
+
+    /// Commit timeout.
+    #[serde(with = "humantime_serde")]
+    pub commit: Duration,
+}
+
 impl ConsensusConfig {
+    /// Check the configuration is usable: every phase needs a timeout.
+    pub fn validate(&self) -> Result<()> {
+        for (name, timeout) in [
+            ("propose_timeout", self.propose_timeout),
+            ("prevote_timeout", self.prevote_timeout),
+            ("commit_timeout", self.commit_timeout),
+        ] {
+            if timeout.is_zero() {
+                return Err(ConsensusError::InvalidConfig {
+                    reason: format!("{} must be non-zero", name),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Effective timeouts for rounds `0..=max_round`, for display.
+    ///
+    /// `max_round` is capped at the last round before giving up on a
+    /// height (`max_rounds - 1`).
+    pub fn effective_schedule(&self, max_round: u64) -> Vec<RoundTimeouts> {
+        let last = max_round.min(self.max_rounds.saturating_sub(1));
+        (0..=last)
+            .map(|round| RoundTimeouts {
+                round,
+                propose: self.propose_timeout_for_round(round),
+                prevote: self.prevote_timeout_for_round(round),
+                commit: self.commit_timeout_for_round(round),
+            })
+            .collect()
+    }
+
     /// Calculate propose timeout for a specific round (exponential backoff).
+    ///
+    /// Includes the block execution budget, so a leader executing a block
+    /// does not eat into the time its proposal needs to propagate.
     pub fn propose_timeout_for_round(&self, round: u64) -> Duration {
-        self.propose_timeout + self.timeout_delta * round as u32
+        self.block_execution_budget + self.propose_timeout + self.timeout_delta * round as u32
+    }
+
+    /// Seed for weighted leader selection at `(height, round)`.
+    ///
+    /// Derived from `leader_seed` when set, otherwise from `chain_id`.
+    pub fn leader_seed_for(&self, height: u64, round: u64) -> LeaderSeed {
+        match &self.leader_seed {
+            Some(base) => LeaderSeed::derive_from(base, height, round),
+            None => LeaderSeed::derive(&self.chain_id, height, round),
+        }
     }
 
     /// Calculate prevote timeout for a specific round.
@@ -87,6 +229,22 @@ mod tests {
         assert_eq!(config.max_rounds, 10);
     }
 
+    #[test]
+    fn partial_config_takes_defaults() {
+        let config: ConsensusConfig =
+            serde_json::from_str(r#"{"propose_timeout": 1500, "max_rounds": 4}"#).unwrap();
+        assert_eq!(config.propose_timeout, Duration::from_millis(1500));
+        assert_eq!(config.max_rounds, 4);
+        assert_eq!(config.prevote_timeout, ConsensusConfig::default().prevote_timeout);
+        assert!(config.validate().is_ok());
+
+        let zero = ConsensusConfig {
+            commit_timeout: Duration::ZERO,
+            ..ConsensusConfig::default()
+        };
+        assert!(matches!(zero.validate(), Err(ConsensusError::InvalidConfig { .. })));
+    }
+
     #[test]
     fn exponential_backoff() {
         let config = ConsensusConfig::default();
@@ -99,4 +257,61 @@ mod tests {
         assert!(t2 > t1);
         assert_eq!(t1 - t0, config.timeout_delta);
     }
+
+    #[test]
+    fn effective_schedule_matches_helpers() {
+        let config = ConsensusConfig::default();
+        let schedule = config.effective_schedule(100);
+
+        // Capped at max_rounds
+        assert_eq!(schedule.len(), config.max_rounds as usize);
+
+        for (i, entry) in schedule.iter().enumerate() {
+            let round = i as u64;
+            assert_eq!(entry.round, round);
+            assert_eq!(entry.propose, config.propose_timeout_for_round(round));
+            assert_eq!(entry.prevote, config.prevote_timeout_for_round(round));
+            assert_eq!(entry.commit, config.commit_timeout_for_round(round));
+        }
+
+        for pair in schedule.windows(2) {
+            assert!(pair[1].propose > pair[0].propose);
+            assert!(pair[1].prevote > pair[0].prevote);
+            assert!(pair[1].commit > pair[0].commit);
+        }
+
+        let json = serde_json::to_string(&config.effective_schedule(0)).unwrap();
+        assert_eq!(json, r#"[{"round":0,"propose":4000,"prevote":2000,"commit":2000}]"#);
+    }
+
+    #[test]
+    fn leader_seed_override() {
+        let config = ConsensusConfig::default();
+        assert_eq!(
+            config.leader_seed_for(3, 1),
+            LeaderSeed::derive("unykorn-devnet", 3, 1)
+        );
+
+        let seeded = ConsensusConfig {
+            leader_seed: Some([9u8; 32]),
+            ..ConsensusConfig::default()
+        };
+        assert_eq!(seeded.leader_seed_for(3, 1), LeaderSeed::derive_from(&[9u8; 32], 3, 1));
+        assert_ne!(seeded.leader_seed_for(3, 1), config.leader_seed_for(3, 1));
+    }
+
+    #[test]
+    fn propose_timeout_includes_execution_budget() {
+        let config = ConsensusConfig {
+            block_execution_budget: Duration::from_millis(750),
+            ..ConsensusConfig::default()
+        };
+
+        assert_eq!(
+            config.propose_timeout_for_round(0),
+            config.propose_timeout + Duration::from_millis(750)
+        );
+        // Only the propose phase pays for execution
+        assert_eq!(config.prevote_timeout_for_round(0), config.prevote_timeout);
+    }
 }
@@ -10,12 +10,20 @@
 //!
 //! Consensus decides WHICH block becomes canonical.
 
+use crate::buffer::FutureBuffer;
+use crate::clock::{Clock, SystemClock};
 use crate::config::ConsensusConfig;
 use crate::error::{ConsensusError, Result};
+use crate::observer::{ConsensusObserver, NoopObserver};
 use crate::types::*;
-
-use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
-use tokio::sync::{mpsc, RwLock};
+use crate::validity::{AcceptAll, ProposalValidator};
+use crate::verify::{verify_certificate, verify_commit, verify_message, verify_prevote, verify_proposal};
+
+use ed25519_dalek::{Signer, SigningKey};
+use std::sync::Arc;
+use tar::{ConsensusStore, RoundStateSource};
+use tokio::sync::{mpsc, Notify, RwLock, RwLockWriteGuard};
+use tokio::time::Instant;
 use tracing::{debug, info, warn};
 
 /// Events emitted by the consensus engine.
@@ -27,12 +35,20 @@ pub enum ConsensusEvent {
     BroadcastPrevote(Prevote),
     /// Need to broadcast a commit.
     BroadcastCommit(Commit),
+    /// Need to broadcast a finality certificate.
+    BroadcastCertificate(FinalityCertificate),
     /// Block has been finalized.
     BlockFinalized {
         height: u64,
         block_hash: BlockHash,
         certificate: FinalityCertificate,
     },
+    /// A validator signed conflicting votes; persist as evidence.
+    Equivocation {
+        validator: ValidatorId,
+        first: Box<ConsensusMessage>,
+        second: Box<ConsensusMessage>,
+    },
     /// Round timed out, moving to next round.
     RoundTimeout { height: u64, round: u64 },
     /// Request to execute a block (calls MARS).
@@ -54,24 +70,58 @@ pub enum ProcessResult {
     NeedMoreVotes,
     /// Message was stale/duplicate.
     Ignored,
+    /// Message is for a later height or round and was kept for replay.
+    Buffered,
 }
 
 /// The core consensus engine.
 pub struct ConsensusEngine {
     /// Configuration.
     config: ConsensusConfig,
-    /// Validator set.
-    validator_set: ValidatorSet,
+    /// Validator set pinned for the current height (see `start_height`).
+    validator_set: RwLock<Arc<ValidatorSet>>,
+    /// Validator set installed for a future height, with that height.
+    pending_validator_set: RwLock<Option<(u64, ValidatorSet)>>,
     /// Our validator keypair.
     signing_key: SigningKey,
     /// Our validator ID.
     our_id: ValidatorId,
     /// Current round state.
     state: RwLock<RoundState>,
+    /// When the current round started (leaders begin execution here).
+    round_started: RwLock<Instant>,
+    /// When the current height started, for finalization latency.
+    height_started: RwLock<Instant>,
+    /// When the current phase started; phase timeouts run from here.
+    phase_started: RwLock<Instant>,
+    /// Wakes the timeout driver when the phase changes.
+    phase_changed: Notify,
+    /// Time source for deadlines and latency.
+    clock: Arc<dyn Clock>,
     /// Finalized heights.
     finalized: RwLock<std::collections::HashMap<u64, FinalityCertificate>>,
+    /// Next finalized height to hand to MARS for application.
+    next_to_apply: RwLock<u64>,
+    /// Highest finalized height; messages at or below it are dropped unread.
+    height_floor: RwLock<u64>,
+    /// Hash every proposal at the current height must extend, once known
+    /// (see `bootstrap`).
+    parent_hash: RwLock<Option<BlockHash>>,
+    /// Messages for later heights and rounds, replayed when we get there.
+    future: RwLock<FutureBuffer>,
     /// Event sender.
     event_tx: mpsc::UnboundedSender<ConsensusEvent>,
+    /// Notified of proposals, votes, timeouts and finality.
+    observer: Arc<dyn ConsensusObserver>,
+    /// Checks proposal contents before we prevote for them.
+    proposal_validator: Arc<dyn ProposalValidator>,
+    /// Where the round state is saved after each change, if anywhere.
+    store: Option<Arc<ConsensusStore>>,
+    /// Set by `rejoin` until the next height starts: later rounds backed
+    /// by enough weight are joined without waiting out timeouts.
+    catching_up: RwLock<bool>,
+    /// Height whose saved round state was lost; we sign nothing there.
+    abstain_height: Option<u64>,
 }
 
 impl ConsensusEngine {
@@ -83,16 +133,125 @@ impl ConsensusEngine {
         event_tx: mpsc::UnboundedSender<ConsensusEvent>,
     ) -> Self {
         let our_id = ValidatorId::from_verifying_key(&signing_key.verifying_key());
+        let future = FutureBuffer::new(
+            config.max_future_heights,
+            config.max_future_rounds,
+            config.max_future_messages,
+        );
 
         Self {
             config,
-            validator_set,
+            validator_set: RwLock::new(Arc::new(validator_set)),
+            pending_validator_set: RwLock::new(None),
             signing_key,
             our_id,
             state: RwLock::new(RoundState::new(1, 0)),
+            round_started: RwLock::new(Instant::now()),
+            height_started: RwLock::new(Instant::now()),
+            phase_started: RwLock::new(Instant::now()),
+            phase_changed: Notify::new(),
+            clock: Arc::new(SystemClock),
             finalized: RwLock::new(std::collections::HashMap::new()),
+            next_to_apply: RwLock::new(1),
+            height_floor: RwLock::new(0),
+            parent_hash: RwLock::new(None),
+            future: RwLock::new(future),
             event_tx,
+            observer: Arc::new(NoopObserver),
+            proposal_validator: Arc::new(AcceptAll),
+            store: None,
+            catching_up: RwLock::new(false),
+            abstain_height: None,
+        }
+    }
+
+    /// Create an engine that resumes the round state saved in `store`.
+    ///
+    /// Votes we cast before a crash stay cast: a recovered `prevoted` or
+    /// `committed` flag is never cleared for that round, so we cannot sign
+    /// a second, conflicting vote. Without a saved state the engine starts
+    /// above the latest finalized height. If the saved state was corrupt,
+    /// what we signed at that height and our lock are lost, so the engine
+    /// follows that height as an observer and only votes again from the
+    /// next one. A validator set scheduled for a later height is scheduled
+    /// again. Every later change to the round state is saved back to
+    /// `store`.
+    pub fn recover(
+        config: ConsensusConfig,
+        validator_set: ValidatorSet,
+        signing_key: SigningKey,
+        event_tx: mpsc::UnboundedSender<ConsensusEvent>,
+        store: Arc<ConsensusStore>,
+    ) -> Result<Self> {
+        let storage_err = |e: tar::StorageError| ConsensusError::Storage { reason: e.to_string() };
+        let (state, source) = store
+            .recover_round_state(|height| RoundState::new(height, 0))
+            .map_err(storage_err)?;
+        let finalized = store.latest_finalized_height().map_err(storage_err)?;
+        let pending: Option<(u64, ValidatorSet)> = store.load_validator_set_after(state.height).map_err(storage_err)?;
+
+        match &source {
+            RoundStateSource::Saved => info!(
+                height = state.height,
+                round = state.round,
+                prevoted = state.prevoted,
+                committed = state.committed,
+                "Recovered round state"
+            ),
+            RoundStateSource::Fresh => {}
+            RoundStateSource::Corrupted { reason } => warn!(
+                height = state.height,
+                reason = %reason,
+                "Saved round state corrupt, sitting out the height"
+            ),
+        }
+
+        let mut engine = Self::new(config, validator_set, signing_key, event_tx);
+        if matches!(source, RoundStateSource::Corrupted { .. }) {
+            engine.abstain_height = Some(state.height);
         }
+        if let Some((effective, validator_set)) = pending {
+            info!(effective_height = effective, "Recovered pending validator set");
+            *engine.pending_validator_set.get_mut() = Some((effective, validator_set));
+        }
+        *engine.state.get_mut() = state;
+        *engine.height_floor.get_mut() = finalized.unwrap_or(0);
+        engine.store = Some(store);
+        Ok(engine)
+    }
+
+    /// Save the round state, if the engine has a store.
+    ///
+    /// Called before any vote leaves the engine, so a restart never
+    /// forgets a vote others may have seen.
+    fn persist(&self, state: &RoundState) -> Result<()> {
+        match &self.store {
+            Some(store) => store
+                .save_round_state(state)
+                .map_err(|e| ConsensusError::Storage { reason: e.to_string() }),
+            None => Ok(()),
+        }
+    }
+
+    /// Set the observer notified of consensus progress.
+    pub fn set_observer(&mut self, observer: Arc<dyn ConsensusObserver>) {
+        self.observer = observer;
+    }
+
+    /// Set the time source (the runtime's clock by default).
+    ///
+    /// The current round and height are treated as starting now.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let now = clock.now();
+        *self.round_started.get_mut() = now;
+        *self.height_started.get_mut() = now;
+        *self.phase_started.get_mut() = now;
+        self.clock = clock;
+    }
+
+    /// Set the check applied to proposal contents (accepts all by default).
+    pub fn set_proposal_validator(&mut self, validator: Arc<dyn ProposalValidator>) {
+        self.proposal_validator = validator;
     }
 
     /// Get our validator ID.
@@ -100,10 +259,129 @@ impl ConsensusEngine {
         &self.our_id
     }
 
+    /// Get the engine configuration.
+    pub fn config(&self) -> &ConsensusConfig {
+        &self.config
+    }
+
+    /// Snapshot of the active validator set.
+    pub async fn validator_set(&self) -> Arc<ValidatorSet> {
+        self.validator_set.read().await.clone()
+    }
+
+    /// Install a new validator set taking effect at `effective_height`.
+    ///
+    /// `proof` must be a finality certificate signed by a quorum of the
+    /// current set, for a height below `effective_height`, certifying
+    /// `validator_set.change_hash(effective_height)`: the prior epoch
+    /// authorizing exactly this set from exactly that height. Otherwise as
+    /// `apply_validator_set`.
+    pub async fn install_validator_set(
+        &self,
+        validator_set: ValidatorSet,
+        effective_height: u64,
+        proof: &FinalityCertificate,
+    ) -> Result<()> {
+        let reject = |reason: &str| ConsensusError::InvalidValidatorSetChange {
+            reason: reason.to_string(),
+        };
+        if proof.height >= effective_height {
+            return Err(reject("proof is not from before the effective height"));
+        }
+        if proof.block_hash != validator_set.change_hash(effective_height) {
+            return Err(reject("proof does not certify this validator set"));
+        }
+        verify_certificate(proof, &*self.validator_set().await)?;
+
+        self.apply_validator_set(validator_set, effective_height).await
+    }
+
+    /// Schedule the validator set of the epoch starting at `effective_height`.
+    ///
+    /// For owners that derive the set from finalized blocks themselves, so
+    /// no proof is needed. The swap happens atomically when `start_height`
+    /// reaches `effective_height`, never mid-height, so rounds of earlier
+    /// heights keep their leaders and quorum. A later call replaces a set
+    /// still pending. With a store, the set is saved for its height before
+    /// it is scheduled (see `ConsensusStore::load_validator_set_at`), and a
+    /// replaced set is deleted, so `recover` schedules the same set again.
+    pub async fn apply_validator_set(
+        &self,
+        validator_set: ValidatorSet,
+        effective_height: u64,
+    ) -> Result<()> {
+        let reject = |reason: &str| ConsensusError::InvalidValidatorSetChange {
+            reason: reason.to_string(),
+        };
+
+        if validator_set.is_empty() {
+            return Err(reject("validator set is empty"));
+        }
+        if effective_height <= self.current_height().await {
+            return Err(reject("effective height is not in the future"));
+        }
+
+        let mut pending = self.pending_validator_set.write().await;
+        if let Some(store) = &self.store {
+            let storage_err = |e: tar::StorageError| ConsensusError::Storage { reason: e.to_string() };
+            store
+                .save_validator_set_at(effective_height, &validator_set)
+                .map_err(storage_err)?;
+            if let Some((replaced, _)) = pending.as_ref().filter(|(h, _)| *h != effective_height) {
+                store.remove_validator_set_at(*replaced).map_err(storage_err)?;
+            }
+        }
+
+        info!(
+            effective_height,
+            validators = validator_set.len(),
+            "Installed validator set"
+        );
+        *pending = Some((effective_height, validator_set));
+
+        Ok(())
+    }
+
+    /// Swap in an installed validator set once its height is reached.
+    ///
+    /// With a store, the new set also becomes the saved current set.
+    async fn activate_pending_validator_set(&self, height: u64) -> Result<()> {
+        let mut pending = self.pending_validator_set.write().await;
+        let Some((effective, validator_set)) = pending.as_ref() else {
+            return Ok(());
+        };
+        if *effective > height {
+            return Ok(());
+        }
+
+        info!(height, validators = validator_set.len(), "Activating validator set");
+        if let Some(store) = &self.store {
+            store
+                .save_validator_set(validator_set)
+                .map_err(|e| ConsensusError::Storage { reason: e.to_string() })?;
+        }
+        if let Some((_, validator_set)) = pending.take() {
+            *self.validator_set.write().await = Arc::new(validator_set);
+        }
+        Ok(())
+    }
+
+    /// Whether this engine only follows consensus, never voting.
+    pub fn is_observer(&self) -> bool {
+        self.config.observer
+    }
+
+    /// Whether we sign nothing at `height`: as an observer, or at a height
+    /// whose round state was lost (see `recover`).
+    fn sits_out(&self, height: u64) -> bool {
+        self.is_observer() || self.abstain_height == Some(height)
+    }
+
     /// Check if we are the leader for the current round.
     pub async fn is_leader(&self) -> bool {
         let state = self.state.read().await;
-        let leader = self.validator_set.leader_for_round(state.round);
+        let validators = self.validator_set().await;
+        let leader = validators.leader_for_round(state.round);
         leader.id == self.our_id
     }
 
@@ -117,28 +395,263 @@ impl ConsensusEngine {
         self.state.read().await.round
     }
 
+    /// Get the proposal being voted on in the current round, if received.
+    pub async fn current_proposal(&self) -> Option<Proposal> {
+        self.state.read().await.proposal.clone()
+    }
+
+    /// Deadline for receiving a proposal in the current round.
+    ///
+    /// Measured from the start of the round and including the block
+    /// execution budget (see `ConsensusConfig::propose_timeout_for_round`).
+    pub async fn propose_deadline(&self) -> Instant {
+        let round = self.state.read().await.round;
+        *self.round_started.read().await + self.config.propose_timeout_for_round(round)
+    }
+
+    /// Deadline for the current round to decide before it times out.
+    ///
+    /// Measured from the start of the round, allowing each phase its
+    /// backed-off timeout.
+    pub async fn round_deadline(&self) -> Instant {
+        let round = self.state.read().await.round;
+        *self.round_started.read().await
+            + self.config.propose_timeout_for_round(round)
+            + self.config.prevote_timeout_for_round(round)
+            + self.config.commit_timeout_for_round(round)
+    }
+
+    /// Deadline for the current phase, or `None` once the height decided.
+    ///
+    /// Measured from when the phase began, using the phase's backed-off
+    /// timeout for the current round, so a round that keeps making
+    /// progress does not time out.
+    pub async fn phase_deadline(&self) -> Option<Instant> {
+        let state = self.state.read().await;
+        let timeout = match state.phase {
+            Phase::Propose => self.config.propose_timeout_for_round(state.round),
+            Phase::Prevote => self.config.prevote_timeout_for_round(state.round),
+            Phase::Commit => self.config.commit_timeout_for_round(state.round),
+            Phase::Completed => return None,
+        };
+        Some(*self.phase_started.read().await + timeout)
+    }
+
+    /// Act on the current phase deadline if it has passed on our clock.
+    ///
+    /// A propose phase that times out ends in a nil prevote (see
+    /// `on_propose_timeout`); any later phase times out the round. For
+    /// owners that poll instead of running `spawn_timeout_driver`.
+    /// Returns whether a deadline had passed.
+    pub async fn check_timeout(&self) -> Result<bool> {
+        match self.phase_deadline().await {
+            Some(deadline) if self.clock.now() >= deadline => {
+                if self.state.read().await.phase == Phase::Propose {
+                    self.on_propose_timeout().await?;
+                } else {
+                    self.on_timeout().await?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Run round timeouts on a background task.
+    ///
+    /// A timer is armed for the current phase deadline and re-armed
+    /// whenever the phase changes; when it elapses without progress the
+    /// round times out. The task runs until the event receiver is
+    /// dropped or the returned handle is aborted.
+    pub fn spawn_timeout_driver(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            while !self.event_tx.is_closed() {
+                let changed = self.phase_changed.notified();
+                let Some(deadline) = self.phase_deadline().await else {
+                    changed.await; // Wait for the next height
+                    continue;
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => {
+                        // The phase may have moved on while we slept
+                        if let Err(e) = self.check_timeout().await {
+                            warn!(error = %e, "Round timeout failed");
+                        }
+                    }
+                    _ = changed => {}
+                }
+            }
+        })
+    }
+
+    /// Move to `phase`, restarting the phase timer.
+    async fn enter_phase(&self, state: &mut RoundState, phase: Phase) {
+        state.phase = phase;
+        *self.phase_started.write().await = self.clock.now();
+        self.phase_changed.notify_one();
+    }
+
+    /// Start the chain from genesis.
+    ///
+    /// Height 1 must extend `genesis_hash`: the leader is asked to execute
+    /// on top of it, and proposals with any other `prev_hash` are voted
+    /// nil. From then on each finalized block becomes the parent the next
+    /// height must extend.
+    pub async fn bootstrap(&self, genesis_hash: BlockHash) -> Result<()> {
+        self.resume_from(0, genesis_hash).await
+    }
+
+    /// Continue the chain above an already applied tip.
+    ///
+    /// Like `bootstrap`, but for a node restarting with blocks up to `tip`:
+    /// consensus starts at `tip + 1`, which must extend `tip_hash`, and
+    /// messages for heights up to the tip are rejected. A round state
+    /// recovered for `tip + 1` (see `recover`) is continued, not restarted.
+    pub async fn resume_from(&self, tip: u64, tip_hash: BlockHash) -> Result<()> {
+        *self.parent_hash.write().await = Some(tip_hash);
+        self.set_height_floor(tip).await;
+        self.set_next_to_apply(tip + 1).await;
+
+        let mut state = self.state.write().await;
+        if state.height != tip + 1 || !state.has_progress() {
+            drop(state);
+            return self.start_height(tip + 1).await;
+        }
+
+        info!(height = state.height, round = state.round, "Continuing recovered round");
+        let now = self.clock.now();
+        *self.round_started.write().await = now;
+        *self.height_started.write().await = now;
+        let phase = state.phase;
+        self.enter_phase(&mut state, phase).await;
+        drop(state);
+
+        self.replay_buffered().await;
+        Ok(())
+    }
+
+    /// Return to consensus after being offline, above an applied tip.
+    ///
+    /// Resumes like `resume_from`, so votes cast before going offline are
+    /// kept and never re-cast differently. Until the next height starts,
+    /// the engine then follows the live round: as soon as validators with
+    /// more than a third of the weight are voting in a later round of the
+    /// current height, it moves there instead of timing out round by round.
+    pub async fn rejoin(&self, tip: u64, tip_hash: BlockHash) -> Result<()> {
+        self.resume_from(tip, tip_hash).await?;
+        *self.catching_up.write().await = true;
+        self.catch_up_round().await?;
+        Ok(())
+    }
+
+    /// Whether `rejoin` is still following peers into later rounds.
+    pub async fn is_catching_up(&self) -> bool {
+        *self.catching_up.read().await
+    }
+
+    /// Move to the latest buffered round of this height backed by more
+    /// than a third of the weight, if later than ours.
+    ///
+    /// At least one honest validator is in such a round, so the rounds
+    /// before it can no longer gather a quorum without us. Signatures are
+    /// checked before a buffered message counts towards the weight. Locks
+    /// carry over as they would through timeouts. Returns whether the
+    /// round changed.
+    async fn catch_up_round(&self) -> Result<bool> {
+        let mut state = self.state.write().await;
+        let validators = self.validator_set().await;
+        let target = self
+            .future
+            .read()
+            .await
+            .later_rounds(state.height, state.round)
+            .find(|(_, messages)| {
+                let signers: std::collections::HashSet<&ValidatorId> = messages
+                    .iter()
+                    .filter(|message| match message {
+                        ConsensusMessage::Proposal(p) => verify_proposal(p, &validators).is_ok(),
+                        ConsensusMessage::Prevote(p) => verify_prevote(p, &validators).is_ok(),
+                        _ => false,
+                    })
+                    .filter_map(ConsensusMessage::signer)
+                    .collect();
+                let weight: u64 = signers
+                    .into_iter()
+                    .filter_map(|id| validators.get(id))
+                    .map(|v| v.weight)
+                    .sum();
+                weight * 3 > validators.total_weight()
+            })
+            .map(|(round, _)| round);
+        let Some(target) = target else {
+            return Ok(false);
+        };
+
+        info!(
+            height = state.height,
+            from = state.round,
+            to = target,
+            "Catching up to live round"
+        );
+        while state.round < target {
+            *state = state.next_round();
+        }
+        *self.round_started.write().await = self.clock.now();
+        self.enter_phase(&mut state, Phase::Propose).await;
+        self.persist(&state)?;
+        drop(state);
+
+        self.replay_buffered().await;
+        Ok(true)
+    }
+
+    /// Hash proposals at the current height must extend, if tracked.
+    pub async fn parent_hash(&self) -> Option<BlockHash> {
+        *self.parent_hash.read().await
+    }
+
     /// Start a new height (called after finalization or genesis).
+    ///
+    /// The validator set is pinned here: a pending set whose height has
+    /// been reached is swapped in while the round state is locked, and no
+    /// other path changes the set, so every round of the height agrees on
+    /// leaders and quorum.
     pub async fn start_height(&self, height: u64) -> Result<()> {
         let mut state = self.state.write().await;
+        *self.catching_up.write().await = false;
+        self.activate_pending_validator_set(height).await?;
+        let validators = self.validator_set().await;
         *state = RoundState::new(height, 0);
+        let now = self.clock.now();
+        *self.round_started.write().await = now;
+        *self.height_started.write().await = now;
+        self.enter_phase(&mut state, Phase::Propose).await;
+        self.persist(&state)?;
 
         info!(height, "Starting consensus for new height");
 
         // If we're the leader, we need to propose
-        if self.validator_set.leader_for_round(0).id == self.our_id {
+        if !self.sits_out(height) && validators.leader_for_round(0).id == self.our_id {
             info!(height, "We are the leader for round 0");
             // Emit event to request block execution from MARS
+            // Without a tracked parent the caller must provide prev_hash
+            let prev_hash = self.parent_hash().await.unwrap_or_default();
             let _ = self.event_tx.send(ConsensusEvent::ExecuteBlock {
                 height,
-                prev_hash: [0u8; 32], // Caller must provide actual prev_hash
+                prev_hash,
                 transactions: Vec::new(),
             });
         }
+        drop(state);
 
+        self.replay_buffered().await;
         Ok(())
     }
 
     /// Create and broadcast a proposal (called by leader after MARS execution).
+    ///
+    /// The proposal is also accepted locally and prevoted on, since our own
+    /// gossip is never processed when it echoes back.
     pub async fn propose(
         &self,
         prev_hash: BlockHash,
@@ -146,10 +659,74 @@ impl ConsensusEngine {
         state_root: StateRoot,
         transactions: Vec<u8>,
     ) -> Result<()> {
+        let mut state = self.state.write().await;
+        let proposal = self
+            .sign_proposal(&state, prev_hash, block_hash, state_root, transactions)
+            .await?;
+
+        info!(
+            height = state.height,
+            round = state.round,
+            block_hash = hex::encode(&block_hash[..8]),
+            "Broadcasting proposal"
+        );
+
+        // Accept our own proposal
+        state.proposal = Some(proposal.clone());
+        self.enter_phase(&mut state, Phase::Prevote).await;
+        drop(state);
+
+        // Broadcast
+        let _ = self
+            .event_tx
+            .send(ConsensusEvent::BroadcastProposal(proposal));
+
+        self.prevote(Some(block_hash)).await?;
+
+        Ok(())
+    }
+
+    /// Create and sign a proposal for the current round without
+    /// broadcasting or accepting it.
+    ///
+    /// Performs the same leadership checks as `propose`, for tests and
+    /// transports that deliver proposals themselves.
+    pub async fn build_proposal(
+        &self,
+        prev_hash: BlockHash,
+        block_hash: BlockHash,
+        state_root: StateRoot,
+        transactions: Vec<u8>,
+    ) -> Result<Proposal> {
         let state = self.state.read().await;
+        self.sign_proposal(&state, prev_hash, block_hash, state_root, transactions)
+            .await
+    }
+
+    /// Check we may lead the current round, then create and sign a proposal.
+    async fn sign_proposal(
+        &self,
+        state: &RoundState,
+        prev_hash: BlockHash,
+        block_hash: BlockHash,
+        state_root: StateRoot,
+        transactions: Vec<u8>,
+    ) -> Result<Proposal> {
+        if self.sits_out(state.height) {
+            return Err(ConsensusError::ObserverMode);
+        }
+
+        let validators = self.validator_set().await;
+
+        // A non-validator can never lead; say so rather than blame the leader
+        if !validators.contains(&self.our_id) {
+            return Err(ConsensusError::NotAValidator {
+                validator: self.our_id.to_hex(),
+            });
+        }
 
         // Verify we're the leader
-        let leader = self.validator_set.leader_for_round(state.round);
+        let leader = validators.leader_for_round(state.round);
         if leader.id != self.our_id {
             return Err(ConsensusError::WrongLeader {
                 expected: leader.id.to_hex(),
@@ -157,6 +734,30 @@ impl ConsensusEngine {
             });
         }
 
+        if let Some(parent) = self.parent_hash().await {
+            if prev_hash != parent {
+                return Err(ConsensusError::InvalidBlock {
+                    reason: format!("proposal must extend {}", hex::encode(&parent[..8])),
+                });
+            }
+        }
+
+        // Execution should fit within its budget, or the proposal risks
+        // arriving after peers have timed out the round
+        let elapsed = self
+            .clock
+            .now()
+            .saturating_duration_since(*self.round_started.read().await);
+        if elapsed > self.config.block_execution_budget {
+            warn!(
+                height = state.height,
+                round = state.round,
+                elapsed_ms = elapsed.as_millis() as u64,
+                budget_ms = self.config.block_execution_budget.as_millis() as u64,
+                "Block execution overran its budget"
+            );
+        }
+
         // Create proposal
         let mut proposal = Proposal {
             height: state.height,
@@ -164,6 +765,14 @@ impl ConsensusEngine {
             prev_hash,
             block_hash,
             state_root,
+            // Cite the latest prevote quorum for this block so locked
+            // validators can follow it
+            pol_round: state
+                .polka_rounds
+                .iter()
+                .rev()
+                .find(|(_, hash)| **hash == block_hash)
+                .map(|(round, _)| *round),
             transactions,
             proposer: self.our_id.clone(),
             signature: Signature64::default(),
@@ -174,24 +783,174 @@ impl ConsensusEngine {
         let signature = self.signing_key.sign(&payload);
         proposal.signature = Signature64::from_bytes(signature.to_bytes());
 
-        info!(
-            height = state.height,
-            round = state.round,
-            block_hash = hex::encode(&block_hash[..8]),
-            "Broadcasting proposal"
-        );
+        Ok(proposal)
+    }
 
-        // Broadcast
-        let _ = self
-            .event_tx
-            .send(ConsensusEvent::BroadcastProposal(proposal));
+    /// Process any incoming consensus message.
+    ///
+    /// Dispatches to the matching `on_*` handler so transports only need
+    /// to hand over the decoded envelope. Messages signed by this node are
+    /// ignored: gossip echoes them back, and our own votes were already
+    /// counted when cast.
+    ///
+    /// Messages for heights at or below the height floor (the highest
+    /// finalized height) are `Ignored` before any signature is checked, so
+    /// replayed ancient votes cost almost nothing. Honest validators send
+    /// such messages too, commits arriving after the quorum, so they are
+    /// not treated as errors.
+    /// Messages for later heights and rounds are verified before they are
+    /// buffered, so a forged copy cannot take the place of the real one.
+    pub async fn process_message(&self, message: ConsensusMessage) -> Result<ProcessResult> {
+        let height = message.height();
+        if height <= *self.height_floor.read().await {
+            debug!(height, "Ignoring message for an already finalized height");
+            return Ok(ProcessResult::Ignored);
+        }
 
-        Ok(())
+        if message.signer() == Some(&self.our_id) {
+            debug!("Ignoring self-originated consensus message");
+            return Ok(ProcessResult::Ignored);
+        }
+
+        let in_window = {
+            let state = self.state.read().await;
+            if Self::is_future(&message, &state) {
+                Some(self.future.read().await.in_window(state.height, state.round, &message))
+            } else {
+                None
+            }
+        };
+        if let Some(in_window) = in_window {
+            if !in_window {
+                debug!(height, "Dropping message too far ahead to buffer");
+                return Ok(ProcessResult::Ignored);
+            }
+            verify_message(&message, &*self.validator_set_at(height).await)?;
+
+            // The round may have moved on while verifying
+            let state = self.state.read().await;
+            if Self::is_future(&message, &state) {
+                let current_height = height == state.height;
+                if !self.future.write().await.insert(state.height, state.round, message) {
+                    debug!(height, "Dropping message too far ahead to buffer");
+                    return Ok(ProcessResult::Ignored);
+                }
+                drop(state);
+                if current_height && self.is_catching_up().await {
+                    self.catch_up_round().await?;
+                }
+                return Ok(ProcessResult::Buffered);
+            }
+        }
+
+        let position = {
+            let state = self.state.read().await;
+            (state.height, state.round)
+        };
+
+        let result = self.dispatch(message).await;
+        self.replay_if_advanced(position).await;
+        result
+    }
+
+    /// The validator set that will be active at `height`, as far as known.
+    async fn validator_set_at(&self, height: u64) -> Arc<ValidatorSet> {
+        if let Some((effective, validator_set)) = self.pending_validator_set.read().await.as_ref() {
+            if height >= *effective {
+                return Arc::new(validator_set.clone());
+            }
+        }
+        self.validator_set().await
+    }
+
+    /// Replay buffered messages if the round moved on from `position`.
+    async fn replay_if_advanced(&self, position: (u64, u64)) {
+        let now = {
+            let state = self.state.read().await;
+            (state.height, state.round)
+        };
+        if now != position {
+            self.replay_buffered().await;
+        }
+    }
+
+    /// Whether a message is for a height or round we have not reached.
+    ///
+    /// Commits count in any round of their height, so only their height
+    /// decides.
+    fn is_future(message: &ConsensusMessage, state: &RoundState) -> bool {
+        match message {
+            ConsensusMessage::Proposal(_) | ConsensusMessage::Prevote(_) => {
+                (message.height(), message.round()) > (state.height, state.round)
+            }
+            ConsensusMessage::Commit(_) | ConsensusMessage::Certificate(_) => {
+                message.height() > state.height
+            }
+        }
+    }
+
+    /// Replay buffered messages that are now for the current height and round.
+    ///
+    /// Replayed nil prevotes can skip the round, so this repeats until the
+    /// round stays put.
+    async fn replay_buffered(&self) {
+        loop {
+            let (height, round) = {
+                let state = self.state.read().await;
+                (state.height, state.round)
+            };
+            let ready = self.future.write().await.take_ready(height, round);
+            for message in ready {
+                if let Err(e) = self.dispatch(message).await {
+                    debug!(height, round, error = %e, "Dropping invalid buffered message");
+                }
+            }
+
+            let state = self.state.read().await;
+            if (state.height, state.round) == (height, round) {
+                return;
+            }
+        }
+    }
+
+    /// Number of messages buffered for later heights and rounds.
+    pub async fn buffered_messages(&self) -> usize {
+        self.future.read().await.len()
+    }
+
+    /// Number of votes held in memory: the current round's prevotes and
+    /// commits plus everything buffered.
+    ///
+    /// Votes for abandoned rounds are cleared when the round advances, and
+    /// buffered rounds are limited to `max_future_rounds` ahead, so this
+    /// stays bounded however many rounds a height takes.
+    pub async fn retained_votes(&self) -> usize {
+        let state = self.state.read().await;
+        state.prevotes.count() + state.commits.count() + self.buffered_messages().await
+    }
+
+    /// Hand a message to its handler.
+    async fn dispatch(&self, message: ConsensusMessage) -> Result<ProcessResult> {
+        match message {
+            ConsensusMessage::Proposal(proposal) => self.on_proposal(proposal).await,
+            ConsensusMessage::Prevote(prevote) => self.on_prevote(prevote).await,
+            ConsensusMessage::Commit(commit) => self.on_commit(commit).await,
+            ConsensusMessage::Certificate(certificate) => self.on_certificate(certificate).await,
+        }
     }
 
     /// Process an incoming proposal.
     pub async fn on_proposal(&self, proposal: Proposal) -> Result<ProcessResult> {
+        // Bound memory before anything is stored, independent of transport limits
+        if proposal.transactions.len() > self.config.max_proposal_tx_bytes {
+            return Err(ConsensusError::ProposalTooLarge {
+                size: proposal.transactions.len(),
+                max: self.config.max_proposal_tx_bytes,
+            });
+        }
+
         let mut state = self.state.write().await;
+        let validators = self.validator_set().await;
 
         // Check height and round
         if proposal.height != state.height {
@@ -202,7 +961,7 @@ impl ConsensusEngine {
         }
 
         // Verify it's from the correct leader
-        let leader = self.validator_set.leader_for_round(state.round);
+        let leader = validators.leader_for_round(state.round);
         if proposal.proposer != leader.id {
             warn!(
                 expected = %leader.id,
@@ -216,11 +975,41 @@ impl ConsensusEngine {
         }
 
         // Verify signature
-        self.verify_proposal_signature(&proposal)?;
+        verify_proposal(&proposal, &validators)?;
+
+        // Must extend our parent and its contents must match the hash we
+        // would commit to; otherwise vote nil
+        let validity = match self.parent_hash().await {
+            Some(parent) if proposal.prev_hash != parent => Err(ConsensusError::InvalidBlock {
+                reason: format!("proposal does not extend {}", hex::encode(&parent[..8])),
+            }),
+            _ => self.proposal_validator.validate(&proposal),
+        };
+        if let Err(e) = validity {
+            warn!(
+                height = state.height,
+                round = state.round,
+                error = %e,
+                "Invalid proposal contents, prevoting nil"
+            );
+            self.enter_phase(&mut state, Phase::Prevote).await;
+            if !state.prevoted {
+                drop(state);
+                self.prevote(None).await?;
+            }
+            return Err(e);
+        }
 
         // Store proposal
+        let first_seen = state.proposal.is_none();
         state.proposal = Some(proposal.clone());
-        state.phase = Phase::Prevote;
+        self.enter_phase(&mut state, Phase::Prevote).await;
+        self.persist(&state)?;
+        self.observer.on_proposal_received(&proposal);
+
+        if first_seen && self.config.rebroadcast_proposals && !self.is_observer() {
+            let _ = self.event_tx.send(ConsensusEvent::BroadcastProposal(proposal.clone()));
+        }
 
         info!(
             height = state.height,
@@ -229,23 +1018,67 @@ impl ConsensusEngine {
             "Received valid proposal, moving to prevote"
         );
 
-        // If we haven't prevoted yet, vote for this block
+        // If we haven't prevoted yet, vote for this block unless we are
+        // locked on another one
         if !state.prevoted {
+            let vote = if Self::lock_permits(&state, &proposal.block_hash) {
+                Some(proposal.block_hash)
+            } else {
+                warn!(
+                    height = state.height,
+                    round = state.round,
+                    locked_round = state.locked_round,
+                    "Locked on another block without proof of lock change, prevoting nil"
+                );
+                None
+            };
             drop(state); // Release lock before async operation
-            self.prevote(Some(proposal.block_hash)).await?;
+            return self.prevote(vote).await;
         }
 
         Ok(ProcessResult::Continue)
     }
 
-    /// Cast a prevote.
-    async fn prevote(&self, block_hash: Option<BlockHash>) -> Result<()> {
+    /// Whether our lock allows prevoting for `block_hash` this round.
+    ///
+    /// Once locked, we only prevote for another block if the current
+    /// proposal cites a round after our lock in which we saw a prevote
+    /// quorum for it.
+    fn lock_permits(state: &RoundState, block_hash: &BlockHash) -> bool {
+        let (Some(locked), Some(locked_round)) = (state.locked_block, state.locked_round) else {
+            return true;
+        };
+        if locked == *block_hash {
+            return true;
+        }
+
+        state
+            .proposal
+            .as_ref()
+            .filter(|proposal| proposal.block_hash == *block_hash)
+            .and_then(|proposal| proposal.pol_round)
+            .is_some_and(|round| {
+                round > locked_round
+                    && round < state.round
+                    && state.polka_rounds.get(&round) == Some(block_hash)
+            })
+    }
+
+    /// Cast a prevote, counting it toward our own tally.
+    async fn prevote(&self, block_hash: Option<BlockHash>) -> Result<ProcessResult> {
         let mut state = self.state.write().await;
+        if self.sits_out(state.height) {
+            return Ok(ProcessResult::Continue);
+        }
+        let validators = self.validator_set().await;
 
         if state.prevoted {
-            return Ok(()); // Already voted
+            return Ok(ProcessResult::Continue); // Already voted
         }
 
+        // Never prevote against our lock
+        let block_hash = block_hash.filter(|hash| Self::lock_permits(&state, hash));
+
         let mut prevote = Prevote {
             height: state.height,
             round: state.round,
@@ -259,6 +1092,10 @@ impl ConsensusEngine {
         prevote.signature = Signature64::from_bytes(signature.to_bytes());
 
         state.prevoted = true;
+        if validators.contains(&self.our_id) && state.prevotes.add(prevote.clone()).is_new() {
+            self.observer.on_prevote(&prevote);
+        }
+        self.persist(&state)?;
 
         debug!(
             height = state.height,
@@ -269,12 +1106,13 @@ impl ConsensusEngine {
 
         let _ = self.event_tx.send(ConsensusEvent::BroadcastPrevote(prevote));
 
-        Ok(())
+        self.commit_on_prevote_quorum(state, &validators).await
     }
 
     /// Process an incoming prevote.
     pub async fn on_prevote(&self, prevote: Prevote) -> Result<ProcessResult> {
         let mut state = self.state.write().await;
+        let validators = self.validator_set().await;
 
         // Check height and round
         if prevote.height != state.height {
@@ -285,19 +1123,28 @@ impl ConsensusEngine {
         }
 
         // Verify validator is known
-        if !self.validator_set.contains(&prevote.validator) {
+        if !validators.contains(&prevote.validator) {
             return Err(ConsensusError::UnknownValidator {
                 validator: prevote.validator.to_hex(),
             });
         }
 
         // Verify signature
-        self.verify_prevote_signature(&prevote)?;
+        verify_prevote(&prevote, &validators)?;
 
         // Add to prevote set
-        if !state.prevotes.add(prevote.clone()) {
-            return Ok(ProcessResult::Ignored); // Duplicate
+        match state.prevotes.add(prevote.clone()) {
+            AddOutcome::New => {}
+            AddOutcome::Duplicate => return Ok(ProcessResult::Ignored),
+            AddOutcome::Equivocation(first, second) => {
+                self.report_equivocation(
+                    ConsensusMessage::Prevote(*first),
+                    ConsensusMessage::Prevote(*second),
+                );
+                return Ok(ProcessResult::Ignored);
+            }
         }
+        self.observer.on_prevote(&prevote);
 
         debug!(
             height = state.height,
@@ -307,10 +1154,40 @@ impl ConsensusEngine {
             "Received prevote"
         );
 
-        // Check for quorum
+        self.commit_on_prevote_quorum(state, &validators).await
+    }
+
+    /// Lock and cast a commit once the proposal has a prevote quorum, or
+    /// skip to the next round once nil has one.
+    ///
+    /// A nil quorum means this round cannot decide, so there is nothing to
+    /// gain from waiting out its timeouts. Buffered messages for the next
+    /// round are replayed by the caller.
+    async fn commit_on_prevote_quorum(
+        &self,
+        mut state: RwLockWriteGuard<'_, RoundState>,
+        validators: &ValidatorSet,
+    ) -> Result<ProcessResult> {
+        let nil_weight = state.prevotes.nil_weight(validators);
+        if nil_weight >= validators.quorum_threshold() {
+            info!(
+                height = state.height,
+                round = state.round,
+                weight = nil_weight,
+                "Nil prevote quorum reached, skipping to next round"
+            );
+            self.advance_round(&mut state, validators).await?;
+            return Ok(ProcessResult::Continue);
+        }
+
         if let Some(block_hash) = &state.proposal.as_ref().map(|p| p.block_hash) {
-            let weight = state.prevotes.weight_for_block(block_hash, &self.validator_set);
-            let quorum = self.validator_set.quorum_threshold();
+            let weight = state.prevotes.weight_for_block(block_hash, validators);
+            let quorum = validators.quorum_threshold();
+
+            if weight >= quorum {
+                let round = state.round;
+                state.polka_rounds.insert(round, *block_hash);
+            }
 
             if weight >= quorum && !state.committed {
                 info!(
@@ -321,25 +1198,29 @@ impl ConsensusEngine {
                     "Prevote quorum reached, moving to commit"
                 );
 
-                state.phase = Phase::Commit;
+                self.enter_phase(&mut state, Phase::Commit).await;
                 state.locked_block = Some(*block_hash);
                 state.locked_round = Some(state.round);
 
                 // Cast commit vote
                 drop(state);
-                self.commit(*block_hash).await?;
+                return self.commit(*block_hash).await;
             }
         }
 
         Ok(ProcessResult::Continue)
     }
 
-    /// Cast a commit vote.
-    async fn commit(&self, block_hash: BlockHash) -> Result<()> {
+    /// Cast a commit vote, counting it toward our own tally.
+    async fn commit(&self, block_hash: BlockHash) -> Result<ProcessResult> {
         let mut state = self.state.write().await;
+        if self.sits_out(state.height) {
+            return Ok(ProcessResult::Continue);
+        }
+        let validators = self.validator_set().await;
 
         if state.committed {
-            return Ok(()); // Already committed
+            return Ok(ProcessResult::Continue); // Already committed
         }
 
         let mut commit = Commit {
@@ -355,6 +1236,10 @@ impl ConsensusEngine {
         commit.signature = Signature64::from_bytes(signature.to_bytes());
 
         state.committed = true;
+        if validators.contains(&self.our_id) {
+            state.commits.add(commit.clone());
+        }
+        self.persist(&state)?;
 
         info!(
             height = state.height,
@@ -365,31 +1250,48 @@ impl ConsensusEngine {
 
         let _ = self.event_tx.send(ConsensusEvent::BroadcastCommit(commit));
 
-        Ok(())
+        Ok(self
+            .finalize_on_commit_quorum(&mut state, block_hash, &validators)
+            .await
+            .map_or(ProcessResult::Continue, ProcessResult::Finalized))
     }
 
     /// Process an incoming commit.
     pub async fn on_commit(&self, commit: Commit) -> Result<ProcessResult> {
         let mut state = self.state.write().await;
+        let validators = self.validator_set().await;
 
         // Check height
         if commit.height != state.height {
             return Ok(ProcessResult::Ignored);
         }
 
+        // Height already decided
+        if state.phase == Phase::Completed {
+            return Ok(ProcessResult::Ignored);
+        }
+
         // Verify validator is known
-        if !self.validator_set.contains(&commit.validator) {
+        if !validators.contains(&commit.validator) {
             return Err(ConsensusError::UnknownValidator {
                 validator: commit.validator.to_hex(),
             });
         }
 
         // Verify signature
-        self.verify_commit_signature(&commit)?;
+        verify_commit(&commit, &validators)?;
 
         // Add to commit set
-        if !state.commits.add(commit.clone()) {
-            return Ok(ProcessResult::Ignored); // Duplicate
+        match state.commits.add(commit.clone()) {
+            AddOutcome::New => {}
+            AddOutcome::Duplicate => return Ok(ProcessResult::Ignored),
+            AddOutcome::Equivocation(first, second) => {
+                self.report_equivocation(
+                    ConsensusMessage::Commit(*first),
+                    ConsensusMessage::Commit(*second),
+                );
+                return Ok(ProcessResult::Ignored);
+            }
         }
 
         debug!(
@@ -399,72 +1301,299 @@ impl ConsensusEngine {
             "Received commit"
         );
 
-        // Check for finality
-        let weight = state
-            .commits
-            .weight_for_block(&commit.block_hash, &self.validator_set);
-        let quorum = self.validator_set.quorum_threshold();
+        Ok(self
+            .finalize_on_commit_quorum(&mut state, commit.block_hash, &validators)
+            .await
+            .map_or(ProcessResult::NeedMoreVotes, ProcessResult::Finalized))
+    }
 
-        if weight >= quorum {
-            info!(
-                height = state.height,
-                round = state.round,
-                block_hash = hex::encode(&commit.block_hash[..8]),
-                weight,
-                "BLOCK FINALIZED"
-            );
+    /// Notify the observer and emit evidence of conflicting votes.
+    fn report_equivocation(&self, first: ConsensusMessage, second: ConsensusMessage) {
+        let Some(validator) = first.signer().cloned() else {
+            return;
+        };
+        warn!(
+            height = first.height(),
+            round = first.round(),
+            validator = %validator,
+            "Equivocation detected"
+        );
+        self.observer
+            .on_equivocation(&validator, first.height(), first.round());
+        let _ = self.event_tx.send(ConsensusEvent::Equivocation {
+            validator,
+            first: Box::new(first),
+            second: Box::new(second),
+        });
+    }
 
-            // Create finality certificate
-            let commits = state.commits.commits_for_block(&commit.block_hash);
-            let certificate = FinalityCertificate::new(
-                state.height,
-                commit.block_hash,
-                commits,
-                weight,
-            );
+    /// Finalize the height once `block_hash` has a commit quorum.
+    async fn finalize_on_commit_quorum(
+        &self,
+        state: &mut RoundState,
+        block_hash: BlockHash,
+        validators: &ValidatorSet,
+    ) -> Option<FinalityCertificate> {
+        let weight = state.commits.weight_for_block(&block_hash, validators);
+        if weight < validators.quorum_threshold() {
+            return None;
+        }
+
+        info!(
+            height = state.height,
+            round = state.round,
+            block_hash = hex::encode(&block_hash[..8]),
+            weight,
+            "BLOCK FINALIZED"
+        );
+
+        // Create finality certificate
+        let commits = state.commits.commits_for_block(&block_hash);
+        let certificate = FinalityCertificate::new(state.height, block_hash, commits, weight);
+
+        // Store finalized block
+        let height = state.height;
+        self.record_finalized(certificate.clone()).await;
+        self.observer.on_finalized(&certificate);
+        let latency = self
+            .clock
+            .now()
+            .saturating_duration_since(*self.height_started.read().await);
+        self.observer.on_finalization_latency(height, latency);
+
+        // Emit finalization event
+        let _ = self.event_tx.send(ConsensusEvent::BlockFinalized {
+            height,
+            block_hash,
+            certificate: certificate.clone(),
+        });
+
+        // Help peers that missed commits finalize immediately
+        if self.config.gossip_finality_certificate {
+            let _ = self
+                .event_tx
+                .send(ConsensusEvent::BroadcastCertificate(certificate.clone()));
+        }
 
-            // Store finalized block
-            let height = state.height;
-            {
-                let mut finalized = self.finalized.write().await;
-                finalized.insert(height, certificate.clone());
+        // The next height must build on this block
+        if let Some(parent) = self.parent_hash.write().await.as_mut() {
+            *parent = block_hash;
+        }
+
+        // Advance to next height
+        self.enter_phase(state, Phase::Completed).await;
+
+        Some(certificate)
+    }
+
+    /// Process an incoming finality certificate.
+    ///
+    /// Each attached commit is verified and counted exactly as if it had
+    /// arrived on its own; the certificate's claimed weight is never
+    /// trusted. A certificate carrying a valid quorum finalizes the height
+    /// in one step.
+    pub async fn on_certificate(&self, certificate: FinalityCertificate) -> Result<ProcessResult> {
+        let mut result = ProcessResult::Ignored;
+
+        for commit in certificate.commits {
+            if commit.height != certificate.height || commit.block_hash != certificate.block_hash {
+                return Err(ConsensusError::InvalidCertificate {
+                    reason: "certificate contains a commit for a different block".to_string(),
+                });
             }
 
-            // Emit finalization event
-            let _ = self.event_tx.send(ConsensusEvent::BlockFinalized {
-                height,
-                block_hash: commit.block_hash,
-                certificate: certificate.clone(),
+            match self.on_commit(commit).await? {
+                ProcessResult::Finalized(cert) => return Ok(ProcessResult::Finalized(cert)),
+                ProcessResult::Ignored => {}
+                other => result = other,
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Finalize a height from a certificate, without replaying its votes.
+    ///
+    /// For catching up: the certificate is verified against the validator
+    /// set for its height, recorded as finalized and announced with
+    /// `BlockFinalized`. If it is for the height being decided or above, its
+    /// block becomes the parent and the engine moves on to the height after
+    /// it. Certificates must come in order: one at or below the latest
+    /// finalized height is rejected with `AlreadyFinalized`, and one that
+    /// skips a height with `InvalidCertificate`.
+    pub async fn finalize_from_certificate(&self, certificate: FinalityCertificate) -> Result<()> {
+        let height = certificate.height;
+        let next = self.height_floor().await + 1;
+        if height < next {
+            return Err(ConsensusError::AlreadyFinalized { height });
+        }
+        if height > next {
+            return Err(ConsensusError::InvalidCertificate {
+                reason: format!("certificate for height {} skips ahead of height {}", height, next),
             });
+        }
 
-            // Advance to next height
-            state.phase = Phase::Completed;
+        let validators = self.validator_set_at(height).await;
+        certificate.verify(&validators)?;
+
+        info!(
+            height,
+            block_hash = hex::encode(&certificate.block_hash[..8]),
+            "Finalized from certificate"
+        );
+
+        self.record_finalized(certificate.clone()).await;
+        self.observer.on_finalized(&certificate);
+        let _ = self.event_tx.send(ConsensusEvent::BlockFinalized {
+            height,
+            block_hash: certificate.block_hash,
+            certificate: certificate.clone(),
+        });
 
-            return Ok(ProcessResult::Finalized(certificate));
+        if height >= self.current_height().await {
+            if let Some(parent) = self.parent_hash.write().await.as_mut() {
+                *parent = certificate.block_hash;
+            }
+            self.start_height(height + 1).await?;
         }
+        Ok(())
+    }
 
-        Ok(ProcessResult::NeedMoreVotes)
+    /// Validators that have not prevoted in the current round, in
+    /// validator set order.
+    pub async fn missing_prevoters(&self) -> Vec<ValidatorId> {
+        let state = self.state.read().await;
+        let validators = self.validator_set().await;
+        validators
+            .iter()
+            .filter(|v| !state.prevotes.has_vote(&v.id))
+            .map(|v| v.id.clone())
+            .collect()
+    }
+
+    /// Validators that have not committed at the current height, in
+    /// validator set order.
+    pub async fn missing_committers(&self) -> Vec<ValidatorId> {
+        let state = self.state.read().await;
+        let validators = self.validator_set().await;
+        validators
+            .iter()
+            .filter(|v| !state.commits.has_commit(&v.id))
+            .map(|v| v.id.clone())
+            .collect()
+    }
+
+    /// Why the current round has not progressed, or `None` if the height
+    /// is already decided.
+    pub async fn progress_blocker(&self) -> Option<ProgressBlocker> {
+        let state = self.state.read().await;
+        let validators = self.validator_set().await;
+        Self::blocker_for(&state, &validators)
+    }
+
+    /// Compute the progress blocker for a round state.
+    fn blocker_for(state: &RoundState, validator_set: &ValidatorSet) -> Option<ProgressBlocker> {
+        let quorum = validator_set.quorum_threshold();
+        let total = validator_set.total_weight();
+
+        // No block can reach quorum even if every outstanding vote joins the leader
+        let split = |leading: u64, cast: u64| leading + (total - cast.min(total)) < quorum;
+
+        match state.phase {
+            Phase::Completed => None,
+            Phase::Propose => Some(ProgressBlocker::WaitingForProposal),
+            Phase::Prevote => {
+                let leading = state
+                    .prevotes
+                    .leading_block(validator_set)
+                    .map_or(0, |(_, weight)| weight);
+                if split(leading, state.prevotes.cast_weight(validator_set)) {
+                    return Some(ProgressBlocker::SplitVotes);
+                }
+
+                let have = state
+                    .proposal
+                    .as_ref()
+                    .map_or(0, |p| state.prevotes.weight_for_block(&p.block_hash, validator_set));
+                Some(ProgressBlocker::WaitingForPrevoteQuorum { have, need: quorum })
+            }
+            Phase::Commit => {
+                let leading = state
+                    .commits
+                    .leading_block(validator_set)
+                    .map_or(0, |(_, weight)| weight);
+                if split(leading, state.commits.cast_weight(validator_set)) {
+                    return Some(ProgressBlocker::SplitVotes);
+                }
+
+                let have = state
+                    .locked_block
+                    .map_or(0, |hash| state.commits.weight_for_block(&hash, validator_set));
+                Some(ProgressBlocker::WaitingForCommitQuorum { have, need: quorum })
+            }
+        }
+    }
+
+    /// Handle a propose phase that timed out without a valid proposal.
+    ///
+    /// We prevote nil and move on to the prevote phase, so a silent leader
+    /// costs the round only until a nil quorum forms rather than every
+    /// phase timeout.
+    pub async fn on_propose_timeout(&self) -> Result<()> {
+        let mut state = self.state.write().await;
+        let position = (state.height, state.round);
+        warn!(
+            height = state.height,
+            round = state.round,
+            "No proposal in time, prevoting nil"
+        );
+        self.enter_phase(&mut state, Phase::Prevote).await;
+        let prevoted = state.prevoted;
+        drop(state);
+
+        if !prevoted {
+            self.prevote(None).await?;
+        }
+        self.replay_if_advanced(position).await;
+        Ok(())
     }
 
     /// Handle round timeout.
     pub async fn on_timeout(&self) -> Result<()> {
         let mut state = self.state.write().await;
+        let validators = self.validator_set().await;
 
         warn!(
             height = state.height,
             round = state.round,
             phase = %state.phase,
+            blocker = Self::blocker_for(&state, &validators).map(|b| b.to_string()),
             "Round timeout"
         );
+        self.advance_round(&mut state, &validators).await?;
+        drop(state);
+
+        self.replay_buffered().await;
+        Ok(())
+    }
 
+    /// Give up on the current round and start the next one.
+    ///
+    /// Reported as a `RoundTimeout` whether the timer ran out or a nil
+    /// quorum cut the round short.
+    async fn advance_round(&self, state: &mut RoundState, validators: &ValidatorSet) -> Result<()> {
         // Emit timeout event
         let _ = self.event_tx.send(ConsensusEvent::RoundTimeout {
             height: state.height,
             round: state.round,
         });
+        self.observer.on_timeout(state.height, state.round);
 
         // Move to next round
         *state = state.next_round();
+        *self.round_started.write().await = self.clock.now();
+        self.enter_phase(state, Phase::Propose).await;
+        self.persist(state)?;
 
         info!(
             height = state.height,
@@ -473,86 +1602,46 @@ impl ConsensusEngine {
         );
 
         // If we're the new leader, request block execution
-        if self.validator_set.leader_for_round(state.round).id == self.our_id {
+        if !self.sits_out(state.height) && validators.leader_for_round(state.round).id == self.our_id {
             info!("We are the leader for round {}", state.round);
         }
-
         Ok(())
     }
 
-    /// Verify proposal signature.
-    fn verify_proposal_signature(&self, proposal: &Proposal) -> Result<()> {
-        let validator = self
-            .validator_set
-            .get(&proposal.proposer)
-            .ok_or_else(|| ConsensusError::UnknownValidator {
-                validator: proposal.proposer.to_hex(),
-            })?;
-
-        let verifying_key = validator.verifying_key().ok_or_else(|| {
-            ConsensusError::InvalidSignature {
-                message_type: "proposal".to_string(),
-            }
-        })?;
-
-        let signature = Signature::from_bytes(proposal.signature.as_bytes());
-        let payload = proposal.signing_payload();
-
-        verifying_key.verify(&payload, &signature).map_err(|_| {
-            ConsensusError::InvalidSignature {
-                message_type: "proposal".to_string(),
-            }
-        })
+    /// Remember a finality certificate, raising the height floor.
+    async fn record_finalized(&self, certificate: FinalityCertificate) {
+        self.set_height_floor(certificate.height).await;
+        let mut finalized = self.finalized.write().await;
+        finalized.insert(certificate.height, certificate);
     }
 
-    /// Verify prevote signature.
-    fn verify_prevote_signature(&self, prevote: &Prevote) -> Result<()> {
-        let validator = self
-            .validator_set
-            .get(&prevote.validator)
-            .ok_or_else(|| ConsensusError::UnknownValidator {
-                validator: prevote.validator.to_hex(),
-            })?;
-
-        let verifying_key = validator.verifying_key().ok_or_else(|| {
-            ConsensusError::InvalidSignature {
-                message_type: "prevote".to_string(),
-            }
-        })?;
-
-        let signature = Signature::from_bytes(prevote.signature.as_bytes());
-        let payload = prevote.signing_payload();
-
-        verifying_key.verify(&payload, &signature).map_err(|_| {
-            ConsensusError::InvalidSignature {
-                message_type: "prevote".to_string(),
-            }
-        })
+    /// Highest height whose messages are rejected unread.
+    pub async fn height_floor(&self) -> u64 {
+        *self.height_floor.read().await
     }
 
-    /// Verify commit signature.
-    fn verify_commit_signature(&self, commit: &Commit) -> Result<()> {
-        let validator = self
-            .validator_set
-            .get(&commit.validator)
-            .ok_or_else(|| ConsensusError::UnknownValidator {
-                validator: commit.validator.to_hex(),
-            })?;
-
-        let verifying_key = validator.verifying_key().ok_or_else(|| {
-            ConsensusError::InvalidSignature {
-                message_type: "commit".to_string(),
-            }
-        })?;
+    /// Raise the height floor, e.g. to the latest finalized height in
+    /// storage after a restart. The floor never moves down.
+    pub async fn set_height_floor(&self, height: u64) {
+        let mut floor = self.height_floor.write().await;
+        *floor = (*floor).max(height);
+    }
 
-        let signature = Signature::from_bytes(commit.signature.as_bytes());
-        let payload = commit.signing_payload();
+    /// Take the next finalized height awaiting application by MARS.
+    ///
+    /// Heights are handed out strictly in order: if height `n` has not
+    /// been finalized yet, nothing above it is returned even if it is.
+    /// Each height is returned once.
+    pub async fn next_finalized_to_apply(&self) -> Option<(u64, BlockHash, FinalityCertificate)> {
+        let mut next = self.next_to_apply.write().await;
+        let certificate = self.finalized.read().await.get(&next).cloned()?;
+        *next += 1;
+        Some((certificate.height, certificate.block_hash, certificate))
+    }
 
-        verifying_key.verify(&payload, &signature).map_err(|_| {
-            ConsensusError::InvalidSignature {
-                message_type: "commit".to_string(),
-            }
-        })
+    /// Set the next height to hand out for application (e.g. after restart).
+    pub async fn set_next_to_apply(&self, height: u64) {
+        *self.next_to_apply.write().await = height;
     }
 
     /// Check if a height has been finalized.
@@ -567,6 +1656,7 @@ impl ConsensusEngine {
 
     /// Fork choice: get the canonical block hash at a height.
     pub async fn fork_choice(&self, height: u64) -> Option<BlockHash> {
+        let validators = self.validator_set().await;
         // Rule 1: Prefer finalized block
         if let Some(cert) = self.finalized.read().await.get(&height) {
             return Some(cert.block_hash);
@@ -589,7 +1679,7 @@ impl ConsensusEngine {
             .map(|h| {
                 (
                     h,
-                    state.commits.weight_for_block(&h, &self.validator_set),
+                    state.commits.weight_for_block(&h, &validators),
                 )
             })
         {
@@ -606,7 +1696,9 @@ impl ConsensusEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::MockClock;
     use rand::rngs::OsRng;
+    use std::time::Duration;
 
     fn create_test_engine() -> (ConsensusEngine, mpsc::UnboundedReceiver<ConsensusEvent>) {
         let (tx, rx) = mpsc::unbounded_channel();
@@ -630,6 +1722,313 @@ mod tests {
         (engine, rx)
     }
 
+    /// Deterministic validator keys for multi-validator tests.
+    fn validator_keys(n: u8) -> Vec<SigningKey> {
+        (0..n).map(|i| SigningKey::from_bytes(&[i + 1; 32])).collect()
+    }
+
+    /// Engine for `keys[0]` with all `keys` as equal-weight validators.
+    fn engine_with_keys(
+        keys: &[SigningKey],
+        config: ConsensusConfig,
+    ) -> (ConsensusEngine, mpsc::UnboundedReceiver<ConsensusEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let engine = ConsensusEngine::new(config, validator_set, keys[0].clone(), tx);
+        (engine, rx)
+    }
+
+    /// A proposal signed by `key`.
+    fn signed_proposal(key: &SigningKey, height: u64, round: u64, block_hash: BlockHash) -> Proposal {
+        let mut proposal = Proposal {
+            height,
+            round,
+            prev_hash: [0u8; 32],
+            block_hash,
+            state_root: [0u8; 32],
+            pol_round: None,
+            transactions: Vec::new(),
+            proposer: ValidatorId::from_verifying_key(&key.verifying_key()),
+            signature: Signature64::default(),
+        };
+        proposal.signature =
+            Signature64::from_bytes(key.sign(&proposal.signing_payload()).to_bytes());
+        proposal
+    }
+
+    /// A prevote signed by `key`.
+    fn signed_prevote(
+        key: &SigningKey,
+        height: u64,
+        round: u64,
+        block_hash: Option<BlockHash>,
+    ) -> Prevote {
+        let mut prevote = Prevote {
+            height,
+            round,
+            block_hash,
+            validator: ValidatorId::from_verifying_key(&key.verifying_key()),
+            signature: Signature64::default(),
+        };
+        prevote.signature =
+            Signature64::from_bytes(key.sign(&prevote.signing_payload()).to_bytes());
+        prevote
+    }
+
+    /// A commit signed by `key`.
+    fn signed_commit(key: &SigningKey, height: u64, round: u64, block_hash: BlockHash) -> Commit {
+        let mut commit = Commit {
+            height,
+            round,
+            block_hash,
+            validator: ValidatorId::from_verifying_key(&key.verifying_key()),
+            signature: Signature64::default(),
+        };
+        commit.signature = Signature64::from_bytes(key.sign(&commit.signing_payload()).to_bytes());
+        commit
+    }
+
+    /// A network of `n` equal-weight validators whose keys are all known,
+    /// with one engine under test running as `keys[us]`.
+    ///
+    /// Messages from the other validators are signed with their real keys
+    /// and fed through `process_message`, as gossip would deliver them.
+    struct Harness {
+        keys: Vec<SigningKey>,
+        engine: ConsensusEngine,
+        events: mpsc::UnboundedReceiver<ConsensusEvent>,
+    }
+
+    impl Harness {
+        /// Build the network and start the engine at height 1.
+        async fn new(n: u8, us: usize) -> Self {
+            let keys = validator_keys(n);
+            let validator_set =
+                ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+            let (tx, events) = mpsc::unbounded_channel();
+            let engine =
+                ConsensusEngine::new(ConsensusConfig::default(), validator_set, keys[us].clone(), tx);
+            engine.start_height(1).await.unwrap();
+            Self { keys, engine, events }
+        }
+
+        /// Validator ID of `keys[i]`.
+        fn id(&self, i: usize) -> ValidatorId {
+            ValidatorId::from_verifying_key(&self.keys[i].verifying_key())
+        }
+
+        /// Deliver a proposal from `keys[from]`.
+        async fn deliver_proposal(
+            &self,
+            from: usize,
+            height: u64,
+            round: u64,
+            block_hash: BlockHash,
+        ) -> Result<ProcessResult> {
+            let proposal = signed_proposal(&self.keys[from], height, round, block_hash);
+            self.engine.process_message(ConsensusMessage::Proposal(proposal)).await
+        }
+
+        /// Deliver a prevote from each of `from`, returning the last result.
+        async fn deliver_prevotes(
+            &self,
+            from: &[usize],
+            height: u64,
+            round: u64,
+            block_hash: Option<BlockHash>,
+        ) -> Result<ProcessResult> {
+            let mut result = Ok(ProcessResult::Ignored);
+            for &i in from {
+                let prevote = signed_prevote(&self.keys[i], height, round, block_hash);
+                result = self.engine.process_message(ConsensusMessage::Prevote(prevote)).await;
+            }
+            result
+        }
+
+        /// Deliver a commit from each of `from`, returning the last result.
+        async fn deliver_commits(
+            &self,
+            from: &[usize],
+            height: u64,
+            round: u64,
+            block_hash: BlockHash,
+        ) -> Result<ProcessResult> {
+            let mut result = Ok(ProcessResult::Ignored);
+            for &i in from {
+                let commit = signed_commit(&self.keys[i], height, round, block_hash);
+                result = self.engine.process_message(ConsensusMessage::Commit(commit)).await;
+            }
+            result
+        }
+
+        /// Take every event emitted so far.
+        fn drain_events(&mut self) -> Vec<ConsensusEvent> {
+            let mut events = Vec::new();
+            while let Ok(event) = self.events.try_recv() {
+                events.push(event);
+            }
+            events
+        }
+    }
+
+    #[tokio::test]
+    async fn single_round_happy_path_finalizes() {
+        // Four validators; we are keys[1] and keys[0] leads round 0
+        let mut net = Harness::new(4, 1).await;
+        let hash = [7u8; 32];
+
+        // Proposal: we prevote for it
+        let result = net.deliver_proposal(0, 1, 0, hash).await;
+        assert!(matches!(result, Ok(ProcessResult::Continue)));
+        let events = net.drain_events();
+        assert!(matches!(
+            events.as_slice(),
+            [ConsensusEvent::BroadcastPrevote(p)] if p.block_hash == Some(hash) && p.validator == net.id(1)
+        ));
+
+        // Our prevote plus two more reach quorum (3 of 4): we commit
+        net.deliver_prevotes(&[0, 2], 1, 0, Some(hash)).await.unwrap();
+        let events = net.drain_events();
+        assert!(matches!(
+            events.as_slice(),
+            [ConsensusEvent::BroadcastCommit(c)] if c.block_hash == hash && c.validator == net.id(1)
+        ));
+        assert!(!net.engine.is_finalized(1).await);
+
+        // One commit is not enough; the second completes the quorum
+        let result = net.deliver_commits(&[0], 1, 0, hash).await;
+        assert!(matches!(result, Ok(ProcessResult::NeedMoreVotes)));
+        let certificate = match net.deliver_commits(&[2], 1, 0, hash).await {
+            Ok(ProcessResult::Finalized(cert)) => cert,
+            other => panic!("expected finalization, got {:?}", other),
+        };
+
+        assert_eq!((certificate.height, certificate.block_hash), (1, hash));
+        assert_eq!(certificate.total_weight, 3);
+        let mut committers: Vec<ValidatorId> =
+            certificate.commits.iter().map(|c| c.validator.clone()).collect();
+        committers.sort_by_key(|id| id.0);
+        let mut expected = vec![net.id(0), net.id(1), net.id(2)];
+        expected.sort_by_key(|id| id.0);
+        assert_eq!(committers, expected);
+
+        let finalized = net.drain_events().into_iter().find_map(|event| match event {
+            ConsensusEvent::BlockFinalized { height, block_hash, certificate } => {
+                Some((height, block_hash, certificate.total_weight))
+            }
+            _ => None,
+        });
+        assert_eq!(finalized, Some((1, hash, 3)));
+        assert!(net.engine.is_finalized(1).await);
+        assert!(verify_certificate(&certificate, &*net.engine.validator_set().await).is_ok());
+
+        // Late votes for the decided height change nothing
+        let late = net.deliver_commits(&[3], 1, 0, hash).await;
+        assert!(matches!(late, Ok(ProcessResult::Ignored)));
+    }
+
+    /// Accepts a proposal only if its hash is a toy digest of its contents.
+    struct HashesContents;
+
+    impl ProposalValidator for HashesContents {
+        fn validate(&self, proposal: &Proposal) -> Result<()> {
+            if proposal.block_hash != [proposal.transactions.len() as u8; 32] {
+                return Err(ConsensusError::InvalidBlock {
+                    reason: "block hash does not match contents".to_string(),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn inconsistent_proposal_is_nil_voted() {
+        let mut net = Harness::new(4, 1).await;
+        net.engine.set_proposal_validator(Arc::new(HashesContents));
+
+        // Signed by the right leader, but the hash does not match the (empty) contents
+        let result = net.deliver_proposal(0, 1, 0, [7u8; 32]).await;
+        assert!(matches!(result, Err(ConsensusError::InvalidBlock { .. })));
+        assert!(net.engine.current_proposal().await.is_none());
+
+        let events = net.drain_events();
+        assert!(matches!(
+            events.as_slice(),
+            [ConsensusEvent::BroadcastPrevote(p)] if p.block_hash.is_none()
+        ));
+
+        // Prevotes for the bogus hash cannot make us commit to it
+        net.deliver_prevotes(&[0, 2, 3], 1, 0, Some([7u8; 32])).await.unwrap();
+        assert!(!net.engine.state.read().await.committed);
+    }
+
+    /// Observer counting each hook call.
+    #[derive(Default)]
+    struct CountingObserver {
+        proposals: std::sync::atomic::AtomicUsize,
+        prevotes: std::sync::atomic::AtomicUsize,
+        finalized: std::sync::atomic::AtomicUsize,
+        timeouts: std::sync::atomic::AtomicUsize,
+        equivocations: std::sync::atomic::AtomicUsize,
+    }
+
+    impl CountingObserver {
+        fn counts(&self) -> [usize; 5] {
+            use std::sync::atomic::Ordering::SeqCst;
+            [
+                self.proposals.load(SeqCst),
+                self.prevotes.load(SeqCst),
+                self.finalized.load(SeqCst),
+                self.timeouts.load(SeqCst),
+                self.equivocations.load(SeqCst),
+            ]
+        }
+    }
+
+    impl ConsensusObserver for CountingObserver {
+        fn on_proposal_received(&self, _proposal: &Proposal) {
+            self.proposals.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+        fn on_prevote(&self, _prevote: &Prevote) {
+            self.prevotes.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+        fn on_finalized(&self, _certificate: &FinalityCertificate) {
+            self.finalized.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+        fn on_timeout(&self, _height: u64, _round: u64) {
+            self.timeouts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+        fn on_equivocation(&self, _validator: &ValidatorId, _height: u64, _round: u64) {
+            self.equivocations.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn observer_sees_finalization_flow() {
+        let mut net = Harness::new(4, 1).await;
+        let observer = Arc::new(CountingObserver::default());
+        net.engine.set_observer(observer.clone());
+        let hash = [7u8; 32];
+
+        net.deliver_proposal(0, 1, 0, hash).await.unwrap();
+        assert_eq!(observer.counts(), [1, 1, 0, 0, 0]); // plus our own prevote
+
+        net.deliver_prevotes(&[0, 2], 1, 0, Some(hash)).await.unwrap();
+        assert_eq!(observer.counts(), [1, 3, 0, 0, 0]);
+
+        // keys[2] votes again for another block
+        net.deliver_prevotes(&[2], 1, 0, Some([8u8; 32])).await.unwrap();
+        assert_eq!(observer.counts(), [1, 3, 0, 0, 1]);
+
+        net.deliver_commits(&[0, 2], 1, 0, hash).await.unwrap();
+        assert_eq!(observer.counts(), [1, 3, 1, 0, 1]);
+
+        net.engine.start_height(2).await.unwrap();
+        net.engine.on_timeout().await.unwrap();
+        assert_eq!(observer.counts(), [1, 3, 1, 1, 1]);
+    }
+
     #[tokio::test]
     async fn engine_creation() {
         let (engine, _rx) = create_test_engine();
@@ -645,6 +2044,979 @@ mod tests {
         assert_eq!(engine.current_round().await, 0);
     }
 
+    #[tokio::test]
+    async fn deserialized_validator_set_accepts_votes() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let our_key = SigningKey::generate(&mut OsRng);
+        let peer_key = SigningKey::generate(&mut OsRng);
+
+        let original = ValidatorSet::new(vec![
+            our_key.verifying_key().to_bytes(),
+            peer_key.verifying_key().to_bytes(),
+        ]);
+
+        // Round-trip through storage format: the index is rebuilt on load
+        let json = serde_json::to_string(&original).unwrap();
+        let loaded: ValidatorSet = serde_json::from_str(&json).unwrap();
+        assert!(loaded.is_indexed());
+
+        let engine = ConsensusEngine::new(ConsensusConfig::default(), loaded, our_key, tx);
+
+        let mut prevote = Prevote {
+            height: 1,
+            round: 0,
+            block_hash: Some([7u8; 32]),
+            validator: ValidatorId::from_verifying_key(&peer_key.verifying_key()),
+            signature: Signature64::default(),
+        };
+        let signature = peer_key.sign(&prevote.signing_payload());
+        prevote.signature = Signature64::from_bytes(signature.to_bytes());
+
+        let result = engine.on_prevote(prevote).await;
+        assert!(matches!(result, Ok(ProcessResult::Continue)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn propose_deadline_accounts_for_execution_budget() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let validator_set = ValidatorSet::new(vec![signing_key.verifying_key().to_bytes()]);
+        let config = ConsensusConfig {
+            propose_timeout: Duration::from_millis(100),
+            block_execution_budget: Duration::from_millis(500),
+            ..ConsensusConfig::default()
+        };
+        let engine = ConsensusEngine::new(config, validator_set, signing_key, tx);
+
+        engine.start_height(1).await.unwrap();
+        let deadline = engine.propose_deadline().await;
+
+        // Stubbed slow execution: longer than the bare propose timeout,
+        // but within the execution budget
+        tokio::time::advance(Duration::from_millis(400)).await;
+        assert!(Instant::now() < deadline);
+        engine.propose([0u8; 32], [1u8; 32], [2u8; 32], Vec::new()).await.unwrap();
+
+        // Without the budget the round would already have timed out
+        assert!(Instant::now() > deadline - engine.config().block_execution_budget);
+    }
+
+    #[tokio::test]
+    async fn oversized_proposal_rejected() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let signing_key = SigningKey::generate(&mut OsRng);
+        let validator_set = ValidatorSet::new(vec![signing_key.verifying_key().to_bytes()]);
+        let config = ConsensusConfig {
+            max_proposal_tx_bytes: 1024,
+            ..ConsensusConfig::default()
+        };
+        let engine = ConsensusEngine::new(config, validator_set, signing_key.clone(), tx);
+
+        let mut proposal = Proposal {
+            height: 1,
+            round: 0,
+            prev_hash: [0u8; 32],
+            block_hash: [1u8; 32],
+            state_root: [2u8; 32],
+            pol_round: None,
+            transactions: vec![0u8; 1025],
+            proposer: engine.our_id().clone(),
+            signature: Signature64::default(),
+        };
+        let signature = signing_key.sign(&proposal.signing_payload());
+        proposal.signature = Signature64::from_bytes(signature.to_bytes());
+
+        let result = engine.on_proposal(proposal).await;
+        assert!(matches!(
+            result,
+            Err(ConsensusError::ProposalTooLarge { size: 1025, max: 1024 })
+        ));
+        assert!(engine.state.read().await.proposal.is_none());
+    }
+
+    #[tokio::test]
+    async fn certificate_finalizes_in_one_step() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        let block_hash = [9u8; 32];
+
+        let commits: Vec<Commit> = keys[1..]
+            .iter()
+            .map(|k| signed_commit(k, 1, 0, block_hash))
+            .collect();
+        let certificate = FinalityCertificate::new(1, block_hash, commits, 3);
+
+        let result = engine.on_certificate(certificate).await.unwrap();
+        assert!(matches!(result, ProcessResult::Finalized(ref c) if c.total_weight == 3));
+        assert!(engine.is_finalized(1).await);
+    }
+
+    #[tokio::test]
+    async fn certificate_with_forged_commit_rejected() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        let block_hash = [9u8; 32];
+
+        let mut commits: Vec<Commit> = keys[1..]
+            .iter()
+            .map(|k| signed_commit(k, 1, 0, block_hash))
+            .collect();
+        commits[2].signature = Signature64::default();
+        let certificate = FinalityCertificate::new(1, block_hash, commits, 3);
+
+        assert!(engine.on_certificate(certificate).await.is_err());
+        assert!(!engine.is_finalized(1).await);
+    }
+
+    #[tokio::test]
+    async fn certificate_with_mismatched_commit_rejected() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        let block_hash = [9u8; 32];
+
+        let mut commits: Vec<Commit> = keys[1..]
+            .iter()
+            .map(|k| signed_commit(k, 1, 0, block_hash))
+            .collect();
+        commits[2] = signed_commit(&keys[3], 1, 0, [8u8; 32]);
+        let certificate = FinalityCertificate::new(1, block_hash, commits, 3);
+
+        assert!(matches!(
+            engine.on_certificate(certificate).await,
+            Err(ConsensusError::InvalidCertificate { .. })
+        ));
+        assert!(!engine.is_finalized(1).await);
+    }
+
+    #[tokio::test]
+    async fn finalizing_engine_gossips_certificate() {
+        let keys = validator_keys(4);
+        let config = ConsensusConfig {
+            gossip_finality_certificate: true,
+            ..ConsensusConfig::default()
+        };
+        let (engine, mut rx) = engine_with_keys(&keys, config);
+        let block_hash = [9u8; 32];
+
+        for key in &keys[1..] {
+            engine.on_commit(signed_commit(key, 1, 0, block_hash)).await.unwrap();
+        }
+
+        let mut gossiped = None;
+        while let Ok(event) = rx.try_recv() {
+            if let ConsensusEvent::BroadcastCertificate(cert) = event {
+                gossiped = Some(cert);
+            }
+        }
+        let cert = gossiped.expect("certificate should be gossiped");
+        assert_eq!(cert.height, 1);
+        assert_eq!(cert.commits.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn received_proposal_rebroadcast_once() {
+        let keys = validator_keys(4);
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let config = ConsensusConfig {
+            rebroadcast_proposals: true,
+            ..ConsensusConfig::default()
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let engine = ConsensusEngine::new(config, validator_set, keys[1].clone(), tx);
+        engine.start_height(1).await.unwrap();
+
+        // The same proposal arriving again, as gossip echoes do
+        let proposal = signed_proposal(&keys[0], 1, 0, [7u8; 32]);
+        for _ in 0..3 {
+            engine.process_message(ConsensusMessage::Proposal(proposal.clone())).await.unwrap();
+        }
+
+        let mut rebroadcasts = 0;
+        while let Ok(event) = rx.try_recv() {
+            if let ConsensusEvent::BroadcastProposal(p) = event {
+                assert_eq!(p.block_hash, proposal.block_hash);
+                rebroadcasts += 1;
+            }
+        }
+        assert_eq!(rebroadcasts, 1);
+    }
+
+    #[tokio::test]
+    async fn process_message_dispatches_each_variant() {
+        let keys = validator_keys(4);
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let (tx, _rx) = mpsc::unbounded_channel();
+        // Run as keys[1] so the leader's proposal is not our own
+        let engine = ConsensusEngine::new(ConsensusConfig::default(), validator_set, keys[1].clone(), tx);
+        let block_hash = [9u8; 32];
+
+        // keys[0] leads round 0; we prevote for it
+        let proposal = signed_proposal(&keys[0], 1, 0, block_hash);
+        let result = engine.process_message(ConsensusMessage::Proposal(proposal)).await;
+        assert!(matches!(result, Ok(ProcessResult::Continue)));
+
+        let prevote = signed_prevote(&keys[2], 1, 0, Some(block_hash));
+        let result = engine.process_message(ConsensusMessage::Prevote(prevote)).await;
+        assert!(matches!(result, Ok(ProcessResult::Continue)));
+
+        let commit = signed_commit(&keys[2], 1, 0, block_hash);
+        let result = engine.process_message(ConsensusMessage::Commit(commit)).await;
+        assert!(matches!(result, Ok(ProcessResult::NeedMoreVotes)));
+
+        let commits = [&keys[0], &keys[3]].iter().map(|k| signed_commit(k, 1, 0, block_hash)).collect();
+        let certificate = FinalityCertificate::new(1, block_hash, commits, 2);
+        let result = engine.process_message(ConsensusMessage::Certificate(certificate)).await;
+        assert!(matches!(result, Ok(ProcessResult::Finalized(_))));
+    }
+
+    #[tokio::test]
+    async fn votes_below_height_floor_ignored() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.start_height(1).await.unwrap();
+        finalize_height_one(&engine, &keys).await;
+        assert_eq!(engine.height_floor().await, 1);
+        engine.start_height(2).await.unwrap();
+
+        // A replayed vote from the finalized height, even with a bad signature
+        let mut replayed = signed_commit(&keys[1], 1, 0, [1u8; 32]);
+        replayed.signature = Signature64::default();
+        assert!(matches!(
+            engine.process_message(ConsensusMessage::Commit(replayed)).await,
+            Ok(ProcessResult::Ignored)
+        ));
+
+        // So is an honest commit that reaches us again after the quorum
+        let late = signed_commit(&keys[3], 1, 0, [1u8; 32]);
+        assert!(matches!(
+            engine.process_message(ConsensusMessage::Commit(late)).await,
+            Ok(ProcessResult::Ignored)
+        ));
+
+        // The floor only moves up
+        engine.set_height_floor(0).await;
+        assert_eq!(engine.height_floor().await, 1);
+    }
+
+    #[tokio::test]
+    async fn self_originated_messages_ignored() {
+        let keys = validator_keys(4);
+        let (engine, mut rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        let hash = [5u8; 32];
+
+        // Our own proposal counts our prevote once, when cast
+        engine.propose([0u8; 32], hash, [0u8; 32], Vec::new()).await.unwrap();
+        assert_eq!(engine.state.read().await.prevotes.count(), 1);
+
+        // Gossip echoes our messages back; none of them count again
+        while let Ok(event) = rx.try_recv() {
+            let echoed = match event {
+                ConsensusEvent::BroadcastProposal(p) => ConsensusMessage::Proposal(p),
+                ConsensusEvent::BroadcastPrevote(v) => ConsensusMessage::Prevote(v),
+                _ => continue,
+            };
+            let result = engine.process_message(echoed).await;
+            assert!(matches!(result, Ok(ProcessResult::Ignored)));
+        }
+        assert_eq!(engine.state.read().await.prevotes.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn finalized_heights_drain_in_order() {
+        let (engine, _rx) = create_test_engine();
+        let cert = |height: u64| FinalityCertificate::new(height, [height as u8; 32], Vec::new(), 3);
+
+        // Finalizations arrive out of order
+        engine.record_finalized(cert(3)).await;
+        engine.record_finalized(cert(2)).await;
+        assert!(engine.next_finalized_to_apply().await.is_none()); // 1 missing
+
+        engine.record_finalized(cert(1)).await;
+        let drained: Vec<u64> = [
+            engine.next_finalized_to_apply().await,
+            engine.next_finalized_to_apply().await,
+            engine.next_finalized_to_apply().await,
+        ]
+        .into_iter()
+        .map(|entry| entry.unwrap().0)
+        .collect();
+        assert_eq!(drained, vec![1, 2, 3]);
+        assert!(engine.next_finalized_to_apply().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn propose_without_membership_is_not_a_validator() {
+        let keys = validator_keys(4);
+        let outsider = SigningKey::from_bytes(&[99u8; 32]);
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let (event_tx, _rx) = mpsc::unbounded_channel();
+        let engine = ConsensusEngine::new(ConsensusConfig::default(), validator_set, outsider, event_tx);
+        engine.start_height(1).await.unwrap();
+
+        let err = engine.propose([0u8; 32], [1u8; 32], [2u8; 32], Vec::new()).await.unwrap_err();
+        assert!(matches!(err, ConsensusError::NotAValidator { .. }));
+    }
+
+    #[tokio::test]
+    async fn observer_finalizes_without_voting() {
+        let keys = validator_keys(4);
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let config = ConsensusConfig {
+            observer: true,
+            ..ConsensusConfig::default()
+        };
+        let (event_tx, mut rx) = mpsc::unbounded_channel();
+        // Even holding the round-0 leader's key, an observer stays silent
+        let engine = ConsensusEngine::new(config, validator_set, keys[0].clone(), event_tx);
+        engine.start_height(1).await.unwrap();
+        let hash = [9u8; 32];
+
+        engine.on_proposal(signed_proposal(&keys[0], 1, 0, hash)).await.unwrap();
+        for key in &keys[1..] {
+            engine.on_prevote(signed_prevote(key, 1, 0, Some(hash))).await.unwrap();
+        }
+        let mut result = ProcessResult::Continue;
+        for key in &keys[1..] {
+            result = engine.on_commit(signed_commit(key, 1, 0, hash)).await.unwrap();
+        }
+
+        let certificate = match result {
+            ProcessResult::Finalized(cert) => cert,
+            other => panic!("expected finalization, got {:?}", other),
+        };
+        assert_eq!(certificate.block_hash, hash);
+        assert_eq!(certificate.commits.len(), 3);
+        assert!(certificate
+            .commits
+            .iter()
+            .all(|c| c.validator != *engine.our_id()));
+
+        // Only the finalization itself was emitted
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], ConsensusEvent::BlockFinalized { .. }));
+
+        assert!(matches!(
+            engine.propose([0u8; 32], hash, [0u8; 32], Vec::new()).await,
+            Err(ConsensusError::ObserverMode)
+        ));
+    }
+
+    #[tokio::test]
+    async fn progress_blocker_reports_stall_reason() {
+        let keys = validator_keys(4);
+        let hash = [5u8; 32];
+
+        // Nothing received yet
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.start_height(1).await.unwrap();
+        assert_eq!(engine.progress_blocker().await, Some(ProgressBlocker::WaitingForProposal));
+
+        // Proposal but too few prevotes
+        engine.on_proposal(signed_proposal(&keys[0], 1, 0, hash)).await.unwrap();
+        engine.on_prevote(signed_prevote(&keys[1], 1, 0, Some(hash))).await.unwrap();
+        assert_eq!(
+            engine.progress_blocker().await,
+            Some(ProgressBlocker::WaitingForPrevoteQuorum { have: 2, need: 3 })
+        );
+
+        // Prevote quorum reached, too few commits
+        engine.on_prevote(signed_prevote(&keys[2], 1, 0, Some(hash))).await.unwrap();
+        engine.on_commit(signed_commit(&keys[1], 1, 0, hash)).await.unwrap();
+        assert_eq!(
+            engine.progress_blocker().await,
+            Some(ProgressBlocker::WaitingForCommitQuorum { have: 2, need: 3 })
+        );
+
+        // Finalized: nothing blocks
+        engine.on_commit(signed_commit(&keys[2], 1, 0, hash)).await.unwrap();
+        assert_eq!(engine.progress_blocker().await, None);
+    }
+
+    #[tokio::test]
+    async fn missing_voters_complement_voters() {
+        let net = Harness::new(4, 1).await;
+        let hash = [5u8; 32];
+        let all: Vec<ValidatorId> = (0..4).map(|i| net.id(i)).collect();
+        assert_eq!(net.engine.missing_prevoters().await, all);
+        assert_eq!(net.engine.missing_committers().await, all);
+
+        // We prevote on the proposal; keys[3] prevotes too
+        net.deliver_proposal(0, 1, 0, hash).await.unwrap();
+        net.deliver_prevotes(&[3], 1, 0, Some(hash)).await.unwrap();
+        assert_eq!(net.engine.missing_prevoters().await, vec![net.id(0), net.id(2)]);
+
+        // keys[0] completes the quorum, so we commit; keys[2] commits as well
+        net.deliver_prevotes(&[0], 1, 0, Some(hash)).await.unwrap();
+        net.deliver_commits(&[2], 1, 0, hash).await.unwrap();
+        assert_eq!(net.engine.missing_prevoters().await, vec![net.id(2)]);
+        assert_eq!(net.engine.missing_committers().await, vec![net.id(0), net.id(3)]);
+    }
+
+    #[tokio::test]
+    async fn fresh_network_finalizes_height_one_from_genesis() {
+        let keys = validator_keys(4);
+        let genesis = [0xAAu8; 32];
+        let set = ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let mut nodes: Vec<(ConsensusEngine, mpsc::UnboundedReceiver<ConsensusEvent>)> = keys
+            .iter()
+            .map(|key| {
+                let (tx, rx) = mpsc::unbounded_channel();
+                (ConsensusEngine::new(ConsensusConfig::default(), set.clone(), key.clone(), tx), rx)
+            })
+            .collect();
+        for (engine, _) in &nodes {
+            engine.bootstrap(genesis).await.unwrap();
+        }
+
+        // Only keys[0] is asked to build height 1, on top of genesis
+        let mut execute = Vec::new();
+        for (i, (_, rx)) in nodes.iter_mut().enumerate() {
+            while let Ok(event) = rx.try_recv() {
+                if let ConsensusEvent::ExecuteBlock { height, prev_hash, .. } = event {
+                    execute.push((i, height, prev_hash));
+                }
+            }
+        }
+        assert_eq!(execute, vec![(0, 1, genesis)]);
+
+        // A proposal on any other parent is refused
+        let block_hash = [1u8; 32];
+        assert!(nodes[0].0.propose([0u8; 32], block_hash, [0u8; 32], Vec::new()).await.is_err());
+        nodes[0].0.propose(genesis, block_hash, [0u8; 32], Vec::new()).await.unwrap();
+
+        // Gossip every broadcast to the other engines until nothing is left
+        let mut finalized = vec![false; nodes.len()];
+        loop {
+            let mut outbox = Vec::new();
+            for (i, (_, rx)) in nodes.iter_mut().enumerate() {
+                while let Ok(event) = rx.try_recv() {
+                    match event {
+                        ConsensusEvent::BroadcastProposal(p) => outbox.push((i, ConsensusMessage::Proposal(p))),
+                        ConsensusEvent::BroadcastPrevote(p) => outbox.push((i, ConsensusMessage::Prevote(p))),
+                        ConsensusEvent::BroadcastCommit(c) => outbox.push((i, ConsensusMessage::Commit(c))),
+                        ConsensusEvent::BlockFinalized { height: 1, block_hash: hash, .. } => {
+                            assert_eq!(hash, block_hash);
+                            finalized[i] = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if outbox.is_empty() {
+                break;
+            }
+            for (from, message) in outbox {
+                for (to, (engine, _)) in nodes.iter().enumerate() {
+                    if to != from {
+                        let _ = engine.process_message(message.clone()).await;
+                    }
+                }
+            }
+        }
+
+        assert_eq!(finalized, vec![true; 4]);
+        for (engine, _) in &nodes {
+            assert_eq!(engine.parent_hash().await, Some(block_hash));
+        }
+    }
+
+    #[derive(Default)]
+    struct LatencyRecorder(std::sync::Mutex<Vec<(u64, Duration)>>);
+
+    impl ConsensusObserver for LatencyRecorder {
+        fn on_finalization_latency(&self, height: u64, latency: Duration) {
+            self.0.lock().unwrap().push((height, latency));
+        }
+    }
+
+    #[tokio::test]
+    async fn mock_clock_drives_timeouts_and_latency() {
+        let mut net = Harness::new(4, 1).await;
+        let clock = Arc::new(MockClock::new());
+        let latencies = Arc::new(LatencyRecorder::default());
+        net.engine.set_clock(clock.clone());
+        net.engine.set_observer(latencies.clone());
+        net.engine.start_height(1).await.unwrap();
+        net.drain_events();
+
+        // No proposal arrives, so we prevote nil when the propose phase
+        // times out, then round 0 times out in the prevote phase
+        let round0 = net.engine.config().propose_timeout_for_round(0);
+        let prevote0 = net.engine.config().prevote_timeout_for_round(0);
+
+        clock.advance(round0 - Duration::from_millis(1));
+        assert!(!net.engine.check_timeout().await.unwrap());
+        clock.advance(Duration::from_millis(1));
+        assert!(net.engine.check_timeout().await.unwrap());
+        assert_eq!(net.engine.current_round().await, 0);
+        clock.advance(prevote0);
+        assert!(net.engine.check_timeout().await.unwrap());
+        assert_eq!(net.engine.current_round().await, 1);
+        assert!(net
+            .drain_events()
+            .iter()
+            .any(|e| matches!(e, ConsensusEvent::RoundTimeout { height: 1, round: 0 })));
+
+        // Round 1 restarts the deadline with backoff
+        clock.advance(round0);
+        assert!(!net.engine.check_timeout().await.unwrap());
+
+        net.deliver_commits(&[0, 2, 3], 1, 1, [5u8; 32]).await.unwrap();
+        assert_eq!(*latencies.0.lock().unwrap(), vec![(1, round0 * 2 + prevote0)]);
+        clock.advance(round0 * 10);
+        assert!(!net.engine.check_timeout().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn build_proposal_signs_without_broadcasting() {
+        let mut net = Harness::new(4, 0).await;
+        net.drain_events();
+
+        let proposal = net
+            .engine
+            .build_proposal([0u8; 32], [5u8; 32], [6u8; 32], vec![1, 2, 3])
+            .await
+            .unwrap();
+        assert_eq!((proposal.height, proposal.round), (1, 0));
+        assert_eq!(proposal.proposer, net.id(0));
+        verify_proposal(&proposal, &*net.engine.validator_set().await).unwrap();
+
+        // Nothing was sent or accepted
+        assert!(net.drain_events().is_empty());
+        assert!(net.engine.current_proposal().await.is_none());
+
+        // Only the round leader may build one
+        let follower = Harness::new(4, 1).await;
+        assert!(matches!(
+            follower.engine.build_proposal([0u8; 32], [5u8; 32], [6u8; 32], Vec::new()).await,
+            Err(ConsensusError::WrongLeader { .. })
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_driver_times_out_silent_round() {
+        let keys = validator_keys(4);
+        let config = ConsensusConfig {
+            propose_timeout: Duration::from_millis(100),
+            prevote_timeout: Duration::from_millis(100),
+            block_execution_budget: Duration::ZERO,
+            ..ConsensusConfig::default()
+        };
+        // keys[0] would lead round 0; we are keys[1] and nobody proposes
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let set = ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let engine = Arc::new(ConsensusEngine::new(config, set, keys[1].clone(), tx));
+        engine.start_height(1).await.unwrap();
+        let driver = engine.clone().spawn_timeout_driver();
+
+        let event = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                if let Some(ConsensusEvent::RoundTimeout { height, round }) = rx.recv().await {
+                    return (height, round);
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(event, (1, 0));
+        assert_eq!(engine.current_round().await, 1);
+        driver.abort();
+    }
+
+    #[tokio::test]
+    async fn double_sign_emits_evidence() {
+        let mut net = Harness::new(4, 1).await;
+        net.deliver_prevotes(&[2], 1, 0, Some([5u8; 32])).await.unwrap();
+        net.deliver_prevotes(&[2], 1, 0, Some([5u8; 32])).await.unwrap();
+        net.drain_events();
+
+        // Identical resubmission above was harmless; a different block is not
+        let result = net.deliver_prevotes(&[2], 1, 0, Some([6u8; 32])).await.unwrap();
+        assert!(matches!(result, ProcessResult::Ignored));
+        let evidence: Vec<_> = net
+            .drain_events()
+            .into_iter()
+            .filter_map(|e| match e {
+                ConsensusEvent::Equivocation { validator, first, second } => {
+                    Some((validator, *first, *second))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(evidence.len(), 1);
+        let (validator, first, second) = &evidence[0];
+        assert_eq!(*validator, net.id(2));
+        assert!(matches!(first, ConsensusMessage::Prevote(p) if p.block_hash == Some([5u8; 32])));
+        assert!(matches!(second, ConsensusMessage::Prevote(p) if p.block_hash == Some([6u8; 32])));
+    }
+
+    #[tokio::test]
+    async fn future_round_votes_replayed_on_arrival() {
+        let net = Harness::new(4, 1).await;
+
+        // Round 1 prevotes arrive while we are still in round 0
+        let result = net.deliver_prevotes(&[2, 3], 1, 1, None).await.unwrap();
+        assert!(matches!(result, ProcessResult::Buffered));
+        assert_eq!(net.engine.buffered_messages().await, 2);
+
+        net.engine.on_timeout().await.unwrap();
+        assert_eq!(net.engine.buffered_messages().await, 0);
+        assert_eq!(net.engine.missing_prevoters().await, vec![net.id(0), net.id(1)]);
+    }
+
+    #[tokio::test]
+    async fn far_future_flood_stays_within_cap() {
+        let config = ConsensusConfig {
+            max_future_messages: 16,
+            ..ConsensusConfig::default()
+        };
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, config.clone());
+        engine.start_height(1).await.unwrap();
+        let prevote = |key: &SigningKey, height: u64, round: u64| {
+            ConsensusMessage::Prevote(signed_prevote(key, height, round, None))
+        };
+
+        // Absurd heights are never buffered
+        let result = engine.process_message(prevote(&keys[2], u64::MAX, 0)).await.unwrap();
+        assert!(matches!(result, ProcessResult::Ignored));
+
+        // Nor is anything a validator did not sign
+        for i in 0..100u64 {
+            let mut signer = [0u8; 32];
+            signer[..8].copy_from_slice(&i.to_be_bytes());
+            let junk = Prevote {
+                height: 2,
+                round: 0,
+                block_hash: None,
+                validator: ValidatorId(signer),
+                signature: Signature64::default(),
+            };
+            assert!(engine.process_message(ConsensusMessage::Prevote(junk)).await.is_err());
+        }
+        assert_eq!(engine.buffered_messages().await, 0);
+
+        // Validators voting in every height and round within reach (two,
+        // so no round has a nil quorum to skip)
+        for height in 2..2 + config.max_future_heights {
+            for round in 0..=config.max_future_rounds {
+                for key in &keys[1..3] {
+                    engine.process_message(prevote(key, height, round)).await.unwrap();
+                }
+            }
+        }
+        assert_eq!(engine.buffered_messages().await, config.max_future_messages);
+
+        // The next height's messages survive the flood and are replayed
+        engine.start_height(2).await.unwrap();
+        let id = ValidatorId::from_verifying_key(&keys[2].verifying_key());
+        assert!(!engine.missing_prevoters().await.contains(&id));
+    }
+
+    #[tokio::test]
+    async fn forged_future_vote_cannot_displace_genuine() {
+        let net = Harness::new(4, 1).await;
+
+        // A forgery in validator 2's name arrives first
+        let mut forged = signed_prevote(&net.keys[2], 1, 1, None);
+        forged.signature = Signature64::default();
+        assert!(net.engine.process_message(ConsensusMessage::Prevote(forged)).await.is_err());
+        assert_eq!(net.engine.buffered_messages().await, 0);
+
+        let result = net.deliver_prevotes(&[2], 1, 1, None).await.unwrap();
+        assert!(matches!(result, ProcessResult::Buffered));
+        net.engine.on_timeout().await.unwrap();
+        assert!(!net.engine.missing_prevoters().await.contains(&net.id(2)));
+    }
+
+    #[tokio::test]
+    async fn vote_history_bounded_across_rounds() {
+        let config = ConsensusConfig::default();
+        let net = Harness::new(4, 1).await;
+
+        let mut peak = 0;
+        for round in 0..100 {
+            // Peers vote in this round and spray votes for every later one
+            net.deliver_prevotes(&[0, 2, 3], 1, round, None).await.unwrap();
+            for ahead in 1..=8 {
+                net.deliver_prevotes(&[2, 3], 1, round + ahead, None).await.unwrap();
+            }
+            peak = peak.max(net.engine.retained_votes().await);
+            net.engine.on_timeout().await.unwrap();
+        }
+
+        // Current round plus the buffered window, two voters per round
+        let window = 4 + 2 * config.max_future_rounds as usize;
+        assert!(peak <= window, "retained {} votes, bound {}", peak, window);
+    }
+
+    #[tokio::test]
+    async fn progress_blocker_detects_split_votes() {
+        let keys = validator_keys(4);
+        let hash = [5u8; 32];
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.start_height(1).await.unwrap();
+
+        engine.on_proposal(signed_proposal(&keys[0], 1, 0, hash)).await.unwrap();
+        engine.on_prevote(signed_prevote(&keys[1], 1, 0, Some(hash))).await.unwrap();
+        engine.on_prevote(signed_prevote(&keys[2], 1, 0, None)).await.unwrap();
+        engine.on_prevote(signed_prevote(&keys[3], 1, 0, Some([6u8; 32]))).await.unwrap();
+
+        assert_eq!(engine.progress_blocker().await, Some(ProgressBlocker::SplitVotes));
+    }
+
+    /// Finalize height 1 on `engine` with commits from `keys[1..]`.
+    async fn finalize_height_one(engine: &ConsensusEngine, keys: &[SigningKey]) -> FinalityCertificate {
+        let hash = [1u8; 32];
+        let mut result = ProcessResult::Continue;
+        for key in &keys[1..] {
+            result = engine.on_commit(signed_commit(key, 1, 0, hash)).await.unwrap();
+        }
+        match result {
+            ProcessResult::Finalized(cert) => cert,
+            other => panic!("expected finalization, got {:?}", other),
+        }
+    }
+
+    /// A certificate for height 1 by `keys[1..]` authorizing `validator_set`
+    /// from `effective_height`.
+    fn set_change_proof(keys: &[SigningKey], validator_set: &ValidatorSet, effective_height: u64) -> FinalityCertificate {
+        let hash = validator_set.change_hash(effective_height);
+        let commits: Vec<Commit> = keys[1..].iter().map(|key| signed_commit(key, 1, 0, hash)).collect();
+        let weight = commits.len() as u64;
+        FinalityCertificate::new(1, hash, commits, weight)
+    }
+
+    #[tokio::test]
+    async fn install_validator_set_with_valid_proof() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.start_height(1).await.unwrap();
+
+        let next_keys: Vec<SigningKey> = (10..13).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+        let next_set =
+            ValidatorSet::new(next_keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let proof = set_change_proof(&keys, &next_set, 3);
+        engine.install_validator_set(next_set, 3, &proof).await.unwrap();
+
+        // Not yet active at height 2
+        engine.start_height(2).await.unwrap();
+        assert_eq!(engine.validator_set().await.len(), 4);
+
+        engine.start_height(3).await.unwrap();
+        let active = engine.validator_set().await;
+        assert_eq!(active.len(), 3);
+        assert!(active.contains(&ValidatorId::from_verifying_key(&next_keys[0].verifying_key())));
+    }
+
+    #[tokio::test]
+    async fn pending_validator_set_does_not_change_leaders_mid_height() {
+        let keys = validator_keys(4);
+        let id = |key: &SigningKey| ValidatorId::from_verifying_key(&key.verifying_key());
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.start_height(1).await.unwrap();
+
+        // Same validators, reversed order: every round's leader changes
+        let reversed =
+            ValidatorSet::new(keys.iter().rev().map(|k| k.verifying_key().to_bytes()).collect());
+        let proof = set_change_proof(&keys, &reversed, 2);
+        engine.install_validator_set(reversed, 2, &proof).await.unwrap();
+
+        // Later rounds of height 1 still follow the pinned set
+        assert!(engine.is_leader().await);
+        engine.on_timeout().await.unwrap();
+        assert_eq!(engine.validator_set().await.leader_for_round(1).id, id(&keys[1]));
+        assert!(!engine.is_leader().await);
+
+        // The new set applies from the next height
+        engine.start_height(2).await.unwrap();
+        assert_eq!(engine.validator_set().await.leader_for_round(0).id, id(&keys[3]));
+        assert!(!engine.is_leader().await);
+    }
+
+    #[tokio::test]
+    async fn install_validator_set_rejects_bad_proof() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.start_height(1).await.unwrap();
+        let next_set = ValidatorSet::new(vec![[42u8; 32]]);
+        let proof = set_change_proof(&keys, &next_set, 5);
+
+        // Too few signers
+        let mut weak = proof.clone();
+        weak.commits.truncate(2);
+        assert!(matches!(
+            engine.install_validator_set(next_set.clone(), 5, &weak).await,
+            Err(ConsensusError::InvalidCertificate { .. })
+        ));
+
+        // Forged commit
+        let mut forged = proof.clone();
+        forged.commits[0].signature = Signature64::default();
+        assert!(engine.install_validator_set(next_set.clone(), 5, &forged).await.is_err());
+
+        // Backdated
+        assert!(matches!(
+            engine.install_validator_set(next_set.clone(), 1, &proof).await,
+            Err(ConsensusError::InvalidValidatorSetChange { .. })
+        ));
+
+        // Certifies another set, or this set from another height
+        let other = ValidatorSet::new(vec![[43u8; 32]]);
+        assert!(matches!(
+            engine.install_validator_set(other, 5, &proof).await,
+            Err(ConsensusError::InvalidValidatorSetChange { .. })
+        ));
+        assert!(matches!(
+            engine.install_validator_set(next_set, 6, &proof).await,
+            Err(ConsensusError::InvalidValidatorSetChange { .. })
+        ));
+
+        engine.start_height(5).await.unwrap();
+        assert_eq!(engine.validator_set().await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn current_proposal_tracks_round() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.start_height(1).await.unwrap();
+        assert!(engine.current_proposal().await.is_none());
+
+        engine.on_proposal(signed_proposal(&keys[0], 1, 0, [4u8; 32])).await.unwrap();
+        let proposal = engine.current_proposal().await.unwrap();
+        assert_eq!(proposal.block_hash, [4u8; 32]);
+        assert_eq!(proposal.round, 0);
+
+        // A new round starts without a proposal
+        engine.on_timeout().await.unwrap();
+        assert!(engine.current_proposal().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn locked_validator_prevotes_nil_for_other_block() {
+        // Four validators; we are keys[2], keys[0] leads round 0 and keys[1] round 1
+        let mut net = Harness::new(4, 2).await;
+        let (a, b) = ([0xAAu8; 32], [0xBBu8; 32]);
+
+        // Round 0: a prevote quorum for A locks us on it
+        net.deliver_proposal(0, 1, 0, a).await.unwrap();
+        net.deliver_prevotes(&[0, 1], 1, 0, Some(a)).await.unwrap();
+        net.drain_events();
+
+        // Round 1: a proposal for B without proof of lock change
+        net.engine.on_timeout().await.unwrap();
+        net.drain_events();
+        net.deliver_proposal(1, 1, 1, b).await.unwrap();
+
+        let events = net.drain_events();
+        assert!(matches!(
+            events.as_slice(),
+            [ConsensusEvent::BroadcastPrevote(p)] if p.round == 1 && p.block_hash.is_none()
+        ));
+    }
+
+    #[tokio::test]
+    async fn certificates_finalize_a_gap_of_heights() {
+        let mut net = Harness::new(4, 3).await;
+        let certify = |net: &Harness, height: u64, signers: &[usize]| {
+            let commits: Vec<Commit> = signers
+                .iter()
+                .map(|&i| signed_commit(&net.keys[i], height, 0, [height as u8; 32]))
+                .collect();
+            FinalityCertificate::new(height, [height as u8; 32], commits, signers.len() as u64)
+        };
+
+        // One vote short of quorum changes nothing
+        let short = certify(&net, 1, &[0, 1]);
+        assert!(net.engine.finalize_from_certificate(short).await.is_err());
+        assert_eq!(net.engine.current_height().await, 1);
+
+        for height in 1..=3 {
+            let certificate = certify(&net, height, &[0, 1, 2]);
+            net.engine.finalize_from_certificate(certificate).await.unwrap();
+        }
+        assert_eq!(net.engine.current_height().await, 4);
+        assert!(net.engine.is_finalized(2).await);
+
+        let finalized: Vec<u64> = net
+            .drain_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                ConsensusEvent::BlockFinalized { height, .. } => Some(height),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(finalized, vec![1, 2, 3]);
+
+        // Nothing at or below the latest finalized height
+        let stale = certify(&net, 2, &[0, 1, 2]);
+        assert!(matches!(
+            net.engine.finalize_from_certificate(stale).await,
+            Err(ConsensusError::AlreadyFinalized { height: 2 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn certificates_must_not_skip_heights() {
+        let mut net = Harness::new(4, 3).await;
+        net.engine.bootstrap([0u8; 32]).await.unwrap();
+        let certify = |net: &Harness, height: u64| {
+            let commits: Vec<Commit> = (0..3)
+                .map(|i| signed_commit(&net.keys[i], height, 0, [height as u8; 32]))
+                .collect();
+            FinalityCertificate::new(height, [height as u8; 32], commits, 3)
+        };
+
+        net.engine.finalize_from_certificate(certify(&net, 1)).await.unwrap();
+        assert!(matches!(
+            net.engine.finalize_from_certificate(certify(&net, 3)).await,
+            Err(ConsensusError::InvalidCertificate { .. })
+        ));
+        assert!(!net.engine.is_finalized(3).await);
+        assert_eq!(net.engine.current_height().await, 2);
+        assert_eq!(net.engine.parent_hash().await, Some([1u8; 32]));
+
+        net.engine.finalize_from_certificate(certify(&net, 2)).await.unwrap();
+        net.engine.finalize_from_certificate(certify(&net, 3)).await.unwrap();
+        let finalized: Vec<u64> = net
+            .drain_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                ConsensusEvent::BlockFinalized { height, .. } => Some(height),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(finalized, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn certificate_behind_the_engine_keeps_parent() {
+        let keys = validator_keys(4);
+        let (engine, _rx) = engine_with_keys(&keys, ConsensusConfig::default());
+        engine.bootstrap([0u8; 32]).await.unwrap();
+        engine.start_height(3).await.unwrap();
+        *engine.parent_hash.write().await = Some([2u8; 32]);
+
+        // Height 1 was never finalized here; its certificate must not
+        // replace the parent height 3 extends
+        let commits: Vec<Commit> = keys[..3].iter().map(|k| signed_commit(k, 1, 0, [1u8; 32])).collect();
+        let certificate = FinalityCertificate::new(1, [1u8; 32], commits, 3);
+        engine.finalize_from_certificate(certificate).await.unwrap();
+
+        assert!(engine.is_finalized(1).await);
+        assert_eq!(engine.current_height().await, 3);
+        assert_eq!(engine.parent_hash().await, Some([2u8; 32]));
+    }
+
     #[tokio::test]
     async fn timeout_advances_round() {
         let (engine, _rx) = create_test_engine();
@@ -655,4 +3027,307 @@ mod tests {
         assert_eq!(engine.current_round().await, 1);
         assert_eq!(engine.current_height().await, 1); // Same height
     }
+
+    #[tokio::test]
+    async fn recovery_after_simulated_crash_keeps_commit() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().to_path_buf();
+        let keys = validator_keys(4);
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let (a, b) = ([0xAAu8; 32], [0xBBu8; 32]);
+        let recover = |store: Arc<ConsensusStore>| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let engine = ConsensusEngine::recover(
+                ConsensusConfig::default(),
+                validator_set.clone(),
+                keys[2].clone(),
+                tx,
+                store,
+            )
+            .unwrap();
+            (engine, rx)
+        };
+
+        // Session 1: we are keys[2] and commit to A in round 0
+        {
+            let store = Arc::new(ConsensusStore::new(path.clone()).unwrap());
+            let (engine, mut rx) = recover(store);
+            engine.bootstrap([0u8; 32]).await.unwrap();
+            let proposal = signed_proposal(&keys[0], 1, 0, a);
+            engine.process_message(ConsensusMessage::Proposal(proposal)).await.unwrap();
+            for key in &keys[..2] {
+                let prevote = signed_prevote(key, 1, 0, Some(a));
+                engine.process_message(ConsensusMessage::Prevote(prevote)).await.unwrap();
+            }
+            let mut committed = false;
+            while let Ok(event) = rx.try_recv() {
+                committed |= matches!(event, ConsensusEvent::BroadcastCommit(_));
+            }
+            assert!(committed);
+        }
+
+        // Session 2: recover and pick up where we left off
+        let store = Arc::new(ConsensusStore::new(path).unwrap());
+        let (engine, mut rx) = recover(store);
+        engine.resume_from(0, [0u8; 32]).await.unwrap();
+        {
+            let state = engine.state.read().await;
+            assert_eq!((state.height, state.round), (1, 0));
+            assert!(state.prevoted && state.committed);
+            assert_eq!(state.locked_block, Some(a));
+        }
+
+        // The quorum seen again does not produce a second commit
+        let prevote = signed_prevote(&keys[3], 1, 0, Some(a));
+        engine.process_message(ConsensusMessage::Prevote(prevote)).await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // The lock survives too: B in the next round gets a nil prevote
+        engine.on_timeout().await.unwrap();
+        while rx.try_recv().is_ok() {}
+        let proposal = signed_proposal(&keys[1], 1, 1, b);
+        engine.process_message(ConsensusMessage::Proposal(proposal)).await.unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ConsensusEvent::BroadcastPrevote(p)) if p.round == 1 && p.block_hash.is_none()
+        ));
+    }
+
+    #[tokio::test]
+    async fn corrupted_round_state_sits_out_its_height() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let keys = validator_keys(4);
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let a = [0xAAu8; 32];
+        std::fs::write(temp.path().join("round_state.json"), b"{ not json").unwrap();
+
+        let store = Arc::new(ConsensusStore::new(temp.path().to_path_buf()).unwrap());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let engine = ConsensusEngine::recover(
+            ConsensusConfig::default(),
+            validator_set,
+            keys[2].clone(),
+            tx,
+            store,
+        )
+        .unwrap();
+        engine.rejoin(0, [0u8; 32]).await.unwrap();
+
+        // We may have voted at height 1 before the file was lost: sign nothing
+        let proposal = signed_proposal(&keys[0], 1, 0, a);
+        engine.process_message(ConsensusMessage::Proposal(proposal)).await.unwrap();
+        for key in [&keys[0], &keys[1], &keys[3]] {
+            let prevote = signed_prevote(key, 1, 0, Some(a));
+            engine.process_message(ConsensusMessage::Prevote(prevote)).await.unwrap();
+        }
+        for key in [&keys[0], &keys[1], &keys[3]] {
+            let commit = signed_commit(key, 1, 0, a);
+            engine.process_message(ConsensusMessage::Commit(commit)).await.unwrap();
+        }
+        while let Ok(event) = rx.try_recv() {
+            assert!(!matches!(
+                event,
+                ConsensusEvent::BroadcastPrevote(_) | ConsensusEvent::BroadcastCommit(_)
+            ));
+        }
+        assert!(engine.is_finalized(1).await);
+
+        // The next height is ours to vote at again
+        engine.resume_from(1, [0u8; 32]).await.unwrap();
+        let proposal = signed_proposal(&keys[0], 2, 0, a);
+        engine.process_message(ConsensusMessage::Proposal(proposal)).await.unwrap();
+        let mut prevoted = false;
+        while let Ok(event) = rx.try_recv() {
+            prevoted |= matches!(event, ConsensusEvent::BroadcastPrevote(p) if p.height == 2);
+        }
+        assert!(prevoted);
+    }
+
+    #[tokio::test]
+    async fn restarted_validator_rejoins_live_round_without_equivocating() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let keys = validator_keys(4);
+        let validator_set =
+            ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+        let (a, b) = ([0xAAu8; 32], [0xBBu8; 32]);
+        let recover = || {
+            let store = Arc::new(ConsensusStore::new(temp.path().to_path_buf()).unwrap());
+            let (tx, rx) = mpsc::unbounded_channel();
+            let engine = ConsensusEngine::recover(
+                ConsensusConfig::default(),
+                validator_set.clone(),
+                keys[2].clone(),
+                tx,
+                store,
+            )
+            .unwrap();
+            (engine, rx)
+        };
+
+        // Before going offline we prevote A in round 0
+        {
+            let (engine, mut rx) = recover();
+            engine.bootstrap([0u8; 32]).await.unwrap();
+            let proposal = signed_proposal(&keys[0], 1, 0, a);
+            engine.process_message(ConsensusMessage::Proposal(proposal)).await.unwrap();
+            assert!(matches!(
+                rx.try_recv(),
+                Ok(ConsensusEvent::BroadcastPrevote(p)) if p.round == 0 && p.block_hash == Some(a)
+            ));
+        }
+
+        // Back online: the round-0 vote is reloaded and not cast again
+        let (engine, mut rx) = recover();
+        engine.rejoin(0, [0u8; 32]).await.unwrap();
+        assert!(engine.is_catching_up().await);
+        assert_eq!(engine.current_round().await, 0);
+        let conflicting = signed_proposal(&keys[0], 1, 0, b);
+        engine.process_message(ConsensusMessage::Proposal(conflicting)).await.unwrap();
+
+        // Peers have moved on to round 1; one of them is not enough to follow
+        let proposal = signed_proposal(&keys[1], 1, 1, b);
+        engine.process_message(ConsensusMessage::Proposal(proposal)).await.unwrap();
+        assert_eq!(engine.current_round().await, 0);
+        let prevote = signed_prevote(&keys[0], 1, 1, Some(b));
+        engine.process_message(ConsensusMessage::Prevote(prevote)).await.unwrap();
+        assert_eq!(engine.current_round().await, 1);
+
+        // The live round reaches finality with us
+        let prevote = signed_prevote(&keys[1], 1, 1, Some(b));
+        engine.process_message(ConsensusMessage::Prevote(prevote)).await.unwrap();
+        for key in &keys[..2] {
+            let commit = signed_commit(key, 1, 1, b);
+            engine.process_message(ConsensusMessage::Commit(commit)).await.unwrap();
+        }
+
+        let mut prevotes = Vec::new();
+        let mut finalized = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                ConsensusEvent::BroadcastPrevote(p) => prevotes.push((p.round, p.block_hash)),
+                ConsensusEvent::BlockFinalized { height, block_hash, .. } => {
+                    finalized = Some((height, block_hash))
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(prevotes, vec![(1, Some(b))]);
+        assert_eq!(finalized, Some((1, b)));
+    }
+
+    #[tokio::test]
+    async fn propose_timeout_prevotes_nil() {
+        let mut net = Harness::new(4, 1).await;
+        let clock = Arc::new(MockClock::new());
+        net.engine.set_clock(clock.clone());
+        net.engine.start_height(1).await.unwrap();
+        net.drain_events();
+
+        clock.advance(net.engine.config().propose_timeout_for_round(0));
+        assert!(net.engine.check_timeout().await.unwrap());
+
+        // Still round 0, now waiting on prevotes with ours cast for nil
+        assert_eq!(net.engine.current_round().await, 0);
+        let events = net.drain_events();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            ConsensusEvent::BroadcastPrevote(Prevote { round: 0, block_hash: None, .. })
+        )));
+        assert!(!events.iter().any(|e| matches!(e, ConsensusEvent::RoundTimeout { .. })));
+    }
+
+    #[tokio::test]
+    async fn nil_prevote_quorum_skips_round() {
+        let mut net = Harness::new(4, 1).await;
+        let clock = Arc::new(MockClock::new());
+        net.engine.set_clock(clock.clone());
+        net.engine.start_height(1).await.unwrap();
+        clock.advance(net.engine.config().propose_timeout_for_round(0));
+        net.engine.check_timeout().await.unwrap();
+
+        // An early round-1 prevote waits in the buffer meanwhile
+        let early = net.deliver_prevotes(&[0], 1, 1, None).await.unwrap();
+        assert!(matches!(early, ProcessResult::Buffered));
+        net.deliver_prevotes(&[2], 1, 0, None).await.unwrap();
+        assert_eq!(net.engine.current_round().await, 0);
+        net.drain_events();
+
+        // The third nil vote makes a quorum: no waiting for the prevote timeout
+        net.deliver_prevotes(&[3], 1, 0, None).await.unwrap();
+        assert_eq!(net.engine.current_round().await, 1);
+        assert!(net
+            .drain_events()
+            .iter()
+            .any(|e| matches!(e, ConsensusEvent::RoundTimeout { height: 1, round: 0 })));
+        assert!(!net.engine.missing_prevoters().await.contains(&net.id(0)));
+    }
+
+    #[tokio::test]
+    async fn applied_validator_set_takes_effect_at_its_height() {
+        let keys = validator_keys(7);
+        let pubkeys: Vec<[u8; 32]> = keys.iter().map(|k| k.verifying_key().to_bytes()).collect();
+        let first = ValidatorSet::new(pubkeys[..4].to_vec());
+        let next = ValidatorSet::new(pubkeys.clone());
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(ConsensusStore::new(temp.path().to_path_buf()).unwrap());
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let engine =
+            ConsensusEngine::recover(ConsensusConfig::default(), first.clone(), keys[0].clone(), tx, store.clone())
+                .unwrap();
+        engine.start_height(1).await.unwrap();
+
+        let invalid = |result: Result<()>| matches!(result, Err(ConsensusError::InvalidValidatorSetChange { .. }));
+        assert!(invalid(engine.apply_validator_set(ValidatorSet::new(Vec::new()), 10).await));
+        assert!(invalid(engine.apply_validator_set(next.clone(), 1).await));
+        engine.apply_validator_set(next.clone(), 10).await.unwrap();
+        let saved: Option<ValidatorSet> = store.load_validator_set_at(10).unwrap();
+        assert_eq!(saved.unwrap().len(), 7);
+
+        let leaders = |set: &ValidatorSet| -> Vec<ValidatorId> {
+            (0..7).map(|round| set.leader_for_round(round).id.clone()).collect()
+        };
+        assert_ne!(first.quorum_threshold(), next.quorum_threshold());
+        for height in 1..=12 {
+            engine.start_height(height).await.unwrap();
+            let active = engine.validator_set().await;
+            let expected = if height < 10 { &first } else { &next };
+            assert_eq!(active.quorum_threshold(), expected.quorum_threshold(), "height {}", height);
+            assert_eq!(leaders(&active), leaders(expected), "height {}", height);
+        }
+
+        // The active set is saved as the current one
+        let current: ValidatorSet = store.load_validator_set().unwrap().unwrap();
+        assert_eq!(current.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn pending_validator_set_survives_restart() {
+        let keys = validator_keys(7);
+        let pubkeys: Vec<[u8; 32]> = keys.iter().map(|k| k.verifying_key().to_bytes()).collect();
+        let first = ValidatorSet::new(pubkeys[..4].to_vec());
+        let temp = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(ConsensusStore::new(temp.path().to_path_buf()).unwrap());
+        let recover = || {
+            let (tx, _rx) = mpsc::unbounded_channel();
+            ConsensusEngine::recover(ConsensusConfig::default(), first.clone(), keys[0].clone(), tx, store.clone())
+                .unwrap()
+        };
+
+        let engine = recover();
+        engine.start_height(3).await.unwrap();
+        engine.persist(&*engine.state.read().await).unwrap();
+        // Replaced before it took effect: only the later set is still due
+        engine.apply_validator_set(ValidatorSet::new(pubkeys[..5].to_vec()), 8).await.unwrap();
+        engine.apply_validator_set(ValidatorSet::new(pubkeys.clone()), 10).await.unwrap();
+        drop(engine);
+
+        let engine = recover();
+        for height in [9, 10] {
+            engine.start_height(height).await.unwrap();
+        }
+        assert_eq!(engine.validator_set().await.len(), 7);
+        assert!(engine.validator_set().await.is_indexed());
+    }
 }
@@ -23,10 +23,22 @@ pub enum ConsensusError {
     #[error("duplicate vote from validator {validator} in round {round}")]
     DuplicateVote { validator: String, round: u64 },
 
+    /// Proposal transactions exceed the configured byte budget.
+    #[error("proposal transactions are {size} bytes, maximum is {max}")]
+    ProposalTooLarge { size: usize, max: usize },
+
     /// Vote is for unknown block.
     #[error("vote references unknown block hash {hash}")]
     UnknownBlock { hash: String },
 
+    /// Our own key is not in the validator set.
+    #[error("local node {validator} is not in the validator set")]
+    NotAValidator { validator: String },
+
+    /// Observers never propose.
+    #[error("engine is in observer mode")]
+    ObserverMode,
+
     /// Validator not in validator set.
     #[error("unknown validator: {validator}")]
     UnknownValidator { validator: String },
@@ -35,6 +47,14 @@ pub enum ConsensusError {
     #[error("invalid signature on {message_type}")]
     InvalidSignature { message_type: String },
 
+    /// Finality certificate failed verification.
+    #[error("invalid finality certificate: {reason}")]
+    InvalidCertificate { reason: String },
+
+    /// Validator set change rejected.
+    #[error("invalid validator set change: {reason}")]
+    InvalidValidatorSetChange { reason: String },
+
     /// Quorum not reached within timeout.
     #[error("quorum timeout in round {round} phase {phase}")]
     QuorumTimeout { round: u64, phase: String },
@@ -59,6 +79,18 @@ pub enum ConsensusError {
     #[error("timeout expired for {operation}")]
     Timeout { operation: String },
 
+    /// Wire message could not be decoded exactly.
+    #[error("malformed consensus message: {reason}")]
+    MalformedMessage { reason: String },
+
+    /// Engine configuration is unusable.
+    #[error("invalid consensus configuration: {reason}")]
+    InvalidConfig { reason: String },
+
+    /// Round state could not be persisted or recovered.
+    #[error("consensus storage error: {reason}")]
+    Storage { reason: String },
+
     /// Serialization error.
     #[error("serialization error: {0}")]
     Serialization(String),
@@ -0,0 +1,109 @@
+//! Seeded, weight-proportional leader selection.
+//!
+//! There is one leader schedule, `ValidatorSet::leader_for_round`; a seed
+//! only picks the round at which to enter it. Every validator must compute
+//! the same leader, so the seed comes from data all of them can derive. By
+//! default the seed for `(height, round)` is
+//!
+//! ```text
+//! SHA-256(chain_id || height (u64 BE) || round (u64 BE))
+//! ```
+//!
+//! Tests can replace the chain id with a fixed seed of their own (see
+//! `ConsensusConfig::leader_seed`) to get a reproducible schedule that
+//! does not depend on any network name.
+
+use crate::types::{Validator, ValidatorSet};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Seed for picking the leader of one round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LeaderSeed([u8; 32]);
+
+impl LeaderSeed {
+    /// Derive the default seed for a round from the chain id.
+    pub fn derive(chain_id: &str, height: u64, round: u64) -> Self {
+        Self::derive_from(chain_id.as_bytes(), height, round)
+    }
+
+    /// Derive the seed for a round from an explicit base seed.
+    ///
+    /// Used in place of `derive` when the base seed is overridden.
+    pub fn derive_from(base: &[u8], height: u64, round: u64) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(base);
+        hasher.update(height.to_be_bytes());
+        hasher.update(round.to_be_bytes());
+        Self(hasher.finalize().into())
+    }
+
+    /// Use raw bytes as a seed.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Get the raw seed bytes.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl ValidatorSet {
+    /// Pick a leader with probability proportional to voting weight.
+    ///
+    /// The first eight seed bytes select a round of `leader_for_round`, so
+    /// seeded and per-round selection follow the same weighted schedule.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the set is empty.
+    pub fn weighted_leader(&self, seed: &LeaderSeed) -> &Validator {
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&seed.as_bytes()[..8]);
+        self.leader_for_round(u64::from_be_bytes(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weighted_set() -> ValidatorSet {
+        ValidatorSet::from_validators(
+            (1u8..=4)
+                .map(|i| Validator::with_weight([i; 32], i as u64))
+                .collect(),
+        )
+    }
+
+    fn schedule(set: &ValidatorSet, seed: impl Fn(u64) -> LeaderSeed) -> Vec<[u8; 32]> {
+        (0..32).map(|round| set.weighted_leader(&seed(round)).pubkey).collect()
+    }
+
+    #[test]
+    fn same_seed_same_schedule() {
+        let (a, b) = (weighted_set(), weighted_set());
+
+        let default = |round| LeaderSeed::derive("unykorn-devnet", 7, round);
+        assert_eq!(schedule(&a, default), schedule(&b, default));
+
+        let fixed = |round| LeaderSeed::derive_from(&[42u8; 32], 7, round);
+        assert_eq!(schedule(&a, fixed), schedule(&b, fixed));
+        assert_ne!(schedule(&a, fixed), schedule(&a, default));
+    }
+
+    #[test]
+    fn selection_follows_round_schedule() {
+        let set = weighted_set();
+
+        for round in [0, 1, 7, set.total_weight() - 1, u64::MAX] {
+            let mut bytes = [0u8; 32];
+            bytes[..8].copy_from_slice(&round.to_be_bytes());
+            assert_eq!(
+                set.weighted_leader(&LeaderSeed::from_bytes(bytes)).pubkey,
+                set.leader_for_round(round).pubkey
+            );
+        }
+    }
+}
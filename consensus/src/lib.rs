@@ -38,7 +38,7 @@
 //! let engine = ConsensusEngine::new(config, validators, keypair, event_tx);
 //!
 //! // Process incoming messages
-//! engine.on_proposal(proposal).await?;
+//! engine.process_message(ConsensusMessage::Proposal(proposal)).await?;
 //! engine.on_prevote(prevote).await?;
 //! engine.on_commit(commit).await?;
 //!
@@ -52,16 +52,30 @@
 //! }
 //! ```
 
+pub mod buffer;
+pub mod clock;
 pub mod config;
 pub mod engine;
 pub mod error;
+pub mod leader;
+pub mod observer;
 pub mod types;
+pub mod validity;
+pub mod verify;
+pub mod wire;
 
 // Re-exports for convenience
-pub use config::ConsensusConfig;
+pub use buffer::FutureBuffer;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use config::{ConsensusConfig, RoundTimeouts};
 pub use engine::{ConsensusEngine, ConsensusEvent, ProcessResult};
 pub use error::{ConsensusError, Result};
+pub use leader::LeaderSeed;
+pub use observer::{ConsensusObserver, NoopObserver};
 pub use types::{
-    BlockHash, Commit, CommitSet, ConsensusMessage, FinalityCertificate, Phase, Prevote,
-    PrevoteSet, Proposal, RoundState, StateRoot, Validator, ValidatorId, ValidatorSet,
+    AddOutcome, BlockHash, Commit, CommitSet, ConsensusMessage, FinalityCertificate, Phase, Prevote,
+    PrevoteSet, ProgressBlocker, Proposal, RoundState, StateRoot, Validator, ValidatorId, ValidatorSet,
 };
+pub use validity::{AcceptAll, ProposalValidator};
+pub use verify::{verify_certificate, verify_commit, verify_message, verify_prevote, verify_proposal};
+pub use wire::{decode_message, encode_message};
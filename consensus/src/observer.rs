@@ -0,0 +1,40 @@
+//! Hooks for observing consensus progress.
+//!
+//! The engine reports what it sees through a `ConsensusObserver`, so the
+//! embedding node can feed metrics, logs or alerts from consensus without
+//! the engine depending on any particular backend. Every hook has a no-op
+//! default; implementors override only what they need.
+
+use crate::types::{FinalityCertificate, Prevote, Proposal, ValidatorId};
+use std::time::Duration;
+
+/// Receives notifications from the consensus engine.
+///
+/// Hooks are called synchronously from the engine, sometimes while it holds
+/// its round state lock, so they must be cheap and must not call back into
+/// the engine.
+pub trait ConsensusObserver: Send + Sync {
+    /// A valid proposal from the round leader was accepted.
+    fn on_proposal_received(&self, _proposal: &Proposal) {}
+
+    /// A prevote (ours or a peer's) was counted.
+    fn on_prevote(&self, _prevote: &Prevote) {}
+
+    /// A height was finalized.
+    fn on_finalized(&self, _certificate: &FinalityCertificate) {}
+
+    /// A height was finalized `latency` after it started.
+    fn on_finalization_latency(&self, _height: u64, _latency: Duration) {}
+
+    /// A round timed out without deciding.
+    fn on_timeout(&self, _height: u64, _round: u64) {}
+
+    /// A validator signed conflicting votes in the same round.
+    fn on_equivocation(&self, _validator: &ValidatorId, _height: u64, _round: u64) {}
+}
+
+/// Observer that ignores every notification.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopObserver;
+
+impl ConsensusObserver for NoopObserver {}
@@ -6,9 +6,11 @@
 //! - Proposals, prevotes, and commits
 //! - Finality certificates
 
+use crate::error::ConsensusError;
 use ed25519_dalek::VerifyingKey;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// A 32-byte block hash.
 pub type BlockHash = [u8; 32];
@@ -71,9 +73,14 @@ impl ValidatorId {
         Self(bytes)
     }
 
+    /// Create from an Ed25519 public key (canonical address derivation).
+    pub fn from_pubkey(pubkey: &[u8; 32]) -> Self {
+        Self(mars::address_from_pubkey(pubkey))
+    }
+
     /// Create from verifying key.
     pub fn from_verifying_key(key: &VerifyingKey) -> Self {
-        Self(key.to_bytes())
+        Self::from_pubkey(&key.to_bytes())
     }
 
     /// Get the underlying bytes.
@@ -107,10 +114,15 @@ pub struct Validator {
 impl Validator {
     /// Create a new validator with weight 1.
     pub fn new(pubkey: [u8; 32]) -> Self {
+        Self::with_weight(pubkey, 1)
+    }
+
+    /// Create a new validator with the given voting weight.
+    pub fn with_weight(pubkey: [u8; 32], weight: u64) -> Self {
         Self {
-            id: ValidatorId::from_bytes(pubkey),
+            id: ValidatorId::from_pubkey(&pubkey),
             pubkey,
-            weight: 1,
+            weight,
         }
     }
 
@@ -121,7 +133,11 @@ impl Validator {
 }
 
 /// The set of active validators for a given epoch.
+///
+/// Deserialization goes through `StoredValidatorSet`, so a loaded set
+/// always has its lookup index built.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StoredValidatorSet")]
 pub struct ValidatorSet {
     /// Ordered list of validators (order matters for leader selection).
     validators: Vec<Validator>,
@@ -135,7 +151,11 @@ pub struct ValidatorSet {
 impl ValidatorSet {
     /// Create a new validator set from a list of public keys.
     pub fn new(pubkeys: Vec<[u8; 32]>) -> Self {
-        let validators: Vec<Validator> = pubkeys.into_iter().map(Validator::new).collect();
+        Self::from_validators(pubkeys.into_iter().map(Validator::new).collect())
+    }
+
+    /// Create a validator set from validators with explicit weights.
+    pub fn from_validators(validators: Vec<Validator>) -> Self {
         let total_weight = validators.iter().map(|v| v.weight).sum();
         let by_id = validators
             .iter()
@@ -150,7 +170,50 @@ impl ValidatorSet {
         }
     }
 
-    /// Rebuild the lookup index after deserialization.
+    /// Export as a stable, human-readable JSON document.
+    ///
+    /// Lists each validator's hex public key and weight in set order.
+    /// Unlike the storage serialization, no internal index is included.
+    pub fn to_json(&self) -> crate::Result<String> {
+        let document = ValidatorSetDocument {
+            validators: self
+                .validators
+                .iter()
+                .map(|v| ValidatorEntry {
+                    pubkey: hex::encode(v.pubkey),
+                    weight: v.weight,
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&document)
+            .map_err(|e| ConsensusError::Serialization(e.to_string()))
+    }
+
+    /// Import a document produced by `to_json`, rebuilding the index.
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let document: ValidatorSetDocument = serde_json::from_str(json)
+            .map_err(|e| ConsensusError::Serialization(e.to_string()))?;
+
+        let mut validators = Vec::with_capacity(document.validators.len());
+        for entry in document.validators {
+            let bytes = hex::decode(&entry.pubkey)
+                .map_err(|e| ConsensusError::Serialization(format!("pubkey {}: {}", entry.pubkey, e)))?;
+            let pubkey: [u8; 32] = bytes.try_into().map_err(|_| {
+                ConsensusError::Serialization(format!("pubkey {} is not 32 bytes", entry.pubkey))
+            })?;
+            if validators.iter().any(|v: &Validator| v.pubkey == pubkey) {
+                return Err(ConsensusError::Serialization(format!(
+                    "duplicate validator {}",
+                    entry.pubkey
+                )));
+            }
+            validators.push(Validator::with_weight(pubkey, entry.weight));
+        }
+
+        Ok(Self::from_validators(validators))
+    }
+
+    /// Rebuild the lookup index from the validator list.
     pub fn rebuild_index(&mut self) {
         self.by_id = self
             .validators
@@ -160,6 +223,11 @@ impl ValidatorSet {
             .collect();
     }
 
+    /// Check whether the lookup index covers every validator.
+    pub fn is_indexed(&self) -> bool {
+        self.by_id.len() == self.validators.len()
+    }
+
     /// Number of validators.
     pub fn len(&self) -> usize {
         self.validators.len()
@@ -180,10 +248,54 @@ impl ValidatorSet {
         self.by_id.contains_key(id)
     }
 
-    /// Get the leader for a given round (deterministic rotation).
+    /// Get the leader for a given round (deterministic weighted rotation).
+    ///
+    /// Weights are first divided by their greatest common divisor, and a
+    /// cycle of rounds gives each validator as many turns as its reduced
+    /// weight. Validators of equal weight share one bucket of turns and
+    /// take them in order, so equal weights rotate one round per validator.
+    /// The `k`-th of a bucket's `n` turns falls at `(2k + 1) / 2n` of the
+    /// cycle, spreading each bucket's turns evenly instead of handing them
+    /// out in one run.
+    ///
+    /// This is the only leader schedule; `weighted_leader` enters it at a
+    /// seeded round.
     pub fn leader_for_round(&self, round: u64) -> &Validator {
-        let index = (round as usize) % self.validators.len();
-        &self.validators[index]
+        let divisor = self.validators.iter().fold(0, |g, v| gcd(g, v.weight));
+        if divisor == 0 {
+            // No weight at all: plain rotation
+            return &self.validators[(round as usize) % self.validators.len()];
+        }
+
+        let mut buckets: Vec<(u64, Vec<&Validator>)> = Vec::new();
+        for validator in self.validators.iter().filter(|v| v.weight > 0) {
+            match buckets.iter_mut().find(|(_, members)| members[0].weight == validator.weight) {
+                Some((turns, members)) => {
+                    *turns += validator.weight / divisor;
+                    members.push(validator);
+                }
+                None => buckets.push((validator.weight / divisor, vec![validator])),
+            }
+        }
+        let turns: Vec<u64> = buckets.iter().map(|(count, _)| *count).collect();
+
+        let point = round % (self.total_weight / divisor);
+        for (index, (count, members)) in buckets.iter().enumerate() {
+            // First turn of this bucket not ahead of the point
+            let (mut low, mut high) = (0, *count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if turns_before(&turns, index, mid) < point {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            if low < *count && turns_before(&turns, index, low) == point {
+                return members[(low % members.len() as u64) as usize];
+            }
+        }
+        unreachable!("point is below the reduced total weight")
     }
 
     /// Calculate quorum threshold (2/3 + 1 of total weight).
@@ -192,6 +304,19 @@ impl ValidatorSet {
         (self.total_weight * 2) / 3 + 1
     }
 
+    /// Check whether the `available` validators alone carry quorum weight.
+    ///
+    /// IDs not in the set are ignored. When this is false, no block can be
+    /// finalized until more validators come online.
+    pub fn reachable_quorum(&self, available: &HashSet<ValidatorId>) -> bool {
+        let weight: u64 = available
+            .iter()
+            .filter_map(|id| self.get(id))
+            .map(|v| v.weight)
+            .sum();
+        weight >= self.quorum_threshold()
+    }
+
     /// Calculate the maximum faulty validators tolerated.
     pub fn max_faulty(&self) -> u64 {
         // f < n/3, so max f = (n-1)/3
@@ -207,6 +332,78 @@ impl ValidatorSet {
     pub fn iter(&self) -> impl Iterator<Item = &Validator> {
         self.validators.iter()
     }
+
+    /// The value a quorum of the current set certifies to authorize this
+    /// set from `effective_height` (see `ConsensusEngine::install_validator_set`).
+    ///
+    /// Covers every validator's key and weight in set order, under a tag
+    /// no block hash starts with.
+    pub fn change_hash(&self, effective_height: u64) -> BlockHash {
+        let mut hasher = Sha256::new();
+        hasher.update(b"VALIDATOR_SET");
+        hasher.update(effective_height.to_le_bytes());
+        for validator in &self.validators {
+            hasher.update(validator.pubkey);
+            hasher.update(validator.weight.to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Greatest common divisor, with `gcd(0, b) == b`.
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Count the turns in a cycle ahead of bucket `index`'s `turn`-th one.
+///
+/// The `k`-th of a bucket's `n` turns falls at `(2k + 1) / 2n`; ties go to
+/// the earlier bucket.
+fn turns_before(turns: &[u64], index: usize, turn: u64) -> u64 {
+    let count = u128::from(turns[index]);
+    let time = 2 * u128::from(turn) + 1;
+
+    turns
+        .iter()
+        .enumerate()
+        .map(|(other, &other_count)| {
+            // Turn l is ahead when (2l + 1) / 2n' < time / 2n
+            let limit = time * u128::from(other_count) + u128::from(other < index);
+            let odd = (limit - 1) / count;
+            odd.div_ceil(2).min(u128::from(other_count)) as u64
+        })
+        .sum()
+}
+
+/// Storage form of a `ValidatorSet`, without the lookup index.
+///
+/// The serialized total weight is ignored and recomputed.
+#[derive(Deserialize)]
+struct StoredValidatorSet {
+    validators: Vec<Validator>,
+}
+
+impl From<StoredValidatorSet> for ValidatorSet {
+    fn from(stored: StoredValidatorSet) -> Self {
+        Self::from_validators(stored.validators)
+    }
+}
+
+/// Human-readable validator set document (see `ValidatorSet::to_json`).
+#[derive(Serialize, Deserialize)]
+struct ValidatorSetDocument {
+    validators: Vec<ValidatorEntry>,
+}
+
+/// A single validator in a `ValidatorSetDocument`.
+#[derive(Serialize, Deserialize)]
+struct ValidatorEntry {
+    pubkey: String,
+    weight: u64,
 }
 
 /// Consensus round phase.
@@ -233,6 +430,54 @@ impl std::fmt::Display for Phase {
     }
 }
 
+/// Why the current round has not progressed yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgressBlocker {
+    /// No valid proposal received from the leader.
+    WaitingForProposal,
+    /// Proposal received, prevote weight for it below quorum.
+    WaitingForPrevoteQuorum { have: u64, need: u64 },
+    /// Prevote quorum reached, commit weight below quorum.
+    WaitingForCommitQuorum { have: u64, need: u64 },
+    /// Votes are spread so that no block can reach quorum this round.
+    SplitVotes,
+}
+
+impl std::fmt::Display for ProgressBlocker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgressBlocker::WaitingForProposal => write!(f, "waiting for proposal"),
+            ProgressBlocker::WaitingForPrevoteQuorum { have, need } => {
+                write!(f, "waiting for prevote quorum ({}/{})", have, need)
+            }
+            ProgressBlocker::WaitingForCommitQuorum { have, need } => {
+                write!(f, "waiting for commit quorum ({}/{})", have, need)
+            }
+            ProgressBlocker::SplitVotes => write!(f, "split votes"),
+        }
+    }
+}
+
+/// Result of adding a vote to a `PrevoteSet` or `CommitSet`.
+#[derive(Debug, Clone)]
+pub enum AddOutcome<V> {
+    /// First vote from this validator; counted.
+    New,
+    /// The validator already has a vote counted and this one does not
+    /// conflict with it (e.g. an identical resubmission).
+    Duplicate,
+    /// The validator signed a different block in the same round: the
+    /// vote already counted, then the conflicting one. Slashable evidence.
+    Equivocation(Box<V>, Box<V>),
+}
+
+impl<V> AddOutcome<V> {
+    /// Whether the vote was counted.
+    pub fn is_new(&self) -> bool {
+        matches!(self, AddOutcome::New)
+    }
+}
+
 /// A block proposal from the round leader.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proposal {
@@ -246,6 +491,11 @@ pub struct Proposal {
     pub block_hash: BlockHash,
     /// State root after executing transactions.
     pub state_root: StateRoot,
+    /// Earlier round with a prevote quorum for this block, if any. Lets
+    /// validators locked on another block since before that round unlock
+    /// (proof of lock change).
+    #[serde(default)]
+    pub pol_round: Option<u64>,
     /// Serialized transactions (opaque to consensus).
     pub transactions: Vec<u8>,
     /// Proposer's validator ID.
@@ -264,6 +514,13 @@ impl Proposal {
         payload.extend_from_slice(&self.prev_hash);
         payload.extend_from_slice(&self.block_hash);
         payload.extend_from_slice(&self.state_root);
+        match self.pol_round {
+            Some(round) => {
+                payload.push(1);
+                payload.extend_from_slice(&round.to_le_bytes());
+            }
+            None => payload.push(0),
+        }
         payload
     }
 }
@@ -353,10 +610,42 @@ impl FinalityCertificate {
             total_weight,
         }
     }
+
+    /// Weight of the distinct members of `validator_set` that signed.
+    ///
+    /// Unlike `total_weight`, this cannot be inflated by whoever built the
+    /// certificate. Signatures are not checked here.
+    pub fn recompute_weight(&self, validator_set: &ValidatorSet) -> u64 {
+        let signers: HashSet<&ValidatorId> = self.commits.iter().map(|c| &c.validator).collect();
+        signers
+            .into_iter()
+            .filter_map(|id| validator_set.get(id))
+            .map(|v| v.weight)
+            .sum()
+    }
+
+    /// Check the certificate against `validator_set`.
+    ///
+    /// Every commit's signature is checked against its validator's key,
+    /// signers must be distinct members of the set, and their recomputed
+    /// weight must reach quorum and match `total_weight`. Call this before
+    /// trusting a certificate received from a peer.
+    pub fn verify(&self, validator_set: &ValidatorSet) -> crate::Result<()> {
+        crate::verify::verify_certificate(self, validator_set)
+    }
+
+    /// Round in which the certificate's commits were cast.
+    pub fn round(&self) -> u64 {
+        self.commits.first().map(|c| c.round).unwrap_or(0)
+    }
 }
 
 /// Collection of prevotes for a round.
+///
+/// Serialized as the list of votes; the indexes are rebuilt on load, as
+/// JSON has no keys for them.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(into = "Vec<Prevote>", from = "Vec<Prevote>")]
 pub struct PrevoteSet {
     /// Prevotes indexed by validator.
     votes: HashMap<ValidatorId, Prevote>,
@@ -372,12 +661,18 @@ impl PrevoteSet {
         Self::default()
     }
 
-    /// Add a prevote, returns true if new.
-    pub fn add(&mut self, prevote: Prevote) -> bool {
+    /// Add a prevote.
+    ///
+    /// A second prevote from a validator is not counted. If it names a
+    /// different block than the first it is reported as equivocation.
+    pub fn add(&mut self, prevote: Prevote) -> AddOutcome<Prevote> {
         let validator = prevote.validator.clone();
 
-        if self.votes.contains_key(&validator) {
-            return false; // Duplicate
+        if let Some(existing) = self.votes.get(&validator) {
+            if existing.round == prevote.round && existing.block_hash != prevote.block_hash {
+                return AddOutcome::Equivocation(Box::new(existing.clone()), Box::new(prevote));
+            }
+            return AddOutcome::Duplicate;
         }
 
         match &prevote.block_hash {
@@ -393,7 +688,7 @@ impl PrevoteSet {
         }
 
         self.votes.insert(validator, prevote);
-        true
+        AddOutcome::New
     }
 
     /// Check if we have a vote from this validator.
@@ -401,6 +696,11 @@ impl PrevoteSet {
         self.votes.contains_key(validator)
     }
 
+    /// Get the vote counted for this validator.
+    pub fn get(&self, validator: &ValidatorId) -> Option<&Prevote> {
+        self.votes.get(validator)
+    }
+
     /// Get total weight voting for a specific block.
     pub fn weight_for_block(&self, block_hash: &BlockHash, validator_set: &ValidatorSet) -> u64 {
         self.by_block
@@ -415,22 +715,59 @@ impl PrevoteSet {
             .unwrap_or(0)
     }
 
+    /// Get total weight voting nil.
+    pub fn nil_weight(&self, validator_set: &ValidatorSet) -> u64 {
+        self.nil_votes
+            .iter()
+            .filter_map(|v| validator_set.get(v))
+            .map(|v| v.weight)
+            .sum()
+    }
+
     /// Get the block hash with the most votes (if any).
     pub fn leading_block(&self, validator_set: &ValidatorSet) -> Option<(BlockHash, u64)> {
         self.by_block
-            .iter()
-            .map(|(hash, _)| (*hash, self.weight_for_block(hash, validator_set)))
+            .keys()
+            .map(|hash| (*hash, self.weight_for_block(hash, validator_set)))
             .max_by_key(|(_, weight)| *weight)
     }
 
+    /// Total weight of all votes collected, including nil votes.
+    pub fn cast_weight(&self, validator_set: &ValidatorSet) -> u64 {
+        self.votes
+            .keys()
+            .filter_map(|v| validator_set.get(v))
+            .map(|v| v.weight)
+            .sum()
+    }
+
     /// Total votes collected.
     pub fn count(&self) -> usize {
         self.votes.len()
     }
 }
 
+impl From<PrevoteSet> for Vec<Prevote> {
+    fn from(set: PrevoteSet) -> Self {
+        set.votes.into_values().collect()
+    }
+}
+
+impl From<Vec<Prevote>> for PrevoteSet {
+    fn from(votes: Vec<Prevote>) -> Self {
+        let mut set = Self::new();
+        for vote in votes {
+            set.add(vote);
+        }
+        set
+    }
+}
+
 /// Collection of commits for a round.
+///
+/// Serialized as the list of commits, like `PrevoteSet`.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(into = "Vec<Commit>", from = "Vec<Commit>")]
 pub struct CommitSet {
     /// Commits indexed by validator.
     commits: HashMap<ValidatorId, Commit>,
@@ -444,12 +781,19 @@ impl CommitSet {
         Self::default()
     }
 
-    /// Add a commit, returns true if new.
-    pub fn add(&mut self, commit: Commit) -> bool {
+    /// Add a commit.
+    ///
+    /// Only a validator's first commit for the height is counted. A second
+    /// one for a different block in the same round is reported as
+    /// equivocation.
+    pub fn add(&mut self, commit: Commit) -> AddOutcome<Commit> {
         let validator = commit.validator.clone();
 
-        if self.commits.contains_key(&validator) {
-            return false; // Duplicate
+        if let Some(existing) = self.commits.get(&validator) {
+            if existing.round == commit.round && existing.block_hash != commit.block_hash {
+                return AddOutcome::Equivocation(Box::new(existing.clone()), Box::new(commit));
+            }
+            return AddOutcome::Duplicate;
         }
 
         let block_hash = commit.block_hash;
@@ -459,7 +803,7 @@ impl CommitSet {
             .push(commit.clone());
 
         self.commits.insert(validator, commit);
-        true
+        AddOutcome::New
     }
 
     /// Check if we have a commit from this validator.
@@ -467,6 +811,11 @@ impl CommitSet {
         self.commits.contains_key(validator)
     }
 
+    /// Get the commit counted for this validator.
+    pub fn get(&self, validator: &ValidatorId) -> Option<&Commit> {
+        self.commits.get(validator)
+    }
+
     /// Get total weight committing to a specific block.
     pub fn weight_for_block(&self, block_hash: &BlockHash, validator_set: &ValidatorSet) -> u64 {
         self.by_block
@@ -486,12 +835,45 @@ impl CommitSet {
         self.by_block.get(block_hash).cloned().unwrap_or_default()
     }
 
+    /// Get the block hash with the most commit weight (if any).
+    pub fn leading_block(&self, validator_set: &ValidatorSet) -> Option<(BlockHash, u64)> {
+        self.by_block
+            .keys()
+            .map(|hash| (*hash, self.weight_for_block(hash, validator_set)))
+            .max_by_key(|(_, weight)| *weight)
+    }
+
+    /// Total weight of all commits collected.
+    pub fn cast_weight(&self, validator_set: &ValidatorSet) -> u64 {
+        self.commits
+            .keys()
+            .filter_map(|v| validator_set.get(v))
+            .map(|v| v.weight)
+            .sum()
+    }
+
     /// Total commits collected.
     pub fn count(&self) -> usize {
         self.commits.len()
     }
 }
 
+impl From<CommitSet> for Vec<Commit> {
+    fn from(set: CommitSet) -> Self {
+        set.commits.into_values().collect()
+    }
+}
+
+impl From<Vec<Commit>> for CommitSet {
+    fn from(commits: Vec<Commit>) -> Self {
+        let mut set = Self::new();
+        for commit in commits {
+            set.add(commit);
+        }
+        set
+    }
+}
+
 /// Current state of a consensus round.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoundState {
@@ -515,6 +897,9 @@ pub struct RoundState {
     pub locked_block: Option<BlockHash>,
     /// Round we locked on.
     pub locked_round: Option<u64>,
+    /// Rounds of this height that reached a prevote quorum, by block.
+    #[serde(default)]
+    pub polka_rounds: BTreeMap<u64, BlockHash>,
 }
 
 impl RoundState {
@@ -531,9 +916,19 @@ impl RoundState {
             committed: false,
             locked_block: None,
             locked_round: None,
+            polka_rounds: BTreeMap::new(),
         }
     }
 
+    /// Whether anything happened at this height beyond starting it.
+    pub fn has_progress(&self) -> bool {
+        self.round > 0
+            || self.proposal.is_some()
+            || self.prevoted
+            || self.committed
+            || self.locked_block.is_some()
+    }
+
     /// Advance to next round (same height).
     pub fn next_round(&self) -> Self {
         Self {
@@ -547,6 +942,7 @@ impl RoundState {
             committed: false,
             locked_block: self.locked_block, // Carry forward lock
             locked_round: self.locked_round,
+            polka_rounds: self.polka_rounds.clone(),
         }
     }
 
@@ -565,6 +961,8 @@ pub enum ConsensusMessage {
     Prevote(Prevote),
     /// Commit.
     Commit(Commit),
+    /// Finality certificate (a bundle of quorum commits).
+    Certificate(FinalityCertificate),
 }
 
 impl ConsensusMessage {
@@ -574,6 +972,7 @@ impl ConsensusMessage {
             ConsensusMessage::Proposal(p) => p.height,
             ConsensusMessage::Prevote(p) => p.height,
             ConsensusMessage::Commit(c) => c.height,
+            ConsensusMessage::Certificate(c) => c.height,
         }
     }
 
@@ -583,6 +982,18 @@ impl ConsensusMessage {
             ConsensusMessage::Proposal(p) => p.round,
             ConsensusMessage::Prevote(p) => p.round,
             ConsensusMessage::Commit(c) => c.round,
+            ConsensusMessage::Certificate(c) => c.round(),
+        }
+    }
+
+    /// Get the validator that signed this message (certificates carry
+    /// many signers and report none).
+    pub fn signer(&self) -> Option<&ValidatorId> {
+        match self {
+            ConsensusMessage::Proposal(p) => Some(&p.proposer),
+            ConsensusMessage::Prevote(p) => Some(&p.validator),
+            ConsensusMessage::Commit(c) => Some(&c.validator),
+            ConsensusMessage::Certificate(_) => None,
         }
     }
 }
@@ -607,6 +1018,108 @@ mod tests {
         assert_eq!(vs.max_faulty(), 1);
     }
 
+    #[test]
+    fn leader_rotation_follows_weight() {
+        let vs = ValidatorSet::from_validators(vec![
+            Validator::with_weight([1u8; 32], 1),
+            Validator::with_weight([2u8; 32], 1),
+            Validator::with_weight([3u8; 32], 8),
+        ]);
+        let schedule = |vs: &ValidatorSet| -> Vec<[u8; 32]> {
+            (0..100).map(|round| vs.leader_for_round(round).pubkey).collect()
+        };
+
+        let leaders = schedule(&vs);
+        let count = |i: u8| leaders.iter().filter(|pk| **pk == [i; 32]).count();
+        assert_eq!((count(1), count(2), count(3)), (10, 10, 80));
+
+        // The heavy validator's turns are split around the light ones
+        let longest_run = leaders
+            .split(|pk| *pk != [3u8; 32])
+            .map(<[_]>::len)
+            .max()
+            .unwrap();
+        assert_eq!(longest_run, 4);
+
+        // A pure function of the round
+        let again = ValidatorSet::from_validators(vs.iter().cloned().collect());
+        assert_eq!(schedule(&again), leaders);
+
+        // Equal weights rotate one round per validator
+        let equal = ValidatorSet::from_validators(
+            (1u8..=3).map(|i| Validator::with_weight([i; 32], 5)).collect(),
+        );
+        let rotation: Vec<u8> = (0..6).map(|r| equal.leader_for_round(r).pubkey[0]).collect();
+        assert_eq!(rotation, vec![1, 2, 3, 1, 2, 3]);
+
+        // Lighter validators are spread between the heavier one's turns
+        let mixed = ValidatorSet::from_validators(vec![
+            Validator::with_weight([1u8; 32], 2),
+            Validator::with_weight([2u8; 32], 1),
+            Validator::with_weight([3u8; 32], 1),
+        ]);
+        let rotation: Vec<u8> = (0..4).map(|r| mixed.leader_for_round(r).pubkey[0]).collect();
+        assert_eq!(rotation, vec![1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn reachable_quorum_depends_on_available_weight() {
+        let vs = ValidatorSet::from_validators(vec![
+            Validator::with_weight([1u8; 32], 5),
+            Validator::with_weight([2u8; 32], 2),
+            Validator::with_weight([3u8; 32], 2),
+        ]);
+        // Quorum = 2*9/3 + 1 = 7
+        let available = |ids: &[u8]| ids.iter().map(|&i| ValidatorId([i; 32])).collect::<HashSet<_>>();
+
+        assert!(vs.reachable_quorum(&available(&[1, 2])));
+        assert!(!vs.reachable_quorum(&available(&[2, 3])));
+        // Unknown IDs add nothing
+        assert!(!vs.reachable_quorum(&available(&[1, 9])));
+    }
+
+    #[test]
+    fn validator_id_matches_account_address() {
+        let keypair = tev::Keypair::from_secret(&[5u8; 32]);
+        let pubkey = keypair.public_key();
+        let verifying_key = VerifyingKey::from_bytes(&pubkey).unwrap();
+
+        let address = mars::address_from_pubkey(&pubkey);
+        assert_eq!(ValidatorId::from_verifying_key(&verifying_key).0, address);
+        assert_eq!(ValidatorId::from_pubkey(&pubkey).0, address);
+        assert_eq!(Validator::new(pubkey).id.0, address);
+    }
+
+    #[test]
+    fn validator_set_json_round_trip() {
+        let vs = ValidatorSet::from_validators(vec![
+            Validator::with_weight([0xaa; 32], 1),
+            Validator::with_weight([0xbb; 32], 5),
+        ]);
+
+        let json = vs.to_json().unwrap();
+        assert!(json.contains(&hex::encode([0xaa; 32])));
+        assert!(json.contains(&hex::encode([0xbb; 32])));
+        assert!(json.contains("\"weight\": 5"));
+
+        let imported = ValidatorSet::from_json(&json).unwrap();
+        assert!(imported.is_indexed());
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported.total_weight(), 6);
+        assert_eq!(imported.get(&ValidatorId([0xbb; 32])).unwrap().weight, 5);
+        assert_eq!(imported.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_deserialized_set_is_indexed() {
+        let set = ValidatorSet::new(vec![[0xaa; 32], [0xbb; 32]]);
+        let json = serde_json::to_string(&set).unwrap();
+        let loaded: ValidatorSet = serde_json::from_str(&json).unwrap();
+
+        assert!(loaded.contains(&ValidatorId::from_pubkey(&[0xbb; 32])));
+        assert_eq!(loaded.total_weight(), 2);
+    }
+
     #[test]
     fn leader_rotation() {
         let vs = test_validator_set();
@@ -634,7 +1147,7 @@ mod tests {
                 validator: ValidatorId([i as u8; 32]),
                 signature: Signature64::default(),
             };
-            assert!(prevotes.add(prevote));
+            assert!(prevotes.add(prevote).is_new());
         }
 
         assert_eq!(prevotes.count(), 3);
@@ -653,13 +1166,49 @@ mod tests {
             signature: Signature64::default(),
         };
 
-        assert!(prevotes.add(prevote.clone()));
-        assert!(!prevotes.add(prevote)); // Duplicate
+        assert!(prevotes.add(prevote.clone()).is_new());
+        assert!(matches!(prevotes.add(prevote), AddOutcome::Duplicate));
+    }
+
+    #[test]
+    fn conflicting_votes_are_equivocation() {
+        let mut prevotes = PrevoteSet::new();
+        let vote = |block_hash| Prevote {
+            height: 1,
+            round: 0,
+            block_hash,
+            validator: ValidatorId([0u8; 32]),
+            signature: Signature64::default(),
+        };
+
+        assert!(prevotes.add(vote(Some([1u8; 32]))).is_new());
+        match prevotes.add(vote(None)) {
+            AddOutcome::Equivocation(first, second) => {
+                assert_eq!(first.block_hash, Some([1u8; 32]));
+                assert_eq!(second.block_hash, None);
+            }
+            other => panic!("expected equivocation, got {:?}", other),
+        }
+        // The first vote is still the one counted
+        assert_eq!(prevotes.count(), 1);
+
+        let mut commits = CommitSet::new();
+        let commit = |round, block_hash| Commit {
+            height: 1,
+            round,
+            block_hash,
+            validator: ValidatorId([0u8; 32]),
+            signature: Signature64::default(),
+        };
+        assert!(commits.add(commit(0, [1u8; 32])).is_new());
+        assert!(matches!(commits.add(commit(0, [1u8; 32])), AddOutcome::Duplicate));
+        assert!(matches!(commits.add(commit(1, [2u8; 32])), AddOutcome::Duplicate));
+        assert!(matches!(commits.add(commit(0, [2u8; 32])), AddOutcome::Equivocation(..)));
     }
 
     #[test]
     fn round_state_progression() {
-        let mut state = RoundState::new(1, 0);
+        let state = RoundState::new(1, 0);
         assert_eq!(state.phase, Phase::Propose);
 
         let next_round = state.next_round();
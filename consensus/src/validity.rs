@@ -0,0 +1,31 @@
+//! Application-level proposal validity.
+//!
+//! Consensus treats a proposal's transactions as opaque bytes, so it
+//! cannot tell whether the proposed `block_hash` and `state_root` really
+//! describe them. The embedding node supplies a `ProposalValidator` that
+//! decodes the contents and checks them; a proposal it rejects is
+//! prevoted nil, so the engine never commits to a hash that does not
+//! correspond to the proposed block.
+
+use crate::error::Result;
+use crate::types::Proposal;
+
+/// Checks a proposal's contents before the engine prevotes for it.
+///
+/// Called after the proposer and signature are verified, while the
+/// engine holds its round state lock; implementations must not call back
+/// into the engine.
+pub trait ProposalValidator: Send + Sync {
+    /// Return an error if the proposal is not a valid block.
+    fn validate(&self, proposal: &Proposal) -> Result<()>;
+}
+
+/// Validator that accepts every proposal.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AcceptAll;
+
+impl ProposalValidator for AcceptAll {
+    fn validate(&self, _proposal: &Proposal) -> Result<()> {
+        Ok(())
+    }
+}
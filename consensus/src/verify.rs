@@ -0,0 +1,284 @@
+//! Stateless signature verification for consensus messages.
+//!
+//! These checks depend only on the message and a validator set, so tooling
+//! and tests can verify messages without a running engine.
+
+use crate::error::{ConsensusError, Result};
+use crate::types::{
+    Commit, ConsensusMessage, FinalityCertificate, Prevote, Proposal, Signature64, ValidatorId,
+    ValidatorSet,
+};
+
+use ed25519_dalek::{Signature, Verifier};
+use std::collections::HashSet;
+
+/// Verify a proposal's signature against its proposer's key in `validator_set`.
+pub fn verify_proposal(proposal: &Proposal, validator_set: &ValidatorSet) -> Result<()> {
+    verify_signed(
+        validator_set,
+        &proposal.proposer,
+        &proposal.signature,
+        &proposal.signing_payload(),
+        "proposal",
+    )
+}
+
+/// Verify a prevote's signature against its validator's key in `validator_set`.
+pub fn verify_prevote(prevote: &Prevote, validator_set: &ValidatorSet) -> Result<()> {
+    verify_signed(
+        validator_set,
+        &prevote.validator,
+        &prevote.signature,
+        &prevote.signing_payload(),
+        "prevote",
+    )
+}
+
+/// Verify a commit's signature against its validator's key in `validator_set`.
+pub fn verify_commit(commit: &Commit, validator_set: &ValidatorSet) -> Result<()> {
+    verify_signed(
+        validator_set,
+        &commit.validator,
+        &commit.signature,
+        &commit.signing_payload(),
+        "commit",
+    )
+}
+
+/// Verify any consensus message against `validator_set`.
+pub fn verify_message(message: &ConsensusMessage, validator_set: &ValidatorSet) -> Result<()> {
+    match message {
+        ConsensusMessage::Proposal(proposal) => verify_proposal(proposal, validator_set),
+        ConsensusMessage::Prevote(prevote) => verify_prevote(prevote, validator_set),
+        ConsensusMessage::Commit(commit) => verify_commit(commit, validator_set),
+        ConsensusMessage::Certificate(certificate) => verify_certificate(certificate, validator_set),
+    }
+}
+
+/// Verify a finality certificate against `validator_set`.
+///
+/// Every commit must be for the certificate's height and block, signed by
+/// a distinct member of the set, and together they must reach quorum. The
+/// certificate's claimed weight must match the weight recomputed from them.
+pub fn verify_certificate(certificate: &FinalityCertificate, validator_set: &ValidatorSet) -> Result<()> {
+    let invalid = |reason: &str| ConsensusError::InvalidCertificate {
+        reason: reason.to_string(),
+    };
+
+    let mut signers = HashSet::new();
+    let mut weight = 0u64;
+
+    for commit in &certificate.commits {
+        if commit.height != certificate.height || commit.block_hash != certificate.block_hash {
+            return Err(invalid("commit for a different block"));
+        }
+        if !signers.insert(commit.validator.clone()) {
+            return Err(invalid("duplicate signer"));
+        }
+        verify_commit(commit, validator_set)?;
+        weight += validator_set.get(&commit.validator).map_or(0, |v| v.weight);
+    }
+
+    if weight < validator_set.quorum_threshold() {
+        return Err(invalid("commits do not reach quorum"));
+    }
+    if weight != certificate.total_weight {
+        return Err(invalid("claimed weight does not match commits"));
+    }
+
+    Ok(())
+}
+
+/// Check `signature` over `payload` by `signer`, who must be in the set.
+fn verify_signed(
+    validator_set: &ValidatorSet,
+    signer: &ValidatorId,
+    signature: &Signature64,
+    payload: &[u8],
+    message_type: &str,
+) -> Result<()> {
+    let validator = validator_set
+        .get(signer)
+        .ok_or_else(|| ConsensusError::UnknownValidator {
+            validator: signer.to_hex(),
+        })?;
+
+    let invalid = || ConsensusError::InvalidSignature {
+        message_type: message_type.to_string(),
+    };
+
+    let verifying_key = validator.verifying_key().ok_or_else(invalid)?;
+    let signature = Signature::from_bytes(signature.as_bytes());
+
+    verifying_key.verify(payload, &signature).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn setup() -> (SigningKey, ValidatorSet) {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let set = ValidatorSet::new(vec![key.verifying_key().to_bytes()]);
+        (key, set)
+    }
+
+    fn signed_proposal(key: &SigningKey) -> Proposal {
+        let mut proposal = Proposal {
+            height: 1,
+            round: 0,
+            prev_hash: [0u8; 32],
+            block_hash: [1u8; 32],
+            state_root: [2u8; 32],
+            pol_round: None,
+            transactions: vec![3, 4, 5],
+            proposer: ValidatorId::from_verifying_key(&key.verifying_key()),
+            signature: Signature64::default(),
+        };
+        let signature = key.sign(&proposal.signing_payload());
+        proposal.signature = Signature64::from_bytes(signature.to_bytes());
+        proposal
+    }
+
+    #[test]
+    fn valid_proposal_verifies() {
+        let (key, set) = setup();
+        assert!(verify_proposal(&signed_proposal(&key), &set).is_ok());
+    }
+
+    #[test]
+    fn tampered_proposal_rejected() {
+        let (key, set) = setup();
+        let mut proposal = signed_proposal(&key);
+        proposal.block_hash = [9u8; 32];
+
+        assert!(matches!(
+            verify_proposal(&proposal, &set),
+            Err(ConsensusError::InvalidSignature { .. })
+        ));
+    }
+
+    #[test]
+    fn proposal_from_outsider_rejected() {
+        let (_, set) = setup();
+        let outsider = SigningKey::from_bytes(&[8u8; 32]);
+
+        assert!(matches!(
+            verify_proposal(&signed_proposal(&outsider), &set),
+            Err(ConsensusError::UnknownValidator { .. })
+        ));
+    }
+
+    #[test]
+    fn certificate_with_inflated_weight_rejected() {
+        let (key, set) = setup();
+        let mut commit = Commit {
+            height: 1,
+            round: 0,
+            block_hash: [1u8; 32],
+            validator: ValidatorId::from_verifying_key(&key.verifying_key()),
+            signature: Signature64::default(),
+        };
+        commit.signature = Signature64::from_bytes(key.sign(&commit.signing_payload()).to_bytes());
+
+        let mut certificate = FinalityCertificate::new(1, [1u8; 32], vec![commit], 1);
+        assert_eq!(certificate.recompute_weight(&set), 1);
+        assert!(verify_certificate(&certificate, &set).is_ok());
+
+        certificate.total_weight = 10;
+        assert!(matches!(
+            verify_certificate(&certificate, &set),
+            Err(ConsensusError::InvalidCertificate { .. })
+        ));
+    }
+
+    /// Four equal validators and a certificate for block [1; 32] at height 1
+    /// signed by the first `signers` of them.
+    fn certificate_from(signers: usize) -> (ValidatorSet, FinalityCertificate) {
+        let keys: Vec<SigningKey> = (1u8..=4).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+        let set = ValidatorSet::new(keys.iter().map(|k| k.verifying_key().to_bytes()).collect());
+
+        let commits: Vec<Commit> = keys[..signers]
+            .iter()
+            .map(|key| {
+                let mut commit = Commit {
+                    height: 1,
+                    round: 0,
+                    block_hash: [1u8; 32],
+                    validator: ValidatorId::from_verifying_key(&key.verifying_key()),
+                    signature: Signature64::default(),
+                };
+                commit.signature =
+                    Signature64::from_bytes(key.sign(&commit.signing_payload()).to_bytes());
+                commit
+            })
+            .collect();
+
+        let certificate = FinalityCertificate::new(1, [1u8; 32], commits, signers as u64);
+        (set, certificate)
+    }
+
+    #[test]
+    fn quorum_certificate_verifies() {
+        let (set, certificate) = certificate_from(3);
+        assert!(certificate.verify(&set).is_ok());
+    }
+
+    #[test]
+    fn certificate_with_forged_signature_rejected() {
+        let (set, mut certificate) = certificate_from(3);
+        // Signed by the right validator's key, but over another block
+        let other_payload = Commit {
+            block_hash: [2u8; 32],
+            ..certificate.commits[1].clone()
+        }
+        .signing_payload();
+        let key = SigningKey::from_bytes(&[2u8; 32]);
+        certificate.commits[1].signature =
+            Signature64::from_bytes(key.sign(&other_payload).to_bytes());
+
+        assert!(matches!(
+            certificate.verify(&set),
+            Err(ConsensusError::InvalidSignature { .. })
+        ));
+    }
+
+    #[test]
+    fn certificate_one_vote_short_rejected() {
+        let (set, certificate) = certificate_from(2);
+        assert!(matches!(
+            certificate.verify(&set),
+            Err(ConsensusError::InvalidCertificate { .. })
+        ));
+    }
+
+    #[test]
+    fn votes_verify() {
+        let (key, set) = setup();
+        let validator = ValidatorId::from_verifying_key(&key.verifying_key());
+
+        let mut prevote = Prevote {
+            height: 1,
+            round: 0,
+            block_hash: Some([1u8; 32]),
+            validator: validator.clone(),
+            signature: Signature64::default(),
+        };
+        prevote.signature = Signature64::from_bytes(key.sign(&prevote.signing_payload()).to_bytes());
+        assert!(verify_prevote(&prevote, &set).is_ok());
+
+        let mut commit = Commit {
+            height: 1,
+            round: 0,
+            block_hash: [1u8; 32],
+            validator,
+            signature: Signature64::default(),
+        };
+        commit.signature = Signature64::from_bytes(key.sign(&commit.signing_payload()).to_bytes());
+        assert!(verify_commit(&commit, &set).is_ok());
+
+        commit.round = 1;
+        assert!(verify_commit(&commit, &set).is_err());
+    }
+}
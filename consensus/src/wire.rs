@@ -0,0 +1,97 @@
+//! Wire encoding for consensus messages.
+//!
+//! Decoding is strict: the input must be exactly one well-formed message
+//! no larger than the caller's limit. Truncated input and trailing bytes
+//! are both rejected, so a peer cannot get a partially decoded message
+//! past us.
+
+use crate::error::{ConsensusError, Result};
+use crate::types::ConsensusMessage;
+
+use bincode::Options;
+
+/// Encoding options shared by `encode_message` and `decode_message`.
+fn options(max_len: usize) -> impl Options {
+    bincode::options()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .with_limit(max_len as u64)
+}
+
+/// Encode a consensus message for the wire.
+pub fn encode_message(message: &ConsensusMessage) -> Result<Vec<u8>> {
+    options(usize::MAX)
+        .serialize(message)
+        .map_err(|e| ConsensusError::Serialization(e.to_string()))
+}
+
+/// Decode exactly one consensus message of at most `max_len` bytes.
+pub fn decode_message(bytes: &[u8], max_len: usize) -> Result<ConsensusMessage> {
+    if bytes.len() > max_len {
+        return Err(ConsensusError::MalformedMessage {
+            reason: format!("{} bytes exceeds limit of {}", bytes.len(), max_len),
+        });
+    }
+
+    options(max_len)
+        .deserialize(bytes)
+        .map_err(|e| ConsensusError::MalformedMessage {
+            reason: e.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Prevote, Signature64, ValidatorId};
+
+    const LIMIT: usize = 1024;
+
+    fn sample() -> ConsensusMessage {
+        ConsensusMessage::Prevote(Prevote {
+            height: 3,
+            round: 1,
+            block_hash: Some([7u8; 32]),
+            validator: ValidatorId([1u8; 32]),
+            signature: Signature64([2u8; 64]),
+        })
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = encode_message(&sample()).unwrap();
+        let decoded = decode_message(&bytes, LIMIT).unwrap();
+        assert_eq!(decoded.height(), 3);
+        assert_eq!(decoded.round(), 1);
+    }
+
+    #[test]
+    fn truncated_message_rejected() {
+        let bytes = encode_message(&sample()).unwrap();
+        for len in [0, 4, bytes.len() / 2, bytes.len() - 1] {
+            assert!(matches!(
+                decode_message(&bytes[..len], LIMIT),
+                Err(ConsensusError::MalformedMessage { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn trailing_garbage_rejected() {
+        let mut bytes = encode_message(&sample()).unwrap();
+        bytes.extend_from_slice(&[0xde, 0xad]);
+        assert!(matches!(
+            decode_message(&bytes, LIMIT),
+            Err(ConsensusError::MalformedMessage { .. })
+        ));
+    }
+
+    #[test]
+    fn oversized_message_rejected() {
+        let bytes = encode_message(&sample()).unwrap();
+        assert!(matches!(
+            decode_message(&bytes, bytes.len() - 1),
+            Err(ConsensusError::MalformedMessage { .. })
+        ));
+    }
+}
@@ -0,0 +1,29 @@
+//! Account addresses.
+//!
+//! Every 32-byte identity in the system — transaction senders and
+//! recipients, consensus validator IDs, and network node IDs — is
+//! derived from an Ed25519 public key through this module, so the same
+//! key always maps to the same address everywhere.
+
+/// A 32-byte account address.
+pub type Address = [u8; 32];
+
+/// Derive the canonical address for an Ed25519 public key.
+///
+/// The address is the public key itself. This keeps addresses
+/// verifiable against signatures without a lookup table.
+pub fn address_from_pubkey(pubkey: &[u8; 32]) -> Address {
+    *pubkey
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_deterministic() {
+        let pubkey = [7u8; 32];
+        assert_eq!(address_from_pubkey(&pubkey), address_from_pubkey(&pubkey));
+        assert_ne!(address_from_pubkey(&pubkey), address_from_pubkey(&[8u8; 32]));
+    }
+}
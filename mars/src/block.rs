@@ -5,6 +5,7 @@
 
 use crate::tx::Transaction;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// A blockchain block.
 ///
@@ -39,6 +40,16 @@ pub struct Block {
 }
 
 impl Block {
+    /// Version of the persisted block layout; bump when fields change.
+    ///
+    /// Version 2 added `Transaction::fee` and `Transaction::valid_until`.
+    /// The fee field first shipped still tagged version 1, so a version 1
+    /// block may hold either layout; both are refused as outdated now.
+    /// Version 3 length-prefixes transaction payloads in the hashed bytes.
+    /// Each changed block hashes, so older chains cannot be migrated, only
+    /// resynced.
+    pub const SCHEMA_VERSION: u32 = 3;
+
     /// Create a new block.
     pub fn new(
         height: u64,
@@ -61,19 +72,42 @@ impl Block {
         }
     }
 
-    /// Create the genesis block.
+    /// Create the genesis block with a zero timestamp.
     pub fn genesis() -> Self {
+        Self::genesis_at(0)
+    }
+
+    /// Create the genesis block for a chain started at `timestamp`.
+    ///
+    /// The timestamp is part of the genesis hash, so chains started at
+    /// different times have different genesis blocks.
+    pub fn genesis_at(timestamp: u64) -> Self {
         Self {
             height: 0,
             parent_hash: [0u8; 32],
             state_root: [0u8; 32],
-            timestamp: 0,
+            timestamp,
             txs: Vec::new(),
             producer: [0u8; 32],
             signature: vec![0u8; 64],
         }
     }
 
+    /// Create the genesis block of the chain named `chain_id`, started at
+    /// `timestamp`.
+    ///
+    /// The genesis block has no parent, so its `parent_hash` carries the
+    /// SHA-256 of the chain ID instead: chains sharing everything but the
+    /// ID still have different genesis hashes. An empty ID gives the same
+    /// block as `genesis_at`.
+    pub fn genesis_for(chain_id: &str, timestamp: u64) -> Self {
+        let mut genesis = Self::genesis_at(timestamp);
+        if !chain_id.is_empty() {
+            genesis.parent_hash = Sha256::digest(chain_id.as_bytes()).into();
+        }
+        genesis
+    }
+
     /// Get the bytes to be signed.
     pub fn signing_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
@@ -89,15 +123,9 @@ impl Block {
         bytes
     }
 
-    /// Compute block hash (simplified - use proper hash in production).
+    /// Compute the block hash: SHA-256 over `signing_bytes`.
     pub fn hash(&self) -> [u8; 32] {
-        let bytes = self.signing_bytes();
-        let mut hash = [0u8; 32];
-        // Simple hash for now - replace with proper crypto hash
-        for (i, byte) in bytes.iter().enumerate() {
-            hash[i % 32] ^= byte;
-        }
-        hash
+        Sha256::digest(self.signing_bytes()).into()
     }
 
     /// Set the signature for this block.
@@ -135,6 +163,39 @@ mod tests {
         assert_eq!(block1.hash(), block2.hash());
     }
 
+    #[test]
+    fn test_genesis_timestamp_changes_hash() {
+        assert_eq!(Block::genesis().hash(), Block::genesis_at(0).hash());
+
+        let a = Block::genesis_at(1_700_000_000);
+        let b = Block::genesis_at(1_700_000_001);
+        assert_eq!(a.timestamp, 1_700_000_000);
+        assert_ne!(a.hash(), b.hash());
+        assert_ne!(a.hash(), Block::genesis().hash());
+    }
+
+    #[test]
+    fn test_genesis_chain_id_changes_hash() {
+        assert_eq!(Block::genesis_for("", 5).hash(), Block::genesis_at(5).hash());
+
+        let a = Block::genesis_for("unykorn-dev", 5);
+        let b = Block::genesis_for("unykorn-test", 5);
+        assert!(a.is_genesis());
+        assert_ne!(a.hash(), b.hash());
+        assert_ne!(a.hash(), Block::genesis_at(5).hash());
+    }
+
+    #[test]
+    fn test_layout_matches_schema_version() {
+        // A layout change shows up here first: bump SCHEMA_VERSION with it
+        let tx = Transaction::with_payload([1u8; 32], [2u8; 32], 100, 0, vec![7u8; 3])
+            .with_fee(1)
+            .with_valid_until(9);
+        let block = Block::new(1, [0u8; 32], [0u8; 32], vec![tx], [3u8; 32]);
+        let encoded = bincode::serialize(&block).unwrap();
+        assert_eq!((Block::SCHEMA_VERSION, encoded.len()), (3, 371));
+    }
+
     #[test]
     fn test_block_with_transactions() {
         let tx = Transaction::new([1u8; 32], [2u8; 32], 100, 0);
@@ -144,4 +205,36 @@ mod tests {
         assert_eq!(block.tx_count(), 1);
         assert!(!block.is_genesis());
     }
+
+    #[test]
+    fn test_hash_commits_to_transaction_amounts() {
+        let block_with = |amount| {
+            let txs = vec![
+                Transaction::new([1u8; 32], [2u8; 32], amount, 0),
+                Transaction::new([2u8; 32], [1u8; 32], 5, 0),
+            ];
+            Block::new(1, [0u8; 32], [0u8; 32], txs, [3u8; 32])
+        };
+
+        let a = block_with(100);
+        let b = block_with(101);
+        assert_ne!(a.hash(), b.hash());
+        assert_eq!(a.hash(), a.hash());
+        assert_eq!(a.hash(), block_with(100).hash());
+    }
+
+    #[test]
+    fn test_hash_separates_transaction_payloads() {
+        // Without a length prefix, [a + b's fields, c] and [a, b + c] hash
+        // the same bytes
+        let c = Transaction::new([5u8; 32], [6u8; 32], 7, 0);
+        let b = Transaction::with_payload([3u8; 32], [4u8; 32], 5, 0, c.signing_bytes());
+        let b_fields = b.signing_bytes()[..96].to_vec();
+        let a = |payload| Transaction::with_payload([1u8; 32], [2u8; 32], 1, 0, payload);
+
+        let x = Block::new(1, [0u8; 32], [0u8; 32], vec![a(b_fields), c.clone()], [9u8; 32]);
+        let y = Block { txs: vec![a(Vec::new()), b], ..x.clone() };
+        assert_ne!(x.txs, y.txs);
+        assert_ne!(x.hash(), y.hash());
+    }
 }
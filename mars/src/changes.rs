@@ -0,0 +1,125 @@
+//! Per-block state change sets.
+//!
+//! A change set records the final balance and nonce of every account a
+//! block modified. Light clients and fast sync can rebuild state by
+//! applying change sets in height order instead of re-executing
+//! transactions.
+
+use crate::{Address, Block, State};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// An account's values after a block.
+///
+/// Zero balance and nonce mean the block removed the account (see
+/// `RuntimeConfig::existential_deposit`).
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountChange {
+    /// New balance
+    pub balance: u64,
+
+    /// New nonce
+    pub nonce: u64,
+}
+
+/// The accounts changed by one block.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StateChangeSet {
+    /// Height of the block that produced the changes
+    pub height: u64,
+
+    /// Changed accounts, ordered by address
+    pub changes: BTreeMap<Address, AccountChange>,
+}
+
+impl StateChangeSet {
+    /// Compute the changes between `before` and `after` for `touched` accounts.
+    ///
+    /// Accounts whose balance and nonce are both unchanged are left out.
+    pub fn between<'a>(
+        before: &State,
+        after: &State,
+        touched: impl IntoIterator<Item = &'a Address>,
+    ) -> Self {
+        let changes = touched
+            .into_iter()
+            .filter(|address| {
+                before.balance(address) != after.balance(address)
+                    || before.nonce(address) != after.nonce(address)
+            })
+            .map(|address| {
+                let change = AccountChange {
+                    balance: after.balance(address),
+                    nonce: after.nonce(address),
+                };
+                (*address, change)
+            })
+            .collect();
+
+        Self {
+            height: after.height,
+            changes,
+        }
+    }
+
+    /// Compute the changes `block` made, given the state before and after it.
+    pub fn for_block(before: &State, after: &State, block: &Block) -> Self {
+        let mut touched: Vec<Address> = block.txs.iter().flat_map(|tx| [tx.from, tx.to]).collect();
+        touched.push(block.producer); // Collects the fees
+        Self::between(before, after, &touched)
+    }
+
+    /// Apply the changes to `state`, advancing it to this change set's height.
+    ///
+    /// The state root is recomputed afterwards.
+    pub fn apply_to(&self, state: &mut State) {
+        for (address, change) in &self.changes {
+            if change.balance == 0 && change.nonce == 0 {
+                state.remove_account(address);
+                continue;
+            }
+            state.set_balance(address, change.balance);
+            // Nonces are only stored once used, as in normal execution
+            if change.nonce != state.nonce(address) {
+                state.nonces.insert(*address, change.nonce);
+            }
+        }
+        state.height = self.height;
+        state.compute_state_root();
+    }
+
+    /// Number of changed accounts.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Check if the block changed no accounts.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unchanged_accounts_left_out() {
+        let mut before = State::new();
+        before.set_balance(&[1u8; 32], 10);
+        before.set_balance(&[2u8; 32], 5);
+
+        let mut after = before.clone();
+        after.height = 1;
+        after.set_balance(&[1u8; 32], 7);
+
+        let changes = StateChangeSet::between(&before, &after, &[[1u8; 32], [2u8; 32]]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes.changes[&[1u8; 32]], AccountChange { balance: 7, nonce: 0 });
+
+        let mut replayed = before.clone();
+        changes.apply_to(&mut replayed);
+        assert_eq!(replayed.balance(&[1u8; 32]), 7);
+        assert_eq!(replayed.height, 1);
+    }
+}
@@ -11,10 +11,42 @@ pub enum RuntimeError {
     #[error("invalid transaction: {reason}")]
     InvalidTransaction { reason: String },
 
+    /// Transaction offers less than the node's minimum fee
+    #[error("fee too low: offered {fee}, minimum is {min_fee}")]
+    FeeTooLow { fee: u64, min_fee: u64 },
+
+    /// The mempool is full and the transaction does not outbid anything
+    #[error("mempool full: {capacity} transactions pending")]
+    MempoolFull { capacity: usize },
+
+    /// Transaction is past its `valid_until` height
+    #[error("transaction expired: valid until #{valid_until}, block is #{height}")]
+    Expired { valid_until: u64, height: u64 },
+
+    /// Transfer would create an account below the existential deposit
+    #[error("amount {amount} would create an account below the existential deposit of {minimum}")]
+    BelowExistentialDeposit { amount: u64, minimum: u64 },
+
+    /// Transaction sends to an address reserved against transfers
+    #[error("recipient {} is a reserved address", hex(.address))]
+    ReservedRecipient { address: [u8; 32] },
+
     /// Block validation failed
     #[error("invalid block: {reason}")]
     InvalidBlock { reason: String },
 
+    /// Recomputed state root does not match the block's claim
+    #[error(
+        "state root mismatch: block claims {}, computed {} ({} accounts touched)",
+        hex(.claimed), hex(.computed), .accounts.len()
+    )]
+    StateRootMismatch {
+        claimed: [u8; 32],
+        computed: [u8; 32],
+        /// Accounts the block's transactions touched, in first-touch order
+        accounts: Vec<[u8; 32]>,
+    },
+
     /// State transition failed
     #[error("state transition failed: {reason}")]
     StateTransitionFailed { reason: String },
@@ -23,7 +55,16 @@ pub enum RuntimeError {
     #[error("block height mismatch: expected {expected}, got {got}")]
     HeightMismatch { expected: u64, got: u64 },
 
+    /// Rollback target is below the oldest block the undo log still holds
+    #[error("cannot roll back to #{target}: undo log only reaches #{earliest}")]
+    RollbackTooDeep { target: u64, earliest: u64 },
+
     /// Duplicate transaction detected
     #[error("duplicate transaction: nonce {nonce} already used")]
     DuplicateNonce { nonce: u64 },
 }
+
+/// Lowercase hex for error messages.
+fn hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
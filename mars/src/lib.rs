@@ -14,14 +14,21 @@
 //! If MARS says "no", the network does not matter.
 //! Every change to reality passes through this runtime.
 
+pub mod address;
 pub mod state;
 pub mod tx;
 pub mod block;
 pub mod runtime;
 pub mod error;
+pub mod changes;
+pub mod merkle;
+pub mod undo;
 
+pub use address::{address_from_pubkey, Address};
 pub use state::State;
 pub use tx::Transaction;
 pub use block::Block;
-pub use runtime::Runtime;
+pub use runtime::{EstimatedInclusion, Runtime, RuntimeConfig};
 pub use error::RuntimeError;
+pub use changes::{AccountChange, StateChangeSet};
+pub use merkle::{verify_state_proof, MerkleProof};
@@ -0,0 +1,143 @@
+//! Merkle tree over account state.
+//!
+//! The state root is the root of a binary SHA-256 Merkle tree whose leaves
+//! are the accounts, one per address in address order. A `MerkleProof`
+//! lets a light client check one account's balance and nonce against a
+//! block's `state_root` without the rest of the state.
+//!
+//! Leaves and inner nodes are hashed under different prefixes, so a leaf
+//! can never pass for an inner node. A node without a sibling moves up a
+//! level unchanged rather than being paired with itself.
+
+use crate::Address;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefix of a leaf hash.
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix of an inner node hash.
+const NODE_PREFIX: u8 = 0x01;
+
+/// One level of a Merkle proof: the sibling to combine with.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    /// The sibling node's hash
+    pub sibling: [u8; 32],
+
+    /// Whether the sibling is the left-hand node
+    pub sibling_on_left: bool,
+}
+
+/// Proof that an account is a leaf of a state root.
+///
+/// Levels where the path had no sibling are omitted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Siblings from the leaf up to the root
+    pub steps: Vec<ProofStep>,
+}
+
+/// Hash an account into a leaf.
+pub fn leaf_hash(address: &Address, balance: u64, nonce: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(address);
+    hasher.update(balance.to_le_bytes());
+    hasher.update(nonce.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Hash two child nodes into their parent.
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The level above `level`.
+fn parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+/// Root of the tree over `leaves`; all zeros when there are none.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+    level[0]
+}
+
+/// Proof for the leaf at `index`, or `None` if there is no such leaf.
+pub fn merkle_proof(leaves: &[[u8; 32]], mut index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut steps = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        if sibling < level.len() {
+            steps.push(ProofStep {
+                sibling: level[sibling],
+                sibling_on_left: sibling < index,
+            });
+        }
+        level = parent_level(&level);
+        index /= 2;
+    }
+
+    Some(MerkleProof { steps })
+}
+
+/// Check that `address` holds `balance` and `nonce` under state root `root`.
+pub fn verify_state_proof(
+    root: &[u8; 32],
+    address: &Address,
+    balance: u64,
+    nonce: u64,
+    proof: &MerkleProof,
+) -> bool {
+    let computed = proof.steps.iter().fold(leaf_hash(address, balance, nonce), |node, step| {
+        if step.sibling_on_left {
+            node_hash(&step.sibling, &node)
+        } else {
+            node_hash(&node, &step.sibling)
+        }
+    });
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_leaf_proves_for_odd_sizes() {
+        for count in 1..=9u8 {
+            let leaves: Vec<[u8; 32]> = (0..count).map(|i| leaf_hash(&[i; 32], i as u64, 0)).collect();
+            let root = merkle_root(&leaves);
+
+            for i in 0..count {
+                let proof = merkle_proof(&leaves, i as usize).unwrap();
+                assert!(verify_state_proof(&root, &[i; 32], i as u64, 0, &proof));
+                assert!(!verify_state_proof(&root, &[i; 32], i as u64 + 1, 0, &proof));
+            }
+            assert!(merkle_proof(&leaves, count as usize).is_none());
+        }
+    }
+}
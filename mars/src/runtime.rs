@@ -11,16 +11,58 @@
 //! - No networking or disk IO
 //! - Pure functions for state transitions
 
-use crate::{Block, RuntimeError, State, Transaction};
+use crate::undo::{UndoLog, UndoRecord};
+use crate::{Address, Block, RuntimeError, State, StateChangeSet, Transaction};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// Default tolerance for block timestamps ahead of local time (seconds).
+pub const DEFAULT_MAX_TIMESTAMP_SKEW: u64 = 15;
+
+/// Default maximum number of transactions in one block.
+pub const DEFAULT_MAX_BLOCK_TXS: usize = 10_000;
+
+/// Default maximum number of pending transactions.
+pub const DEFAULT_MAX_MEMPOOL: usize = 50_000;
+
+/// Default number of recent blocks that can be rolled back.
+pub const DEFAULT_MAX_UNDO_DEPTH: usize = 64;
+
+/// Default interval between produced blocks (seconds).
+pub const DEFAULT_BLOCK_INTERVAL: u64 = 3;
+
+/// Rules every node of a chain must agree on to reach the same state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// Smallest balance an account may be left with (0 = no minimum).
+    ///
+    /// Transfers creating an account with less are refused, and senders
+    /// left with less at the end of a block are reaped: the rest of their
+    /// balance is burned and the account removed, nonce included, so it
+    /// takes no space in state. Funded again, the account starts over at
+    /// nonce 0, and transactions it signed before being reaped become valid
+    /// again; only a `valid_until` height keeps them from being replayed.
+    pub existential_deposit: u64,
+}
+
+/// Where a pending transaction is expected to land.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EstimatedInclusion {
+    /// Full blocks that will be produced before the one including it
+    pub blocks_ahead: u64,
+
+    /// Rough wait until inclusion, assuming blocks arrive on schedule (seconds)
+    pub approx_seconds: u64,
+}
 
 /// The core runtime execution engine.
 ///
 /// # Usage
 ///
 /// ```rust
-/// use mars::Runtime;
+/// use mars::{Runtime, RuntimeConfig};
 ///
-/// let mut runtime = Runtime::new();
+/// let mut runtime = Runtime::new(RuntimeConfig::default());
 /// // Submit transactions, produce blocks, etc.
 /// ```
 pub struct Runtime {
@@ -32,37 +74,229 @@ pub struct Runtime {
 
     /// Last finalized block hash
     last_block_hash: [u8; 32],
+
+    /// Timestamp of the last finalized block
+    last_block_timestamp: u64,
+
+    /// How far ahead of local time a block timestamp may be (seconds)
+    max_timestamp_skew: u64,
+
+    /// Producers allowed to author blocks (empty = any producer)
+    authorized_producers: HashSet<[u8; 32]>,
+
+    /// Smallest fee accepted into the mempool
+    min_fee: u64,
+
+    /// Destinations transfers may not be sent to (empty = none)
+    reserved_addresses: HashSet<Address>,
+
+    /// Most transactions taken into one block
+    max_block_txs: usize,
+
+    /// Most transactions held pending
+    max_mempool: usize,
+
+    /// Expected interval between blocks (seconds)
+    block_interval: u64,
+
+    /// Pre-images of the most recent blocks, for `rollback_to`
+    undo: UndoLog,
+
+    /// Chain rules
+    config: RuntimeConfig,
 }
 
 impl Runtime {
     /// Create a new runtime with genesis state.
-    pub fn new() -> Self {
+    pub fn new(config: RuntimeConfig) -> Self {
         let genesis = Block::genesis();
         Self {
             state: State::new(),
             mempool: Vec::new(),
             last_block_hash: genesis.hash(),
+            last_block_timestamp: genesis.timestamp,
+            max_timestamp_skew: DEFAULT_MAX_TIMESTAMP_SKEW,
+            authorized_producers: HashSet::new(),
+            min_fee: 0,
+            reserved_addresses: HashSet::new(),
+            max_block_txs: DEFAULT_MAX_BLOCK_TXS,
+            max_mempool: DEFAULT_MAX_MEMPOOL,
+            block_interval: DEFAULT_BLOCK_INTERVAL,
+            undo: UndoLog::new(DEFAULT_MAX_UNDO_DEPTH),
+            config,
         }
     }
 
     /// Create a runtime with existing state (for restart recovery).
-    pub fn with_state(state: State, last_block_hash: [u8; 32]) -> Self {
+    ///
+    /// `parent` is the last applied block; new blocks must extend it.
+    pub fn with_state(state: State, parent: &Block, config: RuntimeConfig) -> Self {
         Self {
             state,
             mempool: Vec::new(),
-            last_block_hash,
+            last_block_hash: parent.hash(),
+            last_block_timestamp: parent.timestamp,
+            max_timestamp_skew: DEFAULT_MAX_TIMESTAMP_SKEW,
+            authorized_producers: HashSet::new(),
+            min_fee: 0,
+            reserved_addresses: HashSet::new(),
+            max_block_txs: DEFAULT_MAX_BLOCK_TXS,
+            max_mempool: DEFAULT_MAX_MEMPOOL,
+            block_interval: DEFAULT_BLOCK_INTERVAL,
+            undo: UndoLog::new(DEFAULT_MAX_UNDO_DEPTH),
+            config,
         }
     }
 
+    /// The chain rules this runtime executes under.
+    pub fn config(&self) -> RuntimeConfig {
+        self.config
+    }
+
+    /// Set how far ahead of local time a block timestamp may be (seconds).
+    pub fn set_max_timestamp_skew(&mut self, seconds: u64) {
+        self.max_timestamp_skew = seconds;
+    }
+
+    /// Set the smallest fee accepted into the mempool.
+    pub fn set_min_fee(&mut self, min_fee: u64) {
+        self.min_fee = min_fee;
+    }
+
+    /// Refuse transactions sending to any of `addresses`.
+    ///
+    /// Catches accidental burns, e.g. to the all-zero address. Empty by
+    /// default, leaving every destination allowed. Block validation applies
+    /// the same check, so every node of a chain needs the same set.
+    pub fn set_reserved_addresses(&mut self, addresses: impl IntoIterator<Item = Address>) {
+        self.reserved_addresses = addresses.into_iter().collect();
+    }
+
+    /// Set the most transactions taken into one block.
+    ///
+    /// Transactions past the limit stay pending for later blocks.
+    pub fn set_max_block_txs(&mut self, max_block_txs: usize) {
+        self.max_block_txs = max_block_txs.max(1);
+    }
+
+    /// Set the most transactions held pending.
+    ///
+    /// When full, a new transaction only gets in by outbidding the cheapest
+    /// evictable one (see `submit_transaction`).
+    pub fn set_max_mempool(&mut self, max_mempool: usize) {
+        self.max_mempool = max_mempool.max(1);
+    }
+
+    /// Set how many recent blocks `rollback_to` can undo.
+    pub fn set_max_undo_depth(&mut self, depth: usize) {
+        self.undo.set_max_depth(depth);
+    }
+
+    /// Set the expected interval between blocks (seconds).
+    ///
+    /// Only used to estimate inclusion times.
+    pub fn set_block_interval(&mut self, seconds: u64) {
+        self.block_interval = seconds;
+    }
+
+    /// Restrict block production to the given producer keys.
+    ///
+    /// An empty set leaves block production open to any producer.
+    pub fn set_authorized_producers(&mut self, producers: impl IntoIterator<Item = [u8; 32]>) {
+        self.authorized_producers = producers.into_iter().collect();
+    }
+
+    /// Check whether a producer may author blocks.
+    pub fn is_authorized_producer(&self, producer: &[u8; 32]) -> bool {
+        self.authorized_producers.is_empty() || self.authorized_producers.contains(producer)
+    }
+
     /// Submit a transaction to the mempool.
     ///
-    /// Returns an error if the transaction is invalid.
+    /// Returns an error if the transaction is invalid or offers less than
+    /// the minimum fee. When the mempool is full, the cheapest transaction
+    /// that is the last pending one of another sender is evicted if the new
+    /// one pays more; otherwise `MempoolFull` is returned. Only a sender's
+    /// last transaction is evictable, so no sender is left with a nonce gap.
     pub fn submit_transaction(&mut self, tx: Transaction) -> Result<(), RuntimeError> {
+        if tx.fee < self.min_fee {
+            return Err(RuntimeError::FeeTooLow {
+                fee: tx.fee,
+                min_fee: self.min_fee,
+            });
+        }
         self.validate_transaction(&tx)?;
+
+        if self.mempool.len() >= self.max_mempool {
+            match self.eviction_candidate(&tx.from) {
+                Some(index) if self.mempool[index].fee < tx.fee => {
+                    self.mempool.remove(index);
+                }
+                _ => return Err(RuntimeError::MempoolFull { capacity: self.max_mempool }),
+            }
+        }
         self.mempool.push(tx);
         Ok(())
     }
 
+    /// Index of the cheapest pending transaction that is its sender's last
+    /// and not from `exclude`; the latest such on ties.
+    fn eviction_candidate(&self, exclude: &Address) -> Option<usize> {
+        let mut tails: HashMap<Address, usize> = HashMap::new();
+        for (index, tx) in self.mempool.iter().enumerate() {
+            tails.insert(tx.from, index);
+        }
+        tails
+            .into_iter()
+            .filter(|(from, _)| from != exclude)
+            .min_by_key(|&(_, index)| (self.mempool[index].fee, Reverse(index)))
+            .map(|(_, index)| index)
+    }
+
+    /// Submit a bundle of transactions to the mempool, in order.
+    ///
+    /// Each transaction is validated as if the earlier ones were already
+    /// pending. If any is rejected, none of the bundle is kept and the
+    /// error for the first rejected transaction is returned.
+    pub fn submit_bundle(&mut self, txs: Vec<Transaction>) -> Result<(), RuntimeError> {
+        // Submissions may evict, so keep the whole mempool to roll back to
+        let pending = self.mempool.clone();
+        for tx in txs {
+            if let Err(e) = self.submit_transaction(tx) {
+                self.mempool = pending;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore previously pending transactions (e.g. after a restart).
+    ///
+    /// Each transaction is re-validated against the current state in order;
+    /// transactions that are no longer valid are dropped.
+    ///
+    /// Returns the number of transactions that were restored.
+    pub fn restore_mempool(&mut self, txs: Vec<Transaction>) -> usize {
+        let mut restored = 0;
+        for tx in txs {
+            if self.submit_transaction(tx).is_ok() {
+                restored += 1;
+            }
+        }
+        restored
+    }
+
+    /// Re-validate pending transactions against the current state.
+    ///
+    /// Transactions that are no longer valid, such as those included in a
+    /// block applied with `apply_block`, are dropped. Returns the number
+    /// of transactions dropped.
+    pub fn prune_mempool(&mut self) -> usize {
+        let pending = std::mem::take(&mut self.mempool);
+        let total = pending.len();
+        total - self.restore_mempool(pending)
+    }
+
     /// Validate a transaction against current state.
     ///
     /// # Checks
@@ -70,9 +304,33 @@ impl Runtime {
     /// - Sender has sufficient balance
     /// - Nonce matches expected value (accounting for pending mempool txs)
     /// - Amount is non-zero
+    /// - Recipient is not a reserved address
+    /// - A new recipient receives at least the existential deposit
+    /// - The next block is not past its `valid_until` height
     pub fn validate_transaction(&self, tx: &Transaction) -> Result<(), RuntimeError> {
-        // Count pending transactions from the same sender in mempool
-        let pending_count = self.mempool.iter()
+        self.validate_after(tx, &self.mempool)
+    }
+
+    /// Validate a transaction as if `pending` were applied before it.
+    fn validate_after(&self, tx: &Transaction, pending: &[Transaction]) -> Result<(), RuntimeError> {
+        // Checked against the next block, the earliest that could include it
+        let height = self.state.height + 1;
+        if tx.is_expired_at(height) {
+            return Err(RuntimeError::Expired { valid_until: tx.valid_until, height });
+        }
+        if self.reserved_addresses.contains(&tx.to) {
+            return Err(RuntimeError::ReservedRecipient { address: tx.to });
+        }
+
+        // Judged by the state before the block, not by earlier transfers
+        // in it, so the outcome cannot depend on how a block is ordered
+        let minimum = self.config.existential_deposit;
+        if tx.amount < minimum && !self.state.exists(&tx.to) {
+            return Err(RuntimeError::BelowExistentialDeposit { amount: tx.amount, minimum });
+        }
+
+        // Count pending transactions from the same sender
+        let pending_count = pending.iter()
             .filter(|t| t.from == tx.from)
             .count() as u64;
 
@@ -82,20 +340,23 @@ impl Runtime {
             return Err(RuntimeError::DuplicateNonce { nonce: tx.nonce });
         }
 
-        // Calculate pending outgoing amount
-        let pending_amount: u64 = self.mempool.iter()
+        // Calculate pending outgoing amount, fees included
+        let pending_amount: u64 = pending.iter()
             .filter(|t| t.from == tx.from)
-            .map(|t| t.amount)
+            .map(|t| t.amount.saturating_add(t.fee))
             .sum();
 
-        // Check balance (account for pending transactions)
+        // Check balance covers amount and fee (account for pending transactions)
+        let needed = tx.amount.checked_add(tx.fee).ok_or_else(|| RuntimeError::InvalidTransaction {
+            reason: "amount plus fee overflows".to_string(),
+        })?;
         let balance = self.state.balance(&tx.from);
         let available = balance.saturating_sub(pending_amount);
-        if available < tx.amount {
+        if available < needed {
             return Err(RuntimeError::InvalidTransaction {
                 reason: format!(
                     "insufficient balance: have {}, need {}",
-                    available, tx.amount
+                    available, needed
                 ),
             });
         }
@@ -103,43 +364,113 @@ impl Runtime {
         Ok(())
     }
 
-    /// Apply a single transaction to state.
+    /// Apply a single transaction to state, paying its fee to `producer`.
+    fn apply_transaction(&mut self, tx: &Transaction, producer: &Address) -> Result<(), RuntimeError> {
+        Self::apply_transaction_to(&mut self.state, tx, producer)
+    }
+
+    /// Apply a single transaction to `state`, paying its fee to `producer`.
     ///
     /// This is a pure function - same inputs always produce same outputs.
-    fn apply_transaction(&mut self, tx: &Transaction) -> Result<(), RuntimeError> {
-        // Debit sender
-        let sender_balance = self.state.balance(&tx.from);
-        self.state.set_balance(&tx.from, sender_balance - tx.amount);
+    fn apply_transaction_to(state: &mut State, tx: &Transaction, producer: &Address) -> Result<(), RuntimeError> {
+        // Debit sender for amount and fee
+        let sender_balance = state.balance(&tx.from);
+        state.set_balance(&tx.from, sender_balance - tx.amount - tx.fee);
 
         // Credit recipient
-        let recipient_balance = self.state.balance(&tx.to);
-        self.state.set_balance(&tx.to, recipient_balance + tx.amount);
+        let recipient_balance = state.balance(&tx.to);
+        state.set_balance(&tx.to, recipient_balance + tx.amount);
+
+        // Credit the block producer with the fee
+        if tx.fee > 0 {
+            let producer_balance = state.balance(producer);
+            state.set_balance(producer, producer_balance + tx.fee);
+        }
 
         // Increment sender nonce
-        self.state.increment_nonce(&tx.from);
+        state.increment_nonce(&tx.from);
 
         Ok(())
     }
 
+    /// Reap the senders of `txs` left below `minimum`, at the end of a block.
+    ///
+    /// The account is removed with its nonce (see `RuntimeConfig`).
+    fn reap_senders(state: &mut State, txs: &[Transaction], minimum: u64) {
+        for tx in txs {
+            if state.balance(&tx.from) < minimum {
+                state.remove_account(&tx.from);
+            }
+        }
+    }
+
+    /// Drop pending transactions of senders `block` reaped.
+    ///
+    /// They were validated against a balance that reaping burned.
+    fn drop_reaped_pending(&mut self, block: &Block) {
+        if self.config.existential_deposit == 0 {
+            return;
+        }
+        let reaped: HashSet<Address> = block
+            .txs
+            .iter()
+            .map(|tx| tx.from)
+            .filter(|address| self.state.balance(address) == 0)
+            .collect();
+        if reaped.is_empty() {
+            return;
+        }
+        self.mempool.retain(|tx| !reaped.contains(&tx.from));
+    }
+
     /// Produce a new block from pending transactions.
     ///
-    /// This drains the mempool and creates a block at the next height.
+    /// Pending transactions are taken highest fee first, each sender's in
+    /// nonce order, up to the per-block limit; the rest stay pending.
+    /// Transactions below the minimum fee or past their `valid_until`
+    /// height are dropped, along with any later transactions from the same
+    /// sender that depend on their nonce.
     pub fn produce_block(&mut self, producer: [u8; 32]) -> Block {
-        // Take all mempool transactions
-        let txs: Vec<Transaction> = self.mempool.drain(..).collect();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.produce_block_at(producer, now)
+    }
+
+    /// Produce a block like `produce_block`, stamped `timestamp`.
+    ///
+    /// For reproducible chains, such as replay fixtures.
+    pub fn produce_block_at(&mut self, producer: [u8; 32], timestamp: u64) -> Block {
+        // Take the best-paying transactions that meet the fee floor, up to
+        // the limit; the others that meet it stay pending, in mempool order
+        let order = self.canonical_order();
+        let mut slots: Vec<Option<Transaction>> = self.mempool.drain(..).map(Some).collect();
+        let txs: Vec<Transaction> = order
+            .iter()
+            .take(self.max_block_txs)
+            .filter_map(|&index| slots[index].take())
+            .collect();
+        let mut remaining: Vec<usize> = order.into_iter().skip(self.max_block_txs).collect();
+        remaining.sort_unstable();
+        self.mempool = remaining.into_iter().filter_map(|index| slots[index].take()).collect();
+
+        // Save what the block will change, for rollback
+        let before = self.state.clone();
 
         // Apply all transactions
         for tx in &txs {
             // Transactions were already validated on submission
-            let _ = self.apply_transaction(tx);
+            let _ = self.apply_transaction(tx, &producer);
         }
+        Self::reap_senders(&mut self.state, &txs, self.config.existential_deposit);
 
         // Update state
         self.state.height += 1;
         self.state.compute_state_root();
 
         // Create block
-        let block = Block::new(
+        let mut block = Block::new(
             self.state.height,
             self.last_block_hash,
             self.state.state_root,
@@ -147,17 +478,50 @@ impl Runtime {
             producer,
         );
 
+        // Never go backwards, even if the local clock does
+        block.timestamp = timestamp.max(self.last_block_timestamp);
+
+        self.undo.push(UndoRecord::capture(&before, self.last_block_hash, self.last_block_timestamp, &block));
         self.last_block_hash = block.hash();
+        self.last_block_timestamp = block.timestamp;
+        self.drop_reaped_pending(&block);
         block
     }
 
+    /// Build the block `produce_block` would, without changing anything.
+    ///
+    /// For proposing a block to consensus: once it is final, apply it with
+    /// `apply_block` and drop its transactions with `prune_mempool`.
+    pub fn build_block(&self, producer: [u8; 32]) -> Result<Block, RuntimeError> {
+        let txs: Vec<Transaction> = self
+            .canonical_order()
+            .into_iter()
+            .take(self.max_block_txs)
+            .map(|index| self.mempool[index].clone())
+            .collect();
+
+        let mut state = self.state.clone();
+        for tx in &txs {
+            Self::apply_transaction_to(&mut state, tx, &producer)?;
+        }
+        Self::reap_senders(&mut state, &txs, self.config.existential_deposit);
+        state.height += 1;
+        state.compute_state_root();
+
+        let mut block = Block::new(state.height, self.last_block_hash, state.state_root, txs, producer);
+        block.timestamp = block.timestamp.max(self.last_block_timestamp);
+        Ok(block)
+    }
+
     /// Validate a block from the network.
     ///
     /// # Checks
     ///
     /// - Height is exactly current + 1
     /// - Parent hash matches
-    /// - All transactions are valid
+    /// - Timestamp is not before the parent's, nor too far ahead of local time
+    /// - Producer is authorized
+    /// - All transactions are valid, in block order, and none has expired
     pub fn validate_block(&self, block: &Block) -> Result<(), RuntimeError> {
         // Check height
         let expected_height = self.state.height + 1;
@@ -175,9 +539,38 @@ impl Runtime {
             });
         }
 
-        // Validate all transactions
-        for tx in &block.txs {
-            self.validate_transaction(tx)?;
+        // Check timestamp
+        if block.timestamp < self.last_block_timestamp {
+            return Err(RuntimeError::InvalidBlock {
+                reason: format!(
+                    "timestamp {} is before parent timestamp {}",
+                    block.timestamp, self.last_block_timestamp
+                ),
+            });
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if block.timestamp > now.saturating_add(self.max_timestamp_skew) {
+            return Err(RuntimeError::InvalidBlock {
+                reason: format!(
+                    "timestamp {} is more than {}s ahead of local time {}",
+                    block.timestamp, self.max_timestamp_skew, now
+                ),
+            });
+        }
+
+        // Check producer
+        if !self.is_authorized_producer(&block.producer) {
+            return Err(RuntimeError::InvalidBlock {
+                reason: "unauthorized producer".to_string(),
+            });
+        }
+
+        // Validate all transactions in order, independent of our mempool
+        for (i, tx) in block.txs.iter().enumerate() {
+            self.validate_after(tx, &block.txs[..i])?;
         }
 
         Ok(())
@@ -185,18 +578,104 @@ impl Runtime {
 
     /// Apply a validated block to state.
     ///
-    /// Call `validate_block` first!
-    pub fn apply_block(&mut self, block: &Block) -> Result<(), RuntimeError> {
+    /// Call `validate_block` first! The state root is recomputed after
+    /// execution; if it differs from the block's claim the state is left
+    /// untouched and `StateRootMismatch` reports both roots together with
+    /// the accounts the block touched, to help chase nondeterminism.
+    ///
+    /// Returns the accounts the block changed, for persistence alongside it.
+    pub fn apply_block(&mut self, block: &Block) -> Result<StateChangeSet, RuntimeError> {
+        let (state, changes) = Self::execute_block(&self.state, block, &self.config)?;
+        self.adopt_block(block, state);
+        Ok(changes)
+    }
+
+    /// Execute a validated block against `state` without touching the runtime.
+    ///
+    /// The work of `apply_block`, split out so it can run off the thread
+    /// that owns the runtime; hand the result to `adopt_block`. `config`
+    /// must be the runtime's. Returns the state after the block and the
+    /// accounts it changed.
+    pub fn execute_block(
+        state: &State,
+        block: &Block,
+        config: &RuntimeConfig,
+    ) -> Result<(State, StateChangeSet), RuntimeError> {
+        let mut after = state.clone();
+
         // Apply all transactions
         for tx in &block.txs {
-            self.apply_transaction(tx)?;
+            Self::apply_transaction_to(&mut after, tx, &block.producer)?;
         }
+        Self::reap_senders(&mut after, &block.txs, config.existential_deposit);
 
         // Update state
-        self.state.height = block.height;
-        self.state.state_root = block.state_root;
+        after.height = block.height;
+        after.compute_state_root();
+
+        if after.state_root != block.state_root {
+            let computed = after.state_root;
+
+            let mut accounts: Vec<[u8; 32]> = Vec::new();
+            for address in block.txs.iter().flat_map(|tx| [tx.from, tx.to]) {
+                if !accounts.contains(&address) {
+                    accounts.push(address);
+                }
+            }
+
+            return Err(RuntimeError::StateRootMismatch {
+                claimed: block.state_root,
+                computed,
+                accounts,
+            });
+        }
+
+        let changes = StateChangeSet::for_block(state, &after, block);
+        Ok((after, changes))
+    }
+
+    /// Make `block` the new tip, with `state` as produced by `execute_block`.
+    ///
+    /// The caller must ensure the block still extends the current tip.
+    pub fn adopt_block(&mut self, block: &Block, state: State) {
+        self.undo.push(UndoRecord::capture(&self.state, self.last_block_hash, self.last_block_timestamp, block));
+        self.state = state;
         self.last_block_hash = block.hash();
+        self.last_block_timestamp = block.timestamp;
+        self.drop_reaped_pending(block);
+    }
 
+    /// Undo the latest blocks until the chain is back at `height`.
+    ///
+    /// Balances, nonces, the state root and the tip hash return to what
+    /// they were at `height`. Only the last `max_undo_depth` blocks applied
+    /// by this runtime can be undone; asking for more fails with
+    /// `RollbackTooDeep` and changes nothing. Transactions of undone blocks
+    /// are not returned to the mempool.
+    pub fn rollback_to(&mut self, height: u64) -> Result<(), RuntimeError> {
+        let current = self.state.height;
+        if height > current {
+            return Err(RuntimeError::HeightMismatch { expected: current, got: height });
+        }
+        if height == current {
+            return Ok(());
+        }
+        match self.undo.earliest_height() {
+            Some(earliest) if earliest <= height => {}
+            earliest => {
+                return Err(RuntimeError::RollbackTooDeep {
+                    target: height,
+                    earliest: earliest.unwrap_or(current),
+                })
+            }
+        }
+
+        while self.state.height > height {
+            let record = self.undo.pop().expect("undo log reaches the target");
+            record.restore(&mut self.state);
+            self.last_block_hash = record.parent_hash;
+            self.last_block_timestamp = record.parent_timestamp;
+        }
         Ok(())
     }
 
@@ -210,6 +689,64 @@ impl Runtime {
         self.mempool.len()
     }
 
+    /// Get the pending transactions.
+    pub fn mempool(&self) -> &[Transaction] {
+        &self.mempool
+    }
+
+    /// Estimate when a pending transaction will be included.
+    ///
+    /// Follows the order `produce_block` takes transactions in, skipping
+    /// those it would drop, and assumes every block is filled to the limit
+    /// and produced on schedule. Returns `None` if the transaction is not
+    /// pending or would be dropped.
+    pub fn estimated_inclusion(&self, tx_hash: &[u8; 32]) -> Option<EstimatedInclusion> {
+        let position = self
+            .canonical_order()
+            .into_iter()
+            .position(|index| self.mempool[index].hash() == *tx_hash)?;
+        let blocks_ahead = (position / self.max_block_txs) as u64;
+        Some(EstimatedInclusion {
+            blocks_ahead,
+            approx_seconds: (blocks_ahead + 1).saturating_mul(self.block_interval),
+        })
+    }
+
+    /// Mempool indexes in the order blocks take them, skipping those
+    /// `produce_block` would drop.
+    ///
+    /// The next transaction is always the highest-paying one whose sender
+    /// has nothing earlier still waiting, so nonces stay in order; ties go
+    /// to the one submitted first.
+    fn canonical_order(&self) -> Vec<usize> {
+        // Each sender's transactions in nonce order, up to the first below
+        // the fee floor or expired
+        let height = self.state.height + 1;
+        let mut skipped_senders = HashSet::new();
+        let mut queues: HashMap<Address, VecDeque<usize>> = HashMap::new();
+        for (index, tx) in self.mempool.iter().enumerate() {
+            if skipped_senders.contains(&tx.from) || tx.fee < self.min_fee || tx.is_expired_at(height) {
+                skipped_senders.insert(tx.from);
+                continue;
+            }
+            queues.entry(tx.from).or_default().push_back(index);
+        }
+
+        let head = |index: usize| (self.mempool[index].fee, Reverse(index));
+        let mut heads: BinaryHeap<(u64, Reverse<usize>)> =
+            queues.values().filter_map(|queue| queue.front().map(|&index| head(index))).collect();
+        let mut order = Vec::with_capacity(self.mempool.len());
+        while let Some((_, Reverse(index))) = heads.pop() {
+            order.push(index);
+            let queue = queues.get_mut(&self.mempool[index].from).expect("sender has a queue");
+            queue.pop_front();
+            if let Some(&next) = queue.front() {
+                heads.push(head(next));
+            }
+        }
+        order
+    }
+
     /// Get the last block hash.
     pub fn last_block_hash(&self) -> [u8; 32] {
         self.last_block_hash
@@ -223,7 +760,7 @@ impl Runtime {
 
 impl Default for Runtime {
     fn default() -> Self {
-        Self::new()
+        Self::new(RuntimeConfig::default())
     }
 }
 
@@ -232,15 +769,13 @@ mod tests {
     use super::*;
 
     fn funded_runtime() -> Runtime {
-        let mut runtime = Runtime::new();
         // Fund an account for testing
-        runtime.state.set_balance(&[1u8; 32], 1000);
-        runtime
+        runtime_with_senders(1, RuntimeConfig::default())
     }
 
     #[test]
     fn test_new_runtime() {
-        let runtime = Runtime::new();
+        let runtime = Runtime::default();
         assert_eq!(runtime.height(), 0);
         assert_eq!(runtime.mempool_size(), 0);
     }
@@ -256,7 +791,7 @@ mod tests {
 
     #[test]
     fn test_reject_insufficient_balance() {
-        let mut runtime = Runtime::new();
+        let mut runtime = Runtime::default();
         let tx = Transaction::new([1u8; 32], [2u8; 32], 100, 0);
 
         let result = runtime.submit_transaction(tx);
@@ -277,6 +812,241 @@ mod tests {
         assert_eq!(runtime.mempool_size(), 0);
     }
 
+    #[test]
+    fn test_min_fee() {
+        let mut runtime = funded_runtime();
+        runtime.set_min_fee(10);
+
+        let cheap = Transaction::new([1u8; 32], [2u8; 32], 100, 0).with_fee(9);
+        assert_eq!(
+            runtime.submit_transaction(cheap),
+            Err(RuntimeError::FeeTooLow { fee: 9, min_fee: 10 })
+        );
+
+        let at_floor = Transaction::new([1u8; 32], [2u8; 32], 100, 0).with_fee(10);
+        assert!(runtime.submit_transaction(at_floor).is_ok());
+    }
+
+    #[test]
+    fn test_balance_must_cover_fee() {
+        let mut runtime = funded_runtime();
+
+        // 1000 covers the amount but not the fee on top
+        let tx = Transaction::new([1u8; 32], [2u8; 32], 1000, 0).with_fee(1);
+        assert!(matches!(
+            runtime.submit_transaction(tx),
+            Err(RuntimeError::InvalidTransaction { .. })
+        ));
+
+        // Pending fees count against the balance like pending amounts
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 500, 0).with_fee(10)).unwrap();
+        let over = Transaction::new([1u8; 32], [2u8; 32], 490, 1).with_fee(1);
+        assert!(runtime.submit_transaction(over).is_err());
+        let exact = Transaction::new([1u8; 32], [2u8; 32], 489, 1).with_fee(1);
+        assert!(runtime.submit_transaction(exact).is_ok());
+    }
+
+    #[test]
+    fn test_fees_credited_to_producer() {
+        let producer = [3u8; 32];
+        let mut producer_rt = funded_runtime();
+        producer_rt.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 0).with_fee(7)).unwrap();
+        producer_rt.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 50, 1).with_fee(3)).unwrap();
+        let block = producer_rt.produce_block(producer);
+
+        assert_eq!(producer_rt.state.balance(&[1u8; 32]), 1000 - 150 - 10);
+        assert_eq!(producer_rt.state.balance(&[2u8; 32]), 150);
+        assert_eq!(producer_rt.state.balance(&producer), 10);
+
+        // A peer applying the block credits the same producer
+        let mut runtime = funded_runtime();
+        let changes = runtime.apply_block(&block).unwrap();
+        assert_eq!(runtime.state, producer_rt.state);
+        assert_eq!(changes.changes.get(&producer).map(|c| c.balance), Some(10));
+    }
+
+    #[test]
+    fn test_reserved_recipient_rejected() {
+        let mut runtime = funded_runtime();
+        runtime.set_reserved_addresses([[0u8; 32]]);
+
+        let burn = Transaction::new([1u8; 32], [0u8; 32], 100, 0);
+        assert_eq!(
+            runtime.submit_transaction(burn),
+            Err(RuntimeError::ReservedRecipient { address: [0u8; 32] })
+        );
+
+        let normal = Transaction::new([1u8; 32], [2u8; 32], 100, 0);
+        assert!(runtime.submit_transaction(normal).is_ok());
+    }
+
+    #[test]
+    fn test_produce_block_drops_under_floor() {
+        let mut runtime = funded_runtime();
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 0)).unwrap();
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 1).with_fee(5)).unwrap();
+
+        // Floor raised after the transactions were admitted
+        runtime.set_min_fee(5);
+        let block = runtime.produce_block([3u8; 32]);
+
+        // The second depends on the first's nonce, so both are left out
+        assert!(block.txs.is_empty());
+        assert_eq!(runtime.state.balance(&[1u8; 32]), 1000);
+    }
+
+    #[test]
+    fn test_valid_until_enforced() {
+        let mut runtime = funded_runtime();
+        runtime.produce_block([3u8; 32]);
+
+        // The next block is #2: valid until #2 is accepted, until #1 is not
+        let tx = Transaction::new([1u8; 32], [2u8; 32], 100, 0).with_valid_until(2);
+        runtime.submit_transaction(tx).unwrap();
+        let late = Transaction::new([1u8; 32], [2u8; 32], 100, 1).with_valid_until(1);
+        assert_eq!(
+            runtime.submit_transaction(late),
+            Err(RuntimeError::Expired { valid_until: 1, height: 2 })
+        );
+
+        // A block including an expired transaction is invalid
+        let mut block = runtime.build_block([3u8; 32]).unwrap();
+        assert!(runtime.validate_block(&block).is_ok());
+        block.txs = vec![Transaction::new([1u8; 32], [2u8; 32], 100, 0).with_valid_until(1)];
+        assert_eq!(
+            runtime.validate_block(&block),
+            Err(RuntimeError::Expired { valid_until: 1, height: 2 })
+        );
+    }
+
+    #[test]
+    fn test_expired_txs_purged_during_production() {
+        let mut runtime = funded_runtime();
+        runtime.state.set_balance(&[4u8; 32], 1000);
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 0).with_valid_until(1)).unwrap();
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 1)).unwrap();
+        let lasting = Transaction::new([4u8; 32], [2u8; 32], 100, 0).with_valid_until(2);
+        runtime.submit_transaction(lasting.clone()).unwrap();
+
+        // Block #1 passes without them, so by #2 sender one's first
+        // transaction has expired, taking the one depending on its nonce
+        runtime.state.height = 1;
+        let block = runtime.produce_block([3u8; 32]);
+
+        assert_eq!(block.txs, vec![lasting]);
+        assert_eq!(runtime.mempool_size(), 0);
+    }
+
+    #[test]
+    fn test_block_tx_limit_and_estimated_inclusion() {
+        let mut runtime = funded_runtime();
+        runtime.set_max_block_txs(2);
+        runtime.set_block_interval(5);
+
+        let txs: Vec<Transaction> = (0..5)
+            .map(|nonce| Transaction::new([1u8; 32], [2u8; 32], 10, nonce))
+            .collect();
+        for tx in &txs {
+            runtime.submit_transaction(tx.clone()).unwrap();
+        }
+
+        let first = runtime.estimated_inclusion(&txs[1].hash()).unwrap();
+        assert_eq!(first, EstimatedInclusion { blocks_ahead: 0, approx_seconds: 5 });
+
+        // Beyond the first block's capacity
+        let later = runtime.estimated_inclusion(&txs[4].hash()).unwrap();
+        assert!(later.blocks_ahead >= 1);
+        assert_eq!(later, EstimatedInclusion { blocks_ahead: 2, approx_seconds: 15 });
+
+        assert!(runtime.estimated_inclusion(&[9u8; 32]).is_none());
+
+        // Production follows the same order and limit
+        let block = runtime.produce_block([3u8; 32]);
+        assert_eq!(block.txs, txs[..2].to_vec());
+        assert_eq!(runtime.mempool_size(), 3);
+        assert_eq!(runtime.estimated_inclusion(&txs[4].hash()).unwrap().blocks_ahead, 1);
+    }
+
+    /// A runtime with accounts `[1; 32]` to `[senders; 32]` funded.
+    fn runtime_with_senders(senders: u8, config: RuntimeConfig) -> Runtime {
+        let mut runtime = Runtime::new(config);
+        for sender in 1..=senders {
+            runtime.state.set_balance(&[sender; 32], 1000);
+        }
+        runtime
+    }
+
+    #[test]
+    fn test_full_mempool_rejects_cheaper_tx() {
+        let mut runtime = runtime_with_senders(3, RuntimeConfig::default());
+        runtime.set_max_mempool(2);
+        runtime.submit_transaction(Transaction::new([1u8; 32], [9u8; 32], 10, 0).with_fee(5)).unwrap();
+        runtime.submit_transaction(Transaction::new([2u8; 32], [9u8; 32], 10, 0).with_fee(5)).unwrap();
+
+        // Matching the cheapest fee is not enough
+        let tx = Transaction::new([3u8; 32], [9u8; 32], 10, 0).with_fee(5);
+        assert_eq!(runtime.submit_transaction(tx), Err(RuntimeError::MempoolFull { capacity: 2 }));
+        assert_eq!(runtime.mempool_size(), 2);
+    }
+
+    #[test]
+    fn test_full_mempool_evicts_cheapest_tx() {
+        let mut runtime = runtime_with_senders(3, RuntimeConfig::default());
+        runtime.set_max_mempool(3);
+        let cheap = Transaction::new([1u8; 32], [9u8; 32], 10, 0).with_fee(1);
+        runtime.submit_transaction(cheap.clone()).unwrap();
+        runtime.submit_transaction(Transaction::new([2u8; 32], [9u8; 32], 10, 0).with_fee(2)).unwrap();
+        // Not evictable: sender 2 has a later transaction depending on it
+        runtime.submit_transaction(Transaction::new([2u8; 32], [9u8; 32], 10, 1).with_fee(3)).unwrap();
+
+        let pricier = Transaction::new([3u8; 32], [9u8; 32], 10, 0).with_fee(4);
+        runtime.submit_transaction(pricier.clone()).unwrap();
+
+        assert_eq!(runtime.mempool_size(), 3);
+        assert!(!runtime.mempool().contains(&cheap));
+        assert!(runtime.mempool().contains(&pricier));
+    }
+
+    #[test]
+    fn test_block_assembled_by_fee_in_nonce_order() {
+        let mut runtime = runtime_with_senders(3, RuntimeConfig::default());
+        let sender_one = [
+            Transaction::new([1u8; 32], [9u8; 32], 10, 0).with_fee(1),
+            Transaction::new([1u8; 32], [9u8; 32], 10, 1).with_fee(9),
+        ];
+        let sender_two = Transaction::new([2u8; 32], [9u8; 32], 10, 0).with_fee(5);
+        let sender_three = Transaction::new([3u8; 32], [9u8; 32], 10, 0).with_fee(3);
+        for tx in sender_one.iter().chain([&sender_two, &sender_three]) {
+            runtime.submit_transaction(tx.clone()).unwrap();
+        }
+
+        // Sender one's pricey second transaction waits for its cheap first one
+        let block = runtime.produce_block([4u8; 32]);
+        assert_eq!(
+            block.txs,
+            vec![sender_two, sender_three, sender_one[0].clone(), sender_one[1].clone()]
+        );
+    }
+
+    #[test]
+    fn test_build_block_leaves_runtime_untouched() {
+        let mut runtime = funded_runtime();
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 0)).unwrap();
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 50, 1)).unwrap();
+
+        let block = runtime.build_block([3u8; 32]).unwrap();
+        assert_eq!((block.height, block.tx_count()), (1, 2));
+        assert_eq!(runtime.height(), 0);
+        assert_eq!(runtime.mempool_size(), 2);
+
+        // Once final, the block applies and its transactions leave the mempool
+        runtime.validate_block(&block).unwrap();
+        runtime.apply_block(&block).unwrap();
+        assert_eq!(runtime.prune_mempool(), 2);
+        assert_eq!(runtime.state.balance(&[2u8; 32]), 150);
+        assert_eq!(runtime.last_block_hash(), block.hash());
+    }
+
     #[test]
     fn test_state_transition() {
         let mut runtime = funded_runtime();
@@ -292,6 +1062,115 @@ mod tests {
         assert_eq!(runtime.state.nonce(&sender), 1);
     }
 
+    #[test]
+    fn test_apply_block_reports_change_set() {
+        let mut producer_rt = funded_runtime();
+        producer_rt
+            .submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 0))
+            .unwrap();
+        let block = producer_rt.produce_block([3u8; 32]);
+
+        let mut runtime = funded_runtime();
+        let before = runtime.state.clone();
+        let changes = runtime.apply_block(&block).unwrap();
+
+        assert_eq!(changes.height, 1);
+        assert_eq!(changes.len(), 2);
+        for (address, change) in &changes.changes {
+            assert_eq!(change.balance, runtime.state.balance(address));
+            assert_eq!(change.nonce, runtime.state.nonce(address));
+        }
+
+        // Replaying the change set reproduces the executed state
+        let mut replayed = before;
+        changes.apply_to(&mut replayed);
+        assert_eq!(replayed, runtime.state);
+    }
+
+    #[test]
+    fn test_reject_unauthorized_producer() {
+        let mut producer_rt = Runtime::default();
+        let block = producer_rt.produce_block([3u8; 32]);
+
+        let mut runtime = Runtime::default();
+        assert!(runtime.validate_block(&block).is_ok()); // Open by default
+
+        runtime.set_authorized_producers([[4u8; 32]]);
+        assert!(matches!(
+            runtime.validate_block(&block),
+            Err(RuntimeError::InvalidBlock { .. })
+        ));
+
+        runtime.set_authorized_producers([[3u8; 32], [4u8; 32]]);
+        assert!(runtime.validate_block(&block).is_ok());
+    }
+
+    #[test]
+    fn test_reject_far_future_timestamp() {
+        let mut runtime = Runtime::default();
+        runtime.set_max_timestamp_skew(10);
+
+        let mut block = Runtime::default().produce_block([3u8; 32]);
+        block.timestamp += 60;
+        assert!(matches!(
+            runtime.validate_block(&block),
+            Err(RuntimeError::InvalidBlock { .. })
+        ));
+
+        block.timestamp -= 55;
+        assert!(runtime.validate_block(&block).is_ok());
+    }
+
+    #[test]
+    fn test_reject_non_monotonic_timestamp() {
+        let mut producer_rt = Runtime::default();
+        let parent = producer_rt.produce_block([3u8; 32]);
+        let mut child = producer_rt.produce_block([3u8; 32]);
+
+        let mut runtime = Runtime::default();
+        runtime.apply_block(&parent).unwrap();
+
+        child.timestamp = parent.timestamp - 1;
+        assert!(matches!(
+            runtime.validate_block(&child),
+            Err(RuntimeError::InvalidBlock { .. })
+        ));
+
+        // Equal timestamps are allowed
+        child.timestamp = parent.timestamp;
+        assert!(runtime.validate_block(&child).is_ok());
+    }
+
+    #[test]
+    fn test_state_root_mismatch_reports_both_roots() {
+        let mut producer_rt = funded_runtime();
+        producer_rt
+            .submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 100, 0))
+            .unwrap();
+        let mut block = producer_rt.produce_block([3u8; 32]);
+        let computed = block.state_root;
+        block.state_root = [0xee; 32];
+
+        let mut runtime = funded_runtime();
+        let err = runtime.apply_block(&block).unwrap_err();
+
+        match &err {
+            RuntimeError::StateRootMismatch { claimed, computed: ours, accounts } => {
+                assert_eq!(*claimed, [0xee; 32]);
+                assert_eq!(*ours, computed);
+                assert_eq!(accounts, &vec![[1u8; 32], [2u8; 32]]);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        let message = err.to_string();
+        assert!(message.contains(&"ee".repeat(32)));
+        assert!(message.contains(&computed.iter().map(|b| format!("{:02x}", b)).collect::<String>()));
+
+        // Nothing was applied
+        assert_eq!(runtime.height(), 0);
+        assert_eq!(runtime.state.balance(&[1u8; 32]), 1000);
+    }
+
     #[test]
     fn test_nonce_enforcement() {
         let mut runtime = funded_runtime();
@@ -305,4 +1184,152 @@ mod tests {
         let tx2 = Transaction::new(sender, [2u8; 32], 100, 0);
         assert!(runtime.submit_transaction(tx2).is_err());
     }
+    #[test]
+    fn test_bundle_is_all_or_nothing() {
+        let mut runtime = funded_runtime();
+        let sender = [1u8; 32];
+        runtime.submit_transaction(Transaction::new(sender, [2u8; 32], 100, 0)).unwrap();
+
+        // Third transaction reuses a nonce: nothing from the bundle lands
+        let bad = vec![
+            Transaction::new(sender, [2u8; 32], 100, 1),
+            Transaction::new(sender, [2u8; 32], 100, 2),
+            Transaction::new(sender, [2u8; 32], 100, 2),
+        ];
+        assert!(runtime.submit_bundle(bad).is_err());
+        assert_eq!(runtime.mempool_size(), 1);
+
+        let good = vec![
+            Transaction::new(sender, [2u8; 32], 100, 1),
+            Transaction::new(sender, [2u8; 32], 100, 2),
+        ];
+        runtime.submit_bundle(good).unwrap();
+        let nonces: Vec<u64> = runtime.mempool().iter().map(|tx| tx.nonce).collect();
+        assert_eq!(nonces, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_rollback_restores_earlier_state() {
+        let mut runtime = funded_runtime();
+        let sender = [1u8; 32];
+        let mut snapshot = None;
+        for nonce in 0..5 {
+            // Each block pays a fresh account, which the rollback must remove
+            let tx = Transaction::new(sender, [10 + nonce as u8; 32], 10, nonce).with_fee(1);
+            runtime.submit_transaction(tx).unwrap();
+            runtime.produce_block([3u8; 32]);
+            if runtime.height() == 3 {
+                snapshot = Some((runtime.state.clone(), runtime.last_block_hash()));
+            }
+        }
+        assert_eq!(runtime.height(), 5);
+
+        runtime.rollback_to(3).unwrap();
+        let (state, hash) = snapshot.unwrap();
+        assert_eq!(runtime.state, state);
+        assert_eq!(runtime.last_block_hash(), hash);
+
+        // The chain continues from the restored tip
+        runtime.submit_transaction(Transaction::new(sender, [2u8; 32], 10, 3)).unwrap();
+        let block = runtime.produce_block([3u8; 32]);
+        assert_eq!(block.height, 4);
+        assert_eq!(block.parent_hash, hash);
+    }
+
+    #[test]
+    fn test_rollback_limited_to_undo_depth() {
+        let mut producer_rt = funded_runtime();
+        let mut runtime = funded_runtime();
+        runtime.set_max_undo_depth(2);
+        for nonce in 0..4 {
+            producer_rt
+                .submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 10, nonce))
+                .unwrap();
+            runtime.apply_block(&producer_rt.produce_block([3u8; 32])).unwrap();
+        }
+
+        let before = runtime.state.clone();
+        assert_eq!(
+            runtime.rollback_to(1),
+            Err(RuntimeError::RollbackTooDeep { target: 1, earliest: 2 })
+        );
+        assert_eq!(runtime.state, before);
+
+        runtime.rollback_to(2).unwrap();
+        assert_eq!(runtime.height(), 2);
+        assert_eq!(runtime.state.balance(&[2u8; 32]), 20);
+        assert!(runtime.rollback_to(3).is_err());
+    }
+
+    #[test]
+    fn test_dust_transfer_to_new_account_rejected() {
+        let mut runtime = runtime_with_senders(1, RuntimeConfig { existential_deposit: 50 });
+
+        let dust = Transaction::new([1u8; 32], [2u8; 32], 10, 0);
+        assert_eq!(
+            runtime.submit_transaction(dust),
+            Err(RuntimeError::BelowExistentialDeposit { amount: 10, minimum: 50 })
+        );
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 50, 0)).unwrap();
+        runtime.produce_block([3u8; 32]);
+
+        // Small amounts are fine once the account exists
+        runtime.submit_transaction(Transaction::new([1u8; 32], [2u8; 32], 10, 1)).unwrap();
+    }
+
+    #[test]
+    fn test_sender_below_minimum_reaped() {
+        let mut producer_rt = runtime_with_senders(1, RuntimeConfig { existential_deposit: 50 });
+        let sender = [1u8; 32];
+        producer_rt.state.set_balance(&sender, 100);
+        producer_rt.state.set_balance(&[2u8; 32], 50);
+        producer_rt.set_max_block_txs(1);
+        producer_rt.submit_transaction(Transaction::new(sender, [2u8; 32], 60, 0)).unwrap();
+        producer_rt.submit_transaction(Transaction::new(sender, [2u8; 32], 10, 1)).unwrap();
+
+        let mut runtime = runtime_with_senders(1, RuntimeConfig { existential_deposit: 50 });
+        runtime.state.set_balance(&sender, 100);
+        runtime.state.set_balance(&[2u8; 32], 50);
+        let before = runtime.state.clone();
+
+        // 40 left is below the minimum: it is burned, and the pending
+        // transaction it was to pay for is dropped
+        let block = producer_rt.produce_block([3u8; 32]);
+        assert!(!producer_rt.state.exists(&sender));
+        assert_eq!(producer_rt.mempool_size(), 0);
+
+        // Importing nodes reap the same way, and the change set records it
+        runtime.validate_block(&block).unwrap();
+        let changes = runtime.apply_block(&block).unwrap();
+        assert_eq!(runtime.state, producer_rt.state);
+        let mut replayed = before;
+        changes.apply_to(&mut replayed);
+        assert_eq!(replayed, runtime.state);
+    }
+
+    #[test]
+    fn test_reaped_sender_starts_over() {
+        let mut runtime = runtime_with_senders(1, RuntimeConfig { existential_deposit: 50 });
+        let sender = [5u8; 32];
+        runtime.state.set_balance(&sender, 100);
+        runtime.state.set_balance(&[2u8; 32], 50);
+
+        // Leaves 40, so the sender is reaped
+        let spend = Transaction::new(sender, [2u8; 32], 60, 0);
+        let expiring = Transaction::new(sender, [2u8; 32], 100, 0).with_valid_until(1);
+        runtime.submit_transaction(spend.clone()).unwrap();
+        runtime.produce_block([3u8; 32]);
+        assert!(!runtime.state.exists(&sender));
+
+        // Funded again, it starts over at nonce 0: an old transfer without
+        // an expiry height runs again, one past its height does not
+        runtime.submit_transaction(Transaction::new([1u8; 32], sender, 500, 0)).unwrap();
+        runtime.produce_block([3u8; 32]);
+        assert_eq!(runtime.state.nonce(&sender), 0);
+        assert_eq!(
+            runtime.submit_transaction(expiring),
+            Err(RuntimeError::Expired { valid_until: 1, height: 3 })
+        );
+        runtime.submit_transaction(spend).unwrap();
+    }
 }
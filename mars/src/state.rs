@@ -5,9 +5,13 @@
 //! - Serializable (for persistence via TAR)
 //! - Deterministic (same operations always produce same state)
 //! - Clone-friendly (for state snapshots)
+//!
+//! Accounts are kept in ordered maps so identical states always serialize
+//! to identical bytes, whatever order the accounts were inserted in.
 
+use crate::merkle::{leaf_hash, merkle_proof, merkle_root, MerkleProof};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// The canonical blockchain state.
 ///
@@ -24,21 +28,24 @@ pub struct State {
     /// State root hash (computed after each block)
     pub state_root: [u8; 32],
 
-    /// Account balances (address -> balance)
-    pub balances: HashMap<[u8; 32], u64>,
+    /// Account balances (address -> balance), ordered by address
+    pub balances: BTreeMap<[u8; 32], u64>,
 
-    /// Account nonces for replay protection
-    pub nonces: HashMap<[u8; 32], u64>,
+    /// Account nonces for replay protection, ordered by address
+    pub nonces: BTreeMap<[u8; 32], u64>,
 }
 
 impl State {
+    /// Version of the persisted state layout; bump when fields change.
+    pub const SCHEMA_VERSION: u32 = 1;
+
     /// Create a new genesis state.
     pub fn new() -> Self {
         Self {
             height: 0,
             state_root: [0u8; 32],
-            balances: HashMap::new(),
-            nonces: HashMap::new(),
+            balances: BTreeMap::new(),
+            nonces: BTreeMap::new(),
         }
     }
 
@@ -63,13 +70,49 @@ impl State {
         self.balances.insert(*address, balance);
     }
 
+    /// Remove an account's balance and nonce entries.
+    pub fn remove_account(&mut self, address: &[u8; 32]) {
+        self.balances.remove(address);
+        self.nonces.remove(address);
+    }
+
+    /// Check whether an account exists.
+    ///
+    /// An account exists once it has a balance or nonce entry.
+    pub fn exists(&self, address: &[u8; 32]) -> bool {
+        self.balances.contains_key(address) || self.nonces.contains_key(address)
+    }
+
+    /// Every account's address, in address order.
+    ///
+    /// An account exists once it has a balance or nonce entry.
+    fn addresses(&self) -> BTreeSet<&[u8; 32]> {
+        self.balances.keys().chain(self.nonces.keys()).collect()
+    }
+
+    /// Merkle leaves of every account, in address order.
+    fn leaves(&self) -> Vec<[u8; 32]> {
+        self.addresses()
+            .into_iter()
+            .map(|address| leaf_hash(address, self.balance(address), self.nonce(address)))
+            .collect()
+    }
+
     /// Compute and update the state root.
-    /// This is a placeholder - real implementation would use Merkle tree.
+    ///
+    /// The root of a Merkle tree over every account's address, balance and
+    /// nonce, in address order (see `merkle`).
     pub fn compute_state_root(&mut self) {
-        // Simple hash of height for now - replace with proper Merkle root
-        let mut root = [0u8; 32];
-        root[0..8].copy_from_slice(&self.height.to_le_bytes());
-        self.state_root = root;
+        self.state_root = merkle_root(&self.leaves());
+    }
+
+    /// Prove an account's balance and nonce against the state root.
+    ///
+    /// `None` if the account does not exist. Check the proof with
+    /// `verify_state_proof`.
+    pub fn prove(&self, address: &[u8; 32]) -> Option<MerkleProof> {
+        let index = self.addresses().into_iter().position(|a| a == address)?;
+        merkle_proof(&self.leaves(), index)
     }
 }
 
@@ -111,4 +154,68 @@ mod tests {
         state.increment_nonce(&addr);
         assert_eq!(state.nonce(&addr), 2);
     }
+
+    #[test]
+    fn test_identical_states_serialize_identically() {
+        let addresses: Vec<[u8; 32]> = (0u8..64).map(|i| [i; 32]).collect();
+
+        let mut forward = State::new();
+        for (i, addr) in addresses.iter().enumerate() {
+            forward.set_balance(addr, i as u64 * 10);
+            forward.increment_nonce(addr);
+        }
+
+        let mut backward = State::new();
+        for (i, addr) in addresses.iter().enumerate().rev() {
+            backward.increment_nonce(addr);
+            backward.set_balance(addr, i as u64 * 10);
+        }
+
+        assert_eq!(
+            bincode::serialize(&forward).unwrap(),
+            bincode::serialize(&backward).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_state_root_commits_to_accounts() {
+        let mut a = State::new();
+        a.set_balance(&[1u8; 32], 100);
+        let mut b = a.clone();
+        b.set_balance(&[1u8; 32], 101);
+        let mut c = a.clone();
+        c.increment_nonce(&[1u8; 32]);
+
+        for state in [&mut a, &mut b, &mut c] {
+            state.compute_state_root();
+        }
+        assert_ne!(a.state_root, b.state_root);
+        assert_ne!(a.state_root, c.state_root);
+
+        let root = a.state_root;
+        a.compute_state_root();
+        assert_eq!(a.state_root, root);
+    }
+
+    #[test]
+    fn test_account_proof_against_state_root() {
+        let mut state = State::new();
+        for i in 1..=5u8 {
+            state.set_balance(&[i; 32], 100 * i as u64);
+        }
+        state.increment_nonce(&[3u8; 32]);
+        state.increment_nonce(&[9u8; 32]); // Nonce without a balance entry
+        state.compute_state_root();
+
+        let proof = state.prove(&[3u8; 32]).unwrap();
+        assert!(crate::verify_state_proof(&state.state_root, &[3u8; 32], 300, 1, &proof));
+
+        // A tampered balance or nonce does not verify
+        assert!(!crate::verify_state_proof(&state.state_root, &[3u8; 32], 301, 1, &proof));
+        assert!(!crate::verify_state_proof(&state.state_root, &[3u8; 32], 300, 0, &proof));
+
+        let proof = state.prove(&[9u8; 32]).unwrap();
+        assert!(crate::verify_state_proof(&state.state_root, &[9u8; 32], 0, 1, &proof));
+        assert!(state.prove(&[7u8; 32]).is_none());
+    }
 }
@@ -4,20 +4,23 @@
 //! They must be signed and verified by TEV before reaching MARS.
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// A blockchain transaction.
 ///
 /// # Fields
 ///
-/// - `from`: Sender's public key (32 bytes)
+/// - `from`: Sender's address (32 bytes, see `address_from_pubkey`)
 /// - `to`: Recipient's address (32 bytes)
 /// - `amount`: Amount to transfer
 /// - `nonce`: Replay protection counter
+/// - `fee`: Fee paid to the block producer
+/// - `valid_until`: Last block height that may include it (0 = no expiry)
 /// - `payload`: Optional data payload
 /// - `signature`: Ed25519 signature (verified by TEV)
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Transaction {
-    /// Sender's public key
+    /// Sender's address (derived from the signing public key)
     pub from: [u8; 32],
 
     /// Recipient's address
@@ -29,6 +32,12 @@ pub struct Transaction {
     /// Sender's nonce (for replay protection)
     pub nonce: u64,
 
+    /// Fee paid to the producer of the block that includes it
+    pub fee: u64,
+
+    /// Last block height that may include it (0 = never expires)
+    pub valid_until: u64,
+
     /// Optional payload data
     pub payload: Vec<u8>,
 
@@ -44,6 +53,8 @@ impl Transaction {
             to,
             amount,
             nonce,
+            fee: 0,
+            valid_until: 0,
             payload: Vec::new(),
             signature: vec![0u8; 64],
         }
@@ -62,23 +73,53 @@ impl Transaction {
             to,
             amount,
             nonce,
+            fee: 0,
+            valid_until: 0,
             payload,
             signature: vec![0u8; 64],
         }
     }
 
+    /// Set the fee paid to the block producer.
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Set the last block height that may include the transaction.
+    pub fn with_valid_until(mut self, height: u64) -> Self {
+        self.valid_until = height;
+        self
+    }
+
+    /// Whether the transaction may no longer be included in a block at `height`.
+    pub fn is_expired_at(&self, height: u64) -> bool {
+        self.valid_until != 0 && self.valid_until < height
+    }
+
     /// Get the bytes to be signed.
     /// This is the canonical serialization for signature verification.
+    ///
+    /// The payload is length-prefixed, so transactions concatenated in a
+    /// block cannot shift bytes into each other's fields.
     pub fn signing_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.from);
         bytes.extend_from_slice(&self.to);
         bytes.extend_from_slice(&self.amount.to_le_bytes());
         bytes.extend_from_slice(&self.nonce.to_le_bytes());
+        bytes.extend_from_slice(&self.fee.to_le_bytes());
+        bytes.extend_from_slice(&self.valid_until.to_le_bytes());
+        bytes.extend_from_slice(&(self.payload.len() as u64).to_le_bytes());
         bytes.extend_from_slice(&self.payload);
         bytes
     }
 
+    /// Compute the transaction hash: SHA-256 over `signing_bytes`.
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(self.signing_bytes()).into()
+    }
+
     /// Set the signature for this transaction.
     pub fn set_signature(&mut self, sig: [u8; 64]) {
         self.signature = sig.to_vec();
@@ -109,4 +150,16 @@ mod tests {
 
         assert_eq!(tx1.signing_bytes(), tx2.signing_bytes());
     }
+
+    #[test]
+    fn test_signing_bytes_cover_fee() {
+        let tx = Transaction::new([1u8; 32], [2u8; 32], 100, 0);
+        let paid = tx.clone().with_fee(1);
+
+        assert_ne!(tx.signing_bytes(), paid.signing_bytes());
+        assert_ne!(tx.hash(), paid.hash());
+
+        let expiring = tx.clone().with_valid_until(10);
+        assert_ne!(tx.hash(), expiring.hash());
+    }
 }
@@ -0,0 +1,117 @@
+//! Undo log for rolling back recent blocks.
+//!
+//! Before a block is applied, the balance and nonce entries of every
+//! account it can touch (senders, recipients and the producer) are saved,
+//! together with the chain tip it extended. Undoing the block puts those
+//! entries back exactly, including removing accounts the block created, so
+//! the state root after a rollback matches the one before the block.
+//!
+//! Only the most recent `max_depth` blocks are kept.
+
+use crate::{Address, Block, State};
+use std::collections::{BTreeMap, VecDeque};
+
+/// What one block changed, as it was before the block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UndoRecord {
+    /// Height of the block the record undoes
+    pub height: u64,
+
+    /// Tip hash before the block
+    pub parent_hash: [u8; 32],
+
+    /// Tip timestamp before the block
+    pub parent_timestamp: u64,
+
+    /// State root before the block
+    pub state_root: [u8; 32],
+
+    /// Balance and nonce entries before the block (`None` = absent)
+    pub accounts: BTreeMap<Address, (Option<u64>, Option<u64>)>,
+}
+
+impl UndoRecord {
+    /// Save what `block` can change in `state`, the state it extends.
+    pub fn capture(state: &State, parent_hash: [u8; 32], parent_timestamp: u64, block: &Block) -> Self {
+        let touched = block
+            .txs
+            .iter()
+            .flat_map(|tx| [tx.from, tx.to])
+            .chain([block.producer]);
+        let accounts = touched
+            .map(|address| {
+                let entries = (state.balances.get(&address).copied(), state.nonces.get(&address).copied());
+                (address, entries)
+            })
+            .collect();
+
+        Self {
+            height: block.height,
+            parent_hash,
+            parent_timestamp,
+            state_root: state.state_root,
+            accounts,
+        }
+    }
+
+    /// Put the saved entries back into `state` and step it back one height.
+    pub fn restore(&self, state: &mut State) {
+        for (address, (balance, nonce)) in &self.accounts {
+            match balance {
+                Some(balance) => state.balances.insert(*address, *balance),
+                None => state.balances.remove(address),
+            };
+            match nonce {
+                Some(nonce) => state.nonces.insert(*address, *nonce),
+                None => state.nonces.remove(address),
+            };
+        }
+        state.height = self.height - 1;
+        state.state_root = self.state_root;
+    }
+}
+
+/// Undo records of the most recent blocks, oldest first.
+#[derive(Clone, Debug)]
+pub struct UndoLog {
+    records: VecDeque<UndoRecord>,
+    max_depth: usize,
+}
+
+impl UndoLog {
+    /// Create an empty log keeping up to `max_depth` blocks.
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            records: VecDeque::new(),
+            max_depth,
+        }
+    }
+
+    /// Set how many blocks are kept, dropping the oldest beyond it.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+        self.truncate();
+    }
+
+    /// Record a block just applied.
+    pub fn push(&mut self, record: UndoRecord) {
+        self.records.push_back(record);
+        self.truncate();
+    }
+
+    /// Take the record of the latest block.
+    pub fn pop(&mut self) -> Option<UndoRecord> {
+        self.records.pop_back()
+    }
+
+    /// Lowest height that can be rolled back to, if any block is recorded.
+    pub fn earliest_height(&self) -> Option<u64> {
+        self.records.front().map(|record| record.height - 1)
+    }
+
+    fn truncate(&mut self) {
+        while self.records.len() > self.max_depth {
+            self.records.pop_front();
+        }
+    }
+}
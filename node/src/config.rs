@@ -5,7 +5,7 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 
 /// Main node configuration.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct NodeConfig {
     /// Node configuration section
     #[serde(default)]
@@ -18,6 +18,18 @@ pub struct NodeConfig {
     /// Runtime configuration section
     #[serde(default)]
     pub runtime: RuntimeSection,
+
+    /// RPC configuration section
+    #[serde(default)]
+    pub rpc: RpcSection,
+
+    /// Consensus engine configuration section
+    #[serde(default)]
+    pub consensus: consensus::ConsensusConfig,
+
+    /// Genesis configuration section
+    #[serde(default)]
+    pub genesis: GenesisSection,
 }
 
 /// Node-specific configuration.
@@ -30,6 +42,26 @@ pub struct NodeSection {
     /// Log level (trace, debug, info, warn, error)
     #[serde(default = "default_log_level")]
     pub log_level: String,
+
+    /// Blocks behind the best peer still considered synced
+    #[serde(default = "default_sync_threshold")]
+    pub sync_threshold: u64,
+
+    /// Finality certificates to keep, most recent first; those of retained blocks are always kept (unset = keep all, for archive nodes)
+    #[serde(default)]
+    pub finality_retention: Option<u64>,
+
+    /// Finalized blocks to keep; older ones are pruned (unset = keep all, for archive nodes)
+    #[serde(default)]
+    pub block_retention: Option<u64>,
+
+    /// Take a state snapshot every this many finalized heights
+    #[serde(default = "default_snapshot_interval")]
+    pub snapshot_interval: u64,
+
+    /// Re-verify every stored block on startup (slow on long chains)
+    #[serde(default)]
+    pub verify_chain_on_startup: bool,
 }
 
 /// Network configuration.
@@ -50,6 +82,23 @@ pub struct NetworkSection {
     /// Enable peer discovery
     #[serde(default = "default_true")]
     pub enable_discovery: bool,
+
+    /// Seconds before an already-relayed transaction may be relayed again
+    #[serde(default = "default_tx_gossip_ttl")]
+    pub tx_gossip_ttl_secs: u64,
+
+    /// Blocks whose transactions are dropped quietly if gossiped again
+    #[serde(default = "default_included_tx_depth")]
+    pub included_tx_depth: u64,
+
+    /// Disconnect peers whose height differs from ours by more blocks than
+    /// this (unset = never)
+    #[serde(default)]
+    pub max_height_divergence: Option<u64>,
+
+    /// Seconds a peer may stay diverged before it is disconnected
+    #[serde(default = "default_height_divergence_grace")]
+    pub height_divergence_grace_secs: u64,
 }
 
 /// Runtime configuration.
@@ -59,6 +108,10 @@ pub struct RuntimeSection {
     #[serde(default)]
     pub chain_id: String,
 
+    /// Genesis block timestamp (Unix epoch seconds)
+    #[serde(default)]
+    pub genesis_timestamp: u64,
+
     /// Block producer mode
     #[serde(default)]
     pub producer_enabled: bool,
@@ -66,6 +119,80 @@ pub struct RuntimeSection {
     /// Producer's private key (hex encoded, 32 bytes)
     #[serde(default)]
     pub producer_key: Option<String>,
+
+    /// Persist the mempool on shutdown and restore it on startup
+    #[serde(default)]
+    pub persist_mempool: bool,
+
+    /// Producer public keys allowed to author blocks (hex encoded, empty = any);
+    /// under consensus the validator set at each height decides instead
+    #[serde(default)]
+    pub authorized_producers: Vec<String>,
+
+    /// How far ahead of local time a block timestamp may be (seconds)
+    #[serde(default = "default_max_timestamp_skew")]
+    pub max_timestamp_skew_secs: u64,
+
+    /// Smallest transaction fee accepted into the mempool
+    #[serde(default)]
+    pub min_fee: u64,
+
+    /// Smallest balance an account may hold (0 = no minimum); must match
+    /// across the chain
+    #[serde(default)]
+    pub existential_deposit: u64,
+
+    /// Addresses transactions may not send to (hex encoded, empty = none)
+    #[serde(default)]
+    pub reserved_addresses: Vec<String>,
+
+    /// Most transactions taken into one block
+    #[serde(default = "default_max_block_txs")]
+    pub max_block_txs: usize,
+
+    /// Most transactions held pending
+    #[serde(default = "default_max_mempool")]
+    pub max_mempool: usize,
+
+    /// Interval between produced blocks (seconds)
+    #[serde(default = "default_block_interval")]
+    pub block_interval_secs: u64,
+
+    /// Consecutive state-root mismatches before halting in safe mode (0 = never)
+    #[serde(default = "default_safe_mode_mismatches")]
+    pub safe_mode_mismatches: u32,
+}
+
+/// Genesis configuration: the chain's initial committee and balances.
+///
+/// Every node of a chain must use the same section; it is checked
+/// against the stored chain on restart.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GenesisSection {
+    /// Initial validator public keys (hex encoded, empty = no committee)
+    #[serde(default)]
+    pub validators: Vec<String>,
+
+    /// Initial balances as (address, balance) pairs (hex encoded addresses)
+    #[serde(default)]
+    pub allocations: Vec<(String, u64)>,
+
+    /// Later validator sets as (effective height, hex public keys) pairs;
+    /// each is scheduled once the height before it is finalized
+    #[serde(default)]
+    pub validator_epochs: Vec<(u64, Vec<String>)>,
+}
+
+/// RPC configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcSection {
+    /// Enable the HTTP query API
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Port to serve the HTTP query API on
+    #[serde(default = "default_rpc_port")]
+    pub listen_port: u16,
 }
 
 // Default value functions
@@ -77,6 +204,38 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_sync_threshold() -> u64 {
+    2
+}
+
+fn default_snapshot_interval() -> u64 {
+    1000
+}
+
+fn default_max_timestamp_skew() -> u64 {
+    mars::runtime::DEFAULT_MAX_TIMESTAMP_SKEW
+}
+
+fn default_max_block_txs() -> usize {
+    mars::runtime::DEFAULT_MAX_BLOCK_TXS
+}
+
+fn default_max_mempool() -> usize {
+    mars::runtime::DEFAULT_MAX_MEMPOOL
+}
+
+fn default_block_interval() -> u64 {
+    mars::runtime::DEFAULT_BLOCK_INTERVAL
+}
+
+fn default_safe_mode_mismatches() -> u32 {
+    3
+}
+
+fn default_rpc_port() -> u16 {
+    8545
+}
+
 fn default_port() -> u16 {
     30303
 }
@@ -85,6 +244,18 @@ fn default_max_peers() -> usize {
     50
 }
 
+fn default_tx_gossip_ttl() -> u64 {
+    60
+}
+
+fn default_included_tx_depth() -> u64 {
+    64
+}
+
+fn default_height_divergence_grace() -> u64 {
+    120
+}
+
 fn default_true() -> bool {
     true
 }
@@ -94,6 +265,11 @@ impl Default for NodeSection {
         Self {
             data_dir: default_data_dir(),
             log_level: default_log_level(),
+            sync_threshold: default_sync_threshold(),
+            finality_retention: None,
+            block_retention: None,
+            snapshot_interval: default_snapshot_interval(),
+            verify_chain_on_startup: false,
         }
     }
 }
@@ -105,26 +281,40 @@ impl Default for NetworkSection {
             max_peers: default_max_peers(),
             bootstrap_peers: Vec::new(),
             enable_discovery: true,
+            tx_gossip_ttl_secs: default_tx_gossip_ttl(),
+            included_tx_depth: default_included_tx_depth(),
+            max_height_divergence: None,
+            height_divergence_grace_secs: default_height_divergence_grace(),
         }
     }
 }
 
-impl Default for RuntimeSection {
+impl Default for RpcSection {
     fn default() -> Self {
         Self {
-            chain_id: "unykorn-devnet".to_string(),
-            producer_enabled: false,
-            producer_key: None,
+            enabled: false,
+            listen_port: default_rpc_port(),
         }
     }
 }
 
-impl Default for NodeConfig {
+impl Default for RuntimeSection {
     fn default() -> Self {
         Self {
-            node: NodeSection::default(),
-            network: NetworkSection::default(),
-            runtime: RuntimeSection::default(),
+            chain_id: "unykorn-devnet".to_string(),
+            genesis_timestamp: 0,
+            producer_enabled: false,
+            producer_key: None,
+            persist_mempool: false,
+            authorized_producers: Vec::new(),
+            max_timestamp_skew_secs: default_max_timestamp_skew(),
+            min_fee: 0,
+            existential_deposit: 0,
+            reserved_addresses: Vec::new(),
+            max_block_txs: default_max_block_txs(),
+            max_mempool: default_max_mempool(),
+            block_interval_secs: default_block_interval(),
+            safe_mode_mismatches: default_safe_mode_mismatches(),
         }
     }
 }
@@ -135,8 +325,11 @@ impl NodeConfig {
         let content = std::fs::read_to_string(path)
             .map_err(|e| ConfigError::IoError(e.to_string()))?;
         
-        toml::from_str(&content)
-            .map_err(|e| ConfigError::ParseError(e.to_string()))
+        let config: Self = toml::from_str(&content)
+            .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+        config.consensus.validate()
+            .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+        Ok(config)
     }
 
     /// Create a development configuration.
@@ -145,18 +338,41 @@ impl NodeConfig {
             node: NodeSection {
                 data_dir: PathBuf::from("./dev_data"),
                 log_level: "debug".to_string(),
+                sync_threshold: default_sync_threshold(),
+                finality_retention: None,
+                block_retention: None,
+                snapshot_interval: default_snapshot_interval(),
+                verify_chain_on_startup: false,
             },
             network: NetworkSection {
                 listen_port: 30303,
                 max_peers: 10,
                 bootstrap_peers: Vec::new(),
                 enable_discovery: false,
+                tx_gossip_ttl_secs: default_tx_gossip_ttl(),
+                included_tx_depth: default_included_tx_depth(),
+                max_height_divergence: None,
+                height_divergence_grace_secs: default_height_divergence_grace(),
             },
             runtime: RuntimeSection {
                 chain_id: "unykorn-dev".to_string(),
+                genesis_timestamp: 0,
                 producer_enabled: true,
                 producer_key: Some("0".repeat(64)), // Dev key
+                persist_mempool: false,
+                authorized_producers: Vec::new(),
+                max_timestamp_skew_secs: default_max_timestamp_skew(),
+                min_fee: 0,
+                existential_deposit: 0,
+                reserved_addresses: Vec::new(),
+                max_block_txs: default_max_block_txs(),
+                max_mempool: default_max_mempool(),
+                block_interval_secs: default_block_interval(),
+                safe_mode_mismatches: default_safe_mode_mismatches(),
             },
+            rpc: RpcSection::default(),
+            consensus: consensus::ConsensusConfig::default(),
+            genesis: GenesisSection::default(),
         }
     }
 
@@ -166,6 +382,13 @@ impl NodeConfig {
             .parse()
             .unwrap()
     }
+
+    /// Get the RPC listen address.
+    pub fn rpc_addr(&self) -> SocketAddr {
+        format!("0.0.0.0:{}", self.rpc.listen_port)
+            .parse()
+            .unwrap()
+    }
 }
 
 /// Configuration errors.
@@ -176,6 +399,9 @@ pub enum ConfigError {
     
     #[error("Parse error: {0}")]
     ParseError(String),
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
 }
 
 #[cfg(test)]
@@ -196,6 +422,26 @@ mod tests {
         assert_eq!(config.network.max_peers, 10);
     }
 
+    #[test]
+    fn test_zero_consensus_timeout_rejected() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("node.toml");
+        std::fs::write(&path, "[consensus]\nprevote_timeout = 0\n").unwrap();
+        assert!(matches!(NodeConfig::load(&path), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_genesis_section_parses() {
+        let config: NodeConfig = toml::from_str(&format!(
+            "[genesis]\nvalidators = [\"{}\"]\nallocations = [[\"{}\", 1000]]\n",
+            "11".repeat(32),
+            "22".repeat(32),
+        ))
+        .unwrap();
+        assert_eq!(config.genesis.validators.len(), 1);
+        assert_eq!(config.genesis.allocations, vec![("22".repeat(32), 1000)]);
+    }
+
     #[test]
     fn test_serialize_config() {
         let config = NodeConfig::default();
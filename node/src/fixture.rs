@@ -0,0 +1,231 @@
+//! Deterministic replay fixtures.
+//!
+//! A fixture is a canonical chain: funded genesis accounts, then blocks of
+//! TEV-signed transactions with the block hash and state root each block
+//! must produce. Replaying it on any platform must reproduce those values
+//! byte for byte; a difference means execution is not deterministic.
+//!
+//! `unykorn fixture <path>` writes the canonical fixture, and the one
+//! recorded in `fixtures/replay.json` is replayed by the tests. Regenerate
+//! it only when a change to execution is intended.
+
+use crate::import::verify_transaction_payload;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Chain start time of the canonical fixture (Unix epoch seconds).
+const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+
+/// Seconds between fixture blocks.
+const BLOCK_SPACING: u64 = 3;
+
+/// Number of blocks in the canonical fixture.
+const FIXTURE_BLOCKS: u64 = 6;
+
+/// Starting balance of every fixture account.
+const GENESIS_BALANCE: u64 = 1_000_000;
+
+/// A canonical chain and the values replaying it must produce.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayFixture {
+    /// Genesis block timestamp
+    pub genesis_timestamp: u64,
+
+    /// Balances at genesis, by hex address
+    pub genesis_balances: BTreeMap<String, u64>,
+
+    /// Producer address of every block (hex)
+    pub producer: String,
+
+    /// The blocks, in height order from 1
+    pub blocks: Vec<FixtureBlock>,
+}
+
+/// One block of a replay fixture.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureBlock {
+    /// Block height
+    pub height: u64,
+
+    /// Block timestamp
+    pub timestamp: u64,
+
+    /// TEV-format signed transaction payloads (hex), in block order
+    pub transactions: Vec<String>,
+
+    /// Expected block hash (hex)
+    pub block_hash: String,
+
+    /// Expected state root after the block (hex)
+    pub state_root: String,
+}
+
+/// Why a fixture did not replay.
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("invalid fixture: {0}")]
+    InvalidFixture(String),
+
+    #[error("block #{height}: transaction {index} rejected: {reason}")]
+    TransactionRejected { height: u64, index: usize, reason: String },
+
+    #[error("block #{height}: {field} is {actual}, fixture expects {expected}")]
+    Mismatch {
+        height: u64,
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Generate the canonical fixture.
+///
+/// Four accounts with fixed keys pay each other, and the producer fees,
+/// in a fixed pattern; every block is produced at a fixed time, so the
+/// result never varies.
+pub fn generate() -> ReplayFixture {
+    let keypairs: Vec<tev::Keypair> = (1..=4u8).map(|i| tev::Keypair::from_secret(&[i; 32])).collect();
+    let producer = tev::Keypair::from_secret(&[9u8; 32]).public_key();
+    let genesis_balances: BTreeMap<String, u64> = keypairs
+        .iter()
+        .map(|k| (hex::encode(mars::address_from_pubkey(&k.public_key())), GENESIS_BALANCE))
+        .collect();
+
+    let mut fixture = ReplayFixture {
+        genesis_timestamp: GENESIS_TIMESTAMP,
+        genesis_balances,
+        producer: hex::encode(producer),
+        blocks: Vec::new(),
+    };
+    let mut runtime = genesis_runtime(&fixture).expect("canonical genesis is valid");
+
+    for height in 1..=FIXTURE_BLOCKS {
+        let mut payloads = BTreeMap::new();
+        for (i, keypair) in keypairs.iter().enumerate() {
+            // Senders take turns sitting a block out
+            if (i as u64 + height).is_multiple_of(4) {
+                continue;
+            }
+            let from = mars::address_from_pubkey(&keypair.public_key());
+            let to = mars::address_from_pubkey(&keypairs[(i + height as usize) % 4].public_key());
+            let amount = 100 * height + i as u64;
+            let tx = mars::Transaction::new(from, to, amount, runtime.state.nonce(&from))
+                .with_fee(i as u64 + 1);
+            let payload = signed_payload(keypair, &tx);
+
+            payloads.insert(tx.hash(), hex::encode(payload));
+            runtime.submit_transaction(tx).expect("canonical transaction is valid");
+        }
+
+        let timestamp = GENESIS_TIMESTAMP + height * BLOCK_SPACING;
+        let block = runtime.produce_block_at(producer, timestamp);
+        let transactions = block.txs.iter().map(|tx| payloads[&tx.hash()].clone()).collect();
+        fixture.blocks.push(FixtureBlock {
+            height,
+            timestamp: block.timestamp,
+            transactions,
+            block_hash: hex::encode(block.hash()),
+            state_root: hex::encode(block.state_root),
+        });
+    }
+
+    fixture
+}
+
+/// Replay `fixture`, checking every block hash and state root.
+///
+/// Transactions pass through TEV verification like gossip does.
+pub fn replay(fixture: &ReplayFixture) -> Result<(), ReplayError> {
+    let mut runtime = genesis_runtime(fixture)?;
+    let producer = decode_hex32(&fixture.producer)?;
+
+    for (expected_height, block) in (1..).zip(&fixture.blocks) {
+        if block.height != expected_height {
+            return Err(ReplayError::InvalidFixture(format!(
+                "block #{} found where #{} was expected",
+                block.height, expected_height
+            )));
+        }
+
+        for (index, payload) in block.transactions.iter().enumerate() {
+            let rejected = |reason: String| ReplayError::TransactionRejected {
+                height: block.height,
+                index,
+                reason,
+            };
+            let payload = hex::decode(payload).map_err(|e| rejected(e.to_string()))?;
+            let tx = verify_transaction_payload(&payload).map_err(|e| rejected(e.to_string()))?;
+            runtime.submit_transaction(tx).map_err(|e| rejected(e.to_string()))?;
+        }
+
+        let produced = runtime.produce_block_at(producer, block.timestamp);
+        let checks = [
+            ("timestamp", block.timestamp.to_string(), produced.timestamp.to_string()),
+            ("transaction count", block.transactions.len().to_string(), produced.tx_count().to_string()),
+            ("block hash", block.block_hash.clone(), hex::encode(produced.hash())),
+            ("state root", block.state_root.clone(), hex::encode(produced.state_root)),
+        ];
+        for (field, expected, actual) in checks {
+            if expected != actual {
+                return Err(ReplayError::Mismatch { height: block.height, field, expected, actual });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A runtime holding the fixture's genesis state.
+fn genesis_runtime(fixture: &ReplayFixture) -> Result<mars::Runtime, ReplayError> {
+    let mut state = mars::State::new();
+    for (address, balance) in &fixture.genesis_balances {
+        state.set_balance(&decode_hex32(address)?, *balance);
+    }
+    state.compute_state_root();
+
+    let genesis = mars::Block::genesis_at(fixture.genesis_timestamp);
+    Ok(mars::Runtime::with_state(state, &genesis, mars::RuntimeConfig::default()))
+}
+
+/// Build a TEV-format payload of a transaction signed by `keypair`.
+fn signed_payload(keypair: &tev::Keypair, tx: &mars::Transaction) -> Vec<u8> {
+    let data = bincode::serialize(tx).expect("transactions always serialize");
+    tev::sign_payload(keypair, &data)
+}
+
+/// Decode a 32-byte hex value.
+fn decode_hex32(value: &str) -> Result<[u8; 32], ReplayError> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| ReplayError::InvalidFixture(format!("{} is not 32 bytes of hex", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORDED: &str = include_str!("../fixtures/replay.json");
+
+    #[test]
+    fn test_recorded_fixture_replays_exactly() {
+        let fixture: ReplayFixture = serde_json::from_str(RECORDED).unwrap();
+        assert_eq!(fixture.blocks.len() as u64, FIXTURE_BLOCKS);
+        replay(&fixture).unwrap();
+
+        // The generator still produces the recorded chain
+        assert_eq!(generate(), fixture);
+    }
+
+    #[test]
+    fn test_tampered_fixture_reports_mismatch() {
+        let mut fixture: ReplayFixture = serde_json::from_str(RECORDED).unwrap();
+        fixture.blocks[2].state_root = hex::encode([0u8; 32]);
+
+        assert!(matches!(
+            replay(&fixture),
+            Err(ReplayError::Mismatch { height: 3, field: "state root", .. })
+        ));
+    }
+}
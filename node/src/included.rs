@@ -0,0 +1,90 @@
+//! Recently included transactions.
+//!
+//! Gossip keeps circulating a transaction for a while after it lands in a
+//! block. Re-submitting it would fail nonce validation and look like an
+//! invalid transaction, so the node remembers the hashes of transactions
+//! in the last few blocks and drops repeats quietly instead.
+
+use std::collections::{HashSet, VecDeque};
+
+/// Hashes of transactions included in the most recent blocks.
+#[derive(Debug)]
+pub struct IncludedTxs {
+    /// Number of blocks remembered
+    depth: u64,
+
+    /// Transaction hashes per block, oldest first
+    blocks: VecDeque<(u64, Vec<[u8; 32]>)>,
+
+    /// Every remembered hash
+    hashes: HashSet<[u8; 32]>,
+}
+
+impl IncludedTxs {
+    /// Remember transactions from the last `depth` blocks.
+    pub fn new(depth: u64) -> Self {
+        Self {
+            depth,
+            blocks: VecDeque::new(),
+            hashes: HashSet::new(),
+        }
+    }
+
+    /// Record the transactions of a newly applied block.
+    ///
+    /// Blocks more than `depth` below it are forgotten.
+    pub fn record(&mut self, block: &mars::Block) {
+        let hashes: Vec<[u8; 32]> = block.txs.iter().map(|tx| tx.hash()).collect();
+        self.hashes.extend(hashes.iter().copied());
+        self.blocks.push_back((block.height, hashes));
+
+        while let Some((height, _)) = self.blocks.front() {
+            if height + self.depth > block.height {
+                break;
+            }
+            if let Some((_, expired)) = self.blocks.pop_front() {
+                for hash in expired {
+                    self.hashes.remove(&hash);
+                }
+            }
+        }
+    }
+
+    /// Check if a transaction was included in a remembered block.
+    pub fn contains(&self, hash: &[u8; 32]) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    /// Number of remembered transactions.
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Check if no transactions are remembered.
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_with(height: u64, nonce: u64) -> mars::Block {
+        let tx = mars::Transaction::new([1u8; 32], [2u8; 32], 10, nonce);
+        mars::Block::new(height, [0u8; 32], [0u8; 32], vec![tx], [3u8; 32])
+    }
+
+    #[test]
+    fn test_forgets_blocks_beyond_depth() {
+        let mut included = IncludedTxs::new(2);
+        let first = block_with(1, 0);
+        included.record(&first);
+        included.record(&block_with(2, 1));
+        assert!(included.contains(&first.txs[0].hash()));
+
+        included.record(&block_with(3, 2));
+        assert!(!included.contains(&first.txs[0].hash()));
+        assert_eq!(included.len(), 2);
+    }
+}
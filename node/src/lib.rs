@@ -9,7 +9,15 @@
 //! ```
 
 pub mod config;
+pub mod fixture;
+pub mod import;
+pub mod included;
+pub mod metrics;
 pub mod node;
+pub mod proposal;
+pub mod rpc;
 
 pub use config::NodeConfig;
-pub use node::Node;
+pub use metrics::ConsensusMetrics;
+pub use node::{EquivocationEvidence, Node, NodeStatus};
+pub use proposal::BlockProposalValidator;
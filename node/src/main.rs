@@ -7,15 +7,27 @@ use std::path::PathBuf;
 
 #[tokio::main]
 async fn main() {
+    // Parse arguments
+    let args: Vec<String> = std::env::args().collect();
+
+    // Development tool: write the deterministic replay fixture and exit
+    if args.len() > 2 && args[1] == "fixture" {
+        let fixture = node::fixture::generate();
+        let json = serde_json::to_string_pretty(&fixture).expect("fixtures always serialize");
+        if let Err(e) = std::fs::write(&args[2], json + "\n") {
+            eprintln!("Failed to write fixture: {}", e);
+            std::process::exit(1);
+        }
+        println!("Wrote {} blocks to {}", fixture.blocks.len(), args[2]);
+        return;
+    }
+
     println!("╔═══════════════════════════════════════════╗");
     println!("║         UNYKORN L1 BLOCKCHAIN             ║");
     println!("║     MARS · POPEYE · TEV · TAR             ║");
     println!("╚═══════════════════════════════════════════╝");
     println!();
 
-    // Parse arguments
-    let args: Vec<String> = std::env::args().collect();
-    
     let config = if args.len() > 2 && args[1] == "--config" {
         let config_path = PathBuf::from(&args[2]);
         match NodeConfig::load(&config_path) {
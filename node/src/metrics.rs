@@ -0,0 +1,138 @@
+//! Prometheus metrics.
+//!
+//! Consensus progress is recorded by a `ConsensusObserver` backed by a
+//! Prometheus registry, keeping the consensus engine itself free of any
+//! metrics dependency.
+
+use consensus::{ConsensusObserver, FinalityCertificate, Prevote, Proposal, ValidatorId};
+use prometheus_client::encoding::text::encode;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::Registry;
+use std::time::Duration;
+
+/// Consensus counters, exported in the Prometheus text format.
+pub struct ConsensusMetrics {
+    registry: Registry,
+    proposals_received: Counter,
+    prevotes: Counter,
+    blocks_finalized: Counter,
+    round_timeouts: Counter,
+    equivocations: Counter,
+    finalized_height: Gauge,
+    finalization_latency: Histogram,
+}
+
+impl ConsensusMetrics {
+    /// Create the metrics and register them under the `consensus` prefix.
+    pub fn new() -> Self {
+        let mut root = Registry::default();
+        let registry = root.sub_registry_with_prefix("consensus");
+
+        let proposals_received = Counter::default();
+        registry.register(
+            "proposals_received",
+            "Valid proposals accepted from round leaders",
+            proposals_received.clone(),
+        );
+        let prevotes = Counter::default();
+        registry.register("prevotes", "Prevotes counted, including our own", prevotes.clone());
+        let blocks_finalized = Counter::default();
+        registry.register("blocks_finalized", "Heights finalized", blocks_finalized.clone());
+        let round_timeouts = Counter::default();
+        registry.register("round_timeouts", "Rounds that timed out", round_timeouts.clone());
+        let equivocations = Counter::default();
+        registry.register(
+            "equivocations",
+            "Conflicting votes seen from one validator in a round",
+            equivocations.clone(),
+        );
+        let finalized_height = Gauge::default();
+        registry.register(
+            "finalized_height",
+            "Highest finalized height",
+            finalized_height.clone(),
+        );
+
+        let finalization_latency = Histogram::new(exponential_buckets(0.05, 2.0, 10));
+        registry.register(
+            "finalization_latency_seconds",
+            "Time from starting a height to finalizing it",
+            finalization_latency.clone(),
+        );
+
+        Self {
+            registry: root,
+            proposals_received,
+            prevotes,
+            blocks_finalized,
+            round_timeouts,
+            equivocations,
+            finalized_height,
+            finalization_latency,
+        }
+    }
+
+    /// Render every metric in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String cannot fail
+        let _ = encode(&mut out, &self.registry);
+        out
+    }
+}
+
+impl Default for ConsensusMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsensusObserver for ConsensusMetrics {
+    fn on_proposal_received(&self, _proposal: &Proposal) {
+        self.proposals_received.inc();
+    }
+
+    fn on_prevote(&self, _prevote: &Prevote) {
+        self.prevotes.inc();
+    }
+
+    fn on_finalized(&self, certificate: &FinalityCertificate) {
+        self.blocks_finalized.inc();
+        self.finalized_height.set(certificate.height as i64);
+    }
+
+    fn on_finalization_latency(&self, _height: u64, latency: Duration) {
+        self.finalization_latency.observe(latency.as_secs_f64());
+    }
+
+    fn on_timeout(&self, _height: u64, _round: u64) {
+        self.round_timeouts.inc();
+    }
+
+    fn on_equivocation(&self, _validator: &ValidatorId, _height: u64, _round: u64) {
+        self.equivocations.inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observer_updates_metrics() {
+        let metrics = ConsensusMetrics::new();
+        metrics.on_timeout(1, 0);
+        metrics.on_timeout(1, 1);
+        metrics.on_finalized(&FinalityCertificate::new(7, [1u8; 32], Vec::new(), 0));
+        metrics.on_finalization_latency(7, Duration::from_millis(1500));
+
+        let text = metrics.encode();
+        assert!(text.contains("consensus_round_timeouts_total 2"), "{}", text);
+        assert!(text.contains("consensus_blocks_finalized_total 1"), "{}", text);
+        assert!(text.contains("consensus_finalized_height 7"), "{}", text);
+        assert!(text.contains("consensus_equivocations_total 0"), "{}", text);
+        assert!(text.contains("consensus_finalization_latency_seconds_sum 1.5"), "{}", text);
+    }
+}
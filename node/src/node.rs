@@ -2,31 +2,179 @@
 //!
 //! Wires together MARS, POPEYE, TEV, and TAR into a running node.
 
+use crate::import::{
+    self, BlockExecutor, Executed, ImportQueue, Inbound, Verified, IMPORT_QUEUE_CAPACITY,
+    VERIFY_QUEUE_CAPACITY,
+};
+use crate::rpc::{self, RpcCall, RpcRequest, RpcResponse};
+use crate::included::IncludedTxs;
+use crate::metrics::ConsensusMetrics;
+use crate::proposal::BlockProposalValidator;
 use crate::NodeConfig;
 use mars::Runtime;
-use popeye::{Network, NetworkConfig, NetworkMessage};
+use popeye::{Network, NetworkConfig, NetworkMessage, PeerId, SeenCache};
 use popeye::message::NetworkEvent;
-use tar::Storage;
-use tev::{verify_block, verify_transaction};
+use tar::{ConsensusStore, Storage};
+use serde::{Deserialize, Serialize};
+use consensus::{ConsensusEngine, ConsensusEvent};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
+/// Maximum number of relayed transaction hashes remembered.
+const TX_GOSSIP_CACHE_CAPACITY: usize = 10_000;
+
+/// Maximum number of historical account lookups remembered.
+const ACCOUNT_CACHE_CAPACITY: usize = 256;
+
+/// Bytes allowed in a consensus message beyond the proposal's block.
+const CONSENSUS_MESSAGE_OVERHEAD: usize = 64 * 1024;
+
+/// Most blocks requested or served in one `GetBlocks` exchange.
+const MAX_SYNC_BLOCKS: u64 = 128;
+
+/// Blocks a peer may be ahead before we sync from it; a one-block lead is
+/// usually just a block still being gossiped.
+const SYNC_LAG_THRESHOLD: u64 = 1;
+
+/// Most proposed blocks kept waiting for finality; past it, the blocks
+/// for the furthest heights are dropped first.
+const MAX_PROPOSED_BLOCKS: usize = 256;
+
+/// An account's state as of a given height.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct AccountView {
+    /// Height the view was taken at
+    pub height: u64,
+
+    /// Balance at that height
+    pub balance: u64,
+
+    /// Nonce at that height
+    pub nonce: u64,
+}
+
+/// Health / readiness snapshot of a node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct NodeStatus {
+    /// Current block height
+    pub height: u64,
+
+    /// Whether the node is within the sync threshold of its best peer
+    pub synced: bool,
+
+    /// Number of connected peers
+    pub peer_count: usize,
+
+    /// Number of pending transactions
+    pub mempool_size: usize,
+
+    /// Whether the node halted after repeated state-root mismatches
+    pub safe_mode: bool,
+
+    /// What the current consensus round waits for (`None` without consensus)
+    pub progress_blocker: Option<consensus::ProgressBlocker>,
+}
+
+/// Two conflicting messages signed by one validator, kept as evidence.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EquivocationEvidence {
+    /// The validator that signed both
+    pub validator: consensus::ValidatorId,
+
+    /// The message counted first
+    pub first: consensus::ConsensusMessage,
+
+    /// The conflicting message
+    pub second: consensus::ConsensusMessage,
+}
+
 /// The integrated node.
 pub struct Node {
     /// Configuration
     config: NodeConfig,
 
+    /// Genesis block built from the configuration
+    genesis: mars::Block,
+
     /// Runtime (MARS)
     runtime: Runtime,
 
     /// Storage (TAR)
     storage: Storage,
 
+    /// Consensus storage (TAR): finality certificates and round state
+    consensus_store: Arc<ConsensusStore>,
+
+    /// Audit log (TAR): one entry per finalized decision
+    audit_log: tar::AuditLog,
+
     /// Network (POPEYE)
     network: Network,
 
     /// Network event receiver
     network_rx: mpsc::Receiver<NetworkEvent>,
 
+    /// Producer signing key (if configured)
+    producer: Option<tev::Keypair>,
+
+    /// Transactions recently relayed to peers
+    gossiped_txs: SeenCache,
+
+    /// Transactions included in recent blocks
+    included_txs: IncludedTxs,
+
+    /// Recent historical account lookups, oldest first
+    account_cache: VecDeque<((u64, mars::Address), AccountView)>,
+
+    /// Consensus validator set, used to tell validator peers apart
+    validator_set: Option<Arc<consensus::ValidatorSet>>,
+
+    /// Whether connected validators carried quorum at the last check
+    quorum_reachable: bool,
+
+    /// Height of a peer block applied since the last production attempt
+    peer_block_height: Option<u64>,
+
+    /// Prometheus metrics fed by the consensus engine's observer hooks
+    consensus_metrics: Arc<ConsensusMetrics>,
+
+    /// Consensus engine deciding blocks, once enabled
+    consensus: Option<Arc<ConsensusEngine>>,
+
+    /// Sender handed to the consensus engine for its events
+    consensus_tx: mpsc::UnboundedSender<ConsensusEvent>,
+
+    /// Events emitted by the consensus engine
+    consensus_rx: mpsc::UnboundedReceiver<ConsensusEvent>,
+
+    /// Blocks carried by proposals awaiting finality, by hash
+    proposed_blocks: HashMap<[u8; 32], mars::Block>,
+
+    /// Blocks in a row whose state root we computed differently
+    root_mismatches: u32,
+
+    /// Halted after repeated state-root mismatches: no production or voting
+    safe_mode: bool,
+
+    /// Queue to the verification task, while the main loop runs
+    verifier: Option<mpsc::Sender<Inbound>>,
+
+    /// Verified blocks waiting to extend the tip
+    imports: ImportQueue,
+
+    /// Height of the block currently executing off the main loop
+    importing: Option<u64>,
+
+    /// Executes imported blocks on the blocking pool
+    executor: BlockExecutor,
+
+    /// Finished block executions, sent back by the blocking pool
+    executed_tx: mpsc::Sender<Executed>,
+
+    /// Receiver for finished block executions
+    executed_rx: mpsc::Receiver<Executed>,
+
     /// Shutdown signal sender
     shutdown_tx: Option<mpsc::Sender<()>>,
 }
@@ -35,11 +183,28 @@ impl Node {
     /// Create a new node from configuration.
     pub fn new(config: NodeConfig) -> Result<Self, NodeError> {
         // Initialize storage (TAR)
-        let storage = Storage::new(config.node.data_dir.clone())
+        let mut storage = Storage::new(config.node.data_dir.clone())
             .map_err(|e| NodeError::StorageInit(e.to_string()))?;
+        storage.set_block_schema(tar::Schema::new("block", mars::Block::SCHEMA_VERSION));
+        storage.set_state_schema(tar::Schema::new("state", mars::State::SCHEMA_VERSION));
+        let consensus_store = Arc::new(ConsensusStore::new(config.node.data_dir.join("consensus"))
+            .map_err(|e| NodeError::StorageInit(e.to_string()))?);
+        let audit_log = tar::AuditLog::new(config.node.data_dir.join("audit"))
+            .map_err(|e| NodeError::StorageInit(e.to_string()))?;
+
+        // Initialize runtime (MARS) from the configured genesis
+        let genesis_state = Self::genesis_state(&config)?;
+        let genesis = mars::Block {
+            state_root: genesis_state.state_root,
+            ..mars::Block::genesis_for(&config.runtime.chain_id, config.runtime.genesis_timestamp)
+        };
+        let genesis_validators = Self::genesis_validators(&config)?;
+        Self::check_genesis(&storage, &consensus_store, &genesis, genesis_validators.as_ref())?;
 
-        // Initialize runtime (MARS)
-        let runtime = if storage.has_state() {
+        let runtime_config = mars::RuntimeConfig {
+            existential_deposit: config.runtime.existential_deposit,
+        };
+        let mut runtime = if storage.has_state() {
             // Recover from disk
             let state = storage.load_state()
                 .map_err(|e| NodeError::StorageInit(e.to_string()))?;
@@ -47,50 +212,203 @@ impl Node {
                 .map_err(|e| NodeError::StorageInit(e.to_string()))?
                 .unwrap_or(0);
             
-            // Load last block hash
-            let last_hash = if last_height > 0 {
-                let block: mars::Block = storage.load_block(last_height)
-                    .map_err(|e| NodeError::StorageInit(e.to_string()))?;
-                block.hash()
+            // Load last block
+            let last_block = if last_height > 0 {
+                storage.load_block(last_height)
+                    .map_err(|e| NodeError::StorageInit(e.to_string()))?
             } else {
-                mars::Block::genesis().hash()
+                genesis.clone()
             };
-            
-            Runtime::with_state(state, last_hash)
+
+            Runtime::with_state(state, &last_block, runtime_config)
         } else {
-            Runtime::new()
+            // Historical queries replay forward from the genesis snapshot
+            storage.save_snapshot(0, &genesis_state)
+                .map_err(|e| NodeError::StorageInit(e.to_string()))?;
+            if let Some(set) = &genesis_validators {
+                consensus_store.save_validator_set_at(0, set)
+                    .and_then(|_| consensus_store.save_validator_set(set))
+                    .map_err(|e| NodeError::StorageInit(e.to_string()))?;
+            }
+            Runtime::with_state(genesis_state, &genesis, runtime_config)
         };
 
+        // Restrict block authorship to the configured producers
+        let producers = config.runtime.authorized_producers.iter()
+            .map(|key| Self::parse_key(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        runtime.set_authorized_producers(producers);
+        runtime.set_max_timestamp_skew(config.runtime.max_timestamp_skew_secs);
+        runtime.set_min_fee(config.runtime.min_fee);
+        let reserved = config.runtime.reserved_addresses.iter()
+            .map(|address| Self::parse_key(address))
+            .collect::<Result<Vec<_>, _>>()?;
+        runtime.set_reserved_addresses(reserved);
+        runtime.set_max_block_txs(config.runtime.max_block_txs);
+        runtime.set_max_mempool(config.runtime.max_mempool);
+        runtime.set_block_interval(config.runtime.block_interval_secs);
+
+        // Restore pending transactions (re-validated against recovered state)
+        if config.runtime.persist_mempool {
+            let pending: Option<Vec<mars::Transaction>> = storage.load_mempool()
+                .map_err(|e| NodeError::StorageInit(e.to_string()))?;
+            if let Some(txs) = pending {
+                let total = txs.len();
+                let restored = runtime.restore_mempool(txs);
+                println!(
+                    "Restored {} pending transactions ({} dropped as invalid)",
+                    restored,
+                    total - restored
+                );
+                storage.clear_mempool()
+                    .map_err(|e| NodeError::StorageInit(e.to_string()))?;
+            }
+        }
+
         // Initialize network (POPEYE)
-        let node_id = Self::derive_node_id(&config);
-        let network_config = NetworkConfig::new(config.listen_addr(), node_id)
+        let producer = config.runtime.producer_key.as_deref()
+            .map(|key| Self::parse_key(key).map(|secret| tev::Keypair::from_secret(&secret)))
+            .transpose()?;
+        let node_id = Self::derive_node_id(producer.as_ref());
+        let mut network_config = NetworkConfig::new(config.listen_addr(), node_id)
             .with_max_peers(config.network.max_peers);
+        if let Some(threshold) = config.network.max_height_divergence {
+            network_config = network_config.with_height_divergence(
+                threshold,
+                std::time::Duration::from_secs(config.network.height_divergence_grace_secs),
+            );
+        }
         
         let (network, network_rx) = Network::new(network_config);
+        let gossiped_txs = SeenCache::new(
+            std::time::Duration::from_secs(config.network.tx_gossip_ttl_secs),
+            TX_GOSSIP_CACHE_CAPACITY,
+        );
+        let included_txs = IncludedTxs::new(config.network.included_tx_depth);
+        let (executed_tx, executed_rx) = mpsc::channel(1);
+        let (consensus_tx, consensus_rx) = mpsc::unbounded_channel();
 
         Ok(Self {
             config,
+            genesis,
             runtime,
             storage,
+            consensus_store,
+            audit_log,
             network,
             network_rx,
+            producer,
+            gossiped_txs,
+            included_txs,
+            account_cache: VecDeque::new(),
+            validator_set: genesis_validators.map(Arc::new),
+            quorum_reachable: true,
+            peer_block_height: None,
+            consensus_metrics: Arc::new(ConsensusMetrics::new()),
+            consensus: None,
+            consensus_tx,
+            consensus_rx,
+            proposed_blocks: HashMap::new(),
+            root_mismatches: 0,
+            safe_mode: false,
+            verifier: None,
+            imports: ImportQueue::new(IMPORT_QUEUE_CAPACITY),
+            importing: None,
+            executor: Runtime::execute_block,
+            executed_tx,
+            executed_rx,
             shutdown_tx: None,
         })
     }
 
-    /// Derive node ID from config (or generate one).
-    fn derive_node_id(config: &NodeConfig) -> [u8; 32] {
-        if let Some(ref key) = config.runtime.producer_key {
-            // Use producer key as node ID (simplified)
-            let mut id = [0u8; 32];
-            let bytes = key.as_bytes();
-            for (i, &b) in bytes.iter().take(32).enumerate() {
-                id[i] = b;
+    /// The state seeded by the configured genesis allocations.
+    fn genesis_state(config: &NodeConfig) -> Result<mars::State, NodeError> {
+        let mut state = mars::State::new();
+        let mut seen = HashSet::new();
+        for (address, balance) in &config.genesis.allocations {
+            let address = Self::parse_key(address)?;
+            if !seen.insert(address) {
+                return Err(NodeError::InvalidConfig(format!(
+                    "duplicate genesis allocation for {}",
+                    hex::encode(address)
+                )));
             }
-            id
-        } else {
-            // Generate a random ID
-            [0u8; 32]
+            state.set_balance(&address, *balance);
+        }
+        state.compute_state_root();
+        Ok(state)
+    }
+
+    /// The configured genesis validator set, if any validators are listed.
+    fn genesis_validators(config: &NodeConfig) -> Result<Option<consensus::ValidatorSet>, NodeError> {
+        if config.genesis.validators.is_empty() {
+            return Ok(None);
+        }
+        let pubkeys = config.genesis.validators.iter()
+            .map(|key| Self::parse_key(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(consensus::ValidatorSet::new(pubkeys)))
+    }
+
+    /// Check that stored chain data grew from the configured genesis.
+    ///
+    /// The genesis snapshot must hold the configured allocations, the
+    /// first stored block must extend the configured genesis block (whose
+    /// hash covers the chain ID, genesis time and allocations), and a stored genesis
+    /// validator set must match the configured one. Fresh storage passes.
+    fn check_genesis(
+        storage: &Storage,
+        consensus_store: &ConsensusStore,
+        genesis: &mars::Block,
+        validators: Option<&consensus::ValidatorSet>,
+    ) -> Result<(), NodeError> {
+        let storage_err = |e: tar::StorageError| NodeError::StorageInit(e.to_string());
+        let mismatch = |what: &str| {
+            NodeError::GenesisMismatch(format!("configured {} differ from the stored chain", what))
+        };
+
+        if storage.snapshot_at_or_below(0).map_err(storage_err)?.is_some() {
+            let mut stored: mars::State = storage.load_snapshot(0).map_err(storage_err)?;
+            stored.compute_state_root();
+            if stored.state_root != genesis.state_root {
+                return Err(mismatch("allocations"));
+            }
+        }
+
+        if storage.block_exists(1) {
+            let first: mars::Block = storage.load_block(1).map_err(storage_err)?;
+            if first.parent_hash != genesis.hash() {
+                return Err(mismatch("genesis parameters"));
+            }
+        }
+
+        if let Some(configured) = validators {
+            let stored: Option<consensus::ValidatorSet> =
+                consensus_store.load_validator_set_at(0).map_err(storage_err)?;
+            let keys = |set: &consensus::ValidatorSet| -> Vec<([u8; 32], u64)> {
+                set.iter().map(|v| (v.pubkey, v.weight)).collect()
+            };
+            if stored.is_some_and(|stored| keys(&stored) != keys(configured)) {
+                return Err(mismatch("validators"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse a hex-encoded 32-byte key.
+    fn parse_key(key: &str) -> Result<[u8; 32], NodeError> {
+        let bytes = hex::decode(key.trim_start_matches("0x"))
+            .map_err(|e| NodeError::InvalidConfig(format!("invalid key {}: {}", key, e)))?;
+        bytes.try_into()
+            .map_err(|_| NodeError::InvalidConfig(format!("key {} is not 32 bytes", key)))
+    }
+
+    /// Derive node ID from the producer key (or use a placeholder).
+    fn derive_node_id(producer: Option<&tev::Keypair>) -> [u8; 32] {
+        match producer {
+            Some(keypair) => mars::address_from_pubkey(&keypair.public_key()),
+            None => [0u8; 32],
         }
     }
 
@@ -102,11 +420,51 @@ impl Node {
         println!("  Height: {}", self.runtime.height());
         println!("  Producer: {}", self.config.runtime.producer_enabled);
 
+        if self.config.node.verify_chain_on_startup {
+            println!("  Verifying stored chain...");
+            self.verify_chain()?;
+        }
+
+        let (tip, tip_hash) = self.resolve_tip()?;
+        println!("  Tip: #{} ({})", tip, hex::encode(&tip_hash[..8]));
+
+        // A stored validator set means blocks are decided by consensus
+        if self.consensus.is_none() {
+            let stored: Option<consensus::ValidatorSet> = self.consensus_store
+                .load_validator_set()
+                .map_err(|e| NodeError::StorageError(e.to_string()))?;
+            if let Some(validator_set) = stored {
+                println!("  Consensus: {} validators", validator_set.len());
+                self.enable_consensus(validator_set).await?;
+            }
+        }
+        let timeout_driver = self.consensus.clone().map(|engine| engine.spawn_timeout_driver());
+
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
         self.shutdown_tx = Some(shutdown_tx);
 
-        // Block production interval (3 seconds for devnet)
-        let mut block_interval = tokio::time::interval(tokio::time::Duration::from_secs(3));
+        // RPC queries are answered from this loop; the sender is kept alive
+        // so the branch simply stays idle when RPC is disabled.
+        let (rpc_tx, mut rpc_rx) = mpsc::channel::<RpcCall>(64);
+        if self.config.rpc.enabled {
+            let listener = tokio::net::TcpListener::bind(self.config.rpc_addr())
+                .await
+                .map_err(|e| NodeError::RpcError(e.to_string()))?;
+            println!("  RPC: {}", self.config.rpc_addr());
+            tokio::spawn(rpc::serve(listener, rpc_tx.clone()));
+        }
+
+        // Block production interval
+        let mut block_interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            self.config.runtime.block_interval_secs.max(1),
+        ));
+
+        // Report peers that dropped and did not come back in time
+        let mut probation_interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+
+        // Gossip payloads are verified off this loop
+        let (verifier, mut verified_rx) = import::spawn_verifier(VERIFY_QUEUE_CAPACITY);
+        self.verifier = Some(verifier);
 
         loop {
             tokio::select! {
@@ -117,23 +475,72 @@ impl Node {
                     }
                 }
 
-                // Block production (if producer)
-                _ = block_interval.tick(), if self.config.runtime.producer_enabled => {
+                // Accept verified transactions and queue verified blocks
+                Some(verified) = verified_rx.recv() => {
+                    let result = match verified {
+                        Ok(Verified::Transaction { tx, payload }) => {
+                            self.accept_transaction(tx, payload).await.map(|_| ())
+                        }
+                        Ok(Verified::Block { block, payload }) => self.import_block(block, payload),
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = result {
+                        eprintln!("Error handling network payload: {}", e);
+                    }
+                }
+
+                // Adopt a block executed off this loop
+                Some(executed) = self.executed_rx.recv() => {
+                    if let Err(e) = self.finish_import(executed).await {
+                        eprintln!("Error importing block: {}", e);
+                    }
+                }
+
+                // Act on consensus decisions and relay its messages
+                Some(event) = self.consensus_rx.recv() => {
+                    if let Err(e) = self.handle_consensus_event(event).await {
+                        eprintln!("Error handling consensus event: {}", e);
+                    }
+                }
+
+                // Timed block production (if producer, without consensus)
+                _ = block_interval.tick(), if self.config.runtime.producer_enabled && self.consensus.is_none() && !self.safe_mode => {
                     match self.produce_block() {
                         Ok(block) => {
-                            // Broadcast block to peers
-                            let msg = popeye::message::BlockMessage::new(
-                                bincode::serialize(&block).unwrap_or_default(),
-                                block.height,
-                            );
-                            let _ = self.network.broadcast(NetworkMessage::Block(msg)).await;
+                            // Broadcast the signed block to peers
+                            if let Ok(payload) = self.storage.load_block_payload(block.height) {
+                                let msg = popeye::message::BlockMessage::new(payload, block.height);
+                                let _ = self.network.broadcast(NetworkMessage::Block(msg)).await;
+                            }
                         }
+                        // A peer filled this slot first
+                        Err(NodeError::ProductionSuperseded { .. }) => {}
                         Err(e) => {
                             eprintln!("Block production error: {}", e);
                         }
                     }
                 }
 
+                _ = probation_interval.tick() => {
+                    if let Err(e) = self.network.expire_probation().await {
+                        eprintln!("Error expiring peer probation: {}", e);
+                    }
+                    match self.network.check_divergence(self.runtime.height()).await {
+                        Ok(dropped) => {
+                            for (peer, divergence) in dropped {
+                                println!("Disconnected peer {} ({})", peer, divergence);
+                            }
+                        }
+                        Err(e) => eprintln!("Error checking peer heights: {}", e),
+                    }
+                    self.check_quorum_reachable();
+                }
+
+                // Answer RPC queries
+                Some(call) = rpc_rx.recv() => {
+                    self.handle_rpc(call).await;
+                }
+
                 // Handle shutdown
                 _ = shutdown_rx.recv() => {
                     println!("Shutting down...");
@@ -142,17 +549,59 @@ impl Node {
             }
         }
 
+        drop(rpc_tx);
+        self.verifier = None;
+        if let Some(driver) = timeout_driver {
+            driver.abort();
+        }
         Ok(())
     }
 
+    /// Answer an RPC query.
+    async fn handle_rpc(&mut self, call: RpcCall) {
+        let response = match call.request {
+            RpcRequest::Status => RpcResponse::Status(self.status().await),
+            RpcRequest::Height => RpcResponse::Height(self.runtime.height()),
+            RpcRequest::Balance(address) => RpcResponse::Balance(AccountView {
+                height: self.runtime.height(),
+                balance: self.runtime.state.balance(&address),
+                nonce: self.runtime.state.nonce(&address),
+            }),
+            RpcRequest::MempoolSize => RpcResponse::MempoolSize(self.runtime.mempool_size()),
+            RpcRequest::Block(0) => RpcResponse::Block(Some(Box::new(self.genesis.clone()))),
+            RpcRequest::Block(height) => {
+                RpcResponse::Block(self.storage.load_block(height).ok().map(Box::new))
+            }
+            RpcRequest::SubmitTransaction(payload) => {
+                RpcResponse::Submitted(self.submit_transaction(payload).await.map_err(|e| e.to_string()))
+            }
+            RpcRequest::Metrics => RpcResponse::Metrics(self.encode_metrics()),
+        };
+        let _ = call.respond_to.send(response);
+    }
+
     /// Handle a network event.
     async fn handle_network_event(&mut self, event: NetworkEvent) -> Result<(), NodeError> {
         match event {
-            NetworkEvent::MessageReceived { from: _, message } => {
-                self.handle_message(message).await?;
+            NetworkEvent::MessageReceived { from, message } => {
+                // Our own gossip echoed back
+                if &from == self.network.local_id().as_bytes() {
+                    return Ok(());
+                }
+                let from = PeerId::new(from);
+                match message {
+                    NetworkMessage::Handshake(handshake) => self.handle_handshake(from, handshake),
+                    NetworkMessage::GetBlocks { from_height, to_height } => {
+                        self.serve_blocks(from, from_height, to_height)?;
+                    }
+                    NetworkMessage::Blocks(blocks) => self.handle_synced_blocks(from, blocks).await?,
+                    message => self.handle_message(from, message).await?,
+                }
             }
             NetworkEvent::PeerConnected { peer_id } => {
                 println!("Peer connected: {:02x}{:02x}...", peer_id[0], peer_id[1]);
+                let handshake = NetworkMessage::Handshake(self.handshake());
+                let _ = self.network.send_to(&PeerId::new(peer_id), handshake);
             }
             NetworkEvent::PeerDisconnected { peer_id } => {
                 println!("Peer disconnected: {:02x}{:02x}...", peer_id[0], peer_id[1]);
@@ -161,14 +610,35 @@ impl Node {
         Ok(())
     }
 
-    /// Handle an incoming message.
-    async fn handle_message(&mut self, message: NetworkMessage) -> Result<(), NodeError> {
+    /// Handle an incoming message from peer `from`.
+    ///
+    /// While the main loop runs, transactions and blocks are handed to the
+    /// verification task; a full queue drops the payload, as gossip will
+    /// deliver it again.
+    async fn handle_message(&mut self, from: PeerId, message: NetworkMessage) -> Result<(), NodeError> {
         match message {
-            NetworkMessage::Transaction(tx_msg) => {
-                self.handle_transaction(tx_msg.payload).await?;
-            }
-            NetworkMessage::Block(block_msg) => {
-                self.handle_block(block_msg.payload).await?;
+            NetworkMessage::Transaction(tx_msg) => match &self.verifier {
+                Some(verifier) => {
+                    if verifier.try_send(Inbound::Transaction(tx_msg.payload)).is_err() {
+                        eprintln!("Verification queue full, dropping transaction");
+                    }
+                }
+                None => {
+                    self.handle_transaction(tx_msg.payload).await?;
+                }
+            },
+            NetworkMessage::Block(block_msg) => match &self.verifier {
+                Some(verifier) => {
+                    if verifier.try_send(Inbound::Block(block_msg.payload)).is_err() {
+                        eprintln!("Verification queue full, dropping block");
+                    }
+                }
+                None => {
+                    self.handle_block(block_msg.payload).await?;
+                }
+            },
+            NetworkMessage::Consensus(consensus_msg) => {
+                self.handle_consensus_message(from, consensus_msg.payload).await?;
             }
             NetworkMessage::Ping(n) => {
                 // Respond with pong
@@ -177,9 +647,124 @@ impl Node {
             NetworkMessage::Pong(_) => {
                 // Ignore pongs
             }
-            NetworkMessage::Handshake(_) => {
-                // Handle handshake
+            NetworkMessage::Handshake(_)
+            | NetworkMessage::GetBlocks { .. }
+            | NetworkMessage::Blocks(_) => {
+                // Answered per peer in `handle_network_event`
+            }
+        }
+        Ok(())
+    }
+
+    /// Our handshake: the genesis hash identifies the chain.
+    fn handshake(&self) -> popeye::message::HandshakeMessage {
+        popeye::message::HandshakeMessage::new(
+            self.genesis.hash(),
+            self.runtime.height(),
+            *self.network.local_id().as_bytes(),
+        )
+    }
+
+    /// Record a peer's height from its handshake and start catching up if
+    /// it is more than `SYNC_LAG_THRESHOLD` blocks ahead. Peers on another
+    /// chain are ignored.
+    fn handle_handshake(&mut self, from: PeerId, handshake: popeye::message::HandshakeMessage) {
+        if handshake.chain_id != self.genesis.hash() {
+            return;
+        }
+        if self.network.update_peer_height(&from, handshake.height).is_err() {
+            return;
+        }
+        let height = self.runtime.height();
+        if handshake.height > height.saturating_add(SYNC_LAG_THRESHOLD) {
+            self.request_blocks(from, height + 1, handshake.height);
+        }
+    }
+
+    /// Ask `peer` for the blocks from `from_height` up to `peer_height`,
+    /// at most `MAX_SYNC_BLOCKS` at a time.
+    fn request_blocks(&self, peer: PeerId, from_height: u64, peer_height: u64) {
+        if from_height > peer_height {
+            return;
+        }
+        let to_height = peer_height.min(from_height + MAX_SYNC_BLOCKS - 1);
+        let request = NetworkMessage::GetBlocks { from_height, to_height };
+        if let Err(e) = self.network.send_to(&peer, request) {
+            eprintln!("Failed to request blocks from {}: {}", peer, e);
+        }
+    }
+
+    /// Answer a `GetBlocks` from `peer` with the blocks we have.
+    ///
+    /// The range is capped at `MAX_SYNC_BLOCKS` and ends at the first block
+    /// we cannot serve; a request for nothing we have gets no reply. Blocks
+    /// go out with their finality certificates, where we have them.
+    fn serve_blocks(&self, peer: PeerId, from_height: u64, to_height: u64) -> Result<(), NodeError> {
+        let from_height = from_height.max(1);
+        let to_height = to_height
+            .min(from_height.saturating_add(MAX_SYNC_BLOCKS - 1))
+            .min(self.runtime.height());
+
+        let mut blocks = Vec::new();
+        for height in from_height..=to_height {
+            let Ok(payload) = self.storage.load_block_payload(height) else {
+                break;
+            };
+            let mut block = popeye::message::BlockMessage::new(payload, height);
+            let certificate: Option<consensus::FinalityCertificate> = self.consensus_store
+                .load_finality_certificate(height)
+                .map_err(|e| NodeError::StorageError(e.to_string()))?;
+            if let Some(certificate) = certificate {
+                let message = consensus::ConsensusMessage::Certificate(certificate);
+                block = block.with_certificate(consensus::wire::encode_message(&message).map_err(consensus_err)?);
+            }
+            blocks.push(block);
+        }
+
+        if !blocks.is_empty() {
+            self.network.send_to(&peer, NetworkMessage::Blocks(blocks))
+                .map_err(|e| NodeError::NetworkError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Import blocks `peer` sent for a `GetBlocks`, then ask for the next
+    /// range while it is still ahead.
+    ///
+    /// A certificate sent with a block is verified and stored first (see
+    /// `accept_certificate`), and the block must be the certified one; it
+    /// needs no producer signature then. Other blocks are verified and
+    /// queued like gossiped ones.
+    async fn handle_synced_blocks(
+        &mut self,
+        peer: PeerId,
+        blocks: Vec<popeye::message::BlockMessage>,
+    ) -> Result<(), NodeError> {
+        let mut last = None;
+        for mut block in blocks.into_iter().take(MAX_SYNC_BLOCKS as usize) {
+            last = Some(block.height);
+            if let Some(encoded) = block.certificate.take() {
+                let certificate = match consensus::wire::decode_message(&encoded, CONSENSUS_MESSAGE_OVERHEAD) {
+                    Ok(consensus::ConsensusMessage::Certificate(c)) if c.height == block.height => c,
+                    _ => {
+                        return Err(NodeError::ValidationFailed(format!(
+                            "bad certificate for block #{}",
+                            block.height
+                        )))
+                    }
+                };
+                self.accept_certificate(&certificate)?;
+
+                // TEV: The certificate stands in for the producer signature
+                let decoded = import::decode_certified_block(&block.payload, &certificate.block_hash)?;
+                self.import_block(decoded, block.payload)?;
+                continue;
             }
+            self.handle_message(peer, NetworkMessage::Block(block)).await?;
+        }
+
+        if let (Some(last), Some(info)) = (last, self.network.get_peer(&peer)) {
+            self.request_blocks(peer, last + 1, info.height);
         }
         Ok(())
     }
@@ -187,106 +772,983 @@ impl Node {
     /// Handle an incoming transaction.
     ///
     /// Flow: POPEYE → TEV → MARS → (broadcast)
-    async fn handle_transaction(&mut self, payload: Vec<u8>) -> Result<(), NodeError> {
+    ///
+    /// A transaction already relayed within the gossip TTL, or included in
+    /// a recent block, is dropped quietly. Returns whether the transaction
+    /// was relayed.
+    async fn handle_transaction(&mut self, payload: Vec<u8>) -> Result<bool, NodeError> {
         // TEV: Verify signature
-        let verified = verify_transaction(&payload)
-            .map_err(|e| NodeError::ValidationFailed(e.to_string()))?;
+        let tx = import::verify_transaction_payload(&payload)?;
+        self.accept_transaction(tx, payload).await
+    }
 
-        // MARS: Parse and validate
-        let tx: mars::Transaction = bincode::deserialize(verified.data())
-            .map_err(|_| NodeError::InvalidPayload)?;
+    /// Submit a transaction payload from a client rather than a peer.
+    ///
+    /// Flow: TEV → MARS → (broadcast), as for gossip. Returns the
+    /// transaction hash.
+    async fn submit_transaction(&mut self, payload: Vec<u8>) -> Result<[u8; 32], NodeError> {
+        let tx = import::verify_transaction_payload(&payload)?;
+        let hash = tx.hash();
+        self.accept_transaction(tx, payload).await?;
+        Ok(hash)
+    }
+
+    /// Submit a verified transaction and relay it.
+    async fn accept_transaction(
+        &mut self,
+        tx: mars::Transaction,
+        payload: Vec<u8>,
+    ) -> Result<bool, NodeError> {
+        // Already accepted and relayed recently, or already applied
+        let hash = tx.hash();
+        if self.gossiped_txs.contains(&hash) || self.included_txs.contains(&hash) {
+            return Ok(false);
+        }
 
         // MARS: Submit to runtime
         self.runtime.submit_transaction(tx)
             .map_err(|e| NodeError::RuntimeError(e.to_string()))?;
 
         // Broadcast to peers
+        self.gossiped_txs.insert(hash);
         let msg = popeye::message::TransactionMessage::new(payload);
         let _ = self.network.broadcast(NetworkMessage::Transaction(msg)).await;
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// Submit a signed transaction bundle.
+    ///
+    /// Flow: TEV → MARS → (broadcast)
+    ///
+    /// The bundle is accepted or rejected as a unit: if the bundle
+    /// signature, any contained signature or any transaction is invalid,
+    /// nothing is added to the mempool. Accepted transactions are relayed
+    /// individually. Returns the number of transactions accepted.
+    pub async fn submit_bundle(&mut self, payload: Vec<u8>) -> Result<usize, NodeError> {
+        let entries = import::verify_bundle_payload(&payload)?;
+
+        // MARS: Submit in order, all or nothing
+        let txs = entries.iter().map(|(tx, _)| tx.clone()).collect();
+        self.runtime.submit_bundle(txs)
+            .map_err(|e| NodeError::RuntimeError(e.to_string()))?;
+
+        // Broadcast to peers
+        for (tx, tx_payload) in &entries {
+            self.gossiped_txs.insert(tx.hash());
+            let msg = popeye::message::TransactionMessage::new(tx_payload.clone());
+            let _ = self.network.broadcast(NetworkMessage::Transaction(msg)).await;
+        }
+
+        Ok(entries.len())
     }
 
     /// Handle an incoming block.
     ///
     /// Flow: POPEYE → TEV → MARS → TAR
+    ///
+    /// The block is queued and executed off the main loop; `finish_imports`
+    /// waits for it to be applied.
     async fn handle_block(&mut self, payload: Vec<u8>) -> Result<(), NodeError> {
         // TEV: Verify signature
-        let verified = verify_block(&payload)
-            .map_err(|e| NodeError::ValidationFailed(e.to_string()))?;
+        let block = import::verify_block_payload(&payload)?;
+        self.import_block(block, payload)
+    }
+
+    /// Queue a verified block and start executing it if it extends the tip.
+    ///
+    /// Blocks we already have are ignored.
+    fn import_block(&mut self, block: mars::Block, payload: Vec<u8>) -> Result<(), NodeError> {
+        if self.imports.insert(self.runtime.height(), block, payload) {
+            self.start_next_import()?;
+        }
+        Ok(())
+    }
 
-        // MARS: Parse and validate
-        let block: mars::Block = bincode::deserialize(verified.data())
-            .map_err(|_| NodeError::InvalidPayload)?;
+    /// Execute the block for the next height on the blocking pool, unless
+    /// one is already executing.
+    fn start_next_import(&mut self) -> Result<(), NodeError> {
+        if self.importing.is_some() {
+            return Ok(());
+        }
+        let height = self.runtime.height() + 1;
+        let Some((block, payload)) = self.imports.take(height) else {
+            return Ok(());
+        };
+        self.check_canonical(&block)?;
+        self.check_producer(&block)?;
 
-        // MARS: Validate block
+        // MARS: Validate block (including producer authorization)
         self.runtime.validate_block(&block)
             .map_err(|e| NodeError::RuntimeError(e.to_string()))?;
 
+        // MARS: Execute against a copy of the state
+        let state = self.runtime.state.clone();
+        let execute = self.executor;
+        let runtime_config = self.runtime.config();
+        let executed_tx = self.executed_tx.clone();
+        self.importing = Some(height);
+        tokio::task::spawn_blocking(move || {
+            let result = execute(&state, &block, &runtime_config);
+            let _ = executed_tx.blocking_send(Executed { block, payload, result });
+        });
+
+        Ok(())
+    }
+
+    /// Refuse a block that leaves the finalized chain.
+    ///
+    /// Our tip may have been built locally before finality said otherwise,
+    /// so matching `last_block_hash` is not enough: where a finality
+    /// certificate covers the parent's height, the parent must be the
+    /// certified block, and where one covers the block's own height, the
+    /// block must be the certified one.
+    ///
+    /// Under consensus a block is only adopted once a verified certificate
+    /// covers its height; anything else is a block nobody decided.
+    fn check_canonical(&self, block: &mars::Block) -> Result<(), NodeError> {
+        let certified = |height: u64| -> Result<Option<[u8; 32]>, NodeError> {
+            let certificate: Option<consensus::FinalityCertificate> = self.consensus_store
+                .load_finality_certificate(height)
+                .map_err(|e| NodeError::StorageError(e.to_string()))?;
+            Ok(certificate.map(|c| c.block_hash))
+        };
+
+        let parent_height = block.height.saturating_sub(1);
+        let canonical_parent = match parent_height {
+            0 => Some(self.genesis.hash()),
+            height => certified(height)?,
+        };
+        let certified_block = certified(block.height)?;
+        if self.consensus.is_some() && certified_block.is_none() {
+            return Err(NodeError::Uncertified { height: block.height });
+        }
+        let parent_ok = canonical_parent.is_none_or(|hash| hash == block.parent_hash);
+        let block_ok = certified_block.is_none_or(|hash| hash == block.hash());
+        if !(parent_ok && block_ok) {
+            return Err(NodeError::NonCanonical { height: block.height });
+        }
+        Ok(())
+    }
+
+    /// Adopt an executed block, then start on the next queued one.
+    async fn finish_import(&mut self, executed: Executed) -> Result<(), NodeError> {
+        self.importing = None;
+        let result = self.adopt_executed(executed).await;
+        let next = self.start_next_import();
+        result.and(next)
+    }
+
+    /// Store a block that became the tip, with its summary, the resulting
+    /// state and the changes it made.
+    ///
+    /// The `payload` is kept so the block can be served to peers catching
+    /// up: producer-signed, or the plain block where a finality
+    /// certificate vouches for it. Everything but the state is written
+    /// before the block itself, so a stored block is never missing them.
+    fn persist_block(
+        &self,
+        block: &mars::Block,
+        changes: &mars::StateChangeSet,
+        payload: &[u8],
+    ) -> Result<(), tar::StorageError> {
+        let summary = tar::BlockSummary {
+            height: block.height,
+            hash: block.hash(),
+            timestamp: block.timestamp,
+            tx_count: block.tx_count() as u64,
+        };
+        self.storage.save_block_summary(block.height, &summary)?;
+        self.storage.save_block_payload(block.height, payload)?;
+        self.storage.commit_with_changes(block.height, block, changes, &self.runtime.state)
+    }
+
+    /// Make an executed block the tip, persist and relay it.
+    ///
+    /// Production is paused while importing, but a finalized or synced
+    /// block can still move the tip meanwhile; a result executed against
+    /// any other parent is stale and dropped.
+    async fn adopt_executed(&mut self, executed: Executed) -> Result<(), NodeError> {
+        let Executed { block, payload, result } = executed;
+        if block.height != self.runtime.height() + 1 || block.parent_hash != self.runtime.last_block_hash() {
+            println!("Dropping block #{} executed against a stale tip", block.height);
+            return Ok(());
+        }
+        let (state, changes) = self.track_state_root(block.height, result)?;
+
         // MARS: Apply block
-        self.runtime.apply_block(&block)
-            .map_err(|e| NodeError::RuntimeError(e.to_string()))?;
+        self.runtime.adopt_block(&block, state);
 
         // TAR: Persist
-        self.storage.commit(block.height, &block, &self.runtime.state)
+        self.persist_block(&block, &changes, &payload)
             .map_err(|e| NodeError::StorageError(e.to_string()))?;
 
+        self.peer_block_height = Some(block.height);
+        self.included_txs.record(&block);
         println!("Applied block #{}", block.height);
+        self.on_finalized(block.height)?;
 
-        // Broadcast to peers
-        let msg = popeye::message::BlockMessage::new(payload, block.height);
-        let _ = self.network.broadcast(NetworkMessage::Block(msg)).await;
+        // Broadcast to peers; under consensus they learn of it by finality
+        if self.consensus.is_none() {
+            let msg = popeye::message::BlockMessage::new(payload, block.height);
+            let _ = self.network.broadcast(NetworkMessage::Block(msg)).await;
+        }
+
+        Ok(())
+    }
 
+    /// Wait until every importable queued block has been applied.
+    pub async fn finish_imports(&mut self) -> Result<(), NodeError> {
+        while self.importing.is_some() {
+            match self.executed_rx.recv().await {
+                Some(executed) => self.finish_import(executed).await?,
+                None => break,
+            }
+        }
         Ok(())
     }
 
+    /// Count state-root mismatches in a row, entering safe mode past the
+    /// configured limit.
+    ///
+    /// A root we keep computing differently from the network's means a
+    /// determinism bug here or a faulty majority there; either way,
+    /// producing or voting on top of it would fork the chain silently.
+    /// Any block that executes cleanly resets the count.
+    fn track_state_root<T>(
+        &mut self,
+        height: u64,
+        result: Result<T, mars::RuntimeError>,
+    ) -> Result<T, NodeError> {
+        let error = match result {
+            Ok(value) => {
+                self.root_mismatches = 0;
+                return Ok(value);
+            }
+            Err(error) => error,
+        };
+
+        if matches!(error, mars::RuntimeError::StateRootMismatch { .. }) {
+            self.root_mismatches += 1;
+            let limit = self.config.runtime.safe_mode_mismatches;
+            if !self.safe_mode && limit > 0 && self.root_mismatches >= limit {
+                self.safe_mode = true;
+                eprintln!(
+                    "CRITICAL: {} consecutive state-root mismatches (latest at block #{}), \
+                     halting production and voting; operator intervention required",
+                    self.root_mismatches, height
+                );
+            }
+        }
+        Err(NodeError::RuntimeError(error.to_string()))
+    }
+
+    /// Whether the node halted in safe mode.
+    pub fn is_safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
     /// Produce a block (for block producers).
+    ///
+    /// If a peer's block is being applied, or was applied since the last
+    /// attempt and is still the tip, that block filled this slot:
+    /// production is skipped with `ProductionSuperseded` rather than
+    /// racing it, so a timer tick that fires as a peer block lands cannot
+    /// fork the chain.
     pub fn produce_block(&mut self) -> Result<mars::Block, NodeError> {
-        let producer_key = self.config.runtime.producer_key
-            .as_ref()
-            .ok_or(NodeError::NotProducer)?;
+        if self.safe_mode {
+            return Err(NodeError::SafeMode { mismatches: self.root_mismatches });
+        }
+        let keypair = self.producer.as_ref().ok_or(NodeError::NotProducer)?;
+        let producer = mars::address_from_pubkey(&keypair.public_key());
 
-        // Parse producer key
-        let mut key = [0u8; 32];
-        let bytes = producer_key.as_bytes();
-        for (i, &b) in bytes.iter().take(32).enumerate() {
-            key[i] = b;
+        if let Some(height) = self.importing {
+            return Err(NodeError::ProductionSuperseded { height });
+        }
+        if let Some(height) = self.peer_block_height.take() {
+            if height == self.runtime.height() {
+                return Err(NodeError::ProductionSuperseded { height });
+            }
         }
 
         // MARS: Produce block
-        let block = self.runtime.produce_block(key);
+        let before = self.runtime.state.clone();
+        let block = self.runtime.produce_block(producer);
+        let changes = mars::StateChangeSet::for_block(&before, &self.runtime.state, &block);
+        let payload = import::sign_block_payload(keypair, &block);
 
         // TAR: Persist
-        self.storage.commit(block.height, &block, &self.runtime.state)
+        self.persist_block(&block, &changes, &payload)
             .map_err(|e| NodeError::StorageError(e.to_string()))?;
 
+        self.included_txs.record(&block);
         println!("Produced block #{}", block.height);
+        self.on_finalized(block.height)?;
 
         Ok(block)
     }
 
-    /// Get current block height.
-    pub fn height(&self) -> u64 {
-        self.runtime.height()
-    }
+    /// Decide blocks by BFT consensus among `validator_set`.
+    ///
+    /// Replaces timed block production: the engine signs with the producer
+    /// key and starts at the height above the local tip, continuing a round
+    /// saved before a restart. A node whose key
+    /// is not in the set follows consensus as an observer. Blocks are then
+    /// authorized by the validator set at their height rather than the
+    /// configured `authorized_producers`.
+    pub async fn enable_consensus(
+        &mut self,
+        validator_set: consensus::ValidatorSet,
+    ) -> Result<(), NodeError> {
+        let key = self.config.runtime.producer_key.as_deref().ok_or(NodeError::NotProducer)?;
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&Self::parse_key(key)?);
+        let our_id = consensus::ValidatorId::from_verifying_key(&signing_key.verifying_key());
 
-    /// Get mempool size.
-    pub fn mempool_size(&self) -> usize {
-        self.runtime.mempool_size()
-    }
+        let config = consensus::ConsensusConfig {
+            chain_id: self.config.runtime.chain_id.clone(),
+            observer: self.config.consensus.observer || !validator_set.contains(&our_id),
+            ..self.config.consensus.clone()
+        };
+        let validator_set = Arc::new(validator_set);
+        // TAR: Resume any round interrupted by a restart
+        let mut engine = ConsensusEngine::recover(
+            config,
+            (*validator_set).clone(),
+            signing_key,
+            self.consensus_tx.clone(),
+            self.consensus_store.clone(),
+        )
+        .map_err(consensus_err)?;
+        engine.set_observer(self.consensus_observer());
+        engine.set_proposal_validator(Arc::new(BlockProposalValidator));
+        let engine = Arc::new(engine);
 
-    /// Get peer count.
-    pub fn peer_count(&self) -> usize {
-        self.network.peer_count()
-    }
+        engine
+            .rejoin(self.runtime.height(), self.runtime.last_block_hash())
+            .await
+            .map_err(consensus_err)?;
 
-    /// Shutdown the node.
-    pub async fn shutdown(&mut self) {
-        if let Some(tx) = self.shutdown_tx.take() {
-            let _ = tx.send(()).await;
-        }
+        self.runtime.set_authorized_producers([]);
+        self.validator_set = Some(validator_set);
+        self.consensus = Some(engine);
+        Ok(())
     }
-}
+
+    /// The gossip message for a consensus broadcast event, if it is one.
+    pub fn consensus_gossip(event: &ConsensusEvent) -> Result<Option<NetworkMessage>, NodeError> {
+        let message = match event {
+            ConsensusEvent::BroadcastProposal(proposal) => {
+                consensus::ConsensusMessage::Proposal(proposal.clone())
+            }
+            ConsensusEvent::BroadcastPrevote(prevote) => {
+                consensus::ConsensusMessage::Prevote(prevote.clone())
+            }
+            ConsensusEvent::BroadcastCommit(commit) => {
+                consensus::ConsensusMessage::Commit(commit.clone())
+            }
+            ConsensusEvent::BroadcastCertificate(certificate) => {
+                consensus::ConsensusMessage::Certificate(certificate.clone())
+            }
+            _ => return Ok(None),
+        };
+
+        let payload = consensus::wire::encode_message(&message).map_err(consensus_err)?;
+        Ok(Some(NetworkMessage::Consensus(popeye::message::ConsensusMessage::new(payload))))
+    }
+
+    /// Handle an incoming consensus message.
+    ///
+    /// Flow: POPEYE → CONSENSUS
+    ///
+    /// Ignored unless consensus is enabled. A payload that does not decode
+    /// costs `from` `MALFORMED_MESSAGE_PENALTY`. The block a proposal
+    /// carries is remembered, to apply once it is finalized, but only if
+    /// it hashes to the proposed hash and the engine accepted the proposal
+    /// (or verified and buffered it for a later round).
+    async fn handle_consensus_message(&mut self, from: PeerId, payload: Vec<u8>) -> Result<(), NodeError> {
+        let Some(engine) = self.consensus.clone().filter(|_| !self.safe_mode) else {
+            return Ok(());
+        };
+
+        let limit = engine.config().max_proposal_tx_bytes + CONSENSUS_MESSAGE_OVERHEAD;
+        let message = match consensus::wire::decode_message(&payload, limit) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = self.network.penalize(&from, popeye::network::MALFORMED_MESSAGE_PENALTY).await;
+                return Err(consensus_err(e));
+            }
+        };
+        let proposed = match &message {
+            consensus::ConsensusMessage::Proposal(proposal) => {
+                bincode::deserialize::<mars::Block>(&proposal.transactions)
+                    .ok()
+                    .filter(|block| block.hash() == proposal.block_hash)
+            }
+            _ => None,
+        };
+
+        let result = engine.process_message(message).await.map_err(consensus_err)?;
+        if let Some(block) = proposed.filter(|_| !matches!(result, consensus::ProcessResult::Ignored)) {
+            self.remember_proposed(block);
+        }
+        Ok(())
+    }
+
+    /// Keep a proposed block until its height is finalized.
+    ///
+    /// Blocks at or below our tip are never needed again, and the map holds
+    /// at most `MAX_PROPOSED_BLOCKS`, dropping the furthest heights first.
+    fn remember_proposed(&mut self, block: mars::Block) {
+        let tip = self.runtime.height();
+        if block.height <= tip {
+            return;
+        }
+        self.proposed_blocks.retain(|_, proposed| proposed.height > tip);
+        self.proposed_blocks.insert(block.hash(), block);
+
+        while self.proposed_blocks.len() > MAX_PROPOSED_BLOCKS {
+            let furthest = self.proposed_blocks.iter()
+                .max_by_key(|(hash, proposed)| (proposed.height, **hash))
+                .map(|(hash, _)| *hash);
+            if let Some(hash) = furthest {
+                self.proposed_blocks.remove(&hash);
+            }
+        }
+    }
+
+    /// Act on an event from the consensus engine.
+    ///
+    /// Broadcasts are relayed over POPEYE. As round leader we build and
+    /// propose a block; once a block is finalized it is applied and the
+    /// next height begins. Evidence of equivocation is persisted.
+    pub async fn handle_consensus_event(&mut self, event: ConsensusEvent) -> Result<(), NodeError> {
+        if self.safe_mode {
+            return Ok(()); // Our votes stay unsent
+        }
+        if let Some(message) = Self::consensus_gossip(&event)? {
+            let _ = self.network.broadcast(message).await;
+        }
+
+        match event {
+            ConsensusEvent::ExecuteBlock { height, .. } => self.propose_block(height).await,
+            ConsensusEvent::BlockFinalized { height, block_hash, certificate } => {
+                self.apply_finalized(height, block_hash, &certificate).await
+            }
+            ConsensusEvent::Equivocation { validator, first, second } => {
+                println!("Validator {} signed conflicting votes", validator);
+                let evidence = EquivocationEvidence { validator, first: *first, second: *second };
+                self.consensus_store
+                    .save_evidence(evidence.first.height(), &evidence)
+                    .map_err(|e| NodeError::StorageError(e.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Build the block for `height` from the mempool and propose it.
+    async fn propose_block(&mut self, height: u64) -> Result<(), NodeError> {
+        let Some(engine) = self.consensus.clone() else {
+            return Ok(());
+        };
+        if height != self.runtime.height() + 1 {
+            return Ok(()); // Still catching up to the height before
+        }
+        let keypair = self.producer.as_ref().ok_or(NodeError::NotProducer)?;
+        let producer = mars::address_from_pubkey(&keypair.public_key());
+
+        // MARS: Build without applying; applied once finalized
+        let block = self.runtime.build_block(producer)
+            .map_err(|e| NodeError::RuntimeError(e.to_string()))?;
+        let payload = bincode::serialize(&block).map_err(|_| NodeError::InvalidPayload)?;
+        let (parent_hash, block_hash, state_root) = (block.parent_hash, block.hash(), block.state_root);
+        self.remember_proposed(block);
+
+        engine
+            .propose(parent_hash, block_hash, state_root, payload)
+            .await
+            .map_err(consensus_err)
+    }
+
+    /// Apply a block consensus finalized, then start on the next height.
+    async fn apply_finalized(
+        &mut self,
+        height: u64,
+        block_hash: [u8; 32],
+        certificate: &consensus::FinalityCertificate,
+    ) -> Result<(), NodeError> {
+        let storage_err = |e: tar::StorageError| NodeError::StorageError(e.to_string());
+
+        // Already past it: our block must be the finalized one
+        let tip = self.runtime.height();
+        if height <= tip {
+            let ours = match height {
+                0 => self.genesis.hash(),
+                height if height == tip => self.runtime.last_block_hash(),
+                height => self.storage.load_block_summary(height).map_err(storage_err)?.hash,
+            };
+            if ours != block_hash {
+                return Err(NodeError::ChainInconsistent {
+                    height,
+                    reason: "our block differs from the finalized one".to_string(),
+                });
+            }
+        }
+
+        // TAR: Record finality first, so a restart knows the height is decided
+        self.consensus_store.save_finality_certificate(height, certificate).map_err(storage_err)?;
+
+        // TAR: Audit the decision
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        self.audit_log.append(&tar::AuditEntry {
+            height,
+            block_hash,
+            round: certificate.round(),
+            committers: certificate.commits.iter().map(|c| c.validator.0).collect(),
+            weight: certificate.total_weight,
+            timestamp,
+        }).map_err(storage_err)?;
+
+        let block = self.proposed_blocks.remove(&block_hash);
+        self.proposed_blocks.retain(|_, proposed| proposed.height > height);
+
+        match block {
+            Some(block) if height == tip + 1 => {
+                // MARS: Validate and apply, then drop what the block included
+                self.check_producer(&block)?;
+                self.runtime.validate_block(&block)
+                    .map_err(|e| NodeError::RuntimeError(e.to_string()))?;
+                let result = self.runtime.apply_block(&block);
+                let changes = self.track_state_root(block.height, result)?;
+                self.runtime.prune_mempool();
+
+                // TAR: Persist; the certificate vouches for the block when
+                // it is served, so no producer signature is needed
+                let payload = bincode::serialize(&block).map_err(|_| NodeError::InvalidPayload)?;
+                self.persist_block(&block, &changes, &payload).map_err(storage_err)?;
+
+                self.included_txs.record(&block);
+                println!("Finalized block #{}", block.height);
+                self.on_finalized(block.height)?;
+            }
+            _ if height > tip => {
+                // We never saw the proposal, or are still behind: fetch the
+                // blocks like any we fell behind on; the certificate saved
+                // above pins which one is final
+                println!("Finalized block #{} is missing here, fetching from #{}", height, tip + 1);
+                if let Some(peer) = self.network.best_peer() {
+                    self.request_blocks(peer.id, tip + 1, peer.height.max(height));
+                }
+            }
+            _ => {} // Already have it, checked above
+        }
+
+        if let Some(engine) = self.consensus.clone() {
+            if engine.current_height().await <= height {
+                // Finality of this height schedules an epoch starting at the next
+                if let Some(validator_set) = self.validator_epoch(height + 1)? {
+                    engine.apply_validator_set(validator_set, height + 1).await.map_err(consensus_err)?;
+                }
+                engine.start_height(height + 1).await.map_err(consensus_err)?;
+                self.validator_set = Some(engine.validator_set().await);
+            }
+        }
+        Ok(())
+    }
+
+    /// The configured validator set of the epoch starting at `height`, if
+    /// one does.
+    fn validator_epoch(&self, height: u64) -> Result<Option<consensus::ValidatorSet>, NodeError> {
+        let Some((_, keys)) = self.config.genesis.validator_epochs.iter().find(|(start, _)| *start == height) else {
+            return Ok(None);
+        };
+        let pubkeys = keys.iter()
+            .map(|key| Self::parse_key(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(consensus::ValidatorSet::new(pubkeys)))
+    }
+
+    /// Snapshot and prune storage once `height` is final.
+    ///
+    /// Without BFT every applied block is final, so this runs for each one.
+    /// Every `snapshot_interval` heights the state is snapshotted. With a
+    /// `block_retention` window, blocks are pruned below the newest
+    /// snapshot that still leaves the whole window replayable, so
+    /// historical queries keep working for the retained heights. Archive
+    /// nodes (no window) never prune.
+    ///
+    /// Finality certificates past the `finality_retention` window are
+    /// pruned too, but never those of retained blocks: they are served with
+    /// the blocks and pin which ones are canonical.
+    fn on_finalized(&mut self, height: u64) -> Result<(), NodeError> {
+        let storage_err = |e: tar::StorageError| NodeError::StorageError(e.to_string());
+
+        let interval = self.config.node.snapshot_interval.max(1);
+        if height.is_multiple_of(interval) {
+            self.storage.save_snapshot(height, &self.runtime.state).map_err(storage_err)?;
+        }
+
+        let mut retained_from = 1;
+        if let Some(retention) = self.config.node.block_retention {
+            let window_start = (height + 1).saturating_sub(retention.max(1));
+            if let Some(boundary) = self.storage.snapshot_at_or_below(window_start).map_err(storage_err)? {
+                // The boundary block itself is the parent replay starts from
+                self.storage.prune_blocks_below(boundary).map_err(storage_err)?;
+                retained_from = retained_from.max(boundary);
+            }
+        }
+
+        if let Some(retain) = self.config.node.finality_retention {
+            let cutoff = (height + 1).saturating_sub(retain.max(1)).min(retained_from);
+            self.consensus_store.prune_finality_below(cutoff).map_err(storage_err)?;
+        }
+
+        Ok(())
+    }
+
+    /// Observer to install on the consensus engine, recording its
+    /// progress in this node's metrics.
+    pub fn consensus_observer(&self) -> Arc<dyn consensus::ConsensusObserver> {
+        self.consensus_metrics.clone()
+    }
+
+    /// Render consensus metrics in the Prometheus text format.
+    pub fn encode_metrics(&self) -> String {
+        self.consensus_metrics.encode()
+    }
+
+    /// Get current block height.
+    pub fn height(&self) -> u64 {
+        self.runtime.height()
+    }
+
+    /// Get mempool size.
+    pub fn mempool_size(&self) -> usize {
+        self.runtime.mempool_size()
+    }
+
+    /// Get peer count.
+    pub fn peer_count(&self) -> usize {
+        self.network.peer_count()
+    }
+
+    /// Check whether we are within the sync threshold of the best peer.
+    ///
+    /// A node with no peers has nothing to catch up to and counts as synced.
+    pub fn is_synced(&self) -> bool {
+        match self.network.best_peer() {
+            Some(best) => {
+                best.height <= self.runtime.height() + self.config.node.sync_threshold
+            }
+            None => true,
+        }
+    }
+
+    /// Set the consensus validator set used to classify peers.
+    pub fn set_validator_set(&mut self, validator_set: Arc<consensus::ValidatorSet>) {
+        self.validator_set = Some(validator_set);
+    }
+
+    /// Connected peers that are validators, and those that are not.
+    ///
+    /// A peer is a validator when its node id matches a validator id;
+    /// both are derived from the same public key. Without a validator set
+    /// every peer is a non-validator.
+    pub fn classify_peers(&self) -> (Vec<popeye::PeerId>, Vec<popeye::PeerId>) {
+        self.network.partition_peers(|peer| {
+            self.validator_set.as_ref().is_some_and(|set| {
+                set.contains(&consensus::ValidatorId::from_bytes(*peer.as_bytes()))
+            })
+        })
+    }
+
+    /// Total voting weight of connected validator peers (excluding ourselves).
+    pub fn connected_validator_weight(&self) -> u64 {
+        let Some(set) = &self.validator_set else {
+            return 0;
+        };
+        self.classify_peers()
+            .0
+            .iter()
+            .filter_map(|peer| set.get(&consensus::ValidatorId::from_bytes(*peer.as_bytes())))
+            .map(|v| v.weight)
+            .sum()
+    }
+
+    /// Whether we and our connected validators together carry quorum.
+    ///
+    /// `None` when no validator set is known.
+    pub fn quorum_reachable(&self) -> Option<bool> {
+        let set = self.validator_set.as_ref()?;
+        let mut available: HashSet<consensus::ValidatorId> = self
+            .classify_peers()
+            .0
+            .iter()
+            .map(|peer| consensus::ValidatorId::from_bytes(*peer.as_bytes()))
+            .collect();
+        available.insert(consensus::ValidatorId::from_bytes(*self.network.local_id().as_bytes()));
+        Some(set.reachable_quorum(&available))
+    }
+
+    /// Warn when validator connectivity drops below quorum, and when it recovers.
+    fn check_quorum_reachable(&mut self) {
+        let Some(reachable) = self.quorum_reachable() else {
+            return;
+        };
+        if reachable != self.quorum_reachable {
+            if reachable {
+                println!("Validator quorum reachable again");
+            } else {
+                eprintln!(
+                    "Warning: connected validators carry only {} weight; quorum is unreachable",
+                    self.connected_validator_weight()
+                );
+            }
+            self.quorum_reachable = reachable;
+        }
+    }
+
+    /// Get a health / readiness snapshot.
+    pub async fn status(&self) -> NodeStatus {
+        let progress_blocker = match &self.consensus {
+            Some(engine) => engine.progress_blocker().await,
+            None => None,
+        };
+        NodeStatus {
+            height: self.height(),
+            synced: self.is_synced(),
+            peer_count: self.peer_count(),
+            mempool_size: self.mempool_size(),
+            safe_mode: self.safe_mode,
+            progress_blocker,
+        }
+    }
+
+    /// Get an account's balance and nonce as of a past `height`.
+    ///
+    /// Loads the nearest state snapshot at or below `height` and replays
+    /// the stored blocks after it. Recent results are cached.
+    pub fn account_at(&mut self, height: u64, address: &mars::Address) -> Result<AccountView, NodeError> {
+        if height > self.runtime.height() {
+            return Err(NodeError::RuntimeError(format!(
+                "height {} is above current height {}",
+                height,
+                self.runtime.height()
+            )));
+        }
+
+        let key = (height, *address);
+        if let Some((_, view)) = self.account_cache.iter().find(|(k, _)| *k == key) {
+            return Ok(*view);
+        }
+
+        let storage_err = |e: tar::StorageError| NodeError::StorageError(e.to_string());
+        let base = self.storage.snapshot_at_or_below(height)
+            .map_err(storage_err)?
+            .ok_or_else(|| NodeError::StorageError(format!("no snapshot at or below height {}", height)))?;
+
+        let state: mars::State = self.storage.load_snapshot(base).map_err(storage_err)?;
+        let parent = if base > 0 {
+            self.storage.load_block(base).map_err(storage_err)?
+        } else {
+            self.genesis.clone()
+        };
+
+        let mut replay = Runtime::with_state(state, &parent, self.runtime.config());
+        for h in base + 1..=height {
+            let block: mars::Block = self.storage.load_block(h).map_err(storage_err)?;
+            replay.apply_block(&block)
+                .map_err(|e| NodeError::RuntimeError(e.to_string()))?;
+        }
+
+        let view = AccountView {
+            height,
+            balance: replay.state.balance(address),
+            nonce: replay.state.nonce(address),
+        };
+
+        if self.account_cache.len() == ACCOUNT_CACHE_CAPACITY {
+            self.account_cache.pop_front();
+        }
+        self.account_cache.push_back((key, view));
+
+        Ok(view)
+    }
+
+    /// Re-verify the stored chain from genesis to the tip.
+    ///
+    /// Every block must link to its parent and re-executing it must
+    /// reproduce its state root; the replayed state must match the stored
+    /// one. A pruned node starts from the snapshot at its oldest retained
+    /// block. Reports the first inconsistent height.
+    pub fn verify_chain(&self) -> Result<(), NodeError> {
+        let storage_err = |e: tar::StorageError| NodeError::StorageError(e.to_string());
+        let inconsistent = |height: u64, reason: String| NodeError::ChainInconsistent { height, reason };
+
+        let tip = self.storage.latest_block_height().map_err(storage_err)?.unwrap_or(0);
+        let base = if tip == 0 || self.storage.block_exists(1) {
+            0
+        } else {
+            (1..=tip).find(|h| self.storage.block_exists(*h)).unwrap_or(tip)
+        };
+
+        let (state, mut parent) = if base == 0 {
+            let state = match self.storage.snapshot_at_or_below(0).map_err(storage_err)? {
+                Some(_) => self.storage.load_snapshot(0).map_err(storage_err)?,
+                None => mars::State::new(),
+            };
+            (state, self.genesis.clone())
+        } else {
+            if self.storage.snapshot_at_or_below(base).map_err(storage_err)? != Some(base) {
+                return Err(inconsistent(base, "no snapshot at oldest retained block".to_string()));
+            }
+            let state: mars::State = self.storage.load_snapshot(base).map_err(storage_err)?;
+            let block: mars::Block = self.storage.load_block(base).map_err(storage_err)?;
+            (state, block)
+        };
+
+        let mut replay = Runtime::with_state(state, &parent, self.runtime.config());
+        for height in base + 1..=tip {
+            let block: mars::Block = self.storage.load_block(height)
+                .map_err(|e| inconsistent(height, e.to_string()))?;
+            if block.height != height {
+                return Err(inconsistent(height, format!("block claims height {}", block.height)));
+            }
+            if block.parent_hash != parent.hash() {
+                return Err(inconsistent(height, "parent hash mismatch".to_string()));
+            }
+            replay.apply_block(&block)
+                .map_err(|e| inconsistent(height, e.to_string()))?;
+            parent = block;
+        }
+
+        let stored: mars::State = self.storage.load_state().map_err(storage_err)?;
+        if stored != replay.state {
+            return Err(inconsistent(tip, "stored state differs from replayed state".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Determine where the chain resumes from storage and consensus combined.
+    ///
+    /// The tip is the higher of the latest stored block and the latest
+    /// finalized height. Where a block is stored at the finalized height,
+    /// its hash must match the finality certificate. A finalized height
+    /// above the stored blocks resolves to the certificate's hash; the
+    /// block itself still has to be fetched from peers.
+    ///
+    /// Returns the tip height and block hash.
+    pub fn resolve_tip(&self) -> Result<(u64, [u8; 32]), NodeError> {
+        let storage_err = |e: tar::StorageError| NodeError::StorageError(e.to_string());
+
+        let stored = self.storage.latest_block_height().map_err(storage_err)?.unwrap_or(0);
+        let stored_hash = |height: u64| -> Result<[u8; 32], NodeError> {
+            if height == 0 {
+                return Ok(self.genesis.hash());
+            }
+            let block: mars::Block = self.storage.load_block(height).map_err(storage_err)?;
+            Ok(block.hash())
+        };
+
+        let Some(finalized) = self.consensus_store.latest_finalized_height().map_err(storage_err)? else {
+            return Ok((stored, stored_hash(stored)?));
+        };
+        let certificate: consensus::FinalityCertificate = self.consensus_store
+            .load_finality_certificate(finalized)
+            .map_err(storage_err)?
+            .ok_or_else(|| NodeError::StorageError(format!("finality certificate {} missing", finalized)))?;
+
+        if self.storage.block_exists(finalized) && stored_hash(finalized)? != certificate.block_hash {
+            return Err(NodeError::ChainInconsistent {
+                height: finalized,
+                reason: "stored block differs from finalized block".to_string(),
+            });
+        }
+
+        if finalized > stored {
+            Ok((finalized, certificate.block_hash))
+        } else {
+            Ok((stored, stored_hash(stored)?))
+        }
+    }
+
+    /// The validator set in effect at `height`: the latest stored at or
+    /// below it, or else the consensus validator set.
+    fn validator_set_at(&self, height: u64) -> Result<Option<Arc<consensus::ValidatorSet>>, NodeError> {
+        let stored: Option<consensus::ValidatorSet> = self.consensus_store
+            .load_validator_set_at(height)
+            .map_err(|e| NodeError::StorageError(e.to_string()))?;
+        Ok(stored.map(Arc::new).or_else(|| self.validator_set.clone()))
+    }
+
+    /// Under consensus, refuse a block whose producer is not a validator
+    /// at the block's height.
+    ///
+    /// Validator sets change over time, so the static
+    /// `authorized_producers` list only applies without consensus.
+    fn check_producer(&self, block: &mars::Block) -> Result<(), NodeError> {
+        if self.consensus.is_none() {
+            return Ok(());
+        }
+        let producer = consensus::ValidatorId::from_pubkey(&block.producer);
+        let authorized = self.validator_set_at(block.height)?.is_some_and(|set| set.contains(&producer));
+        if !authorized {
+            return Err(NodeError::ValidationFailed(format!(
+                "producer of block #{} is not a validator at its height",
+                block.height
+            )));
+        }
+        Ok(())
+    }
+
+    /// Accept a finality certificate a peer sent with a block during catch-up.
+    ///
+    /// The certificate is checked against the validator set in effect at
+    /// its height (or the consensus validator set, when none is stored)
+    /// before it is stored; without a validator set nothing can be checked
+    /// and the certificate is refused.
+    pub fn accept_certificate(
+        &mut self,
+        certificate: &consensus::FinalityCertificate,
+    ) -> Result<(), NodeError> {
+        let validator_set = self.validator_set_at(certificate.height)?.ok_or_else(|| {
+            NodeError::ValidationFailed("no validator set to check certificate against".to_string())
+        })?;
+        certificate
+            .verify(&validator_set)
+            .map_err(|e| NodeError::ValidationFailed(e.to_string()))?;
+
+        self.consensus_store
+            .save_finality_certificate(certificate.height, certificate)
+            .map_err(|e| NodeError::StorageError(e.to_string()))
+    }
+
+    /// Persist the pending transactions to disk.
+    pub fn save_mempool(&self) -> Result<(), NodeError> {
+        self.storage.save_mempool(&self.runtime.mempool())
+            .map_err(|e| NodeError::StorageError(e.to_string()))
+    }
+
+    /// Shutdown the node.
+    pub async fn shutdown(&mut self) {
+        if let Err(e) = self.finish_imports().await {
+            eprintln!("Failed to finish block import: {}", e);
+        }
+
+        if self.config.runtime.persist_mempool {
+            if let Err(e) = self.save_mempool() {
+                eprintln!("Failed to persist mempool: {}", e);
+            }
+        }
+
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(()).await;
+        }
+    }
+}
 
 /// Node errors.
 #[derive(Debug, thiserror::Error)]
@@ -309,8 +1771,40 @@ pub enum NodeError {
     #[error("not configured as block producer")]
     NotProducer,
 
+    #[error("block production skipped: peer block #{height} arrived first")]
+    ProductionSuperseded { height: u64 },
+
     #[error("network error: {0}")]
     NetworkError(String),
+
+    #[error("rpc error: {0}")]
+    RpcError(String),
+
+    #[error("chain inconsistent at height {height}: {reason}")]
+    ChainInconsistent { height: u64, reason: String },
+
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("consensus error: {0}")]
+    ConsensusError(String),
+
+    #[error("halted in safe mode after {mismatches} consecutive state-root mismatches")]
+    SafeMode { mismatches: u32 },
+
+    #[error("genesis mismatch: {0}")]
+    GenesisMismatch(String),
+
+    #[error("block #{height} does not extend the canonical finalized chain")]
+    NonCanonical { height: u64 },
+
+    #[error("block #{height} is not covered by a finality certificate")]
+    Uncertified { height: u64 },
+}
+
+/// Wrap a consensus error.
+fn consensus_err(e: consensus::ConsensusError) -> NodeError {
+    NodeError::ConsensusError(e.to_string())
 }
 
 #[cfg(test)]
@@ -342,4 +1836,1250 @@ mod tests {
         assert_eq!(block.height, 1);
         assert_eq!(node.height(), 1);
     }
+
+    #[test]
+    fn test_account_at_past_height() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+
+        let mut node = Node::new(config).unwrap();
+        let (sender, recipient) = ([1u8; 32], [2u8; 32]);
+
+        // Genesis allocation
+        node.runtime.state.set_balance(&sender, 1000);
+        node.storage.save_snapshot(0, &node.runtime.state).unwrap();
+
+        node.runtime.submit_transaction(mars::Transaction::new(sender, recipient, 100, 0)).unwrap();
+        node.produce_block().unwrap();
+        node.runtime.submit_transaction(mars::Transaction::new(sender, recipient, 50, 1)).unwrap();
+        node.produce_block().unwrap();
+
+        let view = node.account_at(1, &recipient).unwrap();
+        assert_eq!(view, AccountView { height: 1, balance: 100, nonce: 0 });
+
+        let view = node.account_at(2, &sender).unwrap();
+        assert_eq!((view.balance, view.nonce), (850, 2));
+
+        // Cached views match
+        assert_eq!(node.account_at(1, &recipient).unwrap().balance, 100);
+        assert!(node.account_at(3, &sender).is_err());
+    }
+
+    #[test]
+    fn test_classify_validator_peers() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.producer_key = Some("aa".repeat(32));
+        let mut node = Node::new(config).unwrap();
+
+        let local = *node.network.local_id().as_bytes();
+        let set = consensus::ValidatorSet::from_validators(vec![
+            consensus::Validator::with_weight(local, 1),
+            consensus::Validator::with_weight([2u8; 32], 2),
+            consensus::Validator::with_weight([3u8; 32], 3),
+            consensus::Validator::with_weight([4u8; 32], 4),
+        ]);
+        node.set_validator_set(Arc::new(set));
+
+        for id in [[2u8; 32], [3u8; 32], [9u8; 32]] {
+            let peer = popeye::peer::PeerInfo::new(popeye::PeerId::new(id), "127.0.0.1:30304".parse().unwrap());
+            node.network.add_peer(peer, mpsc::channel(1).0).unwrap();
+        }
+
+        let (mut validators, others) = node.classify_peers();
+        validators.sort_by_key(|p| p.0);
+        assert_eq!(validators, vec![popeye::PeerId::new([2u8; 32]), popeye::PeerId::new([3u8; 32])]);
+        assert_eq!(others, vec![popeye::PeerId::new([9u8; 32])]);
+        assert_eq!(node.connected_validator_weight(), 5);
+
+        // 1 (us) + 5 of 10 is below the quorum of 7
+        assert_eq!(node.quorum_reachable(), Some(false));
+        let peer = popeye::peer::PeerInfo::new(popeye::PeerId::new([4u8; 32]), "127.0.0.1:30305".parse().unwrap());
+        node.network.add_peer(peer, mpsc::channel(1).0).unwrap();
+        assert_eq!(node.quorum_reachable(), Some(true));
+    }
+
+    #[test]
+    fn test_identity_derived_from_producer_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.producer_key = Some("aa".repeat(32));
+
+        let keypair = tev::Keypair::from_secret(&[0xaa; 32]);
+        let address = mars::address_from_pubkey(&keypair.public_key());
+
+        let mut node = Node::new(config).unwrap();
+        assert_eq!(*node.network.local_id().as_bytes(), address);
+        assert_eq!(node.produce_block().unwrap().producer, address);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_sync_progress() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.node.sync_threshold = 2;
+
+        let mut node = Node::new(config).unwrap();
+        assert!(node.status().await.synced); // No peers yet
+
+        let peer = popeye::PeerId::new([9u8; 32]);
+        node.network
+            .add_peer(popeye::peer::PeerInfo::new(peer, "127.0.0.1:9000".parse().unwrap()), mpsc::channel(1).0)
+            .unwrap();
+        node.network.update_peer_height(&peer, 5).unwrap();
+
+        let status = node.status().await;
+        assert_eq!(status.height, 0);
+        assert_eq!(status.peer_count, 1);
+        assert!(!status.synced);
+
+        // Catch up to within the threshold
+        for _ in 0..3 {
+            node.produce_block().unwrap();
+        }
+        let status = node.status().await;
+        assert_eq!(status.height, 3);
+        assert!(status.synced);
+        assert_eq!(status.progress_blocker, None);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_consensus_blocker() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        // The only validator is elsewhere and has not proposed yet
+        node.enable_consensus(consensus::ValidatorSet::new(vec![[9u8; 32]])).await.unwrap();
+        assert_eq!(
+            node.status().await.progress_blocker,
+            Some(consensus::ProgressBlocker::WaitingForProposal)
+        );
+    }
+
+    /// A runtime at `node`'s genesis, for building blocks it will accept.
+    fn genesis_runtime(node: &Node) -> mars::Runtime {
+        mars::Runtime::with_state(mars::State::new(), &node.genesis, mars::RuntimeConfig::default())
+    }
+
+    /// Build a TEV-format block payload signed by `keypair`.
+    fn signed_block_payload(keypair: &tev::Keypair, block: &mars::Block) -> Vec<u8> {
+        tev::sign_payload(keypair, &bincode::serialize(block).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_reject_block_from_unauthorized_producer() {
+        let temp_dir = TempDir::new().unwrap();
+        let authorized = tev::Keypair::from_secret(&[1u8; 32]);
+        let outsider = tev::Keypair::from_secret(&[2u8; 32]);
+
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.authorized_producers = vec![hex::encode(authorized.public_key())];
+        let mut node = Node::new(config).unwrap();
+
+        // Correctly signed, but the producer is not in the authorized set
+        let block = genesis_runtime(&node).produce_block(outsider.public_key());
+        let result = node.handle_block(signed_block_payload(&outsider, &block)).await;
+        assert!(matches!(result, Err(NodeError::RuntimeError(_))));
+        assert_eq!(node.height(), 0);
+
+        // Claiming an authorized producer while signing with another key
+        let block = genesis_runtime(&node).produce_block(authorized.public_key());
+        let result = node.handle_block(signed_block_payload(&outsider, &block)).await;
+        assert!(matches!(result, Err(NodeError::ValidationFailed(_))));
+
+        // The authorized producer is accepted
+        let result = node.handle_block(signed_block_payload(&authorized, &block)).await;
+        assert!(result.is_ok());
+        node.finish_imports().await.unwrap();
+        assert_eq!(node.height(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_peer_block_suppresses_production() {
+        let temp_dir = TempDir::new().unwrap();
+        let peer = tev::Keypair::from_secret(&[3u8; 32]);
+
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.producer_enabled = true;
+        config.runtime.producer_key = Some("aa".repeat(32));
+        let mut node = Node::new(config).unwrap();
+
+        // A peer's block for height 1 lands just before our tick
+        let block = genesis_runtime(&node).produce_block(peer.public_key());
+        node.handle_block(signed_block_payload(&peer, &block)).await.unwrap();
+        node.finish_imports().await.unwrap();
+
+        assert!(matches!(
+            node.produce_block(),
+            Err(NodeError::ProductionSuperseded { height: 1 })
+        ));
+        assert_eq!(node.height(), 1);
+        assert_eq!(node.storage.load_block::<mars::Block>(1).unwrap().hash(), block.hash());
+
+        // The next tick builds on the peer's block
+        assert_eq!(node.produce_block().unwrap().height, 2);
+    }
+
+    #[tokio::test]
+    async fn test_included_transaction_dropped_quietly() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        let peer = tev::Keypair::from_secret(&[3u8; 32]);
+        let wallet = tev::Keypair::from_secret(&[4u8; 32]);
+        let sender = mars::address_from_pubkey(&wallet.public_key());
+        let tx = mars::Transaction::new(sender, [2u8; 32], 10, 0);
+
+        // A peer includes the transaction before we ever see it gossiped
+        let mut builder = genesis_runtime(&node);
+        builder.state.set_balance(&sender, 1000);
+        node.runtime.state.set_balance(&sender, 1000);
+        builder.submit_transaction(tx.clone()).unwrap();
+        let block = builder.produce_block(peer.public_key());
+        node.handle_block(signed_block_payload(&peer, &block)).await.unwrap();
+        node.finish_imports().await.unwrap();
+
+        // The late gossip copy is neither an error nor relayed
+        let payload = signed_tx_payload(&wallet, &tx);
+        assert!(!node.handle_transaction(payload).await.unwrap());
+        assert_eq!(node.mempool_size(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_slow_block_apply_does_not_block_timers() {
+        fn slow_execute(
+            state: &mars::State,
+            block: &mars::Block,
+            config: &mars::RuntimeConfig,
+        ) -> Result<(mars::State, mars::StateChangeSet), mars::RuntimeError> {
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            Runtime::execute_block(state, block, config)
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let peer = tev::Keypair::from_secret(&[3u8; 32]);
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.producer_enabled = true;
+        config.runtime.producer_key = Some("aa".repeat(32));
+        let mut node = Node::new(config).unwrap();
+        node.executor = slow_execute;
+
+        // Blocks arrive out of order; both are queued
+        let mut chain = genesis_runtime(&node);
+        let first = chain.produce_block(peer.public_key());
+        let second = chain.produce_block(peer.public_key());
+        let started = tokio::time::Instant::now();
+        node.handle_block(signed_block_payload(&peer, &second)).await.unwrap();
+        node.handle_block(signed_block_payload(&peer, &first)).await.unwrap();
+
+        // A consensus timeout due now fires on time while block 1 executes
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_millis(20)) => {}
+            _ = node.executed_rx.recv() => panic!("block applied before the timer fired"),
+        }
+        assert!(started.elapsed() < std::time::Duration::from_millis(300));
+        assert_eq!(node.height(), 0);
+        assert!(matches!(
+            node.produce_block(),
+            Err(NodeError::ProductionSuperseded { height: 1 })
+        ));
+
+        // Both apply, in height order
+        node.finish_imports().await.unwrap();
+        assert_eq!(node.height(), 2);
+        assert_eq!(node.runtime.last_block_hash(), second.hash());
+    }
+
+    #[test]
+    fn test_finalization_snapshots_and_prunes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.node.snapshot_interval = 5;
+        config.node.block_retention = Some(5);
+        let mut node = Node::new(config).unwrap();
+
+        for _ in 0..12 {
+            node.produce_block().unwrap();
+        }
+
+        // The last 5 heights (8..=12) stay replayable from the snapshot at 5
+        assert_eq!(node.storage.snapshot_at_or_below(8).unwrap(), Some(5));
+        assert!((1..5).all(|h| !node.storage.block_exists(h)));
+        assert!((5..=12).all(|h| node.storage.block_exists(h)));
+        assert_eq!(node.account_at(8, &[0u8; 32]).unwrap().height, 8);
+
+        // Archive nodes keep everything
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.node.snapshot_interval = 5;
+        let mut archive = Node::new(config).unwrap();
+        for _ in 0..12 {
+            archive.produce_block().unwrap();
+        }
+        assert!((1..=12).all(|h| archive.storage.block_exists(h)));
+    }
+
+    #[test]
+    fn test_finality_retention_prunes_certificates() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.node.finality_retention = Some(2);
+        config.node.block_retention = Some(4);
+        config.node.snapshot_interval = 2;
+        let mut node = Node::new(config).unwrap();
+
+        for height in 1..=8 {
+            node.produce_block().unwrap();
+            let certificate = consensus::FinalityCertificate::new(height, [height as u8; 32], Vec::new(), 0);
+            node.consensus_store.save_finality_certificate(height, &certificate).unwrap();
+        }
+
+        // Blocks 4..=8 are retained, so their certificates are too, though
+        // the window alone would keep only the last two
+        let kept = |height| {
+            node.consensus_store
+                .load_finality_certificate::<consensus::FinalityCertificate>(height)
+                .unwrap()
+                .is_some()
+        };
+        assert_eq!((1..=8).map(kept).collect::<Vec<_>>(), vec![false, false, false, true, true, true, true, true]);
+        assert!((1..=8).all(|h| node.storage.block_exists(h) == kept(h)));
+    }
+
+    #[tokio::test]
+    async fn test_equivocation_evidence_persisted() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        let validator = consensus::ValidatorId([3u8; 32]);
+        let prevote = |hash: u8| {
+            consensus::ConsensusMessage::Prevote(consensus::Prevote {
+                height: 4,
+                round: 1,
+                block_hash: Some([hash; 32]),
+                validator: validator.clone(),
+                signature: consensus::types::Signature64::default(),
+            })
+        };
+        node.handle_consensus_event(ConsensusEvent::Equivocation {
+            validator: validator.clone(),
+            first: Box::new(prevote(5)),
+            second: Box::new(prevote(6)),
+        })
+        .await
+        .unwrap();
+
+        let evidence: Vec<EquivocationEvidence> = node.consensus_store.load_evidence(4).unwrap();
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].validator, validator);
+        assert!(matches!(&evidence[0].second, consensus::ConsensusMessage::Prevote(p) if p.block_hash == Some([6u8; 32])));
+    }
+
+    #[tokio::test]
+    async fn test_import_executed_against_stale_tip_dropped() {
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let node = |dir: &TempDir| {
+            let mut config = NodeConfig::dev();
+            config.node.data_dir = dir.path().to_path_buf();
+            config.genesis.allocations = vec![(hex::encode([1u8; 32]), 1_000)];
+            Node::new(config).unwrap()
+        };
+        let (mut peer, mut node) = (node(&dirs[0]), node(&dirs[1]));
+
+        // Executed against our genesis tip...
+        let block = peer.produce_block().unwrap();
+        let result = Runtime::execute_block(&node.runtime.state, &block, &node.runtime.config());
+        let executed = Executed { block, payload: Vec::new(), result };
+
+        // ...which moved before the result came back
+        node.runtime.submit_transaction(mars::Transaction::new([1u8; 32], [2u8; 32], 5, 0)).unwrap();
+        let ours = node.produce_block().unwrap();
+        assert_ne!(ours.hash(), executed.block.hash());
+        node.finish_import(executed).await.unwrap();
+        assert_eq!(node.height(), 1);
+        assert_eq!(node.runtime.last_block_hash(), ours.hash());
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+        for _ in 0..5 {
+            node.produce_block().unwrap();
+        }
+        assert!(node.verify_chain().is_ok());
+
+        // Rewrite block 3 with a state root it does not produce
+        let mut block: mars::Block = node.storage.load_block(3).unwrap();
+        block.state_root = [9u8; 32];
+        node.storage.save_block(3, &block).unwrap();
+
+        assert!(matches!(
+            node.verify_chain(),
+            Err(NodeError::ChainInconsistent { height: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_fresh_node_honors_genesis_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.genesis.validators = vec!["11".repeat(32), "22".repeat(32)];
+        config.genesis.allocations = vec![("aa".repeat(32), 5_000), ("bb".repeat(32), 7)];
+
+        let node = Node::new(config).unwrap();
+        assert_eq!(node.runtime.state.balance(&[0xAA; 32]), 5_000);
+        assert_eq!(node.runtime.state.balance(&[0xBB; 32]), 7);
+        assert_eq!(node.validator_set.as_ref().unwrap().len(), 2);
+
+        // The genesis block commits to the allocations
+        assert_eq!(node.genesis.state_root, node.runtime.state.state_root);
+        assert_eq!(node.resolve_tip().unwrap(), (0, node.genesis.hash()));
+        let stored: Option<consensus::ValidatorSet> =
+            node.consensus_store.load_validator_set().unwrap();
+        assert_eq!(stored.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_restart_against_different_genesis_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.genesis.validators = vec!["11".repeat(32)];
+        config.genesis.allocations = vec![("aa".repeat(32), 5_000)];
+        {
+            let mut node = Node::new(config.clone()).unwrap();
+            node.produce_block().unwrap();
+        }
+
+        // The same genesis restarts cleanly
+        assert!(Node::new(config.clone()).is_ok());
+
+        let mut other = config.clone();
+        other.genesis.allocations = vec![("aa".repeat(32), 6_000)];
+        assert!(matches!(Node::new(other), Err(NodeError::GenesisMismatch(_))));
+
+        let mut other = config.clone();
+        other.genesis.validators = vec!["22".repeat(32)];
+        assert!(matches!(Node::new(other), Err(NodeError::GenesisMismatch(_))));
+
+        let mut other = config.clone();
+        other.runtime.genesis_timestamp += 1;
+        assert!(matches!(Node::new(other), Err(NodeError::GenesisMismatch(_))));
+
+        let mut other = config;
+        other.runtime.chain_id = "unykorn-other".to_string();
+        assert!(matches!(Node::new(other), Err(NodeError::GenesisMismatch(_))));
+    }
+
+    #[test]
+    fn test_duplicate_genesis_allocation_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        // The first entry is zero, so the balance alone cannot reveal it
+        config.genesis.allocations = vec![("aa".repeat(32), 0), ("aa".repeat(32), 5_000)];
+
+        assert!(matches!(Node::new(config), Err(NodeError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_block_summary_matches_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        let keypair = tev::Keypair::from_secret(&[3u8; 32]);
+        let from = mars::address_from_pubkey(&keypair.public_key());
+        node.runtime.state.set_balance(&from, 1_000);
+        node.runtime.submit_transaction(mars::Transaction::new(from, [9u8; 32], 10, 0)).unwrap();
+        node.produce_block().unwrap();
+
+        let block: mars::Block = node.storage.load_block(1).unwrap();
+        let summary = node.storage.load_block_summary(1).unwrap();
+        assert_eq!(summary.height, block.height);
+        assert_eq!(summary.hash, block.hash());
+        assert_eq!(summary.timestamp, block.timestamp);
+        assert_eq!(summary.tx_count, 1);
+    }
+
+    #[test]
+    fn test_resolve_tip() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+        let genesis = mars::Block::genesis_for(
+            &node.config.runtime.chain_id,
+            node.config.runtime.genesis_timestamp,
+        );
+        assert_eq!(node.resolve_tip().unwrap(), (0, genesis.hash()));
+
+        let blocks: Vec<mars::Block> = (0..3).map(|_| node.produce_block().unwrap()).collect();
+        let certify = |height: u64, hash: [u8; 32]| {
+            consensus::FinalityCertificate::new(height, hash, Vec::new(), 0)
+        };
+
+        // Finalized below the stored tip, hashes agree
+        node.consensus_store.save_finality_certificate(2, &certify(2, blocks[1].hash())).unwrap();
+        assert_eq!(node.resolve_tip().unwrap(), (3, blocks[2].hash()));
+
+        // Finalized ahead of storage: resume from the certificate
+        node.consensus_store.save_finality_certificate(5, &certify(5, [7u8; 32])).unwrap();
+        assert_eq!(node.resolve_tip().unwrap(), (5, [7u8; 32]));
+
+        // Stored block contradicts the finalized one
+        node.consensus_store.save_finality_certificate(6, &certify(6, [8u8; 32])).unwrap();
+        for _ in 0..3 {
+            node.produce_block().unwrap();
+        }
+        assert!(matches!(
+            node.resolve_tip(),
+            Err(NodeError::ChainInconsistent { height: 6, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_block_off_canonical_parent_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+        let keypair = tev::Keypair::from_secret(&[0u8; 32]); // The dev producer key
+
+        // Our block #1 was built locally; the network finalized another one
+        let local = node.produce_block().unwrap();
+        let canonical = [7u8; 32];
+        let certificate = consensus::FinalityCertificate::new(1, canonical, Vec::new(), 0);
+        node.consensus_store.save_finality_certificate(1, &certificate).unwrap();
+
+        // Right height, parent matches our tip, but not the finalized chain
+        let state_root = node.runtime.state.state_root;
+        let block = mars::Block::new(2, local.hash(), state_root, Vec::new(), local.producer);
+        let payload = signed_block_payload(&keypair, &block);
+        assert!(matches!(
+            node.handle_block(payload).await,
+            Err(NodeError::NonCanonical { height: 2 })
+        ));
+        assert_eq!(node.height(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_uncertified_block_refused_under_consensus() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+        let keypair = tev::Keypair::from_secret(&[0u8; 32]); // The dev producer key
+        node.enable_consensus(consensus::ValidatorSet::new(vec![keypair.public_key()])).await.unwrap();
+
+        // Validly signed, but nobody decided it
+        let block = genesis_runtime(&node).produce_block(keypair.public_key());
+        let payload = signed_block_payload(&keypair, &block);
+        assert!(matches!(
+            node.handle_block(payload.clone()).await,
+            Err(NodeError::Uncertified { height: 1 })
+        ));
+        assert_eq!(node.height(), 0);
+
+        // Adopted once finality covers it
+        let certificate = consensus::FinalityCertificate::new(1, block.hash(), Vec::new(), 0);
+        node.consensus_store.save_finality_certificate(1, &certificate).unwrap();
+        node.handle_block(payload).await.unwrap();
+        node.finish_imports().await.unwrap();
+        assert_eq!(node.height(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_producer_authorized_by_validator_set_at_height() {
+        let temp_dir = TempDir::new().unwrap();
+        let validator = tev::Keypair::from_secret(&[0u8; 32]); // The dev producer key
+        let newcomer = tev::Keypair::from_secret(&[6u8; 32]);
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        // The static list is not consulted under consensus
+        config.runtime.authorized_producers = vec![hex::encode(newcomer.public_key())];
+        let mut node = Node::new(config).unwrap();
+        node.enable_consensus(consensus::ValidatorSet::new(vec![validator.public_key()])).await.unwrap();
+        let newcomer_set = consensus::ValidatorSet::new(vec![newcomer.public_key()]);
+        node.consensus_store.save_validator_set_at(2, &newcomer_set).unwrap();
+        let certify = |node: &Node, block: &mars::Block| {
+            let certificate = consensus::FinalityCertificate::new(block.height, block.hash(), Vec::new(), 0);
+            node.consensus_store.save_finality_certificate(block.height, &certificate).unwrap();
+        };
+
+        // Not a validator until height 2
+        let early = genesis_runtime(&node).produce_block(newcomer.public_key());
+        certify(&node, &early);
+        assert!(matches!(
+            node.handle_block(signed_block_payload(&newcomer, &early)).await,
+            Err(NodeError::ValidationFailed(_))
+        ));
+        assert_eq!(node.height(), 0);
+
+        let mut chain = genesis_runtime(&node);
+        let first = chain.produce_block(validator.public_key());
+        let second = chain.produce_block(newcomer.public_key());
+        certify(&node, &first);
+        certify(&node, &second);
+        node.handle_block(signed_block_payload(&validator, &first)).await.unwrap();
+        node.finish_imports().await.unwrap();
+        node.handle_block(signed_block_payload(&newcomer, &second)).await.unwrap();
+        node.finish_imports().await.unwrap();
+        assert_eq!(node.height(), 2);
+    }
+
+    #[test]
+    fn test_unverified_certificate_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        // Claims quorum weight without any commits
+        let forged = consensus::FinalityCertificate::new(4, [7u8; 32], Vec::new(), 1);
+        assert!(node.accept_certificate(&forged).is_err()); // No validator set yet
+
+        node.set_validator_set(Arc::new(consensus::ValidatorSet::new(vec![[1u8; 32]])));
+        assert!(matches!(
+            node.accept_certificate(&forged),
+            Err(NodeError::ValidationFailed(_))
+        ));
+        assert_eq!(node.consensus_store.latest_finalized_height().unwrap(), None);
+    }
+
+    #[test]
+    fn test_certificate_verified_against_stored_set() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.genesis.validators = vec![hex::encode(key.verifying_key().to_bytes())];
+        let mut node = Node::new(config).unwrap();
+
+        let mut commit = consensus::Commit {
+            height: 4,
+            round: 0,
+            block_hash: [7u8; 32],
+            validator: consensus::ValidatorId::from_verifying_key(&key.verifying_key()),
+            signature: consensus::types::Signature64::default(),
+        };
+        commit.signature = consensus::types::Signature64::from_bytes(
+            ed25519_dalek::Signer::sign(&key, &commit.signing_payload()).to_bytes(),
+        );
+        let certificate = consensus::FinalityCertificate::new(4, [7u8; 32], vec![commit], 1);
+
+        // Checked against the genesis set loaded back from the store
+        node.accept_certificate(&certificate).unwrap();
+        assert_eq!(node.consensus_store.latest_finalized_height().unwrap(), Some(4));
+    }
+
+    /// Build a TEV-format transaction payload signed by `keypair`.
+    fn signed_tx_payload(keypair: &tev::Keypair, tx: &mars::Transaction) -> Vec<u8> {
+        tev::sign_payload(keypair, &bincode::serialize(tx).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_relayed_once_within_ttl() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        let keypair = tev::Keypair::from_secret(&[3u8; 32]);
+        let sender = mars::address_from_pubkey(&keypair.public_key());
+        node.runtime.state.set_balance(&sender, 1000);
+
+        let payload = signed_tx_payload(&keypair, &mars::Transaction::new(sender, [2u8; 32], 10, 0));
+
+        assert!(node.handle_transaction(payload.clone()).await.unwrap());
+        assert!(!node.handle_transaction(payload).await.unwrap());
+        assert_eq!(node.mempool_size(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_lands_all_or_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        let keypair = tev::Keypair::from_secret(&[3u8; 32]);
+        let sender = mars::address_from_pubkey(&keypair.public_key());
+        node.runtime.state.set_balance(&sender, 1000);
+
+        let txs: Vec<Vec<u8>> = (0..3)
+            .map(|nonce| signed_tx_payload(&keypair, &mars::Transaction::new(sender, [2u8; 32], 10, nonce)))
+            .collect();
+
+        // One contained signature forged: the whole bundle is rejected
+        let mut bundle = tev::TransactionBundle::new();
+        for payload in &txs {
+            bundle.push(payload.clone());
+        }
+        let last = bundle.transactions[2].len() - 1;
+        bundle.transactions[2][last] ^= 1;
+        assert!(node.submit_bundle(bundle.sign(&keypair)).await.is_err());
+        assert_eq!(node.mempool_size(), 0);
+
+        let bundle = tev::TransactionBundle { transactions: txs };
+        assert_eq!(node.submit_bundle(bundle.sign(&keypair)).await.unwrap(), 3);
+        let nonces: Vec<u64> = node.runtime.mempool().iter().map(|tx| tx.nonce).collect();
+        assert_eq!(nonces, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_mempool_restored_and_revalidated() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.persist_mempool = true;
+
+        let sender = [1u8; 32];
+
+        // First session: queue two transactions and shut down
+        {
+            let mut node = Node::new(config.clone()).unwrap();
+            node.runtime.state.set_balance(&sender, 1000);
+            node.storage.save_state(&node.runtime.state).unwrap();
+
+            node.runtime.submit_transaction(mars::Transaction::new(sender, [2u8; 32], 100, 0)).unwrap();
+            node.runtime.submit_transaction(mars::Transaction::new(sender, [2u8; 32], 100, 1)).unwrap();
+            assert_eq!(node.mempool_size(), 2);
+
+            node.shutdown().await;
+        }
+
+        // Nonce 0 is applied elsewhere before restart, making the first tx stale
+        {
+            let storage = Storage::new(temp_dir.path().to_path_buf()).unwrap();
+            let mut state: mars::State = storage.load_state().unwrap();
+            state.increment_nonce(&sender);
+            storage.save_state(&state).unwrap();
+        }
+
+        // Second session: only the still-valid transaction comes back
+        let node = Node::new(config).unwrap();
+        assert_eq!(node.mempool_size(), 1);
+        assert_eq!(node.runtime.mempool()[0].nonce, 1);
+    }
+
+    #[tokio::test]
+    async fn test_lagging_node_catches_up_via_get_blocks() {
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let mut nodes = Vec::new();
+        for (dir, key) in dirs.iter().zip(["01", "02"]) {
+            let mut config = NodeConfig::dev();
+            config.node.data_dir = dir.path().to_path_buf();
+            config.runtime.producer_key = Some(key.repeat(32));
+            nodes.push(Node::new(config).unwrap());
+        }
+        let (mut ahead, mut behind) = (nodes.remove(0), nodes.remove(0));
+        for _ in 0..10 {
+            ahead.produce_block().unwrap();
+        }
+
+        // Connect the two, each with a channel the other's messages arrive on
+        let (ahead_id, behind_id) = (ahead.network.local_id(), behind.network.local_id());
+        let (to_ahead, mut ahead_inbox) = mpsc::channel(16);
+        let (to_behind, mut behind_inbox) = mpsc::channel(16);
+        let addr = "127.0.0.1:30304".parse().unwrap();
+        ahead.network.add_peer(popeye::peer::PeerInfo::new(behind_id, addr), to_behind).unwrap();
+        behind.network.add_peer(popeye::peer::PeerInfo::new(ahead_id, addr), to_ahead).unwrap();
+        let connected = NetworkEvent::PeerConnected { peer_id: *behind_id.as_bytes() };
+        ahead.handle_network_event(connected).await.unwrap();
+
+        // Relay messages both ways: handshake, then GetBlocks and Blocks
+        for _ in 0..5 {
+            while let Ok(message) = behind_inbox.try_recv() {
+                let event = NetworkEvent::MessageReceived { from: *ahead_id.as_bytes(), message };
+                behind.handle_network_event(event).await.unwrap();
+            }
+            behind.finish_imports().await.unwrap();
+            while let Ok(message) = ahead_inbox.try_recv() {
+                let event = NetworkEvent::MessageReceived { from: *behind_id.as_bytes(), message };
+                ahead.handle_network_event(event).await.unwrap();
+            }
+        }
+
+        assert_eq!(behind.height(), 10);
+        assert_eq!(behind.runtime.last_block_hash(), ahead.runtime.last_block_hash());
+
+        // Heights we don't have get no reply
+        ahead.serve_blocks(behind_id, 11, 20).unwrap();
+        assert!(behind_inbox.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_certificates_served_with_blocks_are_verified() {
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let key = ed25519_dalek::SigningKey::from_bytes(&[1u8; 32]);
+        let validators = consensus::ValidatorSet::new(vec![key.verifying_key().to_bytes()]);
+        let mut nodes = Vec::new();
+        for (dir, key) in dirs.iter().zip(["01", "02"]) {
+            let mut config = NodeConfig::dev();
+            config.node.data_dir = dir.path().to_path_buf();
+            config.runtime.producer_key = Some(key.repeat(32));
+            let mut node = Node::new(config).unwrap();
+            node.set_validator_set(Arc::new(validators.clone()));
+            nodes.push(node);
+        }
+        let (mut ahead, mut behind) = (nodes.remove(0), nodes.remove(0));
+        let blocks: Vec<mars::Block> = (0..2).map(|_| ahead.produce_block().unwrap()).collect();
+
+        // A real certificate for block 1, a forged one for block 2
+        let mut commit = consensus::Commit {
+            height: 1,
+            round: 0,
+            block_hash: blocks[0].hash(),
+            validator: consensus::ValidatorId::from_verifying_key(&key.verifying_key()),
+            signature: consensus::types::Signature64::default(),
+        };
+        commit.signature = consensus::types::Signature64::from_bytes(
+            ed25519_dalek::Signer::sign(&key, &commit.signing_payload()).to_bytes(),
+        );
+        let certified = consensus::FinalityCertificate::new(1, blocks[0].hash(), vec![commit], 1);
+        ahead.consensus_store.save_finality_certificate(1, &certified).unwrap();
+        let forged = consensus::FinalityCertificate::new(2, blocks[1].hash(), Vec::new(), 1);
+        ahead.consensus_store.save_finality_certificate(2, &forged).unwrap();
+
+        let (ahead_id, behind_id) = (ahead.network.local_id(), behind.network.local_id());
+        let (to_behind, mut behind_inbox) = mpsc::channel(4);
+        let addr = "127.0.0.1:30306".parse().unwrap();
+        ahead.network.add_peer(popeye::peer::PeerInfo::new(behind_id, addr), to_behind).unwrap();
+        ahead.serve_blocks(behind_id, 1, 2).unwrap();
+        let message = behind_inbox.try_recv().unwrap();
+
+        let event = NetworkEvent::MessageReceived { from: *ahead_id.as_bytes(), message };
+        assert!(matches!(
+            behind.handle_network_event(event).await,
+            Err(NodeError::ValidationFailed(_))
+        ));
+        behind.finish_imports().await.unwrap();
+
+        // Block 1 and its certificate were taken; nothing past the forgery
+        assert_eq!(behind.height(), 1);
+        assert_eq!(behind.consensus_store.latest_finalized_height().unwrap(), Some(1));
+    }
+
+    /// Start an RPC server on a free port; its queries arrive on the receiver.
+    async fn serve_rpc() -> (std::net::SocketAddr, mpsc::Receiver<RpcCall>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (rpc_tx, rpc_rx) = mpsc::channel(4);
+        tokio::spawn(rpc::serve(listener, rpc_tx));
+        (addr, rpc_rx)
+    }
+
+    #[tokio::test]
+    async fn test_rpc_submits_transaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let keypair = tev::Keypair::from_secret(&[1u8; 32]);
+        let sender = mars::address_from_pubkey(&keypair.public_key());
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.genesis.allocations = vec![(hex::encode(sender), 1_000)];
+        let mut node = Node::new(config).unwrap();
+        let (addr, mut rpc_rx) = serve_rpc().await;
+
+        let mut post = async |body: Vec<u8>| {
+            let response = tokio::spawn(async move { rpc::tests::post(addr, "/tx", &body).await });
+            node.handle_rpc(rpc_rx.recv().await.unwrap()).await;
+            response.await.unwrap()
+        };
+
+        let tx = mars::Transaction::new(sender, [2u8; 32], 100, 0);
+        let payload = signed_tx_payload(&keypair, &tx);
+        let response = post(hex::encode(&payload).into_bytes()).await;
+        assert!(response.starts_with("HTTP/1.1 202"), "{}", response);
+        assert!(response.contains(&hex::encode(tx.hash())));
+
+        // Raw bytes work too; a tampered signature is refused
+        let mut forged = signed_tx_payload(&keypair, &mars::Transaction::new(sender, [2u8; 32], 100, 1));
+        *forged.last_mut().unwrap() ^= 0x01;
+        let response = post(forged).await;
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+        assert!(response.contains("\"error\""));
+
+        assert_eq!(node.runtime.mempool_size(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_reports_height_and_balance() {
+        let temp_dir = TempDir::new().unwrap();
+        let (sender, recipient) = ([1u8; 32], [2u8; 32]);
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.genesis.allocations = vec![(hex::encode(sender), 1_000)];
+        let mut node = Node::new(config).unwrap();
+
+        let (addr, mut rpc_rx) = serve_rpc().await;
+
+        node.runtime.submit_transaction(mars::Transaction::new(sender, recipient, 250, 0)).unwrap();
+        node.produce_block().unwrap();
+
+        // Answer each query the way the run loop does
+        let mut get = async |path: String| {
+            let response = tokio::spawn(async move { rpc::tests::get(addr, &path).await });
+            node.handle_rpc(rpc_rx.recv().await.unwrap()).await;
+            response.await.unwrap()
+        };
+
+        let response = get("/height".to_string()).await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("{\"height\":1}"));
+
+        let response = get(format!("/balance/{}", hex::encode(recipient))).await;
+        assert!(response.ends_with("{\"height\":1,\"balance\":250,\"nonce\":0}"));
+        let response = get(format!("/balance/{}", hex::encode(sender))).await;
+        assert!(response.ends_with("{\"height\":1,\"balance\":750,\"nonce\":1}"));
+
+        assert!(get("/mempool".to_string()).await.ends_with("{\"count\":0}"));
+        assert!(get("/block/1".to_string()).await.starts_with("HTTP/1.1 200"));
+        assert!(get("/block/2".to_string()).await.starts_with("HTTP/1.1 404"));
+
+        let response = get("/metrics".to_string()).await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("Content-Type: text/plain"));
+        assert!(response.contains("consensus_blocks_finalized"));
+    }
+
+    #[tokio::test]
+    async fn test_handshake_from_higher_peer_requests_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+
+        let peer = PeerId::new([5u8; 32]);
+        let (to_peer, mut peer_inbox) = mpsc::channel(4);
+        let addr = "127.0.0.1:30305".parse().unwrap();
+        node.network.add_peer(popeye::peer::PeerInfo::new(peer, addr), to_peer).unwrap();
+        let chain_id = node.genesis.hash();
+        let handshake = |height| NetworkEvent::MessageReceived {
+            from: [5u8; 32],
+            message: NetworkMessage::Handshake(popeye::message::HandshakeMessage::new(
+                chain_id,
+                height,
+                [5u8; 32],
+            )),
+        };
+
+        // One block ahead is recorded but not worth a sync
+        node.handle_network_event(handshake(1)).await.unwrap();
+        assert_eq!(node.network.get_peer(&peer).unwrap().height, 1);
+        assert!(peer_inbox.try_recv().is_err());
+
+        node.handle_network_event(handshake(5)).await.unwrap();
+        assert_eq!(node.network.get_peer(&peer).unwrap().height, 5);
+        assert!(matches!(
+            peer_inbox.try_recv(),
+            Ok(NetworkMessage::GetBlocks { from_height: 1, to_height: 5 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_consensus_message_penalizes_sender() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        let mut node = Node::new(config).unwrap();
+        let validator = node.producer.as_ref().unwrap().public_key();
+        node.enable_consensus(consensus::ValidatorSet::new(vec![validator])).await.unwrap();
+
+        let peer = PeerId::new([5u8; 32]);
+        let (to_peer, _peer_inbox) = mpsc::channel(4);
+        let addr = "127.0.0.1:30305".parse().unwrap();
+        node.network.add_peer(popeye::peer::PeerInfo::new(peer, addr), to_peer).unwrap();
+
+        let garbage = NetworkMessage::Consensus(popeye::message::ConsensusMessage::new(vec![0xff; 8]));
+        assert!(node.handle_message(peer, garbage).await.is_err());
+        assert_eq!(
+            node.network.get_peer(&peer).unwrap().score,
+            popeye::network::MALFORMED_MESSAGE_PENALTY
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validator_epoch_scheduled_by_finality() {
+        let temp_dir = TempDir::new().unwrap();
+        let ours = tev::Keypair::from_secret(&[1u8; 32]).public_key();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.producer_key = Some("01".repeat(32));
+        config.genesis.validator_epochs = vec![(3, vec![hex::encode(ours), hex::encode([9u8; 32])])];
+        let mut node = Node::new(config).unwrap();
+        node.enable_consensus(consensus::ValidatorSet::new(vec![ours])).await.unwrap();
+
+        // Alone, we finalize heights 1 and 2 with the genesis committee
+        for _ in 0..50 {
+            if node.height() >= 2 {
+                break;
+            }
+            while let Ok(event) = node.consensus_rx.try_recv() {
+                node.handle_consensus_event(event).await.unwrap();
+            }
+        }
+        assert_eq!(node.height(), 2);
+
+        // Finalizing height 2 brought in the epoch starting at 3
+        let engine = node.consensus.clone().unwrap();
+        assert_eq!(engine.current_height().await, 3);
+        assert_eq!(engine.validator_set().await.len(), 2);
+        assert_eq!(node.validator_set.as_ref().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_only_accepted_proposals_remembered() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = ed25519_dalek::SigningKey::from_bytes(&[2u8; 32]);
+        let theirs = key.verifying_key().to_bytes();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.producer_key = Some("01".repeat(32));
+        let mut node = Node::new(config).unwrap();
+        let ours = node.producer.as_ref().unwrap().public_key();
+        let validators = consensus::ValidatorSet::new(vec![ours, theirs]);
+        let round = (0..2).find(|&r| validators.leader_for_round(r).pubkey == theirs).unwrap();
+        node.enable_consensus(validators).await.unwrap();
+
+        let block = node.runtime.build_block(mars::address_from_pubkey(&theirs)).unwrap();
+        let proposal = |height: u64, round: u64, block: &mars::Block, block_hash: [u8; 32]| {
+            let mut proposal = consensus::Proposal {
+                height,
+                round,
+                prev_hash: block.parent_hash,
+                block_hash,
+                state_root: block.state_root,
+                pol_round: None,
+                transactions: bincode::serialize(block).unwrap(),
+                proposer: consensus::ValidatorId::from_pubkey(&theirs),
+                signature: consensus::types::Signature64::default(),
+            };
+            proposal.signature = consensus::types::Signature64::from_bytes(
+                ed25519_dalek::Signer::sign(&key, &proposal.signing_payload()).to_bytes(),
+            );
+            consensus::wire::encode_message(&consensus::ConsensusMessage::Proposal(proposal)).unwrap()
+        };
+        let peer = PeerId::new([5u8; 32]);
+
+        node.handle_consensus_message(peer, proposal(1, round, &block, block.hash())).await.unwrap();
+        assert!(node.proposed_blocks.contains_key(&block.hash()));
+
+        let other = |timestamp| mars::Block { timestamp, ..block.clone() };
+        // Buffered for a later round, but not the block it claims to carry
+        let mislabeled = proposal(1, round + 2, &other(1), [9u8; 32]);
+        node.handle_consensus_message(peer, mislabeled).await.unwrap();
+        // Too far ahead for the engine to keep
+        let distant = proposal(1_000, round, &other(2), other(2).hash());
+        node.handle_consensus_message(peer, distant).await.unwrap();
+        assert_eq!(node.proposed_blocks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unseen_finalized_block_fetched() {
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let mut nodes = Vec::new();
+        for (dir, key) in dirs.iter().zip(["01", "02"]) {
+            let mut config = NodeConfig::dev();
+            config.node.data_dir = dir.path().to_path_buf();
+            config.runtime.producer_key = Some(key.repeat(32));
+            nodes.push(Node::new(config).unwrap());
+        }
+        let (mut ahead, mut behind) = (nodes.remove(0), nodes.remove(0));
+        let validators = consensus::ValidatorSet::new(vec![ahead.producer.as_ref().unwrap().public_key()]);
+        ahead.enable_consensus(validators.clone()).await.unwrap();
+        behind.enable_consensus(validators).await.unwrap();
+
+        // The lone validator finalizes block 1 without telling anyone
+        while ahead.height() < 1 {
+            let event = ahead.consensus_rx.try_recv().unwrap();
+            ahead.handle_consensus_event(event).await.unwrap();
+        }
+        let certificate: consensus::FinalityCertificate =
+            ahead.consensus_store.load_finality_certificate(1).unwrap().unwrap();
+
+        let (ahead_id, behind_id) = (ahead.network.local_id(), behind.network.local_id());
+        let (to_ahead, mut ahead_inbox) = mpsc::channel(4);
+        let (to_behind, mut behind_inbox) = mpsc::channel(4);
+        let addr = "127.0.0.1:30307".parse().unwrap();
+        ahead.network.add_peer(popeye::peer::PeerInfo::new(behind_id, addr), to_behind).unwrap();
+        behind.network.add_peer(popeye::peer::PeerInfo::new(ahead_id, addr), to_ahead).unwrap();
+        behind.network.update_peer_height(&ahead_id, 1).unwrap();
+        behind.apply_finalized(1, certificate.block_hash, &certificate).await.unwrap();
+
+        // Consensus moved on while the block is fetched
+        assert_eq!(behind.consensus.as_ref().unwrap().current_height().await, 2);
+        let request = ahead_inbox.try_recv().unwrap();
+        assert!(matches!(request, NetworkMessage::GetBlocks { from_height: 1, to_height: 1 }));
+        let event = NetworkEvent::MessageReceived { from: *behind_id.as_bytes(), message: request };
+        ahead.handle_network_event(event).await.unwrap();
+
+        // Served without a producer signature, backed by the certificate
+        let message = behind_inbox.try_recv().unwrap();
+        let event = NetworkEvent::MessageReceived { from: *ahead_id.as_bytes(), message };
+        behind.handle_network_event(event).await.unwrap();
+        behind.finish_imports().await.unwrap();
+        assert_eq!(behind.height(), 1);
+        assert_eq!(behind.runtime.last_block_hash(), certificate.block_hash);
+
+        // A decision against a block we already hold is an error
+        let other = consensus::FinalityCertificate::new(1, [7u8; 32], Vec::new(), 0);
+        assert!(matches!(
+            behind.apply_finalized(1, [7u8; 32], &other).await,
+            Err(NodeError::ChainInconsistent { height: 1, .. })
+        ));
+        let kept: consensus::FinalityCertificate =
+            behind.consensus_store.load_finality_certificate(1).unwrap().unwrap();
+        assert_eq!(kept.block_hash, certificate.block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_two_validators_finalize_block_via_consensus() {
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let mut nodes = Vec::new();
+        let mut validators = Vec::new();
+        for (dir, key) in dirs.iter().zip(["01", "02"]) {
+            let mut config = NodeConfig::dev();
+            config.node.data_dir = dir.path().to_path_buf();
+            config.runtime.producer_key = Some(key.repeat(32));
+            let node = Node::new(config).unwrap();
+            validators.push(node.producer.as_ref().unwrap().public_key());
+            nodes.push(node);
+        }
+        for node in nodes.iter_mut() {
+            node.enable_consensus(consensus::ValidatorSet::new(validators.clone())).await.unwrap();
+        }
+
+        // Relay each node's broadcasts to the other until both finalize
+        for _ in 0..50 {
+            if nodes.iter().all(|node| node.height() >= 1) {
+                break;
+            }
+            for i in 0..2 {
+                while let Ok(event) = nodes[i].consensus_rx.try_recv() {
+                    if let Some(message) = Node::consensus_gossip(&event).unwrap() {
+                        let from = nodes[i].network.local_id();
+                        nodes[1 - i].handle_message(from, message).await.unwrap();
+                    }
+                    nodes[i].handle_consensus_event(event).await.unwrap();
+                }
+            }
+        }
+
+        assert_eq!(nodes[0].height(), 1);
+        assert_eq!(nodes[1].height(), 1);
+        assert_eq!(nodes[0].runtime.last_block_hash(), nodes[1].runtime.last_block_hash());
+        for node in &nodes {
+            let certificate: Option<consensus::FinalityCertificate> =
+                node.consensus_store.load_finality_certificate(1).unwrap();
+            assert_eq!(certificate.unwrap().block_hash, node.runtime.last_block_hash());
+
+            let audit = node.audit_log.entries().unwrap();
+            assert_eq!(audit.len(), 1);
+            assert_eq!((audit[0].height, audit[0].block_hash), (1, node.runtime.last_block_hash()));
+            assert_eq!(audit[0].committers.len() as u64, audit[0].weight);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restarted_validator_still_votes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.rpc.enabled = false;
+        config.runtime.producer_key = Some("01".repeat(32));
+        let ours = tev::Keypair::from_secret(&[1u8; 32]).public_key();
+        config.genesis.validators = vec![hex::encode(ours)];
+        drop(Node::new(config.clone()).unwrap());
+
+        // The validator set now comes back from the store
+        let mut node = Node::new(config).unwrap();
+        let run = tokio::time::timeout(std::time::Duration::from_secs(5), node.run()).await;
+        assert!(run.is_err());
+
+        assert!(node.height() >= 1);
+        let audit = node.audit_log.entries().unwrap();
+        assert_eq!(audit[0].committers, vec![consensus::ValidatorId::from_pubkey(&ours).0]);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_root_mismatches_enter_safe_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let peer = tev::Keypair::from_secret(&[1u8; 32]);
+        let mut config = NodeConfig::dev();
+        config.node.data_dir = temp_dir.path().to_path_buf();
+        config.runtime.safe_mode_mismatches = 2;
+        let mut node = Node::new(config).unwrap();
+
+        let mut peer_chain = genesis_runtime(&node);
+        let good = peer_chain.produce_block(peer.public_key());
+        let next = peer_chain.produce_block(peer.public_key());
+        let with_root = |block: &mars::Block, root: u8| {
+            let mut block = block.clone();
+            block.state_root = [root; 32];
+            signed_block_payload(&peer, &block)
+        };
+
+        // A clean block in between resets the count
+        node.handle_block(with_root(&good, 9)).await.unwrap();
+        assert!(node.finish_imports().await.is_err());
+        node.handle_block(signed_block_payload(&peer, &good)).await.unwrap();
+        node.finish_imports().await.unwrap();
+        assert!(!node.is_safe_mode());
+
+        for root in [10, 11] {
+            node.handle_block(with_root(&next, root)).await.unwrap();
+            assert!(node.finish_imports().await.is_err());
+        }
+
+        assert!(node.is_safe_mode());
+        assert!(node.status().await.safe_mode);
+        assert!(matches!(node.produce_block(), Err(NodeError::SafeMode { mismatches: 2 })));
+        assert_eq!(node.height(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_consensus_timeouts_from_config_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("node.toml");
+        let toml = format!(
+            "[node]\ndata_dir = {:?}\n\n[runtime]\nproducer_key = \"{}\"\n\n\
+             [consensus]\npropose_timeout = 1500\nprevote_timeout = 700\ncommit_timeout = 900\n",
+            temp_dir.path().join("data"),
+            "01".repeat(32),
+        );
+        std::fs::write(&path, toml).unwrap();
+
+        let config = NodeConfig::load(&path).unwrap();
+        let mut node = Node::new(config).unwrap();
+        let ours = node.producer.as_ref().unwrap().public_key();
+        node.enable_consensus(consensus::ValidatorSet::new(vec![ours])).await.unwrap();
+
+        let engine = node.consensus.as_ref().unwrap();
+        assert_eq!(engine.config().propose_timeout, std::time::Duration::from_millis(1500));
+        assert_eq!(engine.config().prevote_timeout, std::time::Duration::from_millis(700));
+        assert_eq!(engine.config().commit_timeout, std::time::Duration::from_millis(900));
+        assert_eq!(engine.config().max_rounds, consensus::ConsensusConfig::default().max_rounds);
+    }
 }
@@ -0,0 +1,88 @@
+//! Proposal validity for consensus.
+//!
+//! A proposal's opaque `transactions` field carries the bincode-encoded
+//! MARS block. Before the engine prevotes, the node decodes it and checks
+//! that the proposal's header fields describe that block.
+
+use consensus::{ConsensusError, Proposal, ProposalValidator, ValidatorId};
+
+/// Checks that a proposal's hash, height, parent and state root match the
+/// block it carries, and that the proposer produced it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlockProposalValidator;
+
+impl ProposalValidator for BlockProposalValidator {
+    fn validate(&self, proposal: &Proposal) -> consensus::Result<()> {
+        let invalid = |reason: &str| ConsensusError::InvalidBlock {
+            reason: reason.to_string(),
+        };
+
+        let block: mars::Block = bincode::deserialize(&proposal.transactions)
+            .map_err(|_| invalid("proposal does not carry a block"))?;
+
+        if block.hash() != proposal.block_hash {
+            return Err(invalid("block hash does not match proposed block"));
+        }
+        if block.height != proposal.height {
+            return Err(invalid("block height does not match proposal"));
+        }
+        if block.parent_hash != proposal.prev_hash {
+            return Err(invalid("parent hash does not match proposal"));
+        }
+        if block.state_root != proposal.state_root {
+            return Err(invalid("state root does not match proposal"));
+        }
+        if ValidatorId::from_pubkey(&block.producer) != proposal.proposer {
+            return Err(invalid("block producer is not the proposer"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use consensus::types::Signature64;
+
+    fn proposal_for(block: &mars::Block) -> Proposal {
+        Proposal {
+            height: block.height,
+            round: 0,
+            prev_hash: block.parent_hash,
+            block_hash: block.hash(),
+            state_root: block.state_root,
+            pol_round: None,
+            transactions: bincode::serialize(block).unwrap(),
+            proposer: ValidatorId::from_pubkey(&block.producer),
+            signature: Signature64::default(),
+        }
+    }
+
+    #[test]
+    fn test_block_hash_must_match_contents() {
+        let block = mars::Runtime::default().produce_block([1u8; 32]);
+        let mut proposal = proposal_for(&block);
+        assert!(BlockProposalValidator.validate(&proposal).is_ok());
+
+        proposal.block_hash = [9u8; 32];
+        assert!(matches!(
+            BlockProposalValidator.validate(&proposal),
+            Err(ConsensusError::InvalidBlock { .. })
+        ));
+
+        proposal.transactions = vec![1, 2, 3];
+        assert!(BlockProposalValidator.validate(&proposal).is_err());
+    }
+
+    #[test]
+    fn test_block_must_be_produced_by_proposer() {
+        let block = mars::Runtime::default().produce_block([1u8; 32]);
+        let mut proposal = proposal_for(&block);
+        proposal.proposer = ValidatorId::from_pubkey(&[2u8; 32]);
+        assert!(matches!(
+            BlockProposalValidator.validate(&proposal),
+            Err(ConsensusError::InvalidBlock { .. })
+        ));
+    }
+}
@@ -0,0 +1,363 @@
+//! HTTP query API.
+//!
+//! A minimal HTTP/1.1 server exposing node information as JSON.
+//! The server never touches node state directly: every request is
+//! forwarded to the node's main loop over a channel and answered there.
+//!
+//! Queries are read-only `GET`s:
+//!
+//! - `/health`: status, including what consensus waits for; 503 until the
+//!   node is synced
+//! - `/height`: current block height
+//! - `/balance/{hex_address}`: balance and nonce at the current height
+//! - `/mempool`: number of pending transactions
+//! - `/block/{height}`: a stored block; 404 if it is not stored
+//! - `/metrics`: consensus metrics in the Prometheus text format
+//!
+//! `POST /tx` submits a TEV-format signed transaction, sent as hex or raw
+//! bytes. It is verified, added to the mempool and relayed exactly like a
+//! gossiped one; the answer is 202 with its hash, or 400 with the reason
+//! it was refused.
+
+use crate::node::{AccountView, NodeStatus};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+
+/// Maximum size of an HTTP request head.
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// Maximum size of a request body.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Content type of every response except `/metrics`.
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Content type of the Prometheus text exposition format.
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// A query the RPC server needs the node to answer.
+#[derive(Debug)]
+pub enum RpcRequest {
+    /// Health / readiness status
+    Status,
+
+    /// Current block height
+    Height,
+
+    /// An account at the current height
+    Balance(mars::Address),
+
+    /// Number of pending transactions
+    MempoolSize,
+
+    /// The stored block at a height
+    Block(u64),
+
+    /// Submit a signed transaction payload
+    SubmitTransaction(Vec<u8>),
+
+    /// Metrics in the Prometheus text format
+    Metrics,
+}
+
+/// The node's answer to an `RpcRequest`.
+#[derive(Debug)]
+pub enum RpcResponse {
+    /// Health / readiness status
+    Status(NodeStatus),
+
+    /// Current block height
+    Height(u64),
+
+    /// An account at the current height
+    Balance(AccountView),
+
+    /// Number of pending transactions
+    MempoolSize(usize),
+
+    /// The stored block, if there is one
+    Block(Option<Box<mars::Block>>),
+
+    /// Hash of the accepted transaction, or why it was refused
+    Submitted(Result<[u8; 32], String>),
+
+    /// Encoded metrics
+    Metrics(String),
+}
+
+/// A request paired with the channel its response goes back on.
+#[derive(Debug)]
+pub struct RpcCall {
+    /// The query
+    pub request: RpcRequest,
+
+    /// Where to send the answer
+    pub respond_to: oneshot::Sender<RpcResponse>,
+}
+
+/// Accept connections forever, forwarding queries to the node.
+pub async fn serve(listener: TcpListener, calls: mpsc::Sender<RpcCall>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("RPC accept error: {}", e);
+                continue;
+            }
+        };
+
+        let calls = calls.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, calls).await {
+                eprintln!("RPC connection error: {}", e);
+            }
+        });
+    }
+}
+
+/// Serve a single request on a connection.
+async fn handle_connection(
+    mut stream: TcpStream,
+    calls: mpsc::Sender<RpcCall>,
+) -> std::io::Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+
+    // Read until the end of the request head
+    let head_end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if buf.len() >= MAX_REQUEST_BYTES {
+            return write_response(&mut stream, 400, JSON_CONTENT_TYPE, "{\"error\":\"request too large\"}").await;
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    // Then the body, as long as the head says it is
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        return write_response(&mut stream, 400, JSON_CONTENT_TYPE, "{\"error\":\"request too large\"}").await;
+    }
+    let mut body = buf.split_off(head_end);
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    let (status, response) = route(method, path, body, &calls).await;
+    let content_type = if path == "/metrics" && status == 200 {
+        METRICS_CONTENT_TYPE
+    } else {
+        JSON_CONTENT_TYPE
+    };
+    write_response(&mut stream, status, content_type, &response).await
+}
+
+/// Map a request to a status code and body (JSON, except for metrics).
+async fn route(method: &str, path: &str, body: Vec<u8>, calls: &mpsc::Sender<RpcCall>) -> (u16, String) {
+    if method == "POST" && path == "/tx" {
+        return submit_transaction(body, calls).await;
+    }
+    if method != "GET" {
+        return (405, "{\"error\":\"method not allowed\"}".to_string());
+    }
+
+    match path {
+        "/health" => match query(calls, RpcRequest::Status).await {
+            Some(RpcResponse::Status(status)) => {
+                // Orchestrators only route traffic to nodes that are caught up
+                let code = if status.synced { 200 } else { 503 };
+                (code, serde_json::to_string(&status).unwrap_or_default())
+            }
+            _ => unavailable(),
+        },
+        "/height" => match query(calls, RpcRequest::Height).await {
+            Some(RpcResponse::Height(height)) => (200, serde_json::json!({ "height": height }).to_string()),
+            _ => unavailable(),
+        },
+        "/mempool" => match query(calls, RpcRequest::MempoolSize).await {
+            Some(RpcResponse::MempoolSize(count)) => (200, serde_json::json!({ "count": count }).to_string()),
+            _ => unavailable(),
+        },
+        "/metrics" => match query(calls, RpcRequest::Metrics).await {
+            Some(RpcResponse::Metrics(text)) => (200, text),
+            _ => unavailable(),
+        },
+        _ => {
+            if let Some(address) = path.strip_prefix("/balance/") {
+                let Some(address) = parse_address(address) else {
+                    return (400, "{\"error\":\"address must be 32 bytes of hex\"}".to_string());
+                };
+                match query(calls, RpcRequest::Balance(address)).await {
+                    Some(RpcResponse::Balance(view)) => (200, serde_json::to_string(&view).unwrap_or_default()),
+                    _ => unavailable(),
+                }
+            } else if let Some(height) = path.strip_prefix("/block/") {
+                let Ok(height) = height.parse::<u64>() else {
+                    return (400, "{\"error\":\"height must be a number\"}".to_string());
+                };
+                match query(calls, RpcRequest::Block(height)).await {
+                    Some(RpcResponse::Block(Some(block))) => (200, serde_json::to_string(&block).unwrap_or_default()),
+                    Some(RpcResponse::Block(None)) => not_found(),
+                    _ => unavailable(),
+                }
+            } else {
+                not_found()
+            }
+        }
+    }
+}
+
+/// Submit a transaction payload sent as hex or raw bytes.
+async fn submit_transaction(body: Vec<u8>, calls: &mpsc::Sender<RpcCall>) -> (u16, String) {
+    let payload = std::str::from_utf8(&body)
+        .ok()
+        .and_then(|text| hex::decode(text.trim()).ok())
+        .unwrap_or(body);
+
+    match query(calls, RpcRequest::SubmitTransaction(payload)).await {
+        Some(RpcResponse::Submitted(Ok(hash))) => (202, serde_json::json!({ "hash": hex::encode(hash) }).to_string()),
+        Some(RpcResponse::Submitted(Err(reason))) => (400, serde_json::json!({ "error": reason }).to_string()),
+        _ => unavailable(),
+    }
+}
+
+/// Decode a hex account address.
+fn parse_address(hex_address: &str) -> Option<mars::Address> {
+    hex::decode(hex_address).ok()?.try_into().ok()
+}
+
+/// Response for a path or record that does not exist.
+fn not_found() -> (u16, String) {
+    (404, "{\"error\":\"not found\"}".to_string())
+}
+
+/// Forward a query to the node and wait for its answer.
+async fn query(calls: &mpsc::Sender<RpcCall>, request: RpcRequest) -> Option<RpcResponse> {
+    let (respond_to, response) = oneshot::channel();
+    calls.send(RpcCall { request, respond_to }).await.ok()?;
+    response.await.ok()
+}
+
+/// Response used when the node loop is not answering.
+fn unavailable() -> (u16, String) {
+    (503, "{\"error\":\"node unavailable\"}".to_string())
+}
+
+/// Write a complete HTTP response and close the connection.
+async fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Send a `GET` for `path` and return the raw response.
+    pub(crate) async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// Send a `POST` of `body` to `path` and return the raw response.
+    pub(crate) async fn post(addr: std::net::SocketAddr, path: &str, body: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let head = format!(
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            path,
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(body).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (calls_tx, mut calls_rx) = mpsc::channel(16);
+        tokio::spawn(serve(listener, calls_tx));
+
+        // Stand-in for the node loop
+        tokio::spawn(async move {
+            while let Some(call) = calls_rx.recv().await {
+                assert!(matches!(call.request, RpcRequest::Status));
+                let _ = call.respond_to.send(RpcResponse::Status(NodeStatus {
+                    height: 4,
+                    synced: false,
+                    peer_count: 2,
+                    mempool_size: 1,
+                    safe_mode: false,
+                    progress_blocker: Some(consensus::ProgressBlocker::WaitingForProposal),
+                }));
+            }
+        });
+
+        let response = get(addr, "/health").await;
+        assert!(response.starts_with("HTTP/1.1 503"));
+        assert!(response.contains("\"height\":4"));
+        assert!(response.contains("\"progress_blocker\":\"WaitingForProposal\""));
+        assert!(response.contains("\"synced\":false"));
+
+        let response = get(addr, "/nope").await;
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        // Malformed parameters are refused without asking the node
+        assert!(get(addr, "/balance/zz").await.starts_with("HTTP/1.1 400"));
+        assert!(get(addr, "/block/tip").await.starts_with("HTTP/1.1 400"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Wire codecs for gossip messages.
+//!
+//! Every deployment picks one wire format; all peers on a network must
+//! agree on it. Decoding is always bounded by a maximum message size so a
+//! peer cannot make us allocate far more memory than it sent.
+
+use crate::NetworkError;
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Default maximum encoded message size (8 MiB).
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 8 * 1024 * 1024;
+
+/// Encoding used on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    /// Standard bincode with fixed-width integers
+    #[default]
+    Bincode,
+
+    /// Bincode with variable-length integers; smaller for typical messages
+    Compact,
+}
+
+/// Encodes and decodes network messages in a fixed wire format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Codec {
+    /// Wire format
+    pub format: WireFormat,
+
+    /// Largest encoded message accepted or produced
+    pub max_message_size: usize,
+}
+
+impl Codec {
+    /// Create a codec.
+    pub fn new(format: WireFormat, max_message_size: usize) -> Self {
+        Self {
+            format,
+            max_message_size,
+        }
+    }
+
+    /// Encode a message.
+    pub fn encode<T: Serialize>(&self, message: &T) -> Result<Vec<u8>, NetworkError> {
+        let limit = self.max_message_size as u64;
+        let result = match self.format {
+            WireFormat::Bincode => bincode::options()
+                .with_fixint_encoding()
+                .with_limit(limit)
+                .serialize(message),
+            WireFormat::Compact => bincode::options()
+                .with_varint_encoding()
+                .with_limit(limit)
+                .serialize(message),
+        };
+
+        result.map_err(|e| match *e {
+            bincode::ErrorKind::SizeLimit => NetworkError::MessageTooLarge {
+                max: self.max_message_size,
+            },
+            other => NetworkError::SerializationError(other.to_string()),
+        })
+    }
+
+    /// Decode a message.
+    ///
+    /// Rejects input longer than the size limit up front, and bounds the
+    /// memory decoding may claim so inflated length prefixes fail fast.
+    /// Bytes left over after the message are an error in every format.
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, NetworkError> {
+        if bytes.len() > self.max_message_size {
+            return Err(NetworkError::MessageTooLarge {
+                max: self.max_message_size,
+            });
+        }
+
+        let limit = self.max_message_size as u64;
+        let result = match self.format {
+            WireFormat::Bincode => bincode::options()
+                .with_fixint_encoding()
+                .with_limit(limit)
+                .deserialize(bytes),
+            WireFormat::Compact => bincode::options()
+                .with_varint_encoding()
+                .with_limit(limit)
+                .deserialize(bytes),
+        };
+
+        result.map_err(|e| match *e {
+            bincode::ErrorKind::SizeLimit => NetworkError::MessageTooLarge {
+                max: self.max_message_size,
+            },
+            other => NetworkError::DeserializationError(other.to_string()),
+        })
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self::new(WireFormat::default(), DEFAULT_MAX_MESSAGE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{BlockMessage, NetworkMessage, TransactionMessage};
+
+    fn round_trip(codec: Codec) -> Vec<u8> {
+        let message = NetworkMessage::Block(BlockMessage::new(vec![7u8; 100], 42));
+        let bytes = codec.encode(&message).unwrap();
+
+        match codec.decode::<NetworkMessage>(&bytes).unwrap() {
+            NetworkMessage::Block(block) => {
+                assert_eq!(block.height, 42);
+                assert_eq!(block.payload, vec![7u8; 100]);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_bincode_round_trip() {
+        let bytes = round_trip(Codec::default());
+
+        // Wire-compatible with plain bincode
+        let plain = bincode::serialize(&NetworkMessage::Block(BlockMessage::new(vec![7u8; 100], 42))).unwrap();
+        assert_eq!(bytes, plain);
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let compact = round_trip(Codec::new(WireFormat::Compact, DEFAULT_MAX_MESSAGE_SIZE));
+        let standard = round_trip(Codec::default());
+        assert!(compact.len() < standard.len());
+    }
+
+    #[test]
+    fn test_trailing_bytes_rejected() {
+        for codec in [Codec::default(), Codec::new(WireFormat::Compact, DEFAULT_MAX_MESSAGE_SIZE)] {
+            let message = NetworkMessage::Block(BlockMessage::new(vec![7u8; 10], 42));
+            let mut bytes = codec.encode(&message).unwrap();
+            bytes.push(0);
+            assert!(matches!(
+                codec.decode::<NetworkMessage>(&bytes),
+                Err(NetworkError::DeserializationError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_size_limit_rejected() {
+        let codec = Codec::new(WireFormat::Bincode, 64);
+        let message = NetworkMessage::Transaction(TransactionMessage::new(vec![0u8; 128]));
+
+        // Too large to send
+        assert!(matches!(codec.encode(&message), Err(NetworkError::MessageTooLarge { .. })));
+
+        // Too large to receive
+        let bytes = Codec::default().encode(&message).unwrap();
+        assert!(matches!(
+            codec.decode::<NetworkMessage>(&bytes),
+            Err(NetworkError::MessageTooLarge { .. })
+        ));
+
+        // A tiny message claiming a huge payload fails without allocating it
+        let mut bomb = 0u32.to_le_bytes().to_vec(); // Transaction variant
+        bomb.extend_from_slice(&(u64::MAX / 2).to_le_bytes()); // payload length
+        assert!(codec.decode::<NetworkMessage>(&bomb).is_err());
+    }
+}
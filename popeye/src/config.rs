@@ -1,6 +1,56 @@
 //! Network configuration.
 
+use crate::codec::Codec;
+use crate::rate_limit::RateLimit;
 use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Default misbehavior score at which a peer is banned.
+pub const DEFAULT_BAN_THRESHOLD: u32 = 100;
+
+/// Default length of a ban.
+pub const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(600);
+
+/// Default time for one point of misbehavior score to be forgiven.
+pub const DEFAULT_SCORE_DECAY: Duration = Duration::from_secs(6);
+
+/// Default allowance for transactions, blocks and other messages, per peer.
+pub const DEFAULT_MESSAGE_RATE_LIMIT: RateLimit = RateLimit { per_second: 500, burst: 1000 };
+
+/// Default allowance for pings and pongs, per peer.
+pub const DEFAULT_PING_RATE_LIMIT: RateLimit = RateLimit { per_second: 2, burst: 5 };
+
+/// How gossip messages are authenticated at the gossipsub layer.
+///
+/// Every block and transaction is verified by TEV regardless of this
+/// setting; it only controls the extra per-message peer signature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GossipAuthenticity {
+    /// Sign every published message with the node's peer key and reject
+    /// unsigned or forged messages (the default).
+    #[default]
+    Signed,
+
+    /// Publish messages tagged with the author's peer ID but unsigned.
+    ///
+    /// Saves a signature and verification per message. Only for trusted or
+    /// permissioned networks: any peer can relay messages attributed to
+    /// another, so gossip-level origin can no longer be relied on. Messages
+    /// are still deduplicated by content.
+    Unsigned,
+}
+
+/// When to drop peers whose height diverges too far from ours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DivergencePolicy {
+    /// Largest tolerated height difference, in blocks
+    pub threshold: u64,
+
+    /// How long a peer may stay diverged before it is disconnected. For a
+    /// peer far ahead the period restarts whenever our own height advances,
+    /// so peers we are still syncing from are kept.
+    pub grace: Duration,
+}
 
 /// Configuration for the network layer.
 #[derive(Clone, Debug)]
@@ -22,6 +72,34 @@ pub struct NetworkConfig {
 
     /// Bootstrap peers to connect to
     pub bootstrap_peers: Vec<SocketAddr>,
+
+    /// Wire codec for gossip messages
+    pub codec: Codec,
+
+    /// How long a dropped peer may take to reconnect before it is reported
+    /// as disconnected
+    pub disconnect_grace: Duration,
+
+    /// Gossip-layer message authentication
+    pub gossip_authenticity: GossipAuthenticity,
+
+    /// Disconnect peers whose height diverges from ours (None = never)
+    pub height_divergence: Option<DivergencePolicy>,
+
+    /// Misbehavior score at which a peer is disconnected and banned
+    pub ban_threshold: u32,
+
+    /// How long a banned peer is refused
+    pub ban_duration: Duration,
+
+    /// Time for one point of misbehavior score to be forgiven (zero = never)
+    pub score_decay: Duration,
+
+    /// Inbound allowance per peer for everything but pings and pongs
+    pub message_rate_limit: RateLimit,
+
+    /// Inbound allowance per peer for pings and pongs
+    pub ping_rate_limit: RateLimit,
 }
 
 impl NetworkConfig {
@@ -34,6 +112,15 @@ impl NetworkConfig {
             chain_id: [0u8; 32],
             node_id,
             bootstrap_peers: Vec::new(),
+            codec: Codec::default(),
+            disconnect_grace: Duration::from_secs(5),
+            gossip_authenticity: GossipAuthenticity::default(),
+            height_divergence: None,
+            ban_threshold: DEFAULT_BAN_THRESHOLD,
+            ban_duration: DEFAULT_BAN_DURATION,
+            score_decay: DEFAULT_SCORE_DECAY,
+            message_rate_limit: DEFAULT_MESSAGE_RATE_LIMIT,
+            ping_rate_limit: DEFAULT_PING_RATE_LIMIT,
         }
     }
 
@@ -55,6 +142,51 @@ impl NetworkConfig {
         self
     }
 
+    /// Set the wire codec.
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Set the disconnect grace period.
+    pub fn with_disconnect_grace(mut self, grace: Duration) -> Self {
+        self.disconnect_grace = grace;
+        self
+    }
+
+    /// Set the gossip-layer message authentication.
+    pub fn with_gossip_authenticity(mut self, authenticity: GossipAuthenticity) -> Self {
+        self.gossip_authenticity = authenticity;
+        self
+    }
+
+    /// Disconnect peers diverging by more than `threshold` blocks for `grace`.
+    pub fn with_height_divergence(mut self, threshold: u64, grace: Duration) -> Self {
+        self.height_divergence = Some(DivergencePolicy { threshold, grace });
+        self
+    }
+
+    /// Ban peers whose misbehavior score reaches `threshold`, for `duration`.
+    pub fn with_ban_policy(mut self, threshold: u32, duration: Duration) -> Self {
+        self.ban_threshold = threshold;
+        self.ban_duration = duration;
+        self
+    }
+
+    /// Forgive one point of misbehavior score per `interval` (zero = never).
+    pub fn with_score_decay(mut self, interval: Duration) -> Self {
+        self.score_decay = interval;
+        self
+    }
+
+    /// Set the inbound allowances per peer: `messages` for everything but
+    /// pings and pongs, which get `pings`.
+    pub fn with_rate_limits(mut self, messages: RateLimit, pings: RateLimit) -> Self {
+        self.message_rate_limit = messages;
+        self.ping_rate_limit = pings;
+        self
+    }
+
     /// Add bootstrap peers.
     pub fn with_bootstrap_peers(mut self, peers: Vec<SocketAddr>) -> Self {
         self.bootstrap_peers = peers;
@@ -71,6 +203,15 @@ impl Default for NetworkConfig {
             chain_id: [0u8; 32],
             node_id: [0u8; 32],
             bootstrap_peers: Vec::new(),
+            codec: Codec::default(),
+            disconnect_grace: Duration::from_secs(5),
+            gossip_authenticity: GossipAuthenticity::default(),
+            height_divergence: None,
+            ban_threshold: DEFAULT_BAN_THRESHOLD,
+            ban_duration: DEFAULT_BAN_DURATION,
+            score_decay: DEFAULT_SCORE_DECAY,
+            message_rate_limit: DEFAULT_MESSAGE_RATE_LIMIT,
+            ping_rate_limit: DEFAULT_PING_RATE_LIMIT,
         }
     }
 }
@@ -29,6 +29,10 @@ pub enum NetworkError {
     #[error("peer not found: {0}")]
     PeerNotFound(String),
 
+    /// Peer is banned for misbehavior
+    #[error("peer banned: {0}")]
+    PeerBanned(String),
+
     /// Maximum peers reached
     #[error("max peers reached")]
     MaxPeersReached,
@@ -69,6 +73,10 @@ pub enum NetworkError {
     #[error("deserialization error: {0}")]
     DeserializationError(String),
 
+    /// Message exceeds the codec size limit
+    #[error("message exceeds maximum size of {max} bytes")]
+    MessageTooLarge { max: usize },
+
     /// Publish error
     #[error("publish error: {0}")]
     PublishError(String),
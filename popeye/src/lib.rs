@@ -14,16 +14,22 @@
 //! POPEYE hears rumors, not facts.
 //! All messages must pass through TEV before reaching MARS.
 
+pub mod codec;
 pub mod config;
 pub mod error;
 pub mod libp2p_network;
 pub mod message;
 pub mod network;
 pub mod peer;
+pub mod rate_limit;
+pub mod seen_cache;
 
-pub use config::NetworkConfig;
+pub use codec::{Codec, WireFormat};
+pub use config::{DivergencePolicy, GossipAuthenticity, NetworkConfig};
 pub use error::NetworkError;
 pub use libp2p_network::Libp2pNetwork;
 pub use message::NetworkMessage;
 pub use network::Network;
-pub use peer::PeerId;
+pub use peer::{Divergence, PeerId};
+pub use rate_limit::RateLimit;
+pub use seen_cache::SeenCache;
@@ -1,27 +1,42 @@
 //! libp2p-based network implementation.
 //!
 //! Real P2P networking using gossipsub for message propagation.
+//!
+//! Connection bookkeeping goes through the same `Network` as the skeleton
+//! transport, so a flapping connection is held on probation instead of
+//! reported, banned peers are refused, and inbound messages are rate
+//! limited. Events reach the node from that `Network`'s channel.
 
-use crate::config::NetworkConfig;
+use crate::codec::Codec;
+use crate::config::{GossipAuthenticity, NetworkConfig};
 use crate::message::{NetworkEvent, NetworkMessage};
+use crate::network::Network;
+use crate::peer::{self, PeerInfo};
 use crate::NetworkError;
 use futures::StreamExt;
 use libp2p::{
     gossipsub::{self, IdentTopic, MessageAuthenticity, MessageId},
-    identify, mdns, noise,
+    identify, mdns,
+    multiaddr::Protocol,
+    noise,
     swarm::{NetworkBehaviour, SwarmEvent},
     tcp, yamux, Multiaddr, PeerId, Swarm,
 };
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::net::{Ipv4Addr, SocketAddr};
 use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 /// Gossipsub topic for transactions
 const TOPIC_TX: &str = "unykorn/tx/1.0.0";
 /// Gossipsub topic for blocks
 const TOPIC_BLOCK: &str = "unykorn/block/1.0.0";
+/// Gossipsub topic for consensus proposals, votes and certificates
+const TOPIC_CONSENSUS: &str = "unykorn/consensus/1.0.0";
+/// How often peers on probation are checked for an expired grace period
+const PROBATION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Combined network behaviour.
 #[derive(NetworkBehaviour)]
@@ -38,22 +53,27 @@ struct UnykornBehaviour {
 pub struct Libp2pNetwork {
     /// The libp2p swarm
     swarm: Swarm<UnykornBehaviour>,
-    /// Channel to send events to the node
-    event_tx: mpsc::Sender<NetworkEvent>,
+    /// Peer bookkeeping, which also sends events to the node
+    peers: Network,
     /// Transaction topic
     topic_tx: IdentTopic,
     /// Block topic
     topic_block: IdentTopic,
+    /// Consensus topic
+    topic_consensus: IdentTopic,
+    /// Wire codec
+    codec: Codec,
 }
 
 impl Libp2pNetwork {
     /// Create a new libp2p network.
     pub async fn new(config: &NetworkConfig) -> Result<(Self, mpsc::Receiver<NetworkEvent>), NetworkError> {
-        let (event_tx, event_rx) = mpsc::channel(1024);
+        let (peers, event_rx) = Network::new(config.clone());
 
         // Create topics
         let topic_tx = IdentTopic::new(TOPIC_TX);
         let topic_block = IdentTopic::new(TOPIC_BLOCK);
+        let topic_consensus = IdentTopic::new(TOPIC_CONSENSUS);
 
         // Message ID function (for deduplication)
         let message_id_fn = |message: &gossipsub::Message| {
@@ -62,11 +82,19 @@ impl Libp2pNetwork {
             MessageId::from(hasher.finish().to_be_bytes().to_vec())
         };
 
+        // Unsigned messages are accepted, but signatures present are checked
+        let validation_mode = match config.gossip_authenticity {
+            GossipAuthenticity::Signed => gossipsub::ValidationMode::Strict,
+            GossipAuthenticity::Unsigned => gossipsub::ValidationMode::Permissive,
+        };
+        let authenticity = config.gossip_authenticity;
+
         // Gossipsub config
         let gossipsub_config = gossipsub::ConfigBuilder::default()
             .heartbeat_interval(Duration::from_secs(1))
-            .validation_mode(gossipsub::ValidationMode::Strict)
+            .validation_mode(validation_mode)
             .message_id_fn(message_id_fn)
+            .max_transmit_size(config.codec.max_message_size)
             .build()
             .map_err(|e| NetworkError::ConfigError(e.to_string()))?;
 
@@ -81,11 +109,14 @@ impl Libp2pNetwork {
             .map_err(|e| NetworkError::TransportError(e.to_string()))?
             .with_behaviour(|key| {
                 // Gossipsub
-                let gossipsub = gossipsub::Behaviour::new(
-                    MessageAuthenticity::Signed(key.clone()),
-                    gossipsub_config,
-                )
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+                let authenticity = match authenticity {
+                    GossipAuthenticity::Signed => MessageAuthenticity::Signed(key.clone()),
+                    GossipAuthenticity::Unsigned => {
+                        MessageAuthenticity::Author(key.public().to_peer_id())
+                    }
+                };
+                let gossipsub = gossipsub::Behaviour::new(authenticity, gossipsub_config)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
 
                 // mDNS
                 let mdns = mdns::tokio::Behaviour::new(
@@ -114,9 +145,11 @@ impl Libp2pNetwork {
 
         let mut network = Self {
             swarm,
-            event_tx,
+            peers,
             topic_tx: topic_tx.clone(),
             topic_block: topic_block.clone(),
+            topic_consensus: topic_consensus.clone(),
+            codec: config.codec,
         };
 
         // Subscribe to topics
@@ -132,6 +165,12 @@ impl Libp2pNetwork {
             .gossipsub
             .subscribe(&topic_block)
             .map_err(|e| NetworkError::SubscriptionError(e.to_string()))?;
+        network
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .subscribe(&topic_consensus)
+            .map_err(|e| NetworkError::SubscriptionError(e.to_string()))?;
 
         // Listen on configured address
         let listen_addr: Multiaddr = format!("/ip4/{}/tcp/{}", 
@@ -170,12 +209,12 @@ impl Libp2pNetwork {
 
     /// Broadcast a message to all peers via gossipsub.
     pub fn broadcast(&mut self, message: NetworkMessage) -> Result<(), NetworkError> {
-        let data =
-            bincode::serialize(&message).map_err(|e| NetworkError::SerializationError(e.to_string()))?;
+        let data = self.codec.encode(&message)?;
 
         let topic = match &message {
             NetworkMessage::Transaction(_) => &self.topic_tx,
             NetworkMessage::Block(_) => &self.topic_block,
+            NetworkMessage::Consensus(_) => &self.topic_consensus,
             _ => return Ok(()), // Don't broadcast ping/pong/handshake via gossip
         };
 
@@ -190,6 +229,7 @@ impl Libp2pNetwork {
 
     /// Run the network event loop.
     pub async fn run(&mut self, mut shutdown: mpsc::Receiver<()>) {
+        let mut probation = tokio::time::interval(PROBATION_CHECK_INTERVAL);
         loop {
             tokio::select! {
                 event = self.swarm.select_next_some() => {
@@ -197,6 +237,11 @@ impl Libp2pNetwork {
                         error!("Error handling swarm event: {}", e);
                     }
                 }
+                _ = probation.tick() => {
+                    if let Err(e) = self.peers.expire_probation().await {
+                        error!("Error expiring peer probation: {}", e);
+                    }
+                }
                 _ = shutdown.recv() => {
                     info!("Network shutdown requested");
                     break;
@@ -212,9 +257,9 @@ impl Libp2pNetwork {
     ) -> Result<(), NetworkError> {
         match event {
             SwarmEvent::Behaviour(UnykornBehaviourEvent::Gossipsub(
-                gossipsub::Event::Message { message, .. },
+                gossipsub::Event::Message { propagation_source, message, .. },
             )) => {
-                self.handle_gossip_message(message).await?;
+                self.handle_gossip_message(propagation_source, message).await?;
             }
             SwarmEvent::Behaviour(UnykornBehaviourEvent::Mdns(mdns::Event::Discovered(peers))) => {
                 for (peer_id, addr) in peers {
@@ -246,25 +291,27 @@ impl Libp2pNetwork {
                     info.protocol_version
                 );
             }
-            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+            // Only the first connection to a peer connects it
+            SwarmEvent::ConnectionEstablished {
+                peer_id,
+                endpoint,
+                num_established,
+                ..
+            } if num_established.get() == 1 => {
                 info!("Connected to peer: {}", peer_id);
-                let peer_bytes = peer_id_to_bytes(&peer_id);
-                let _ = self
-                    .event_tx
-                    .send(NetworkEvent::PeerConnected {
-                        peer_id: peer_bytes,
-                    })
-                    .await;
+                let addr = socket_addr(endpoint.get_remote_address());
+                self.connection_established(peer_id, addr).await?;
             }
-            SwarmEvent::ConnectionClosed { peer_id, .. } => {
+            // Only the last connection to a peer disconnects it
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
                 info!("Disconnected from peer: {}", peer_id);
-                let peer_bytes = peer_id_to_bytes(&peer_id);
-                let _ = self
-                    .event_tx
-                    .send(NetworkEvent::PeerDisconnected {
-                        peer_id: peer_bytes,
-                    })
-                    .await;
+                self.peers
+                    .peer_disconnected(peer::PeerId::new(peer_id_to_bytes(&peer_id)))
+                    .await?;
             }
             SwarmEvent::NewListenAddr { address, .. } => {
                 info!("Listening on {}", address);
@@ -274,30 +321,55 @@ impl Libp2pNetwork {
         Ok(())
     }
 
-    /// Handle an incoming gossip message.
+    /// Record a peer's first connection, closing it if the peer is refused.
+    ///
+    /// Outgoing messages go through gossipsub, so the per-peer channel the
+    /// bookkeeping asks for is never read.
+    async fn connection_established(&mut self, peer_id: PeerId, addr: SocketAddr) -> Result<(), NetworkError> {
+        let info = PeerInfo::new(peer::PeerId::new(peer_id_to_bytes(&peer_id)), addr);
+        match self.peers.peer_connected(info, mpsc::channel(1).0).await {
+            Err(NetworkError::PeerBanned(_) | NetworkError::MaxPeersReached) => {
+                warn!("Refusing peer: {}", peer_id);
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Handle an incoming gossip message relayed by `propagation_source`.
+    ///
+    /// The bytes go through `Network::handle_raw_message`, so undecodable
+    /// and repeated messages are penalized like on any transport. Rate
+    /// limits and penalties apply to the peer that sent us the message,
+    /// not to its author: honest relays of a busy author must not get it
+    /// banned, and an unsigned author can be forged.
     async fn handle_gossip_message(
         &mut self,
+        propagation_source: PeerId,
         message: gossipsub::Message,
     ) -> Result<(), NetworkError> {
-        let network_message: NetworkMessage = bincode::deserialize(&message.data)
-            .map_err(|e| NetworkError::DeserializationError(e.to_string()))?;
-
-        let from = message
-            .source
-            .map(|p| peer_id_to_bytes(&p))
-            .unwrap_or([0u8; 32]);
-
-        let event = NetworkEvent::MessageReceived {
-            from,
-            message: network_message,
-        };
-
-        self.event_tx
-            .send(event)
-            .await
-            .map_err(|_| NetworkError::ChannelClosed)?;
+        let from = peer::PeerId::new(peer_id_to_bytes(&propagation_source));
+        self.peers.handle_raw_message(from, &message.data).await
+    }
+}
 
-        Ok(())
+/// The IP address and TCP port of a multiaddr, or the unspecified address
+/// if it has none.
+fn socket_addr(addr: &Multiaddr) -> SocketAddr {
+    let mut ip = None;
+    let mut port = None;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::Ip4(v4) => ip = Some(v4.into()),
+            Protocol::Ip6(v6) => ip = Some(v6.into()),
+            Protocol::Tcp(p) => port = Some(p),
+            _ => {}
+        }
+    }
+    match (ip, port) {
+        (Some(ip), Some(port)) => SocketAddr::new(ip, port),
+        _ => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
     }
 }
 
@@ -313,6 +385,10 @@ fn peer_id_to_bytes(peer_id: &PeerId) -> [u8; 32] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::DEFAULT_PING_RATE_LIMIT;
+    use crate::message::TransactionMessage;
+    use crate::network::{MALFORMED_MESSAGE_PENALTY, RATE_LIMIT_PENALTY};
+    use crate::RateLimit;
 
     #[tokio::test]
     async fn test_libp2p_network_creation() {
@@ -323,4 +399,84 @@ mod tests {
         let (network, _rx) = result.unwrap();
         assert!(network.peer_count() == 0);
     }
+
+    #[tokio::test]
+    async fn test_network_builds_in_each_authenticity_mode() {
+        for authenticity in [GossipAuthenticity::Signed, GossipAuthenticity::Unsigned] {
+            let config = NetworkConfig::local(0, [1u8; 32]).with_gossip_authenticity(authenticity);
+            let result = Libp2pNetwork::new(&config).await;
+            assert!(result.is_ok(), "{:?} failed to build", authenticity);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flapping_connection_held_on_probation() {
+        let config = NetworkConfig::local(0, [1u8; 32]).with_disconnect_grace(Duration::from_secs(60));
+        let (mut network, mut rx) = Libp2pNetwork::new(&config).await.unwrap();
+        let peer_id = PeerId::random();
+        let addr: SocketAddr = "127.0.0.1:8081".parse().unwrap();
+
+        network.connection_established(peer_id, addr).await.unwrap();
+        assert!(matches!(rx.try_recv(), Ok(NetworkEvent::PeerConnected { .. })));
+
+        // Drop and come back within the grace period: nothing reported
+        let id = peer::PeerId::new(peer_id_to_bytes(&peer_id));
+        network.peers.peer_disconnected(id).await.unwrap();
+        network.connection_established(peer_id, addr).await.unwrap();
+        assert!(network.peers.expire_probation().await.unwrap().is_empty());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_gossip_limited_by_relaying_peer() {
+        let config = NetworkConfig::local(0, [1u8; 32])
+            .with_rate_limits(RateLimit::new(0, 1), DEFAULT_PING_RATE_LIMIT);
+        let (mut network, _rx) = Libp2pNetwork::new(&config).await.unwrap();
+        let (relay, author) = (PeerId::random(), PeerId::random());
+        network.connection_established(relay, "127.0.0.1:8082".parse().unwrap()).await.unwrap();
+        network.connection_established(author, "127.0.0.1:8083".parse().unwrap()).await.unwrap();
+
+        for payload in [vec![1], vec![2]] {
+            let message = NetworkMessage::Transaction(TransactionMessage::new(payload));
+            let gossip = gossip_from(author, network.codec.encode(&message).unwrap());
+            network.handle_gossip_message(relay, gossip).await.unwrap();
+        }
+
+        // The relay went over its limit; the author is untouched
+        let score = |id: &PeerId| network.peers.get_peer(&peer::PeerId::new(peer_id_to_bytes(id))).unwrap().score;
+        assert_eq!(score(&relay), RATE_LIMIT_PENALTY);
+        assert_eq!(score(&author), 0);
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_gossip_penalizes_relay() {
+        let config = NetworkConfig::local(0, [1u8; 32]);
+        let (mut network, _rx) = Libp2pNetwork::new(&config).await.unwrap();
+        let relay = PeerId::random();
+        network.connection_established(relay, "127.0.0.1:8084".parse().unwrap()).await.unwrap();
+
+        let gossip = gossip_from(PeerId::random(), vec![0xff; 8]);
+        assert!(network.handle_gossip_message(relay, gossip).await.is_err());
+        let id = peer::PeerId::new(peer_id_to_bytes(&relay));
+        assert_eq!(network.peers.get_peer(&id).unwrap().score, MALFORMED_MESSAGE_PENALTY);
+    }
+
+    /// A transaction-topic gossip message authored by `source`.
+    fn gossip_from(source: PeerId, data: Vec<u8>) -> gossipsub::Message {
+        gossipsub::Message {
+            source: Some(source),
+            data,
+            sequence_number: None,
+            topic: IdentTopic::new(TOPIC_TX).hash(),
+        }
+    }
+
+    #[test]
+    fn test_socket_addr_from_multiaddr() {
+        let addr: Multiaddr = "/ip4/10.0.0.7/tcp/30303".parse().unwrap();
+        assert_eq!(socket_addr(&addr), "10.0.0.7:30303".parse().unwrap());
+
+        let addr: Multiaddr = "/ip4/10.0.0.7/udp/30303".parse().unwrap();
+        assert_eq!(socket_addr(&addr), "0.0.0.0:0".parse().unwrap());
+    }
 }
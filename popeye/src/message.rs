@@ -13,9 +13,18 @@ pub enum NetworkMessage {
     /// A block to be propagated
     Block(BlockMessage),
 
+    /// A consensus message (proposal, vote or certificate) to be propagated
+    Consensus(ConsensusMessage),
+
     /// Peer handshake
     Handshake(HandshakeMessage),
 
+    /// Request for the blocks in `from_height..=to_height`, sent to one peer
+    GetBlocks { from_height: u64, to_height: u64 },
+
+    /// Blocks answering a `GetBlocks`, in height order
+    Blocks(Vec<BlockMessage>),
+
     /// Ping for liveness
     Ping(u64),
 
@@ -54,12 +63,40 @@ pub struct BlockMessage {
 
     /// Block height
     pub height: u64,
+
+    /// Encoded finality certificate for the block, sent with blocks served
+    /// for catch-up (opaque to POPEYE, like consensus payloads)
+    #[serde(default)]
+    pub certificate: Option<Vec<u8>>,
 }
 
 impl BlockMessage {
     /// Create a new block message.
     pub fn new(payload: Vec<u8>, height: u64) -> Self {
-        Self { payload, height }
+        Self { payload, height, certificate: None }
+    }
+
+    /// Attach the block's encoded finality certificate.
+    pub fn with_certificate(mut self, certificate: Vec<u8>) -> Self {
+        self.certificate = Some(certificate);
+        self
+    }
+}
+
+/// Consensus propagation message.
+///
+/// POPEYE does not interpret consensus messages; the payload is the
+/// consensus crate's wire encoding.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConsensusMessage {
+    /// Encoded consensus message (signed by its validator)
+    pub payload: Vec<u8>,
+}
+
+impl ConsensusMessage {
+    /// Create a new consensus message.
+    pub fn new(payload: Vec<u8>) -> Self {
+        Self { payload }
     }
 }
 
@@ -5,10 +5,43 @@
 
 use crate::config::NetworkConfig;
 use crate::message::{NetworkEvent, NetworkMessage};
-use crate::peer::{PeerId, PeerInfo};
+use crate::peer::{Divergence, PeerId, PeerInfo};
+use crate::rate_limit::TokenBucket;
+use crate::seen_cache::SeenCache;
 use crate::NetworkError;
-use std::collections::{HashMap, HashSet};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// How long a message hash counts as seen.
+const SEEN_MESSAGE_TTL: Duration = Duration::from_secs(600);
+
+/// Maximum number of remembered message hashes.
+const SEEN_MESSAGE_CAPACITY: usize = 10_000;
+
+/// Penalty for a message that does not decode.
+pub const MALFORMED_MESSAGE_PENALTY: u32 = 20;
+
+/// Penalty for sending a message the same peer already sent us.
+pub const DUPLICATE_MESSAGE_PENALTY: u32 = 5;
+
+/// Penalty for each message dropped over a peer's rate limit.
+pub const RATE_LIMIT_PENALTY: u32 = 1;
+
+/// A connected peer's inbound allowances.
+#[derive(Debug)]
+struct PeerLimits {
+    /// Everything but pings and pongs
+    messages: TokenBucket,
+
+    /// Pings and pongs
+    pings: TokenBucket,
+
+    /// Up to when the peer's score has been decayed
+    decayed_at: Instant,
+}
 
 /// The main network service.
 ///
@@ -24,11 +57,26 @@ pub struct Network {
     /// Connected peers
     peers: HashMap<PeerId, PeerInfo>,
 
+    /// Outgoing message channels of connected peers
+    senders: HashMap<PeerId, mpsc::Sender<NetworkMessage>>,
+
+    /// Recently dropped peers, with when they dropped, awaiting reconnection
+    probation: HashMap<PeerId, (PeerInfo, Instant)>,
+
+    /// Diverged peers, with how, since when, and our height at that time
+    diverged: HashMap<PeerId, (Divergence, Instant, u64)>,
+
+    /// Peers banned for misbehavior, with when the ban ends
+    banned: HashMap<PeerId, Instant>,
+
+    /// Inbound allowances of connected peers
+    limits: HashMap<PeerId, PeerLimits>,
+
     /// Sender for outgoing events
     event_tx: mpsc::Sender<NetworkEvent>,
 
     /// Recently seen message hashes (for deduplication)
-    seen_messages: HashSet<[u8; 32]>,
+    seen_messages: SeenCache,
 }
 
 impl Network {
@@ -43,8 +91,13 @@ impl Network {
             config,
             local_id,
             peers: HashMap::new(),
+            senders: HashMap::new(),
+            probation: HashMap::new(),
+            diverged: HashMap::new(),
+            banned: HashMap::new(),
+            limits: HashMap::new(),
             event_tx,
-            seen_messages: HashSet::new(),
+            seen_messages: SeenCache::new(SEEN_MESSAGE_TTL, SEEN_MESSAGE_CAPACITY),
         };
 
         (network, event_rx)
@@ -65,72 +118,337 @@ impl Network {
         self.peers.len() < self.config.max_peers
     }
 
-    /// Add a peer connection.
-    pub fn add_peer(&mut self, info: PeerInfo) -> Result<(), NetworkError> {
+    /// Add a peer connection, with the channel its outgoing messages go to.
+    ///
+    /// Banned peers are refused until their ban ends.
+    pub fn add_peer(
+        &mut self,
+        info: PeerInfo,
+        sender: mpsc::Sender<NetworkMessage>,
+    ) -> Result<(), NetworkError> {
+        let now = Instant::now();
+        self.banned.retain(|_, until| now < *until);
+        if self.banned.contains_key(&info.id) {
+            return Err(NetworkError::PeerBanned(info.id.to_string()));
+        }
         if !self.can_accept_peer() {
             return Err(NetworkError::MaxPeersReached);
         }
 
+        let limits = PeerLimits {
+            messages: TokenBucket::new(self.config.message_rate_limit, now),
+            pings: TokenBucket::new(self.config.ping_rate_limit, now),
+            decayed_at: now,
+        };
+        self.limits.insert(info.id, limits);
+        self.senders.insert(info.id, sender);
         self.peers.insert(info.id, info);
         Ok(())
     }
 
     /// Remove a peer connection.
     pub fn remove_peer(&mut self, peer_id: &PeerId) -> Option<PeerInfo> {
+        self.limits.remove(peer_id);
+        self.senders.remove(peer_id);
         self.peers.remove(peer_id)
     }
 
+    /// Record a new or re-established connection.
+    ///
+    /// A peer reconnecting within the disconnect grace period is restored
+    /// silently, keeping what we knew about it; the app never saw it leave.
+    /// It passes the same checks as a new peer, and its rate limits start
+    /// over. If it is refused, its disconnect is reported now. Either way,
+    /// messages now go to the new connection's `sender`.
+    pub async fn peer_connected(
+        &mut self,
+        info: PeerInfo,
+        sender: mpsc::Sender<NetworkMessage>,
+    ) -> Result<(), NetworkError> {
+        if let Some((previous, _)) = self.probation.remove(&info.id) {
+            let peer_id = previous.id;
+            if let Err(e) = self.add_peer(previous, sender) {
+                self.notify_peer_disconnected(peer_id).await?;
+                return Err(e);
+            }
+            return Ok(());
+        }
+
+        let peer_id = info.id;
+        self.add_peer(info, sender)?;
+        self.notify_peer_connected(peer_id).await
+    }
+
+    /// Record a dropped connection.
+    ///
+    /// The peer is held on probation for the grace period; the disconnect
+    /// is only reported if it has not reconnected by the time
+    /// `expire_probation` runs after the period ends.
+    pub async fn peer_disconnected(&mut self, peer_id: PeerId) -> Result<(), NetworkError> {
+        let Some(info) = self.remove_peer(&peer_id) else {
+            return Ok(());
+        };
+
+        if self.config.disconnect_grace.is_zero() {
+            return self.notify_peer_disconnected(peer_id).await;
+        }
+
+        self.probation.insert(peer_id, (info, Instant::now()));
+        Ok(())
+    }
+
+    /// Report peers whose grace period ended without a reconnect.
+    ///
+    /// Call periodically. Returns the peers that were removed.
+    pub async fn expire_probation(&mut self) -> Result<Vec<PeerId>, NetworkError> {
+        let grace = self.config.disconnect_grace;
+        let expired: Vec<PeerId> = self
+            .probation
+            .iter()
+            .filter(|(_, (_, dropped_at))| dropped_at.elapsed() >= grace)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for peer_id in &expired {
+            self.probation.remove(peer_id);
+            self.notify_peer_disconnected(*peer_id).await?;
+        }
+
+        Ok(expired)
+    }
+
+    /// Disconnect peers that stayed too far from `our_height` for too long.
+    ///
+    /// Call periodically with our current height; does nothing unless the
+    /// config sets a divergence policy. A peer far behind is dropped once
+    /// the grace period passes. A peer far ahead is something to sync from,
+    /// so its grace period restarts whenever our height advanced since the
+    /// last check; it is only dropped if we stop catching up. Disconnects
+    /// are reported immediately, without probation. Returns the peers that
+    /// were dropped and why.
+    pub async fn check_divergence(
+        &mut self,
+        our_height: u64,
+    ) -> Result<Vec<(PeerId, Divergence)>, NetworkError> {
+        let Some(policy) = self.config.height_divergence else {
+            return Ok(Vec::new());
+        };
+        let now = Instant::now();
+
+        let mut dropped = Vec::new();
+        let mut diverged = HashMap::new();
+        for peer in self.peers.values() {
+            let Some(divergence) = Divergence::classify(our_height, peer.height, policy.threshold)
+            else {
+                continue;
+            };
+
+            let (since, height) = match self.diverged.get(&peer.id) {
+                Some(&(previous, since, height)) if previous == divergence => {
+                    // Still catching up to a peer ahead of us
+                    if divergence == Divergence::FarAhead && our_height > height {
+                        (now, our_height)
+                    } else {
+                        (since, height)
+                    }
+                }
+                _ => (now, our_height),
+            };
+
+            if now.duration_since(since) >= policy.grace {
+                dropped.push((peer.id, divergence));
+            } else {
+                diverged.insert(peer.id, (divergence, since, height));
+            }
+        }
+        self.diverged = diverged;
+
+        for (peer_id, _) in &dropped {
+            self.remove_peer(peer_id);
+            self.notify_peer_disconnected(*peer_id).await?;
+        }
+
+        Ok(dropped)
+    }
+
+    /// Add `amount` to a peer's misbehavior score.
+    ///
+    /// The score first loses one point per `score_decay` since it last
+    /// decayed, so occasional slips of a long-lived peer never add up to
+    /// a ban. A peer whose score reaches the configured ban threshold is
+    /// disconnected at once, without probation, and refused by `add_peer`
+    /// until the ban ends. Returns whether the peer was banned.
+    pub async fn penalize(&mut self, peer_id: &PeerId, amount: u32) -> Result<bool, NetworkError> {
+        let peer = self
+            .peers
+            .get_mut(peer_id)
+            .ok_or_else(|| NetworkError::PeerNotFound(peer_id.to_string()))?;
+        let interval = self.config.score_decay;
+        if let Some(limits) = self.limits.get_mut(peer_id).filter(|_| !interval.is_zero()) {
+            let now = Instant::now();
+            let points = now.saturating_duration_since(limits.decayed_at).as_nanos() / interval.as_nanos();
+            if points >= u128::from(peer.score) {
+                peer.score = 0;
+                limits.decayed_at = now;
+            } else {
+                peer.score -= points as u32;
+                limits.decayed_at += interval * points as u32;
+            }
+        }
+        peer.score = peer.score.saturating_add(amount);
+        if peer.score < self.config.ban_threshold {
+            return Ok(false);
+        }
+
+        warn!(peer = %peer_id, score = peer.score, "Banning misbehaving peer");
+        self.remove_peer(peer_id);
+        self.diverged.remove(peer_id);
+        self.banned.insert(*peer_id, Instant::now() + self.config.ban_duration);
+        self.notify_peer_disconnected(*peer_id).await?;
+        Ok(true)
+    }
+
+    /// Check whether a peer is currently banned.
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.banned
+            .get(peer_id)
+            .is_some_and(|until| Instant::now() < *until)
+    }
+
     /// Get a peer by ID.
     pub fn get_peer(&self, peer_id: &PeerId) -> Option<&PeerInfo> {
         self.peers.get(peer_id)
     }
 
+    /// Update a peer's reported block height.
+    pub fn update_peer_height(&mut self, peer_id: &PeerId, height: u64) -> Result<(), NetworkError> {
+        let peer = self
+            .peers
+            .get_mut(peer_id)
+            .ok_or_else(|| NetworkError::PeerNotFound(peer_id.to_string()))?;
+        peer.update_height(height);
+        Ok(())
+    }
+
+    /// Get the peer reporting the highest block height.
+    pub fn best_peer(&self) -> Option<&PeerInfo> {
+        self.peers.values().max_by_key(|p| p.height)
+    }
+
+    /// Count how many connected peers report each block height.
+    ///
+    /// Lets the caller pick a sync target several peers agree on instead
+    /// of trusting a lone tallest peer.
+    pub fn height_distribution(&self) -> BTreeMap<u64, usize> {
+        let mut distribution = BTreeMap::new();
+        for peer in self.peers.values() {
+            *distribution.entry(peer.height).or_insert(0) += 1;
+        }
+        distribution
+    }
+
     /// Get all connected peer IDs.
     pub fn peer_ids(&self) -> Vec<PeerId> {
         self.peers.keys().copied().collect()
     }
 
+    /// Split connected peers into those `is_validator` accepts and the rest.
+    ///
+    /// POPEYE knows nothing about validator sets; the caller decides.
+    pub fn partition_peers(&self, is_validator: impl Fn(&PeerId) -> bool) -> (Vec<PeerId>, Vec<PeerId>) {
+        self.peers.keys().copied().partition(|id| is_validator(id))
+    }
+
     /// Check if a message has been seen before (deduplication).
     pub fn is_duplicate(&mut self, hash: &[u8; 32]) -> bool {
-        if self.seen_messages.contains(hash) {
-            return true;
-        }
-
-        // Add to seen set
-        self.seen_messages.insert(*hash);
-
-        // Limit size of seen set
-        if self.seen_messages.len() > 10000 {
-            // Simple eviction - clear half
-            let to_keep: Vec<_> = self.seen_messages.iter().take(5000).copied().collect();
-            self.seen_messages.clear();
-            for h in to_keep {
-                self.seen_messages.insert(h);
-            }
-        }
-
-        false
+        !self.seen_messages.insert(*hash)
     }
 
     /// Broadcast a message to all connected peers.
     ///
     /// POPEYE only broadcasts - it never validates the message content.
+    /// Delivery is best effort: a peer whose channel is full or closed is
+    /// skipped rather than holding up the others.
     pub async fn broadcast(&self, message: NetworkMessage) -> Result<(), NetworkError> {
-        // In a real implementation, this would send to all peers.
-        // For now, we just log that we would broadcast.
-        let _ = message;
+        for (peer_id, sender) in &self.senders {
+            if let Err(e) = sender.try_send(message.clone()) {
+                debug!(peer = %peer_id, error = %e, "Skipping peer in broadcast");
+            }
+        }
         Ok(())
     }
 
+    /// Send a message to one connected peer.
+    ///
+    /// Fails if the peer is not connected or its channel is full or closed.
+    pub fn send_to(&self, peer_id: &PeerId, message: NetworkMessage) -> Result<(), NetworkError> {
+        let sender = self
+            .senders
+            .get(peer_id)
+            .ok_or_else(|| NetworkError::PeerNotFound(peer_id.to_string()))?;
+        sender.try_send(message).map_err(|_| NetworkError::SendFailed)
+    }
+
+    /// Handle an encoded message from a peer.
+    ///
+    /// The message is decoded with the configured codec and forwarded like
+    /// `handle_message`, unless it was already seen. Bytes that do not
+    /// decode cost the sender `MALFORMED_MESSAGE_PENALTY`; a message the
+    /// same peer already sent costs `DUPLICATE_MESSAGE_PENALTY`, while one
+    /// first heard from another peer is dropped quietly, as gossip relays
+    /// every message several times.
+    pub async fn handle_raw_message(&mut self, from: PeerId, bytes: &[u8]) -> Result<(), NetworkError> {
+        let connected = self.peers.contains_key(&from);
+        let message = match self.config.codec.decode::<NetworkMessage>(bytes) {
+            Ok(message) => message,
+            Err(e) => {
+                if connected {
+                    self.penalize(&from, MALFORMED_MESSAGE_PENALTY).await?;
+                }
+                return Err(e);
+            }
+        };
+
+        let hash: [u8; 32] = Sha256::digest(bytes).into();
+        let from_peer: [u8; 32] = Sha256::new()
+            .chain_update(from.as_bytes())
+            .chain_update(hash)
+            .finalize()
+            .into();
+        let repeated = self.is_duplicate(&from_peer);
+        if self.is_duplicate(&hash) {
+            if repeated && connected {
+                self.penalize(&from, DUPLICATE_MESSAGE_PENALTY).await?;
+            }
+            return Ok(());
+        }
+
+        self.handle_message(from, message).await
+    }
+
     /// Handle an incoming message from a peer.
     ///
     /// This forwards the message to the event channel without validation.
-    /// TEV will validate before MARS processes.
+    /// TEV will validate before MARS processes. A connected peer's messages
+    /// beyond its rate limit are dropped instead, each costing it
+    /// `RATE_LIMIT_PENALTY`, so one peer cannot flood the channel.
     pub async fn handle_message(
         &mut self,
         from: PeerId,
         message: NetworkMessage,
     ) -> Result<(), NetworkError> {
+        if let Some(limits) = self.limits.get_mut(&from) {
+            let bucket = match message {
+                NetworkMessage::Ping(_) | NetworkMessage::Pong(_) => &mut limits.pings,
+                _ => &mut limits.messages,
+            };
+            if !bucket.try_take(Instant::now()) {
+                debug!(peer = %from, "Dropping message over rate limit");
+                self.penalize(&from, RATE_LIMIT_PENALTY).await?;
+                return Ok(());
+            }
+        }
+
         let event = NetworkEvent::MessageReceived {
             from: *from.as_bytes(),
             message,
@@ -190,6 +508,13 @@ pub async fn run_network(network: Network, mut shutdown: mpsc::Receiver<()>) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::DEFAULT_MESSAGE_RATE_LIMIT;
+    use crate::RateLimit;
+
+    /// A peer channel nobody reads from.
+    fn peer_channel() -> mpsc::Sender<NetworkMessage> {
+        mpsc::channel(1).0
+    }
 
     #[tokio::test]
     async fn test_network_creation() {
@@ -219,9 +544,115 @@ mod tests {
             "127.0.0.1:8083".parse().unwrap(),
         );
 
-        assert!(network.add_peer(peer1).is_ok());
-        assert!(network.add_peer(peer2).is_ok());
-        assert!(network.add_peer(peer3).is_err()); // Max reached
+        assert!(network.add_peer(peer1, peer_channel()).is_ok());
+        assert!(network.add_peer(peer2, peer_channel()).is_ok());
+        assert!(network.add_peer(peer3, peer_channel()).is_err()); // Max reached
+    }
+
+    #[tokio::test]
+    async fn test_best_peer() {
+        let config = NetworkConfig::local(8080, [1u8; 32]);
+        let (mut network, _rx) = Network::new(config);
+        assert!(network.best_peer().is_none());
+
+        for (i, height) in [(2u8, 5u64), (3, 12), (4, 7)] {
+            let id = PeerId::new([i; 32]);
+            network
+                .add_peer(PeerInfo::new(id, "127.0.0.1:8081".parse().unwrap()), peer_channel())
+                .unwrap();
+            network.update_peer_height(&id, height).unwrap();
+        }
+
+        let best = network.best_peer().unwrap();
+        assert_eq!(best.id, PeerId::new([3u8; 32]));
+        assert_eq!(best.height, 12);
+
+        assert!(network.update_peer_height(&PeerId::new([9u8; 32]), 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_height_distribution() {
+        let config = NetworkConfig::local(8080, [1u8; 32]);
+        let (mut network, _rx) = Network::new(config);
+        assert!(network.height_distribution().is_empty());
+
+        for (i, height) in [(2u8, 10u64), (3, 10), (4, 9), (5, 10), (6, 500)] {
+            let id = PeerId::new([i; 32]);
+            network
+                .add_peer(PeerInfo::new(id, "127.0.0.1:8081".parse().unwrap()), peer_channel())
+                .unwrap();
+            network.update_peer_height(&id, height).unwrap();
+        }
+
+        let distribution = network.height_distribution();
+        assert_eq!(distribution, BTreeMap::from([(9, 1), (10, 3), (500, 1)]));
+    }
+
+    #[tokio::test]
+    async fn test_flapping_peer_not_reported_within_grace() {
+        let config = NetworkConfig::local(8080, [1u8; 32])
+            .with_disconnect_grace(Duration::from_millis(50));
+        let (mut network, mut rx) = Network::new(config);
+        let peer = PeerId::new([2u8; 32]);
+        let info = PeerInfo::new(peer, "127.0.0.1:8081".parse().unwrap());
+
+        network.peer_connected(info.clone(), peer_channel()).await.unwrap();
+        network.update_peer_height(&peer, 7).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(NetworkEvent::PeerConnected { .. })));
+
+        // Drop and come back within the grace period
+        network.peer_disconnected(peer).await.unwrap();
+        assert_eq!(network.peer_count(), 0);
+        network.peer_connected(info, peer_channel()).await.unwrap();
+        assert!(network.expire_probation().await.unwrap().is_empty());
+
+        assert!(rx.try_recv().is_err()); // Nothing reported
+        assert_eq!(network.get_peer(&peer).unwrap().height, 7);
+
+        // Drop and stay away
+        network.peer_disconnected(peer).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(network.expire_probation().await.unwrap(), vec![peer]);
+        assert!(matches!(rx.try_recv(), Ok(NetworkEvent::PeerDisconnected { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_stuck_peer_disconnected_after_grace() {
+        let config = NetworkConfig::local(8080, [1u8; 32])
+            .with_height_divergence(10, Duration::from_millis(50));
+        let (mut network, mut rx) = Network::new(config);
+
+        let stuck = PeerId::new([2u8; 32]);
+        let ahead = PeerId::new([3u8; 32]);
+        let close = PeerId::new([4u8; 32]);
+        for (id, height) in [(stuck, 5u64), (ahead, 500), (close, 98)] {
+            network
+                .peer_connected(PeerInfo::new(id, "127.0.0.1:8081".parse().unwrap()), peer_channel())
+                .await
+                .unwrap();
+            network.update_peer_height(&id, height).unwrap();
+        }
+        while rx.try_recv().is_ok() {}
+
+        // Flagged, but still within the grace period
+        assert!(network.check_divergence(100).await.unwrap().is_empty());
+        assert_eq!(network.peer_count(), 3);
+
+        // We keep catching up to the peer ahead; the stuck one is dropped
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let dropped = network.check_divergence(101).await.unwrap();
+        assert_eq!(dropped, vec![(stuck, Divergence::FarBehind)]);
+        assert!(network.get_peer(&stuck).is_none());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(NetworkEvent::PeerDisconnected { peer_id }) if peer_id == [2u8; 32]
+        ));
+
+        // Once we stop making progress, the peer ahead goes too
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        let dropped = network.check_divergence(101).await.unwrap();
+        assert_eq!(dropped, vec![(ahead, Divergence::FarAhead)]);
+        assert_eq!(network.peer_ids(), vec![close]);
     }
 
     #[tokio::test]
@@ -257,4 +688,192 @@ mod tests {
             _ => panic!("wrong event type"),
         }
     }
+
+    #[tokio::test]
+    async fn test_broadcast_reaches_every_peer() {
+        let config = NetworkConfig::local(8080, [1u8; 32]);
+        let (mut network, _rx) = Network::new(config);
+
+        let mut receivers = Vec::new();
+        for i in [2u8, 3] {
+            let (tx, rx) = mpsc::channel(4);
+            let info = PeerInfo::new(PeerId::new([i; 32]), "127.0.0.1:8081".parse().unwrap());
+            network.add_peer(info, tx).unwrap();
+            receivers.push(rx);
+        }
+        // A peer whose connection closed does not stop the broadcast
+        let info = PeerInfo::new(PeerId::new([4u8; 32]), "127.0.0.1:8081".parse().unwrap());
+        network.add_peer(info, peer_channel()).unwrap();
+
+        network.broadcast(NetworkMessage::Ping(7)).await.unwrap();
+        for rx in &mut receivers {
+            assert!(matches!(rx.try_recv(), Ok(NetworkMessage::Ping(7))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_messages_get_peer_banned() {
+        let config = NetworkConfig::local(8080, [1u8; 32])
+            .with_ban_policy(100, Duration::from_millis(50));
+        let (mut network, mut rx) = Network::new(config);
+        let peer = PeerId::new([2u8; 32]);
+        let info = PeerInfo::new(peer, "127.0.0.1:8081".parse().unwrap());
+        network.peer_connected(info.clone(), peer_channel()).await.unwrap();
+        while rx.try_recv().is_ok() {}
+
+        let garbage = [0xffu8; 3];
+        let strikes = 100 / MALFORMED_MESSAGE_PENALTY;
+        for _ in 1..strikes {
+            assert!(network.handle_raw_message(peer, &garbage).await.is_err());
+        }
+        assert_eq!(network.get_peer(&peer).unwrap().score, 80);
+
+        assert!(network.handle_raw_message(peer, &garbage).await.is_err());
+        assert!(network.get_peer(&peer).is_none());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(NetworkEvent::PeerDisconnected { peer_id }) if peer_id == [2u8; 32]
+        ));
+
+        // Refused while banned, welcome back afterwards
+        assert!(matches!(
+            network.peer_connected(info.clone(), peer_channel()).await,
+            Err(NetworkError::PeerBanned(_))
+        ));
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        network.peer_connected(info, peer_channel()).await.unwrap();
+        assert_eq!(network.get_peer(&peer).unwrap().score, 0);
+    }
+
+    #[tokio::test]
+    async fn test_misbehavior_score_decays() {
+        let config = NetworkConfig::local(8080, [1u8; 32]).with_score_decay(Duration::from_millis(10));
+        let (mut network, _rx) = Network::new(config);
+        let peer = PeerId::new([2u8; 32]);
+        let info = PeerInfo::new(peer, "127.0.0.1:8081".parse().unwrap());
+        network.add_peer(info, peer_channel()).unwrap();
+
+        network.penalize(&peer, 3).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // Quiet for longer than the score takes to wear off
+        network.penalize(&peer, DUPLICATE_MESSAGE_PENALTY).await.unwrap();
+        assert_eq!(network.get_peer(&peer).unwrap().score, DUPLICATE_MESSAGE_PENALTY);
+    }
+
+    #[tokio::test]
+    async fn test_well_behaved_peer_retained() {
+        // Pings are sent back to back here, so allow them all
+        let config = NetworkConfig::local(8080, [1u8; 32])
+            .with_rate_limits(DEFAULT_MESSAGE_RATE_LIMIT, RateLimit::new(100, 100));
+        let codec = config.codec;
+        let (mut network, mut rx) = Network::new(config);
+        let (honest, noisy) = (PeerId::new([2u8; 32]), PeerId::new([3u8; 32]));
+        for id in [honest, noisy] {
+            let info = PeerInfo::new(id, "127.0.0.1:8081".parse().unwrap());
+            network.add_peer(info, peer_channel()).unwrap();
+        }
+
+        // Relaying what another peer already sent is normal gossip
+        for n in 0..50 {
+            let bytes = codec.encode(&NetworkMessage::Ping(n)).unwrap();
+            network.handle_raw_message(noisy, &bytes).await.unwrap();
+            network.handle_raw_message(honest, &bytes).await.unwrap();
+        }
+        assert_eq!(network.get_peer(&honest).unwrap().score, 0);
+        let mut delivered = 0;
+        while rx.try_recv().is_ok() {
+            delivered += 1;
+        }
+        assert_eq!(delivered, 50);
+
+        // Sending the same message twice is not
+        let bytes = codec.encode(&NetworkMessage::Ping(7)).unwrap();
+        network.handle_raw_message(noisy, &bytes).await.unwrap();
+        assert_eq!(network.get_peer(&noisy).unwrap().score, DUPLICATE_MESSAGE_PENALTY);
+        assert_eq!(network.peer_count(), 2);
+    }
+
+    /// Drain the event channel, counting delivered messages.
+    fn delivered(rx: &mut mpsc::Receiver<NetworkEvent>) -> usize {
+        let mut count = 0;
+        while let Ok(event) = rx.try_recv() {
+            if matches!(event, NetworkEvent::MessageReceived { .. }) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[tokio::test]
+    async fn test_burst_over_rate_limit_dropped() {
+        let config = NetworkConfig::local(8080, [1u8; 32])
+            .with_rate_limits(RateLimit::new(10, 5), RateLimit::new(1, 2));
+        let (mut network, mut rx) = Network::new(config);
+        let peer = PeerId::new([2u8; 32]);
+        let info = PeerInfo::new(peer, "127.0.0.1:8081".parse().unwrap());
+        network.add_peer(info, peer_channel()).unwrap();
+
+        for _ in 0..8 {
+            network.handle_message(peer, NetworkMessage::Blocks(Vec::new())).await.unwrap();
+        }
+        assert_eq!(delivered(&mut rx), 5);
+        assert_eq!(network.get_peer(&peer).unwrap().score, 3 * RATE_LIMIT_PENALTY);
+
+        // Pings have their own, tighter budget
+        for n in 0..4 {
+            network.handle_message(peer, NetworkMessage::Ping(n)).await.unwrap();
+        }
+        assert_eq!(delivered(&mut rx), 2);
+        assert_eq!(network.get_peer(&peer).unwrap().score, 5 * RATE_LIMIT_PENALTY);
+    }
+
+    #[tokio::test]
+    async fn test_steady_rate_passes() {
+        let config = NetworkConfig::local(8080, [1u8; 32])
+            .with_rate_limits(RateLimit::new(50, 2), RateLimit::new(50, 2));
+        let (mut network, mut rx) = Network::new(config);
+        let peer = PeerId::new([2u8; 32]);
+        let info = PeerInfo::new(peer, "127.0.0.1:8081".parse().unwrap());
+        network.add_peer(info, peer_channel()).unwrap();
+
+        // One message every 25ms stays under 50 per second
+        for n in 0..10 {
+            network.handle_message(peer, NetworkMessage::Ping(n)).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        assert_eq!(delivered(&mut rx), 10);
+        assert_eq!(network.get_peer(&peer).unwrap().score, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_within_grace_keeps_limits() {
+        let config = NetworkConfig::local(8080, [1u8; 32])
+            .with_disconnect_grace(Duration::from_secs(60))
+            .with_rate_limits(RateLimit::new(1, 2), RateLimit::new(1, 2))
+            .with_max_peers(1);
+        let (mut network, mut rx) = Network::new(config);
+        let peer = PeerId::new([2u8; 32]);
+        let info = PeerInfo::new(peer, "127.0.0.1:8081".parse().unwrap());
+        network.peer_connected(info.clone(), peer_channel()).await.unwrap();
+
+        network.peer_disconnected(peer).await.unwrap();
+        network.peer_connected(info.clone(), peer_channel()).await.unwrap();
+        for _ in 0..4 {
+            network.handle_message(peer, NetworkMessage::Blocks(Vec::new())).await.unwrap();
+        }
+        assert_eq!(delivered(&mut rx), 2);
+        assert_eq!(network.get_peer(&peer).unwrap().score, 2 * RATE_LIMIT_PENALTY);
+
+        // A full node refuses the reconnect and reports the peer gone
+        network.peer_disconnected(peer).await.unwrap();
+        let other = PeerInfo::new(PeerId::new([3u8; 32]), "127.0.0.1:8082".parse().unwrap());
+        network.peer_connected(other, peer_channel()).await.unwrap();
+        while rx.try_recv().is_ok() {}
+        assert!(matches!(
+            network.peer_connected(info, peer_channel()).await,
+            Err(NetworkError::MaxPeersReached)
+        ));
+        assert!(matches!(rx.try_recv(), Ok(NetworkEvent::PeerDisconnected { .. })));
+    }
 }
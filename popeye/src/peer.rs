@@ -47,6 +47,40 @@ mod rand {
     }
 }
 
+/// How a peer's reported height diverges from ours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    /// Far ahead of us: sync from it, and drop it only if we cannot catch up
+    /// (likely a different chain)
+    FarAhead,
+
+    /// Far behind us: likely stuck
+    FarBehind,
+}
+
+impl Divergence {
+    /// Classify `peer_height` against `our_height`, or `None` if the two
+    /// are within `threshold` blocks of each other.
+    pub fn classify(our_height: u64, peer_height: u64, threshold: u64) -> Option<Self> {
+        if peer_height > our_height.saturating_add(threshold) {
+            Some(Self::FarAhead)
+        } else if our_height > peer_height.saturating_add(threshold) {
+            Some(Self::FarBehind)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FarAhead => write!(f, "far ahead"),
+            Self::FarBehind => write!(f, "far behind"),
+        }
+    }
+}
+
 /// Information about a connected peer.
 #[derive(Clone, Debug)]
 pub struct PeerInfo {
@@ -64,6 +98,9 @@ pub struct PeerInfo {
 
     /// Connection timestamp
     pub connected_at: u64,
+
+    /// Misbehavior score; penalties add to it until the peer is banned
+    pub score: u32,
 }
 
 impl PeerInfo {
@@ -78,6 +115,7 @@ impl PeerInfo {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs(),
+            score: 0,
         }
     }
 
@@ -106,5 +144,14 @@ mod tests {
 
         assert_eq!(info.id, id);
         assert_eq!(info.version, 1);
+        assert_eq!(info.score, 0);
+    }
+
+    #[test]
+    fn test_divergence_classification() {
+        assert_eq!(Divergence::classify(100, 95, 10), None);
+        assert_eq!(Divergence::classify(100, 110, 10), None);
+        assert_eq!(Divergence::classify(100, 111, 10), Some(Divergence::FarAhead));
+        assert_eq!(Divergence::classify(100, 89, 10), Some(Divergence::FarBehind));
     }
 }
@@ -0,0 +1,80 @@
+//! Token-bucket rate limiting of inbound messages.
+//!
+//! Each connected peer gets a bucket per message class. A bucket holds up
+//! to `burst` tokens and refills at `per_second`; every message takes one,
+//! and a message arriving at an empty bucket is dropped. A peer can
+//! therefore send a short burst, but not more than the sustained rate for
+//! long.
+
+use std::time::Instant;
+
+/// Allowance for one class of messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Sustained messages per second
+    pub per_second: u32,
+
+    /// Messages that may arrive at once after a quiet period
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// Create an allowance of `per_second` with bursts of `burst`.
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        Self { per_second, burst }
+    }
+}
+
+/// Tokens available to one peer for one message class.
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket.
+    pub fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.burst),
+            refilled_at: now,
+        }
+    }
+
+    /// Take a token at `now`, returning false if none is left.
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * f64::from(self.limit.per_second))
+            .min(f64::from(self.limit.burst));
+        self.refilled_at = now;
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_burst_then_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(RateLimit::new(10, 3), start);
+
+        assert!((0..3).all(|_| bucket.try_take(start)));
+        assert!(!bucket.try_take(start));
+
+        // One token back every 100ms, never more than the burst
+        assert!(bucket.try_take(start + Duration::from_millis(100)));
+        assert!(!bucket.try_take(start + Duration::from_millis(150)));
+        let later = start + Duration::from_secs(60);
+        assert_eq!((0..10).filter(|_| bucket.try_take(later)).count(), 3);
+    }
+}
@@ -0,0 +1,124 @@
+//! Bounded, expiring cache of recently seen message hashes.
+//!
+//! Used to suppress duplicate gossip. Entries expire after a TTL so a
+//! message can eventually be gossiped again, and the oldest entries are
+//! evicted once the cache is full.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Recently seen 32-byte hashes with expiry.
+#[derive(Debug)]
+pub struct SeenCache {
+    /// How long an entry counts as seen
+    ttl: Duration,
+
+    /// Maximum number of entries kept
+    capacity: usize,
+
+    /// When each hash was last recorded
+    entries: HashMap<[u8; 32], Instant>,
+
+    /// Insertion order, oldest first (may hold stale records of re-inserted hashes)
+    order: VecDeque<([u8; 32], Instant)>,
+}
+
+impl SeenCache {
+    /// Create a cache whose entries expire after `ttl`.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Check whether `hash` was recorded within the TTL.
+    pub fn contains(&self, hash: &[u8; 32]) -> bool {
+        self.entries
+            .get(hash)
+            .is_some_and(|seen| seen.elapsed() < self.ttl)
+    }
+
+    /// Record `hash`, returning true if it was not already seen within the TTL.
+    pub fn insert(&mut self, hash: [u8; 32]) -> bool {
+        self.evict();
+
+        if self.contains(&hash) {
+            return false;
+        }
+
+        let now = Instant::now();
+        self.entries.insert(hash, now);
+        self.order.push_back((hash, now));
+        self.evict();
+        true
+    }
+
+    /// Number of entries currently held.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop expired entries, and the oldest entries beyond capacity.
+    fn evict(&mut self) {
+        while let Some((hash, seen)) = self.order.front().copied() {
+            let expired = seen.elapsed() >= self.ttl;
+            if !expired && self.entries.len() <= self.capacity {
+                break;
+            }
+
+            self.order.pop_front();
+            // Only remove if this record is the current one for the hash
+            if self.entries.get(&hash) == Some(&seen) {
+                self.entries.remove(&hash);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_within_ttl() {
+        let mut cache = SeenCache::new(Duration::from_secs(60), 100);
+
+        assert!(cache.insert([1u8; 32]));
+        assert!(!cache.insert([1u8; 32]));
+        assert!(cache.contains(&[1u8; 32]));
+        assert!(!cache.contains(&[2u8; 32]));
+    }
+
+    #[test]
+    fn test_entries_expire() {
+        let mut cache = SeenCache::new(Duration::from_millis(20), 100);
+
+        assert!(cache.insert([1u8; 32]));
+        std::thread::sleep(Duration::from_millis(30));
+
+        assert!(!cache.contains(&[1u8; 32]));
+        assert!(cache.insert([1u8; 32]));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_capacity_evicts_oldest() {
+        let mut cache = SeenCache::new(Duration::from_secs(60), 2);
+
+        cache.insert([1u8; 32]);
+        cache.insert([2u8; 32]);
+        cache.insert([3u8; 32]);
+
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains(&[1u8; 32]));
+        assert!(cache.contains(&[3u8; 32]));
+    }
+}
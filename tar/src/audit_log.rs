@@ -0,0 +1,145 @@
+//! Audit log of finalized decisions.
+//!
+//! An append-only record of every finalization, one JSON line per height,
+//! kept for post-hoc analysis. It is separate from the certificate store:
+//! certificates may be pruned, the audit log never is.
+//!
+//! Each line is appended in a single synced write. A crash can still leave
+//! a torn final line; it has no trailing newline, so it is cut off the
+//! next time the log is opened and never read back as an entry.
+
+use crate::retry::{append_durable, FileWriter, FsWriter, RetryPolicy};
+use crate::StorageError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// One finalized decision.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Finalized height
+    pub height: u64,
+
+    /// Hash of the finalized block
+    pub block_hash: [u8; 32],
+
+    /// Round the block was committed in
+    pub round: u64,
+
+    /// Public keys of the validators whose commits finalized it
+    pub committers: Vec<[u8; 32]>,
+
+    /// Combined voting weight of the committers
+    pub weight: u64,
+
+    /// When the node recorded the decision (Unix epoch seconds)
+    pub timestamp: u64,
+}
+
+/// Append-only audit log writer and reader.
+pub struct AuditLog {
+    path: PathBuf,
+    writer: Arc<dyn FileWriter>,
+    retry: RetryPolicy,
+}
+
+impl AuditLog {
+    /// Open the audit log in `base_path`, dropping any torn final line.
+    pub fn new(base_path: PathBuf) -> Result<Self, StorageError> {
+        fs::create_dir_all(&base_path)?;
+        let path = base_path.join("audit.log");
+
+        if path.exists() {
+            let bytes = fs::read(&path)?;
+            let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            if complete < bytes.len() {
+                let file = fs::OpenOptions::new().write(true).open(&path)?;
+                file.set_len(complete as u64)?;
+                file.sync_all()?;
+            }
+        }
+
+        Ok(Self {
+            path,
+            writer: Arc::new(FsWriter),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Set the retry policy for transient write failures.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Append one entry.
+    pub fn append(&self, entry: &AuditEntry) -> Result<(), StorageError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        append_durable(&self.writer, &self.retry, &self.path, &line)
+    }
+
+    /// Every entry, in the order they were appended.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, StorageError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let text = fs::read_to_string(&self.path)?;
+        text.split_terminator('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| StorageError::Corruption {
+                    reason: format!("audit log line {}: {}", index + 1, e),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(height: u64) -> AuditEntry {
+        AuditEntry {
+            height,
+            block_hash: [height as u8; 32],
+            round: height % 2,
+            committers: vec![[1u8; 32], [2u8; 32]],
+            weight: 2,
+            timestamp: 1_700_000_000 + height,
+        }
+    }
+
+    #[test]
+    fn test_entries_read_back_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().to_path_buf()).unwrap();
+        assert!(log.entries().unwrap().is_empty());
+
+        for height in 1..=3 {
+            log.append(&entry(height)).unwrap();
+        }
+
+        // Survives reopening
+        let log = AuditLog::new(temp_dir.path().to_path_buf()).unwrap();
+        log.append(&entry(4)).unwrap();
+        assert_eq!(log.entries().unwrap(), (1..=4).map(entry).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_torn_line_dropped_on_open() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = AuditLog::new(temp_dir.path().to_path_buf()).unwrap();
+        log.append(&entry(1)).unwrap();
+
+        // Simulate a crash partway through the next append
+        FsWriter.append(&temp_dir.path().join("audit.log"), b"{\"height\":2,").unwrap();
+
+        let log = AuditLog::new(temp_dir.path().to_path_buf()).unwrap();
+        log.append(&entry(2)).unwrap();
+        assert_eq!(log.entries().unwrap(), vec![entry(1), entry(2)]);
+    }
+}
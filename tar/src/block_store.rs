@@ -1,22 +1,69 @@
 //! Block storage operations.
 //!
-//! Handles persistent storage of blocks with crash-safe writes.
+//! Handles persistent storage of blocks with crash-safe writes. Blocks
+//! are checksummed (see `schema`), so a damaged file fails to load.
 
+use crate::retry::{write_atomic, FileWriter, FsWriter, RetryPolicy};
+use crate::schema::Schema;
 use crate::StorageError;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Lightweight description of a stored block.
+///
+/// Saved next to the block so its metadata can be read without loading
+/// and decoding the whole block. TAR does not derive it: the caller
+/// builds it from the block it stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSummary {
+    /// Block height
+    pub height: u64,
+
+    /// Block hash
+    pub hash: [u8; 32],
+
+    /// Block timestamp
+    pub timestamp: u64,
+
+    /// Number of transactions in the block
+    pub tx_count: u64,
+}
 
 /// Block storage manager.
 pub struct BlockStore {
     base_path: PathBuf,
+    writer: Arc<dyn FileWriter>,
+    retry: RetryPolicy,
+    schema: Schema,
 }
 
 impl BlockStore {
     /// Create a new block store at the given path.
     pub fn new(base_path: PathBuf) -> Result<Self, StorageError> {
         fs::create_dir_all(&base_path)?;
-        Ok(Self { base_path })
+        Ok(Self {
+            base_path,
+            writer: Arc::new(FsWriter),
+            retry: RetryPolicy::default(),
+            schema: Schema::new("block", 1),
+        })
+    }
+
+    /// Set the retry policy for transient write failures.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Set the expected block layout (version 1 by default).
+    pub fn set_schema(&mut self, schema: Schema) {
+        self.schema = schema;
+    }
+
+    /// Replace the low-level file writer.
+    pub fn set_writer(&mut self, writer: Arc<dyn FileWriter>) {
+        self.writer = writer;
     }
 
     /// Get the path for a block at a given height.
@@ -29,6 +76,16 @@ impl BlockStore {
         self.base_path.join(format!("{:06}.block.tmp", height))
     }
 
+    /// Get the path for the summary of the block at a given height.
+    fn summary_path(&self, height: u64) -> PathBuf {
+        self.base_path.join(format!("{:06}.summary", height))
+    }
+
+    /// Get the path for the signed payload of the block at a given height.
+    fn payload_path(&self, height: u64) -> PathBuf {
+        self.base_path.join(format!("{:06}.payload", height))
+    }
+
     /// Save a block with crash-safe atomic write.
     ///
     /// Uses write-to-temp + rename pattern to ensure atomicity.
@@ -37,15 +94,9 @@ impl BlockStore {
         let final_path = self.block_path(height);
 
         // Serialize
-        let bytes = bincode::serialize(block).map_err(|e| StorageError::Bincode { reason: e.to_string() })?;
+        let bytes = self.schema.encode(block)?;
 
-        // Write to temp file
-        fs::write(&temp_path, &bytes)?;
-
-        // Atomic rename
-        fs::rename(&temp_path, &final_path)?;
-
-        Ok(())
+        write_atomic(&self.writer, &self.retry, &temp_path, &final_path, &bytes)
     }
 
     /// Load a block at a given height.
@@ -59,7 +110,48 @@ impl BlockStore {
         }
 
         let bytes = fs::read(&path)?;
-        bincode::deserialize(&bytes).map_err(|e| StorageError::Bincode { reason: e.to_string() })
+        self.schema.decode(&bytes)
+    }
+
+    /// Save the summary of the block at a given height.
+    pub fn save_summary(&self, height: u64, summary: &BlockSummary) -> Result<(), StorageError> {
+        let temp_path = self.base_path.join(format!("{:06}.summary.tmp", height));
+        let bytes = serde_json::to_vec(summary)?;
+        write_atomic(&self.writer, &self.retry, &temp_path, &self.summary_path(height), &bytes)
+    }
+
+    /// Load the summary of the block at a given height.
+    pub fn load_summary(&self, height: u64) -> Result<BlockSummary, StorageError> {
+        let path = self.summary_path(height);
+
+        if !path.exists() {
+            return Err(StorageError::NotFound {
+                key: format!("block_summary:{}", height),
+            });
+        }
+
+        let bytes = fs::read(&path)?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Save the signed payload the block at a given height was received
+    /// or produced as, so it can be served to peers as-is.
+    pub fn save_payload(&self, height: u64, payload: &[u8]) -> Result<(), StorageError> {
+        let temp_path = self.base_path.join(format!("{:06}.payload.tmp", height));
+        write_atomic(&self.writer, &self.retry, &temp_path, &self.payload_path(height), payload)
+    }
+
+    /// Load the signed payload of the block at a given height.
+    pub fn load_payload(&self, height: u64) -> Result<Vec<u8>, StorageError> {
+        let path = self.payload_path(height);
+
+        if !path.exists() {
+            return Err(StorageError::NotFound {
+                key: format!("block_payload:{}", height),
+            });
+        }
+
+        Ok(fs::read(&path)?)
     }
 
     /// Check if a block exists at a given height.
@@ -67,6 +159,40 @@ impl BlockStore {
         self.block_path(height).exists()
     }
 
+    /// Delete blocks, with their summaries and payloads, strictly below
+    /// `below_height`.
+    ///
+    /// Returns the number of blocks removed.
+    pub fn prune(&self, below_height: u64) -> Result<usize, StorageError> {
+        let mut removed = 0;
+
+        for entry in fs::read_dir(&self.base_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+
+            if let Some(height) = name_str
+                .strip_suffix(".block")
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                if height < below_height {
+                    fs::remove_file(entry.path())?;
+                    removed += 1;
+                }
+            } else if let Some(height) = name_str
+                .strip_suffix(".summary")
+                .or_else(|| name_str.strip_suffix(".payload"))
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                if height < below_height {
+                    fs::remove_file(entry.path())?;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+
     /// Get the highest stored block height.
     pub fn latest_height(&self) -> Result<Option<u64>, StorageError> {
         let mut highest: Option<u64> = None;
@@ -117,6 +243,21 @@ mod tests {
         assert_eq!(block, loaded);
     }
 
+    #[test]
+    fn test_flipped_byte_is_corruption() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = BlockStore::new(temp_dir.path().to_path_buf()).unwrap();
+        store.save(1, &TestBlock { height: 1, data: "test".to_string() }).unwrap();
+
+        let path = temp_dir.path().join("000001.block");
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0x20;
+        fs::write(&path, bytes).unwrap();
+
+        let result: Result<TestBlock, _> = store.load(1);
+        assert!(matches!(result, Err(StorageError::Corruption { .. })));
+    }
+
     #[test]
     fn test_block_not_found() {
         let temp_dir = TempDir::new().unwrap();
@@ -126,6 +267,25 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_save_retries_transient_failure() {
+        use crate::retry::tests::FailingWriter;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = BlockStore::new(temp_dir.path().to_path_buf()).unwrap();
+        store.set_writer(Arc::new(FailingWriter::new(std::io::ErrorKind::Interrupted, 1)));
+        store.set_retry_policy(RetryPolicy::bounded(3, std::time::Duration::from_millis(1)));
+
+        let block = TestBlock {
+            height: 2,
+            data: "retried".to_string(),
+        };
+        store.save(2, &block).unwrap();
+
+        let loaded: TestBlock = store.load(2).unwrap();
+        assert_eq!(block, loaded);
+    }
+
     #[test]
     fn test_latest_height() {
         let temp_dir = TempDir::new().unwrap();
@@ -141,4 +301,23 @@ mod tests {
 
         assert_eq!(store.latest_height().unwrap(), Some(5));
     }
+
+    #[test]
+    fn test_summary_and_payload_pruned_with_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = BlockStore::new(temp_dir.path().to_path_buf()).unwrap();
+        for height in 1..=3 {
+            let summary = BlockSummary { height, hash: [height as u8; 32], timestamp: 100 + height, tx_count: 0 };
+            store.save(height, &height).unwrap();
+            store.save_summary(height, &summary).unwrap();
+            store.save_payload(height, &[height as u8; 4]).unwrap();
+        }
+
+        assert_eq!(store.load_summary(3).unwrap().timestamp, 103);
+        assert_eq!(store.load_payload(3).unwrap(), vec![3u8; 4]);
+        assert_eq!(store.prune(3).unwrap(), 2);
+        assert!(matches!(store.load_summary(2), Err(StorageError::NotFound { .. })));
+        assert!(matches!(store.load_payload(2), Err(StorageError::NotFound { .. })));
+        assert_eq!(store.latest_height().unwrap(), Some(3));
+    }
 }
@@ -0,0 +1,137 @@
+//! State change set storage.
+//!
+//! Persists the account changes each block produced, keyed by height, so
+//! light clients and fast sync can rebuild state without re-executing
+//! transactions.
+
+use crate::retry::{write_atomic, FileWriter, FsWriter, RetryPolicy};
+use crate::StorageError;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Change set storage manager.
+pub struct ChangeSetStore {
+    base_path: PathBuf,
+    writer: Arc<dyn FileWriter>,
+    retry: RetryPolicy,
+}
+
+impl ChangeSetStore {
+    /// Create a new change set store at the given path.
+    pub fn new(base_path: PathBuf) -> Result<Self, StorageError> {
+        fs::create_dir_all(&base_path)?;
+        Ok(Self {
+            base_path,
+            writer: Arc::new(FsWriter),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Set the retry policy for transient write failures.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Get the path for the change set at a given height.
+    fn changes_path(&self, height: u64) -> PathBuf {
+        self.base_path.join(format!("{:06}.changes", height))
+    }
+
+    /// Get the path for a temporary write file.
+    fn temp_path(&self, height: u64) -> PathBuf {
+        self.base_path.join(format!("{:06}.changes.tmp", height))
+    }
+
+    /// Save the change set for a height with crash-safe atomic write.
+    pub fn save<T: Serialize>(&self, height: u64, changes: &T) -> Result<(), StorageError> {
+        let bytes = bincode::serialize(changes).map_err(|e| StorageError::Bincode { reason: e.to_string() })?;
+
+        write_atomic(
+            &self.writer,
+            &self.retry,
+            &self.temp_path(height),
+            &self.changes_path(height),
+            &bytes,
+        )
+    }
+
+    /// Load the change set for a height.
+    pub fn load<T: DeserializeOwned>(&self, height: u64) -> Result<T, StorageError> {
+        let path = self.changes_path(height);
+
+        if !path.exists() {
+            return Err(StorageError::NotFound {
+                key: format!("changes:{}", height),
+            });
+        }
+
+        let bytes = fs::read(&path)?;
+        bincode::deserialize(&bytes).map_err(|e| StorageError::Bincode { reason: e.to_string() })
+    }
+
+    /// Check if a change set exists for a height.
+    pub fn exists(&self, height: u64) -> bool {
+        self.changes_path(height).exists()
+    }
+
+    /// Delete change sets strictly below `below_height`.
+    ///
+    /// Returns the number of change sets removed.
+    pub fn prune(&self, below_height: u64) -> Result<usize, StorageError> {
+        let mut removed = 0;
+
+        for entry in fs::read_dir(&self.base_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+
+            if let Some(height) = name_str
+                .strip_suffix(".changes")
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                if height < below_height {
+                    fs::remove_file(entry.path())?;
+                    removed += 1;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ChangeSetStore::new(temp_dir.path().to_path_buf()).unwrap();
+
+        assert!(!store.exists(3));
+        assert!(store.load::<Vec<(u8, u64)>>(3).is_err());
+
+        store.save(3, &vec![(1u8, 100u64), (2, 50)]).unwrap();
+        assert!(store.exists(3));
+
+        let loaded: Vec<(u8, u64)> = store.load(3).unwrap();
+        assert_eq!(loaded, vec![(1, 100), (2, 50)]);
+    }
+
+    #[test]
+    fn test_prune_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ChangeSetStore::new(temp_dir.path().to_path_buf()).unwrap();
+        for height in 1..=5 {
+            store.save(height, &vec![(height as u8, height)]).unwrap();
+        }
+
+        assert_eq!(store.prune(4).unwrap(), 3);
+        let remaining: Vec<u64> = (1..=5).filter(|&h| store.exists(h)).collect();
+        assert_eq!(remaining, vec![4, 5]);
+    }
+}
@@ -12,17 +12,46 @@ use serde::{de::DeserializeOwned, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+/// Where the round state returned by `recover_round_state` came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoundStateSource {
+    /// Loaded from the saved file.
+    Saved,
+
+    /// Nothing was saved; started fresh after the latest finalized height.
+    Fresh,
+
+    /// The saved file was unreadable and has been moved aside to
+    /// `round_state.json.corrupt`; started fresh after the latest
+    /// finalized height, which the caller must not vote at.
+    Corrupted { reason: String },
+}
+
 /// Persists consensus state for crash recovery.
 pub struct ConsensusStore {
     /// Directory for consensus data.
     base_path: PathBuf,
+
+    /// Number of most recent finality certificates to keep (None = all).
+    finality_retention: Option<u64>,
 }
 
 impl ConsensusStore {
-    /// Create a new consensus store.
+    /// Create a new consensus store that keeps every finality certificate.
     pub fn new(base_path: PathBuf) -> Result<Self, StorageError> {
         fs::create_dir_all(&base_path)?;
-        Ok(Self { base_path })
+        Ok(Self {
+            base_path,
+            finality_retention: None,
+        })
+    }
+
+    /// Keep only the certificates for the last `retain` heights.
+    ///
+    /// Older certificates are pruned whenever a new one is saved. `None`
+    /// keeps them all, as archive nodes do.
+    pub fn set_finality_retention(&mut self, retain: Option<u64>) {
+        self.finality_retention = retain;
     }
 
     /// Atomically write data to a file.
@@ -50,6 +79,9 @@ impl ConsensusStore {
     }
 
     /// Load round state.
+    ///
+    /// A file that does not parse (e.g. torn by a crash on a filesystem
+    /// without atomic rename) is reported as `CorruptRoundState`.
     pub fn load_round_state<T: DeserializeOwned>(&self) -> Result<Option<T>, StorageError> {
         let path = self.base_path.join("round_state.json");
 
@@ -58,10 +90,41 @@ impl ConsensusStore {
         }
 
         let data = fs::read(&path)?;
-        let state = serde_json::from_slice(&data)?;
+        let state = serde_json::from_slice(&data).map_err(|e| StorageError::CorruptRoundState {
+            reason: e.to_string(),
+        })?;
         Ok(Some(state))
     }
 
+    /// Load round state, or rebuild it when it is missing or corrupted.
+    ///
+    /// Without a saved state, the node resumes at the height after the
+    /// latest finality certificate, using `fresh(height)` to build the
+    /// state. A corrupted file loses the votes we signed and our lock, so
+    /// the returned source tells the caller not to vote at that height
+    /// again, or it could sign a conflicting vote. Rather than refuse to
+    /// start, the state is rebuilt the same way, and the corrupted file is
+    /// kept as `round_state.json.corrupt` for inspection.
+    pub fn recover_round_state<T, F>(&self, fresh: F) -> Result<(T, RoundStateSource), StorageError>
+    where
+        T: DeserializeOwned,
+        F: FnOnce(u64) -> T,
+    {
+        let source = match self.load_round_state() {
+            Ok(Some(state)) => return Ok((state, RoundStateSource::Saved)),
+            Ok(None) => RoundStateSource::Fresh,
+            Err(StorageError::CorruptRoundState { reason }) => {
+                let path = self.base_path.join("round_state.json");
+                fs::rename(&path, path.with_extension("json.corrupt"))?;
+                RoundStateSource::Corrupted { reason }
+            }
+            Err(e) => return Err(e),
+        };
+
+        let height = self.latest_finalized_height()?.unwrap_or(0) + 1;
+        Ok((fresh(height), source))
+    }
+
     /// Save a finality certificate.
     pub fn save_finality_certificate<T: Serialize>(
         &self,
@@ -72,7 +135,13 @@ impl ConsensusStore {
             .base_path
             .join(format!("finality_{:08}.json", height));
         let data = serde_json::to_vec_pretty(cert)?;
-        self.atomic_write(&path, &data)
+        self.atomic_write(&path, &data)?;
+
+        if let Some(retain) = self.finality_retention {
+            self.prune_finality_below((height + 1).saturating_sub(retain.max(1)))?;
+        }
+
+        Ok(())
     }
 
     /// Load a finality certificate.
@@ -93,29 +162,79 @@ impl ConsensusStore {
         Ok(Some(cert))
     }
 
+    /// Load a finality certificate and check it against the validator set
+    /// stored for its height.
+    ///
+    /// `verify` recomputes what the certificate claims (e.g. its total
+    /// weight) from the validator set; any complaint is reported as
+    /// `StorageError::Corruption`, since a stored certificate was verified
+    /// before it was written.
+    pub fn load_finality_certificate_verified<C, V, F>(
+        &self,
+        height: u64,
+        verify: F,
+    ) -> Result<Option<C>, StorageError>
+    where
+        C: DeserializeOwned,
+        V: DeserializeOwned,
+        F: FnOnce(&C, &V) -> Result<(), String>,
+    {
+        let Some(cert) = self.load_finality_certificate::<C>(height)? else {
+            return Ok(None);
+        };
+
+        let validators: V = self
+            .load_validator_set_at(height)?
+            .ok_or_else(|| StorageError::NotFound {
+                key: format!("validators:{}", height),
+            })?;
+
+        verify(&cert, &validators).map_err(|reason| StorageError::Corruption {
+            reason: format!("finality certificate at height {}: {}", height, reason),
+        })?;
+
+        Ok(Some(cert))
+    }
+
     /// Get the highest finalized height.
     pub fn latest_finalized_height(&self) -> Result<Option<u64>, StorageError> {
-        let mut max_height: Option<u64> = None;
+        Ok(self.finality_heights()?.into_iter().max())
+    }
+
+    /// Delete finality certificates for heights below `height`.
+    ///
+    /// Returns the number of certificates removed.
+    pub fn prune_finality_below(&self, height: u64) -> Result<usize, StorageError> {
+        let mut removed = 0;
+
+        for old in self.finality_heights()?.into_iter().filter(|h| *h < height) {
+            fs::remove_file(self.base_path.join(format!("finality_{:08}.json", old)))?;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
+    /// Heights of all stored finality certificates, in no particular order.
+    fn finality_heights(&self) -> Result<Vec<u64>, StorageError> {
+        let mut heights = Vec::new();
 
         for entry in fs::read_dir(&self.base_path)? {
             let entry = entry?;
             let name = entry.file_name();
             let name_str = name.to_string_lossy();
 
-            if name_str.starts_with("finality_") && name_str.ends_with(".json") {
-                // Parse height from filename: finality_00000001.json
-                if let Some(height_str) = name_str
-                    .strip_prefix("finality_")
-                    .and_then(|s| s.strip_suffix(".json"))
-                {
-                    if let Ok(height) = height_str.parse::<u64>() {
-                        max_height = Some(max_height.map_or(height, |m| m.max(height)));
-                    }
-                }
+            // Parse height from filename: finality_00000001.json
+            if let Some(height) = name_str
+                .strip_prefix("finality_")
+                .and_then(|s| s.strip_suffix(".json"))
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                heights.push(height);
             }
         }
 
-        Ok(max_height)
+        Ok(heights)
     }
 
     /// Save the validator set.
@@ -138,6 +257,124 @@ impl ConsensusStore {
         Ok(Some(set))
     }
 
+    /// Save the validator set that takes effect at `height`.
+    pub fn save_validator_set_at<T: Serialize>(&self, height: u64, set: &T) -> Result<(), StorageError> {
+        let path = self.base_path.join(format!("validators_{:08}.json", height));
+        let data = serde_json::to_vec_pretty(set)?;
+        self.atomic_write(&path, &data)
+    }
+
+    /// Load the validator set in effect at `height`.
+    ///
+    /// That is the set saved for the highest height not above `height`,
+    /// falling back to the set saved with `save_validator_set`.
+    pub fn load_validator_set_at<T: DeserializeOwned>(&self, height: u64) -> Result<Option<T>, StorageError> {
+        let best = self.validator_set_heights()?.into_iter().filter(|h| *h <= height).max();
+
+        match best {
+            Some(effective) => {
+                let path = self.base_path.join(format!("validators_{:08}.json", effective));
+                let data = fs::read(&path)?;
+                Ok(Some(serde_json::from_slice(&data)?))
+            }
+            None => self.load_validator_set(),
+        }
+    }
+
+    /// Load the first validator set saved for a height above `height`,
+    /// with the height it takes effect at.
+    ///
+    /// This is a set scheduled but not yet in effect at `height`.
+    pub fn load_validator_set_after<T: DeserializeOwned>(
+        &self,
+        height: u64,
+    ) -> Result<Option<(u64, T)>, StorageError> {
+        let Some(effective) = self.validator_set_heights()?.into_iter().filter(|h| *h > height).min() else {
+            return Ok(None);
+        };
+
+        let path = self.base_path.join(format!("validators_{:08}.json", effective));
+        let data = fs::read(&path)?;
+        Ok(Some((effective, serde_json::from_slice(&data)?)))
+    }
+
+    /// Delete the validator set saved for `height`, if there is one.
+    pub fn remove_validator_set_at(&self, height: u64) -> Result<(), StorageError> {
+        let path = self.base_path.join(format!("validators_{:08}.json", height));
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Heights of all validator sets saved with `save_validator_set_at`, in
+    /// no particular order.
+    fn validator_set_heights(&self) -> Result<Vec<u64>, StorageError> {
+        let mut heights = Vec::new();
+
+        for entry in fs::read_dir(&self.base_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+
+            // Parse height from filename: validators_00000001.json
+            if let Some(height) = name_str
+                .strip_prefix("validators_")
+                .and_then(|s| s.strip_suffix(".json"))
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                heights.push(height);
+            }
+        }
+
+        Ok(heights)
+    }
+
+    /// Save evidence of a validator signing conflicting messages at `height`.
+    ///
+    /// Evidence is never pruned; every report is kept, even several for
+    /// one height.
+    pub fn save_evidence<T: Serialize>(&self, height: u64, evidence: &T) -> Result<(), StorageError> {
+        let index = self.evidence_files(height)?.len();
+        let path = self.base_path.join(format!("evidence_{:08}_{}.json", height, index));
+        let data = serde_json::to_vec_pretty(evidence)?;
+        self.atomic_write(&path, &data)
+    }
+
+    /// Load the evidence saved for `height`, in the order it was saved.
+    pub fn load_evidence<T: DeserializeOwned>(&self, height: u64) -> Result<Vec<T>, StorageError> {
+        let mut files = self.evidence_files(height)?;
+        files.sort_unstable_by_key(|(index, _)| *index);
+
+        files
+            .into_iter()
+            .map(|(_, path)| Ok(serde_json::from_slice(&fs::read(path)?)?))
+            .collect()
+    }
+
+    /// Evidence files for `height` with their indexes, in no particular order.
+    fn evidence_files(&self, height: u64) -> Result<Vec<(usize, PathBuf)>, StorageError> {
+        let prefix = format!("evidence_{:08}_", height);
+        let mut files = Vec::new();
+
+        for entry in fs::read_dir(&self.base_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+
+            // Parse index from filename: evidence_00000001_0.json
+            if let Some(index) = name_str
+                .strip_prefix(&prefix)
+                .and_then(|s| s.strip_suffix(".json"))
+                .and_then(|s| s.parse::<usize>().ok())
+            {
+                files.push((index, entry.path()));
+            }
+        }
+
+        Ok(files)
+    }
+
     /// Check if we have any consensus state.
     pub fn has_state(&self) -> bool {
         self.base_path.join("round_state.json").exists()
@@ -148,7 +385,7 @@ impl ConsensusStore {
         if self.base_path.exists() {
             for entry in fs::read_dir(&self.base_path)? {
                 let entry = entry?;
-                if entry.path().extension().map_or(false, |e| e == "json") {
+                if entry.path().extension().is_some_and(|e| e == "json") {
                     fs::remove_file(entry.path())?;
                 }
             }
@@ -175,6 +412,37 @@ mod tests {
         block_hash: [u8; 32],
     }
 
+    #[test]
+    fn corrupted_round_state_starts_after_finalized_height() {
+        let temp = TempDir::new().unwrap();
+        let store = ConsensusStore::new(temp.path().to_path_buf()).unwrap();
+        let fresh = |height| TestRoundState { height, round: 0 };
+
+        let (state, source) = store.recover_round_state(fresh).unwrap();
+        assert_eq!((state.height, source), (1, RoundStateSource::Fresh));
+
+        let cert = TestCert { height: 4, block_hash: [4u8; 32] };
+        store.save_finality_certificate(4, &cert).unwrap();
+        store.save_round_state(&TestRoundState { height: 5, round: 3 }).unwrap();
+
+        // A torn write leaves half a document behind
+        fs::write(temp.path().join("round_state.json"), b"{\"height\": 5, \"rou").unwrap();
+        assert!(matches!(
+            store.load_round_state::<TestRoundState>(),
+            Err(StorageError::CorruptRoundState { .. })
+        ));
+
+        let (state, source) = store.recover_round_state(fresh).unwrap();
+        assert_eq!(state, TestRoundState { height: 5, round: 0 });
+        assert!(matches!(source, RoundStateSource::Corrupted { .. }));
+        assert!(temp.path().join("round_state.json.corrupt").exists());
+
+        // The fresh state saves and loads normally again
+        store.save_round_state(&state).unwrap();
+        let (loaded, source) = store.recover_round_state(fresh).unwrap();
+        assert_eq!((loaded, source), (state, RoundStateSource::Saved));
+    }
+
     #[test]
     fn round_state_persistence() {
         let temp = TempDir::new().unwrap();
@@ -215,6 +483,106 @@ mod tests {
         assert_eq!(latest, Some(5));
     }
 
+    #[test]
+    fn pruning_keeps_recent_certificates() {
+        let temp = TempDir::new().unwrap();
+        let mut store = ConsensusStore::new(temp.path().to_path_buf()).unwrap();
+        let cert = |height: u64| TestCert {
+            height,
+            block_hash: [height as u8; 32],
+        };
+
+        for height in 1..=5 {
+            store.save_finality_certificate(height, &cert(height)).unwrap();
+        }
+
+        assert_eq!(store.prune_finality_below(3).unwrap(), 2);
+        let pruned: Option<TestCert> = store.load_finality_certificate(2).unwrap();
+        assert_eq!(pruned, None);
+        let kept: Option<TestCert> = store.load_finality_certificate(3).unwrap();
+        assert_eq!(kept, Some(cert(3)));
+        assert_eq!(store.latest_finalized_height().unwrap(), Some(5));
+
+        // Retention prunes as new certificates arrive
+        store.set_finality_retention(Some(2));
+        store.save_finality_certificate(6, &cert(6)).unwrap();
+
+        let pruned: Option<TestCert> = store.load_finality_certificate(4).unwrap();
+        assert_eq!(pruned, None);
+        let kept: Option<TestCert> = store.load_finality_certificate(5).unwrap();
+        assert_eq!(kept, Some(cert(5)));
+        assert_eq!(store.latest_finalized_height().unwrap(), Some(6));
+    }
+
+    #[test]
+    fn evidence_is_kept_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut store = ConsensusStore::new(temp_dir.path().to_path_buf()).unwrap();
+        store.set_finality_retention(Some(1));
+
+        store.save_evidence(3, &"first").unwrap();
+        store.save_evidence(3, &"second").unwrap();
+        store.save_evidence(4, &"other").unwrap();
+        store.save_finality_certificate(9, &TestRoundState { height: 9, round: 0 }).unwrap();
+
+        let evidence: Vec<String> = store.load_evidence(3).unwrap();
+        assert_eq!(evidence, vec!["first", "second"]);
+        assert_eq!(store.load_evidence::<String>(4).unwrap(), vec!["other"]);
+        assert!(store.load_evidence::<String>(5).unwrap().is_empty());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct WeightedCert {
+        height: u64,
+        signers: Vec<usize>,
+        total_weight: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct TestValidators {
+        weights: Vec<u64>,
+    }
+
+    /// Recompute the certificate's weight from per-validator weights.
+    fn check_weight(cert: &WeightedCert, validators: &TestValidators) -> Result<(), String> {
+        let actual: u64 = cert.signers.iter().map(|&i| validators.weights[i]).sum();
+        if actual != cert.total_weight {
+            return Err(format!("claims weight {}, commits carry {}", cert.total_weight, actual));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn verified_load_detects_inflated_weight() {
+        let temp = TempDir::new().unwrap();
+        let store = ConsensusStore::new(temp.path().to_path_buf()).unwrap();
+
+        store.save_validator_set(&TestValidators { weights: vec![1, 1, 1, 1] }).unwrap();
+        store.save_validator_set_at(5, &TestValidators { weights: vec![5, 1, 1, 1] }).unwrap();
+
+        // Honest certificates verify against the set in effect at their height
+        let honest = WeightedCert { height: 2, signers: vec![0, 1, 2], total_weight: 3 };
+        store.save_finality_certificate(2, &honest).unwrap();
+        let weighted = WeightedCert { height: 6, signers: vec![0, 1], total_weight: 6 };
+        store.save_finality_certificate(6, &weighted).unwrap();
+
+        let loaded = store.load_finality_certificate_verified(2, check_weight).unwrap();
+        assert_eq!(loaded, Some(honest));
+        let loaded = store.load_finality_certificate_verified(6, check_weight).unwrap();
+        assert_eq!(loaded, Some(weighted));
+
+        // Tampered: same commits, inflated claim
+        let inflated = WeightedCert { height: 3, signers: vec![0, 1], total_weight: 3 };
+        store.save_finality_certificate(3, &inflated).unwrap();
+
+        let result = store.load_finality_certificate_verified(3, check_weight);
+        assert!(matches!(result, Err(StorageError::Corruption { .. })));
+
+        // Unverified load still returns it
+        let raw: Option<WeightedCert> = store.load_finality_certificate(3).unwrap();
+        assert_eq!(raw, Some(inflated));
+    }
+
     #[test]
     fn recovery_after_simulated_crash() {
         let temp = TempDir::new().unwrap();
@@ -25,6 +25,10 @@ pub enum StorageError {
     #[error("data corruption: {reason}")]
     Corruption { reason: String },
 
+    /// The saved round state could not be parsed
+    #[error("round state corrupted: {reason}")]
+    CorruptRoundState { reason: String },
+
     /// Block height mismatch
     #[error("height mismatch: expected {expected}, got {got}")]
     HeightMismatch { expected: u64, got: u64 },
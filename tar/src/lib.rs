@@ -20,13 +20,24 @@
 //! - Round state (height, round, phase)
 //! - Finality certificates
 //! - Validator sets
+//!
+//! Every finalized decision is also appended to an audit log.
 
 pub mod error;
 pub mod storage;
 pub mod block_store;
 pub mod state_store;
 pub mod consensus_store;
+pub mod mempool_store;
+pub mod change_store;
+pub mod retry;
+pub mod schema;
+pub mod audit_log;
 
+pub use audit_log::{AuditEntry, AuditLog};
+pub use block_store::BlockSummary;
 pub use error::StorageError;
 pub use storage::Storage;
-pub use consensus_store::ConsensusStore;
+pub use consensus_store::{ConsensusStore, RoundStateSource};
+pub use retry::RetryPolicy;
+pub use schema::{Migration, Schema};
@@ -0,0 +1,94 @@
+//! Mempool storage operations.
+//!
+//! Persists pending transactions across restarts with crash-safe writes.
+//! The stored mempool is a hint, not a fact: MARS re-validates every
+//! transaction against recovered state when it is loaded.
+
+use crate::StorageError;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Mempool storage manager.
+pub struct MempoolStore {
+    base_path: PathBuf,
+}
+
+impl MempoolStore {
+    /// Create a new mempool store at the given path.
+    pub fn new(base_path: PathBuf) -> Result<Self, StorageError> {
+        fs::create_dir_all(&base_path)?;
+        Ok(Self { base_path })
+    }
+
+    /// Get the path for the pending transactions file.
+    fn pending_path(&self) -> PathBuf {
+        self.base_path.join("pending.mempool")
+    }
+
+    /// Get the path for a temporary write file.
+    fn temp_path(&self) -> PathBuf {
+        self.base_path.join("pending.mempool.tmp")
+    }
+
+    /// Save pending transactions with crash-safe atomic write.
+    pub fn save<T: Serialize>(&self, txs: &T) -> Result<(), StorageError> {
+        let temp_path = self.temp_path();
+        let final_path = self.pending_path();
+
+        let bytes = bincode::serialize(txs).map_err(|e| StorageError::Bincode { reason: e.to_string() })?;
+
+        // Write to temp file
+        fs::write(&temp_path, &bytes)?;
+
+        // Atomic rename
+        fs::rename(&temp_path, &final_path)?;
+
+        Ok(())
+    }
+
+    /// Load pending transactions, if any were saved.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<Option<T>, StorageError> {
+        let path = self.pending_path();
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&path)?;
+        let txs = bincode::deserialize(&bytes).map_err(|e| StorageError::Bincode { reason: e.to_string() })?;
+        Ok(Some(txs))
+    }
+
+    /// Remove the saved mempool (after it has been restored).
+    pub fn clear(&self) -> Result<(), StorageError> {
+        let path = self.pending_path();
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load_mempool() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = MempoolStore::new(temp_dir.path().to_path_buf()).unwrap();
+
+        let loaded: Option<Vec<u64>> = store.load().unwrap();
+        assert_eq!(loaded, None);
+
+        store.save(&vec![1u64, 2, 3]).unwrap();
+        let loaded: Option<Vec<u64>> = store.load().unwrap();
+        assert_eq!(loaded, Some(vec![1, 2, 3]));
+
+        store.clear().unwrap();
+        let loaded: Option<Vec<u64>> = store.load().unwrap();
+        assert_eq!(loaded, None);
+    }
+}
@@ -0,0 +1,260 @@
+//! Bounded retry for atomic file writes.
+//!
+//! A transient filesystem hiccup should not fail a whole block apply.
+//! Writes go through a `FileWriter` and are retried a bounded number of
+//! times with a small backoff, but only for errors that can succeed on a
+//! second attempt:
+//!
+//! - `Interrupted`
+//! - `WouldBlock`
+//! - `TimedOut`
+//!
+//! Everything else (out of space, permission denied, missing directory, ...)
+//! is permanent and returned immediately.
+//!
+//! Retrying is opt-in: the default policy makes a single attempt. The
+//! backoff sleeps the calling thread, which inside async code stalls an
+//! executor worker, so only enable retries for stores written from a
+//! blocking context.
+
+use crate::StorageError;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Low-level file operations used by the atomic write path.
+///
+/// Swappable so tests can inject failures.
+pub trait FileWriter: Send + Sync {
+    /// Write `bytes` to `path`, replacing any existing file.
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()>;
+
+    /// Rename `from` to `to`, replacing any existing file.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Append `bytes` to `path` in one write and sync it, creating the file if needed.
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()>;
+}
+
+/// `FileWriter` backed by the real filesystem.
+#[derive(Debug, Default)]
+pub struct FsWriter;
+
+impl FileWriter for FsWriter {
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        fs::write(path, bytes)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        io::Write::write_all(&mut file, bytes)?;
+        file.sync_data()
+    }
+}
+
+/// How often and how patiently to retry transient write failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts per operation, including the first
+    pub max_attempts: u32,
+
+    /// Delay before the first retry; doubled for each further retry
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Make up to `max_attempts` attempts, waiting `backoff` before the
+    /// first retry and doubling it for each further one.
+    pub fn bounded(max_attempts: u32, backoff: Duration) -> Self {
+        Self { max_attempts, backoff }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Never retry; see the module docs.
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// Whether an I/O error is worth retrying.
+pub fn is_transient(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Run `op`, retrying transient failures according to `policy`.
+fn with_retry(policy: &RetryPolicy, mut op: impl FnMut() -> io::Result<()>) -> io::Result<()> {
+    let mut delay = policy.backoff;
+    let mut attempt = 1;
+
+    loop {
+        match op() {
+            Err(e) if is_transient(&e) && attempt < policy.max_attempts => {
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Crash-safe write: write to `temp_path`, then rename over `final_path`.
+///
+/// Each step is retried independently for transient errors.
+pub(crate) fn write_atomic(
+    writer: &Arc<dyn FileWriter>,
+    policy: &RetryPolicy,
+    temp_path: &Path,
+    final_path: &Path,
+    bytes: &[u8],
+) -> Result<(), StorageError> {
+    // Write to temp file
+    with_retry(policy, || writer.write(temp_path, bytes))?;
+
+    // Atomic rename
+    with_retry(policy, || writer.rename(temp_path, final_path))?;
+
+    Ok(())
+}
+
+/// Durable append of `bytes` to `path`, retried for transient errors.
+pub(crate) fn append_durable(
+    writer: &Arc<dyn FileWriter>,
+    policy: &RetryPolicy,
+    path: &Path,
+    bytes: &[u8],
+) -> Result<(), StorageError> {
+    with_retry(policy, || writer.append(path, bytes))?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tempfile::TempDir;
+
+    /// Fails the first `failures` writes with `kind`, then hits the filesystem.
+    pub(crate) struct FailingWriter {
+        pub(crate) kind: io::ErrorKind,
+        pub(crate) failures: u32,
+        pub(crate) attempts: AtomicU32,
+    }
+
+    impl FailingWriter {
+        pub(crate) fn new(kind: io::ErrorKind, failures: u32) -> Self {
+            Self {
+                kind,
+                failures,
+                attempts: AtomicU32::new(0),
+            }
+        }
+    }
+
+    impl FileWriter for FailingWriter {
+        fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(io::Error::from(self.kind));
+            }
+            fs::write(path, bytes)
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            fs::rename(from, to)
+        }
+
+        fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(io::Error::from(self.kind));
+            }
+            FsWriter.append(path, bytes)
+        }
+    }
+
+    #[test]
+    fn test_transient_failure_is_retried() {
+        let temp_dir = TempDir::new().unwrap();
+        let failing = Arc::new(FailingWriter::new(io::ErrorKind::Interrupted, 2));
+        let writer: Arc<dyn FileWriter> = failing.clone();
+        let policy = RetryPolicy::bounded(3, Duration::from_millis(1));
+
+        let final_path = temp_dir.path().join("data");
+        write_atomic(&writer, &policy, &temp_dir.path().join("data.tmp"), &final_path, b"ok").unwrap();
+
+        assert_eq!(failing.attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(fs::read(final_path).unwrap(), b"ok");
+    }
+
+    #[test]
+    fn test_permanent_failure_is_not_retried() {
+        let temp_dir = TempDir::new().unwrap();
+        let failing = Arc::new(FailingWriter::new(io::ErrorKind::StorageFull, 1));
+        let writer: Arc<dyn FileWriter> = failing.clone();
+
+        let result = write_atomic(
+            &writer,
+            &RetryPolicy::bounded(3, Duration::from_millis(1)),
+            &temp_dir.path().join("data.tmp"),
+            &temp_dir.path().join("data"),
+            b"ok",
+        );
+
+        assert!(matches!(result, Err(StorageError::Io(_))));
+        assert_eq!(failing.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retries_are_bounded() {
+        let temp_dir = TempDir::new().unwrap();
+        let failing = Arc::new(FailingWriter::new(io::ErrorKind::Interrupted, 10));
+        let writer: Arc<dyn FileWriter> = failing.clone();
+        let policy = RetryPolicy::bounded(3, Duration::from_millis(1));
+
+        let result = write_atomic(
+            &writer,
+            &policy,
+            &temp_dir.path().join("data.tmp"),
+            &temp_dir.path().join("data"),
+            b"ok",
+        );
+
+        assert!(result.is_err());
+        assert_eq!(failing.attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_default_policy_does_not_retry() {
+        let temp_dir = TempDir::new().unwrap();
+        let failing = Arc::new(FailingWriter::new(io::ErrorKind::Interrupted, 1));
+        let writer: Arc<dyn FileWriter> = failing.clone();
+
+        let result = write_atomic(
+            &writer,
+            &RetryPolicy::default(),
+            &temp_dir.path().join("data.tmp"),
+            &temp_dir.path().join("data"),
+            b"ok",
+        );
+
+        assert!(result.is_err());
+        assert_eq!(failing.attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,215 @@
+//! Versioned record layouts.
+//!
+//! Blocks and states are stored as
+//!
+//! ```text
+//! "TARC" || length (u32 LE) || crc32 (u32 LE) || schema version (u32 LE) || bincode(record)
+//! ```
+//!
+//! The length and CRC32 cover everything after the header, so a torn or
+//! bit-rotted file is reported as `StorageError::Corruption` instead of
+//! being handed to bincode. Files written before the checksum was added
+//! start directly with the schema version; they are recognised by the
+//! missing magic followed by a version this build knows (at most the
+//! current one) and still read, without an integrity check, until they are
+//! rewritten. Anything else, such as a record whose magic was damaged, is
+//! corrupt.
+//!
+//! The schema version makes a record written with a different struct
+//! layout detected on load instead of being misread. The version is chosen
+//! by the owner of the type (MARS), and bumped whenever a field is added or
+//! changed. Older records can be upgraded by a migration hook; anything
+//! else is reported as `StorageError::Corruption`.
+
+use crate::StorageError;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Marks a record framed with a length and checksum.
+const MAGIC: [u8; 4] = *b"TARC";
+
+/// Bytes before the checksummed part of a record: magic, length, CRC32.
+const HEADER_LEN: usize = 12;
+
+/// Upgrades a record body written with an older schema version to the
+/// current layout, returning the re-encoded body.
+pub type Migration = fn(version: u32, body: &[u8]) -> Result<Vec<u8>, String>;
+
+/// Expected layout of one kind of record.
+#[derive(Clone, Copy, Debug)]
+pub struct Schema {
+    /// Record kind, for error messages
+    pub name: &'static str,
+
+    /// Current layout version
+    pub version: u32,
+
+    /// Upgrade path for older versions
+    pub migrate: Option<Migration>,
+}
+
+impl Schema {
+    /// Create a schema without a migration hook.
+    pub const fn new(name: &'static str, version: u32) -> Self {
+        Self {
+            name,
+            version,
+            migrate: None,
+        }
+    }
+
+    /// Add a migration hook for older versions.
+    pub fn with_migration(mut self, migrate: Migration) -> Self {
+        self.migrate = Some(migrate);
+        self
+    }
+
+    /// Encode a record with the current version.
+    pub fn encode<T: Serialize>(&self, record: &T) -> Result<Vec<u8>, StorageError> {
+        let mut bytes = vec![0u8; HEADER_LEN];
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bincode::serialize_into(&mut bytes, record)
+            .map_err(|e| StorageError::Bincode { reason: e.to_string() })?;
+
+        let length = u32::try_from(bytes.len() - HEADER_LEN).map_err(|_| StorageError::Bincode {
+            reason: format!("{} record exceeds 4 GiB", self.name),
+        })?;
+        let crc = crc32fast::hash(&bytes[HEADER_LEN..]);
+        bytes[..4].copy_from_slice(&MAGIC);
+        bytes[4..8].copy_from_slice(&length.to_le_bytes());
+        bytes[8..12].copy_from_slice(&crc.to_le_bytes());
+        Ok(bytes)
+    }
+
+    /// Decode a record, migrating it if it has an older version.
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let bytes = self.verify(bytes)?;
+        let (version, body) = match bytes.split_first_chunk::<4>() {
+            Some((version, body)) => (u32::from_le_bytes(*version), body),
+            None => {
+                return Err(StorageError::Corruption {
+                    reason: format!("{} record too short for a schema version", self.name),
+                })
+            }
+        };
+
+        let migrated;
+        let body = if version == self.version {
+            body
+        } else {
+            match self.migrate.filter(|_| version < self.version) {
+                Some(migrate) => {
+                    migrated = migrate(version, body).map_err(|reason| StorageError::Corruption {
+                        reason: format!(
+                            "cannot migrate {} from schema version {}: {}",
+                            self.name, version, reason
+                        ),
+                    })?;
+                    &migrated[..]
+                }
+                None => {
+                    return Err(StorageError::Corruption {
+                        reason: format!(
+                            "{} has unknown schema version {} (this build reads version {})",
+                            self.name, version, self.version
+                        ),
+                    })
+                }
+            }
+        };
+
+        bincode::deserialize(body).map_err(|e| StorageError::Bincode { reason: e.to_string() })
+    }
+
+    /// Check a record's length and checksum, returning the part they cover.
+    ///
+    /// Records without the magic predate checksums and are returned whole,
+    /// as long as they start with a known schema version.
+    fn verify<'a>(&self, bytes: &'a [u8]) -> Result<&'a [u8], StorageError> {
+        let corrupt = |reason: String| StorageError::Corruption {
+            reason: format!("{} record {}", self.name, reason),
+        };
+
+        if !bytes.starts_with(&MAGIC) {
+            return match bytes.first_chunk::<4>().map(|version| u32::from_le_bytes(*version)) {
+                Some(version) if version <= self.version => Ok(bytes),
+                _ => Err(corrupt("has neither a checksum header nor a known schema version".to_string())),
+            };
+        }
+
+        let Some((header, payload)) = bytes.split_first_chunk::<HEADER_LEN>() else {
+            return Err(corrupt("too short for its header".to_string()));
+        };
+        let length = u32::from_le_bytes(header[4..8].try_into().expect("4 bytes")) as usize;
+        let crc = u32::from_le_bytes(header[8..12].try_into().expect("4 bytes"));
+
+        if payload.len() != length {
+            return Err(corrupt(format!("is {} bytes, header says {}", payload.len(), length)));
+        }
+        if crc32fast::hash(payload) != crc {
+            return Err(corrupt("failed its checksum".to_string()));
+        }
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mismatched_version_rejected() {
+        let v1 = Schema::new("block", 1);
+        let v2 = Schema::new("block", 2);
+        let bytes = v1.encode(&(7u64, 9u32)).unwrap();
+        assert_eq!(v1.decode::<(u64, u32)>(&bytes).unwrap(), (7, 9));
+
+        let err = v2.decode::<(u64, u32)>(&bytes).unwrap_err();
+        assert!(
+            matches!(&err, StorageError::Corruption { reason } if reason.contains("unknown schema version 1")),
+            "{}",
+            err
+        );
+        assert!(v1.decode::<(u64, u32)>(&v2.encode(&(7u64, 9u32)).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_migration_upgrades_older_records() {
+        // Version 2 appended a field defaulting to zero
+        fn add_field(version: u32, body: &[u8]) -> Result<Vec<u8>, String> {
+            let old: u64 = bincode::deserialize(body).map_err(|e| e.to_string())?;
+            assert_eq!(version, 1);
+            bincode::serialize(&(old, 0u32)).map_err(|e| e.to_string())
+        }
+
+        let bytes = Schema::new("state", 1).encode(&7u64).unwrap();
+        let v2 = Schema::new("state", 2).with_migration(add_field);
+        assert_eq!(v2.decode::<(u64, u32)>(&bytes).unwrap(), (7, 0));
+    }
+
+    #[test]
+    fn test_checksum_detects_damage() {
+        let schema = Schema::new("block", 1);
+        let bytes = schema.encode(&(7u64, 9u32)).unwrap();
+
+        for index in [0, 3, 4, 8, HEADER_LEN, bytes.len() - 1] {
+            let mut damaged = bytes.clone();
+            damaged[index] ^= 0x01;
+            assert!(matches!(
+                schema.decode::<(u64, u32)>(&damaged),
+                Err(StorageError::Corruption { .. })
+            ));
+        }
+        assert!(matches!(
+            schema.decode::<(u64, u32)>(&bytes[..bytes.len() - 2]),
+            Err(StorageError::Corruption { .. })
+        ));
+    }
+
+    #[test]
+    fn test_records_without_checksum_still_read() {
+        let schema = Schema::new("block", 1);
+        let mut legacy = 1u32.to_le_bytes().to_vec();
+        legacy.extend(bincode::serialize(&(7u64, 9u32)).unwrap());
+        assert_eq!(schema.decode::<(u64, u32)>(&legacy).unwrap(), (7, 9));
+    }
+}
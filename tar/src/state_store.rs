@@ -1,22 +1,49 @@
 //! State storage operations.
 //!
 //! Handles persistent storage of blockchain state with crash-safe writes.
+//! States are checksummed like blocks (see `schema`).
 
+use crate::retry::{write_atomic, FileWriter, FsWriter, RetryPolicy};
+use crate::schema::Schema;
 use crate::StorageError;
 use serde::{de::DeserializeOwned, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 /// State storage manager.
 pub struct StateStore {
     base_path: PathBuf,
+    writer: Arc<dyn FileWriter>,
+    retry: RetryPolicy,
+    schema: Schema,
 }
 
 impl StateStore {
     /// Create a new state store at the given path.
     pub fn new(base_path: PathBuf) -> Result<Self, StorageError> {
         fs::create_dir_all(&base_path)?;
-        Ok(Self { base_path })
+        Ok(Self {
+            base_path,
+            writer: Arc::new(FsWriter),
+            retry: RetryPolicy::default(),
+            schema: Schema::new("state", 1),
+        })
+    }
+
+    /// Set the retry policy for transient write failures.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Set the expected state layout (version 1 by default).
+    pub fn set_schema(&mut self, schema: Schema) {
+        self.schema = schema;
+    }
+
+    /// Replace the low-level file writer.
+    pub fn set_writer(&mut self, writer: Arc<dyn FileWriter>) {
+        self.writer = writer;
     }
 
     /// Get the path for the latest state file.
@@ -39,15 +66,9 @@ impl StateStore {
         let temp_path = self.temp_path();
         let final_path = self.latest_path();
 
-        let bytes = bincode::serialize(state).map_err(|e| StorageError::Bincode { reason: e.to_string() })?;
-
-        // Write to temp file
-        fs::write(&temp_path, &bytes)?;
+        let bytes = self.schema.encode(state)?;
 
-        // Atomic rename
-        fs::rename(&temp_path, &final_path)?;
-
-        Ok(())
+        write_atomic(&self.writer, &self.retry, &temp_path, &final_path, &bytes)
     }
 
     /// Load the latest state.
@@ -61,7 +82,7 @@ impl StateStore {
         }
 
         let bytes = fs::read(&path)?;
-        bincode::deserialize(&bytes).map_err(|e| StorageError::Bincode { reason: e.to_string() })
+        self.schema.decode(&bytes)
     }
 
     /// Check if latest state exists.
@@ -74,12 +95,9 @@ impl StateStore {
         let path = self.snapshot_path(height);
         let temp_path = self.base_path.join(format!("snapshot_{:06}.state.tmp", height));
 
-        let bytes = bincode::serialize(state).map_err(|e| StorageError::Bincode { reason: e.to_string() })?;
-
-        fs::write(&temp_path, &bytes)?;
-        fs::rename(&temp_path, &path)?;
+        let bytes = self.schema.encode(state)?;
 
-        Ok(())
+        write_atomic(&self.writer, &self.retry, &temp_path, &path, &bytes)
     }
 
     /// Load a state snapshot at a specific height.
@@ -93,7 +111,31 @@ impl StateStore {
         }
 
         let bytes = fs::read(&path)?;
-        bincode::deserialize(&bytes).map_err(|e| StorageError::Bincode { reason: e.to_string() })
+        self.schema.decode(&bytes)
+    }
+
+    /// Get the highest snapshot height not above `height`.
+    pub fn snapshot_at_or_below(&self, height: u64) -> Result<Option<u64>, StorageError> {
+        let mut best: Option<u64> = None;
+
+        for entry in fs::read_dir(&self.base_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+
+            // Parse height from filename: snapshot_000001.state
+            if let Some(snapshot) = name
+                .to_string_lossy()
+                .strip_prefix("snapshot_")
+                .and_then(|s| s.strip_suffix(".state"))
+                .and_then(|s| s.parse::<u64>().ok())
+            {
+                if snapshot <= height {
+                    best = Some(best.map_or(snapshot, |b| b.max(snapshot)));
+                }
+            }
+        }
+
+        Ok(best)
     }
 }
 
@@ -148,5 +190,10 @@ mod tests {
         let loaded: TestState = store.load_snapshot(100).unwrap();
 
         assert_eq!(state, loaded);
+
+        store.save_snapshot(50, &state).unwrap();
+        assert_eq!(store.snapshot_at_or_below(99).unwrap(), Some(50));
+        assert_eq!(store.snapshot_at_or_below(100).unwrap(), Some(100));
+        assert_eq!(store.snapshot_at_or_below(49).unwrap(), None);
     }
 }
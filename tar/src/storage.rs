@@ -2,7 +2,11 @@
 //!
 //! Provides a unified interface to block and state storage.
 
-use crate::block_store::BlockStore;
+use crate::block_store::{BlockStore, BlockSummary};
+use crate::change_store::ChangeSetStore;
+use crate::mempool_store::MempoolStore;
+use crate::retry::RetryPolicy;
+use crate::schema::Schema;
 use crate::state_store::StateStore;
 use crate::StorageError;
 use serde::{de::DeserializeOwned, Serialize};
@@ -18,6 +22,12 @@ pub struct Storage {
     /// State storage
     state: StateStore,
 
+    /// Pending transaction storage
+    mempool: MempoolStore,
+
+    /// Per-block state change storage
+    changes: ChangeSetStore,
+
     /// Base path for all storage
     base_path: PathBuf,
 }
@@ -28,19 +38,43 @@ impl Storage {
     /// Creates the directory structure if it doesn't exist:
     /// - `{base}/blocks/` - Block storage
     /// - `{base}/state/` - State storage
+    /// - `{base}/mempool/` - Pending transaction storage
+    /// - `{base}/changes/` - Per-block state change storage
     pub fn new(base_path: PathBuf) -> Result<Self, StorageError> {
         std::fs::create_dir_all(&base_path)?;
 
         let blocks = BlockStore::new(base_path.join("blocks"))?;
         let state = StateStore::new(base_path.join("state"))?;
+        let mempool = MempoolStore::new(base_path.join("mempool"))?;
+        let changes = ChangeSetStore::new(base_path.join("changes"))?;
 
         Ok(Self {
             blocks,
             state,
+            mempool,
+            changes,
             base_path,
         })
     }
 
+    /// Set the retry policy for transient block, state and change set write
+    /// failures. Nothing is retried unless a policy is set.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.blocks.set_retry_policy(policy);
+        self.state.set_retry_policy(policy);
+        self.changes.set_retry_policy(policy);
+    }
+
+    /// Set the expected block layout (see `Schema`).
+    pub fn set_block_schema(&mut self, schema: Schema) {
+        self.blocks.set_schema(schema);
+    }
+
+    /// Set the expected state layout, for the latest state and snapshots.
+    pub fn set_state_schema(&mut self, schema: Schema) {
+        self.state.set_schema(schema);
+    }
+
     /// Save a block at a given height.
     pub fn save_block<T: Serialize>(&self, height: u64, block: &T) -> Result<(), StorageError> {
         self.blocks.save(height, block)
@@ -51,6 +85,28 @@ impl Storage {
         self.blocks.load(height)
     }
 
+    /// Save the summary of the block at a given height.
+    ///
+    /// Callers store it with the block, built from that same block.
+    pub fn save_block_summary(&self, height: u64, summary: &BlockSummary) -> Result<(), StorageError> {
+        self.blocks.save_summary(height, summary)
+    }
+
+    /// Load a block's summary without reading the block itself.
+    pub fn load_block_summary(&self, height: u64) -> Result<BlockSummary, StorageError> {
+        self.blocks.load_summary(height)
+    }
+
+    /// Save the signed payload of the block at a given height.
+    pub fn save_block_payload(&self, height: u64, payload: &[u8]) -> Result<(), StorageError> {
+        self.blocks.save_payload(height, payload)
+    }
+
+    /// Load the signed payload of the block at a given height.
+    pub fn load_block_payload(&self, height: u64) -> Result<Vec<u8>, StorageError> {
+        self.blocks.load_payload(height)
+    }
+
     /// Check if a block exists at a given height.
     pub fn block_exists(&self, height: u64) -> bool {
         self.blocks.exists(height)
@@ -76,6 +132,29 @@ impl Storage {
         self.state.has_latest()
     }
 
+    /// Delete blocks, and the change sets they made, strictly below
+    /// `below_height`.
+    ///
+    /// Returns the number of blocks removed.
+    pub fn prune_blocks_below(&self, below_height: u64) -> Result<usize, StorageError> {
+        let removed = self.blocks.prune(below_height)?;
+        self.changes.prune(below_height)?;
+        Ok(removed)
+    }
+
+    /// Delete all but the newest `keep_last` blocks.
+    ///
+    /// The tip is always kept, even with `keep_last` zero, and nothing is
+    /// removed while fewer than `keep_last` blocks are stored. Returns the
+    /// number of blocks removed.
+    pub fn prune_blocks(&self, keep_last: u64) -> Result<usize, StorageError> {
+        let Some(latest) = self.latest_block_height()? else {
+            return Ok(0);
+        };
+        let cutoff = (latest + 1).saturating_sub(keep_last.max(1));
+        self.prune_blocks_below(cutoff)
+    }
+
     /// Save a state snapshot at a specific height.
     pub fn save_snapshot<T: Serialize>(&self, height: u64, state: &T) -> Result<(), StorageError> {
         self.state.save_snapshot(height, state)
@@ -86,6 +165,36 @@ impl Storage {
         self.state.load_snapshot(height)
     }
 
+    /// Get the highest snapshot height not above `height`.
+    pub fn snapshot_at_or_below(&self, height: u64) -> Result<Option<u64>, StorageError> {
+        self.state.snapshot_at_or_below(height)
+    }
+
+    /// Save the state changes a block produced.
+    pub fn save_change_set<T: Serialize>(&self, height: u64, changes: &T) -> Result<(), StorageError> {
+        self.changes.save(height, changes)
+    }
+
+    /// Load the state changes a block produced.
+    pub fn load_change_set<T: DeserializeOwned>(&self, height: u64) -> Result<T, StorageError> {
+        self.changes.load(height)
+    }
+
+    /// Save pending transactions so they survive a restart.
+    pub fn save_mempool<T: Serialize>(&self, txs: &T) -> Result<(), StorageError> {
+        self.mempool.save(txs)
+    }
+
+    /// Load previously saved pending transactions, if any.
+    pub fn load_mempool<T: DeserializeOwned>(&self) -> Result<Option<T>, StorageError> {
+        self.mempool.load()
+    }
+
+    /// Remove saved pending transactions.
+    pub fn clear_mempool(&self) -> Result<(), StorageError> {
+        self.mempool.clear()
+    }
+
     /// Get the base storage path.
     pub fn base_path(&self) -> &PathBuf {
         &self.base_path
@@ -108,6 +217,22 @@ impl Storage {
 
         Ok(())
     }
+
+    /// Like `commit`, with the state changes the block made.
+    ///
+    /// The change set is written before the block, so a stored block
+    /// always has one; a crash in between leaves only a change set for a
+    /// missing block, which the block's re-import overwrites.
+    pub fn commit_with_changes<B: Serialize, C: Serialize, S: Serialize>(
+        &self,
+        height: u64,
+        block: &B,
+        changes: &C,
+        state: &S,
+    ) -> Result<(), StorageError> {
+        self.save_change_set(height, changes)?;
+        self.commit(height, block, state)
+    }
 }
 
 #[cfg(test)]
@@ -126,6 +251,21 @@ mod tests {
         height: u64,
     }
 
+    #[test]
+    fn test_block_from_other_schema_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = Storage::new(temp_dir.path().to_path_buf()).unwrap();
+        storage.commit(1, &TestBlock { height: 1 }, &TestState { height: 1 }).unwrap();
+
+        storage.set_block_schema(Schema::new("block", 2));
+        assert!(matches!(
+            storage.load_block::<TestBlock>(1),
+            Err(StorageError::Corruption { .. })
+        ));
+        // State layout unchanged
+        assert_eq!(storage.load_state::<TestState>().unwrap().height, 1);
+    }
+
     #[test]
     fn test_storage_lifecycle() {
         let temp_dir = TempDir::new().unwrap();
@@ -153,6 +293,48 @@ mod tests {
         assert_eq!(state, loaded_state);
     }
 
+    #[test]
+    fn test_prune_blocks_keeps_last() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path().to_path_buf()).unwrap();
+        for height in 1..=20 {
+            storage.commit(height, &TestBlock { height }, &TestState { height }).unwrap();
+        }
+
+        // Too few blocks to prune anything
+        assert_eq!(storage.prune_blocks(25).unwrap(), 0);
+
+        assert_eq!(storage.prune_blocks(5).unwrap(), 15);
+        let remaining: Vec<u64> = (1..=20).filter(|&h| storage.block_exists(h)).collect();
+        assert_eq!(remaining, (16..=20).collect::<Vec<_>>());
+        assert_eq!(storage.latest_block_height().unwrap(), Some(20));
+
+        // The tip survives even when keeping nothing
+        assert_eq!(storage.prune_blocks(0).unwrap(), 4);
+        assert!(storage.block_exists(20));
+    }
+
+    #[test]
+    fn test_change_sets_pruned_with_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path().to_path_buf()).unwrap();
+        for height in 1..=6 {
+            storage
+                .commit_with_changes(height, &TestBlock { height }, &vec![height], &TestState { height })
+                .unwrap();
+        }
+        assert_eq!(storage.load_change_set::<Vec<u64>>(6).unwrap(), vec![6]);
+
+        assert_eq!(storage.prune_blocks_below(3).unwrap(), 2);
+        assert!(storage.load_change_set::<Vec<u64>>(2).is_err());
+        assert_eq!(storage.load_change_set::<Vec<u64>>(3).unwrap(), vec![3]);
+
+        assert_eq!(storage.prune_blocks(2).unwrap(), 2);
+        let remaining: Vec<u64> =
+            (1..=6).filter(|&h| storage.load_change_set::<Vec<u64>>(h).is_ok()).collect();
+        assert_eq!(remaining, vec![5, 6]);
+    }
+
     #[test]
     fn test_restart_recovery() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,143 @@
+//! Transaction bundles.
+//!
+//! A bundle carries several individually signed transactions in one
+//! envelope signed by the submitter, so a wallet can send a batch as a
+//! single authenticated message. TEV verifies the bundle as a unit: if the
+//! envelope signature or any contained signature is invalid, the whole
+//! bundle is rejected.
+//!
+//! # Format
+//!
+//! The envelope is a payload in the usual format (see `payload`), whose
+//! data is:
+//!
+//! ```text
+//! count (u32 LE) || { length (u32 LE) || transaction payload }*
+//! ```
+//!
+//! Each transaction payload is itself in the `verify_transaction` format.
+
+use crate::payload::{decode_payload, sign_payload};
+use crate::signature::{verify_signature, Keypair};
+use crate::verified::VerifiedBundle;
+use crate::{verify_transaction, ValidationError};
+
+/// Signed transaction payloads to be sent as one bundle.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionBundle {
+    /// Transaction payloads, in submission order
+    pub transactions: Vec<Vec<u8>>,
+}
+
+impl TransactionBundle {
+    /// Create an empty bundle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a signed transaction payload.
+    pub fn push(&mut self, payload: Vec<u8>) {
+        self.transactions.push(payload);
+    }
+
+    /// Encode the bundle data (without the envelope).
+    pub fn data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(self.transactions.len() as u32).to_le_bytes());
+        for payload in &self.transactions {
+            data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            data.extend_from_slice(payload);
+        }
+        data
+    }
+
+    /// Sign the bundle, producing a payload for `verify_bundle`.
+    pub fn sign(&self, keypair: &Keypair) -> Vec<u8> {
+        sign_payload(keypair, &self.data())
+    }
+}
+
+/// Verify a bundle payload and every transaction in it.
+pub fn verify_bundle(payload: &[u8]) -> Result<VerifiedBundle, ValidationError> {
+    let (data, signer, signature) = decode_payload("bundle", payload)?;
+
+    verify_signature(&signer, data, &signature)?;
+
+    let transactions = decode_entries(data)?
+        .into_iter()
+        .map(verify_transaction)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(VerifiedBundle {
+        transactions,
+        signer,
+        signature,
+    })
+}
+
+/// Split bundle data into its transaction payloads.
+fn decode_entries(data: &[u8]) -> Result<Vec<&[u8]>, ValidationError> {
+    let malformed = || ValidationError::InvalidFormat {
+        reason: "malformed bundle data".to_string(),
+    };
+    let read_u32 = |bytes: &[u8], at: usize| -> Result<usize, ValidationError> {
+        let raw = bytes.get(at..at + 4).ok_or_else(malformed)?;
+        Ok(u32::from_le_bytes(raw.try_into().expect("4 bytes")) as usize)
+    };
+
+    let count = read_u32(data, 0)?;
+    let mut entries = Vec::new();
+    let mut at = 4;
+    for _ in 0..count {
+        let len = read_u32(data, at)?;
+        at += 4;
+        entries.push(data.get(at..at + len).ok_or_else(malformed)?);
+        at += len;
+    }
+    if at != data.len() {
+        return Err(malformed());
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_bundle_verifies_as_unit() {
+        let wallet = Keypair::from_secret(&[5u8; 32]);
+        let mut bundle = TransactionBundle::new();
+        bundle.push(sign_payload(&wallet, b"first"));
+        bundle.push(sign_payload(&wallet, b"second"));
+
+        let verified = verify_bundle(&bundle.sign(&wallet)).unwrap();
+        assert_eq!(verified.signer(), &wallet.public_key());
+        let data: Vec<&[u8]> = verified.transactions().iter().map(|tx| tx.data()).collect();
+        assert_eq!(data, vec![&b"first"[..], &b"second"[..]]);
+    }
+
+    #[test]
+    fn test_any_bad_signature_rejects_bundle() {
+        let wallet = Keypair::from_secret(&[5u8; 32]);
+        let mut bundle = TransactionBundle::new();
+        bundle.push(sign_payload(&wallet, b"first"));
+        let mut forged = sign_payload(&wallet, b"second");
+        let last = forged.len() - 1;
+        forged[last] ^= 1;
+        bundle.push(forged);
+
+        // Envelope is valid, one contained transaction is not
+        assert_eq!(
+            verify_bundle(&bundle.sign(&wallet)).unwrap_err(),
+            ValidationError::InvalidSignature
+        );
+
+        // Contents valid, envelope tampered
+        bundle.transactions.pop();
+        let mut payload = bundle.sign(&wallet);
+        payload[12] ^= 1;
+        assert!(verify_bundle(&payload).is_err());
+    }
+}
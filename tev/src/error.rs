@@ -17,7 +17,14 @@ pub enum ValidationError {
     #[error("invalid public key")]
     InvalidPublicKey,
 
-    /// Replay attack detected
-    #[error("replay attack: nonce {nonce} already used")]
-    ReplayDetected { nonce: u64 },
+    /// One transaction in a batch failed verification
+    #[error("transaction {index} in batch: {error}")]
+    BatchItem {
+        index: usize,
+        error: Box<ValidationError>,
+    },
+
+    /// The payload was already verified by the same `ReplayGuard`
+    #[error("replay detected: payload already verified")]
+    ReplayDetected,
 }
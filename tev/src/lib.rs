@@ -13,56 +13,43 @@
 //!
 //! ## Design Principles
 //!
-//! - **Stateless**: No storage, no persistence
+//! - **Stateless**: No storage, no persistence (an opt-in `ReplayGuard`
+//!   is the only state, and only when the caller supplies one)
 //! - **Pure**: Verification only, no side effects
 //! - **Type-safe**: Verified vs Unverified types
 
+pub mod bundle;
 pub mod error;
+pub mod payload;
+pub mod replay;
 pub mod signature;
 pub mod verified;
 
+pub use bundle::{verify_bundle, TransactionBundle};
 pub use error::ValidationError;
-pub use signature::{sign_message, verify_signature, Keypair};
-pub use verified::{VerifiedBlock, VerifiedTransaction};
+pub use payload::{encode_payload, sign_payload, PAYLOAD_MAGIC, PAYLOAD_VERSION};
+pub use replay::ReplayGuard;
+pub use signature::{
+    sign_message, sign_prehashed, verify_prehashed, verify_signature, Keypair, PREHASH_CONTEXT,
+};
+pub use verified::{VerifiedBlock, VerifiedBundle, VerifiedTransaction};
+
+use payload::decode_payload;
+use signature::parse_torsion_free;
 
 /// Verify a raw transaction payload.
 ///
 /// # Format
 ///
-/// The payload must be at least 96 bytes:
-/// - Last 64 bytes: Ed25519 signature
-/// - Preceding 32 bytes: Public key (signer)
-/// - Remaining bytes: Transaction data
+/// The payload is in the `payload` module's format: a header giving the
+/// length of the transaction data, then the data, the signer's public key
+/// and the Ed25519 signature.
 ///
 /// # Returns
 ///
 /// A `VerifiedTransaction` that can be safely passed to MARS.
 pub fn verify_transaction(payload: &[u8]) -> Result<VerifiedTransaction, ValidationError> {
-    if payload.len() < 96 {
-        return Err(ValidationError::InvalidFormat {
-            reason: format!("payload too short: {} bytes, minimum 96", payload.len()),
-        });
-    }
-
-    let sig_start = payload.len() - 64;
-    let pubkey_start = sig_start - 32;
-
-    let data = &payload[..pubkey_start];
-    let pubkey_bytes = &payload[pubkey_start..sig_start];
-    let signature_bytes = &payload[sig_start..];
-
-    // Convert to fixed arrays
-    let pubkey: [u8; 32] = pubkey_bytes
-        .try_into()
-        .map_err(|_| ValidationError::InvalidFormat {
-            reason: "invalid public key length".to_string(),
-        })?;
-
-    let signature: [u8; 64] = signature_bytes
-        .try_into()
-        .map_err(|_| ValidationError::InvalidFormat {
-            reason: "invalid signature length".to_string(),
-        })?;
+    let (data, pubkey, signature) = decode_payload("transaction", payload)?;
 
     // Verify the signature
     verify_signature(&pubkey, data, &signature)?;
@@ -74,34 +61,62 @@ pub fn verify_transaction(payload: &[u8]) -> Result<VerifiedTransaction, Validat
     })
 }
 
-/// Verify a raw block payload.
+/// Verify many transaction payloads with a single batch verification.
 ///
-/// Similar format to transactions but for block data.
-pub fn verify_block(payload: &[u8]) -> Result<VerifiedBlock, ValidationError> {
-    if payload.len() < 96 {
-        return Err(ValidationError::InvalidFormat {
-            reason: format!("block payload too short: {} bytes", payload.len()),
-        });
+/// Much faster than calling `verify_transaction` for each payload, e.g.
+/// for every transaction of a block during sync. All payloads are parsed
+/// first; if the batch then fails, each signature is checked on its own
+/// to find the culprit. Any failure is reported as `BatchItem` with the
+/// index of the offending payload.
+///
+/// Accepts exactly what `verify_transaction` accepts: keys and signatures
+/// with torsion components are refused before the batch check (see the
+/// `signature` module).
+pub fn verify_transactions_batch(
+    payloads: &[&[u8]],
+) -> Result<Vec<VerifiedTransaction>, ValidationError> {
+    let in_batch = |index: usize| {
+        move |error: ValidationError| ValidationError::BatchItem {
+            index,
+            error: Box::new(error),
+        }
+    };
+
+    let mut parsed = Vec::with_capacity(payloads.len());
+    let mut keys = Vec::with_capacity(payloads.len());
+    let mut signatures = Vec::with_capacity(payloads.len());
+    for (index, payload) in payloads.iter().enumerate() {
+        let (data, pubkey, signature) = decode_payload("transaction", payload).map_err(in_batch(index))?;
+        let (key, sig) = parse_torsion_free(&pubkey, &signature).map_err(in_batch(index))?;
+        keys.push(key);
+        signatures.push(sig);
+        parsed.push((data, pubkey, signature));
     }
 
-    let sig_start = payload.len() - 64;
-    let pubkey_start = sig_start - 32;
-
-    let data = &payload[..pubkey_start];
-    let pubkey_bytes = &payload[pubkey_start..sig_start];
-    let signature_bytes = &payload[sig_start..];
+    if !parsed.is_empty() {
+        let messages: Vec<&[u8]> = parsed.iter().map(|(data, _, _)| *data).collect();
+        if ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_err() {
+            for (index, (data, pubkey, signature)) in parsed.iter().enumerate() {
+                verify_signature(pubkey, data, signature).map_err(in_batch(index))?;
+            }
+        }
+    }
 
-    let producer: [u8; 32] = pubkey_bytes
-        .try_into()
-        .map_err(|_| ValidationError::InvalidFormat {
-            reason: "invalid producer key length".to_string(),
-        })?;
+    Ok(parsed
+        .into_iter()
+        .map(|(data, signer, signature)| VerifiedTransaction {
+            data: data.to_vec(),
+            signer,
+            signature,
+        })
+        .collect())
+}
 
-    let signature: [u8; 64] = signature_bytes
-        .try_into()
-        .map_err(|_| ValidationError::InvalidFormat {
-            reason: "invalid block signature length".to_string(),
-        })?;
+/// Verify a raw block payload.
+///
+/// Same format as transactions, carrying block data signed by the producer.
+pub fn verify_block(payload: &[u8]) -> Result<VerifiedBlock, ValidationError> {
+    let (data, producer, signature) = decode_payload("block", payload)?;
 
     verify_signature(&producer, data, &signature)?;
 
@@ -126,13 +141,7 @@ mod tests {
     fn test_verify_valid_transaction() {
         let keypair = Keypair::generate();
         let data = b"test transaction data";
-
-        let mut payload = Vec::new();
-        payload.extend_from_slice(data);
-        payload.extend_from_slice(&keypair.public_key());
-
-        let signature = keypair.sign(data);
-        payload.extend_from_slice(&signature);
+        let payload = sign_payload(&keypair, data);
 
         let result = verify_transaction(&payload);
         assert!(result.is_ok());
@@ -147,12 +156,80 @@ mod tests {
         let keypair = Keypair::generate();
         let data = b"test transaction data";
 
-        let mut payload = Vec::new();
-        payload.extend_from_slice(data);
-        payload.extend_from_slice(&keypair.public_key());
-        payload.extend_from_slice(&[0u8; 64]); // Invalid signature
+        let payload = encode_payload(data, &keypair.public_key(), &[0u8; 64]); // Invalid signature
 
         let result = verify_transaction(&payload);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_batch_verifies_block_sized_set() {
+        let keypairs: Vec<Keypair> = (0..16u8).map(|i| Keypair::from_secret(&[i + 1; 32])).collect();
+        let mut payloads: Vec<Vec<u8>> = (0..512u32)
+            .map(|i| sign_payload(&keypairs[i as usize % 16], &i.to_le_bytes()))
+            .collect();
+
+        let refs: Vec<&[u8]> = payloads.iter().map(Vec::as_slice).collect();
+        let verified = verify_transactions_batch(&refs).unwrap();
+        assert_eq!(verified.len(), 512);
+        assert_eq!(verified[300].data(), &300u32.to_le_bytes());
+        assert_eq!(verified[300].signer(), &keypairs[300 % 16].public_key());
+
+        // One forged signature is pinned to its index
+        let last = payloads[300].len() - 1;
+        payloads[300][last] ^= 1;
+        let refs: Vec<&[u8]> = payloads.iter().map(Vec::as_slice).collect();
+        assert!(matches!(
+            verify_transactions_batch(&refs),
+            Err(ValidationError::BatchItem { index: 300, .. })
+        ));
+        assert!(verify_transactions_batch(&[]).unwrap().is_empty());
+    }
+
+    /// A payload whose signature `R` has an order-four component: it
+    /// satisfies the cofactored equation but not the cofactorless one.
+    fn torsioned_payload(data: &[u8], nonce: u64) -> Vec<u8> {
+        use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+        use curve25519_dalek::edwards::CompressedEdwardsY;
+        use curve25519_dalek::scalar::Scalar;
+        use sha2::{Digest, Sha512};
+
+        let secret = Scalar::from(12345u64);
+        let public_key = (ED25519_BASEPOINT_POINT * secret).compress().to_bytes();
+        let order_four = CompressedEdwardsY([0u8; 32]).decompress().unwrap();
+        let nonce = Scalar::from(nonce);
+        let r = (ED25519_BASEPOINT_POINT * nonce + order_four).compress().to_bytes();
+
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(&Sha512::new().chain_update(r).chain_update(public_key).chain_update(data).finalize());
+        let s = nonce + Scalar::from_bytes_mod_order_wide(&wide) * secret;
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(&r);
+        signature[32..].copy_from_slice(s.as_bytes());
+        encode_payload(data, &public_key, &signature)
+    }
+
+    #[test]
+    fn test_batch_and_single_agree_on_torsion() {
+        // The raw batch check accepts such a payload whenever its
+        // coefficient, derived from the inputs, cancels the torsion: about
+        // one nonce in four
+        let raw_batch_accepts = |payload: &[u8]| {
+            let (data, public_key, signature) = decode_payload("transaction", payload).unwrap();
+            let key = ed25519_dalek::VerifyingKey::from_bytes(&public_key).unwrap();
+            let sig = ed25519_dalek::Signature::from_bytes(&signature);
+            ed25519_dalek::verify_batch(&[data], &[sig], &[key]).is_ok()
+        };
+        let payload = (1..64)
+            .map(|nonce| torsioned_payload(b"transfer", nonce))
+            .find(|payload| raw_batch_accepts(payload))
+            .unwrap();
+
+        assert_eq!(verify_transaction(&payload).unwrap_err(), ValidationError::InvalidSignature);
+        assert!(matches!(
+            verify_transactions_batch(&[&payload]),
+            Err(ValidationError::BatchItem { index: 0, .. })
+        ));
+    }
 }
@@ -0,0 +1,147 @@
+//! The signed payload format.
+//!
+//! Transactions, blocks and bundles all travel as
+//!
+//! ```text
+//! magic "TEV" (3) || version (1) || data length (u32 LE) || data || public key (32) || signature (64)
+//! ```
+//!
+//! The signature covers `data` only. Every field is located from the
+//! header rather than from the end of the payload, so a malformed payload
+//! is reported by the field that is wrong, and later versions can extend
+//! the layout.
+
+use crate::signature::Keypair;
+use crate::ValidationError;
+
+/// First bytes of every payload.
+pub const PAYLOAD_MAGIC: [u8; 3] = *b"TEV";
+
+/// Layout version this build reads and writes.
+pub const PAYLOAD_VERSION: u8 = 1;
+
+/// Bytes before the data: magic, version, data length.
+const HEADER_LEN: usize = 8;
+
+/// A payload split into data, signer key and signature.
+pub(crate) type PayloadParts<'a> = (&'a [u8], [u8; 32], [u8; 64]);
+
+/// Encode `data` with its signer key and signature.
+pub fn encode_payload(data: &[u8], public_key: &[u8; 32], signature: &[u8; 64]) -> Vec<u8> {
+    let length = u32::try_from(data.len()).expect("payload data exceeds 4 GiB");
+    let mut payload = Vec::with_capacity(HEADER_LEN + data.len() + 96);
+    payload.extend_from_slice(&PAYLOAD_MAGIC);
+    payload.push(PAYLOAD_VERSION);
+    payload.extend_from_slice(&length.to_le_bytes());
+    payload.extend_from_slice(data);
+    payload.extend_from_slice(public_key);
+    payload.extend_from_slice(signature);
+    payload
+}
+
+/// Sign `data` with `keypair` and encode the payload.
+pub fn sign_payload(keypair: &Keypair, data: &[u8]) -> Vec<u8> {
+    encode_payload(data, &keypair.public_key(), &keypair.sign(data))
+}
+
+/// Split a payload into data, signer key and signature.
+///
+/// `kind` names the payload in error messages. Signatures are not checked.
+pub(crate) fn decode_payload<'a>(
+    kind: &str,
+    payload: &'a [u8],
+) -> Result<PayloadParts<'a>, ValidationError> {
+    let invalid = |reason: String| ValidationError::InvalidFormat {
+        reason: format!("{} payload {}", kind, reason),
+    };
+
+    let Some((header, rest)) = payload.split_first_chunk::<HEADER_LEN>() else {
+        return Err(invalid(format!(
+            "too short for its header: {} bytes, need {}",
+            payload.len(),
+            HEADER_LEN
+        )));
+    };
+    if header[..3] != PAYLOAD_MAGIC {
+        return Err(invalid("has bad magic bytes".to_string()));
+    }
+    if header[3] != PAYLOAD_VERSION {
+        return Err(invalid(format!(
+            "has unsupported version {} (this build reads version {})",
+            header[3], PAYLOAD_VERSION
+        )));
+    }
+    let length = u32::from_le_bytes(header[4..8].try_into().expect("4 bytes")) as usize;
+
+    if rest.len() < length {
+        return Err(invalid(format!(
+            "declares {} data bytes but only {} follow the header",
+            length,
+            rest.len()
+        )));
+    }
+    let (data, rest) = rest.split_at(length);
+
+    let Some((public_key, rest)) = rest.split_first_chunk::<32>() else {
+        return Err(invalid(format!("public key truncated: {} of 32 bytes", rest.len())));
+    };
+    let Some((signature, rest)) = rest.split_first_chunk::<64>() else {
+        return Err(invalid(format!("signature truncated: {} of 64 bytes", rest.len())));
+    };
+    if !rest.is_empty() {
+        return Err(invalid(format!("has {} trailing bytes", rest.len())));
+    }
+
+    Ok((data, *public_key, *signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(payload: &[u8]) -> String {
+        match decode_payload("transaction", payload) {
+            Err(ValidationError::InvalidFormat { reason }) => reason,
+            other => panic!("expected InvalidFormat, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let keypair = Keypair::from_secret(&[3u8; 32]);
+        for data in [&b""[..], b"x", &[7u8; 300]] {
+            let payload = sign_payload(&keypair, data);
+            let (decoded, public_key, signature) = decode_payload("transaction", &payload).unwrap();
+            assert_eq!(decoded, data);
+            assert_eq!(public_key, keypair.public_key());
+            assert_eq!(signature, keypair.sign(data));
+        }
+    }
+
+    #[test]
+    fn test_truncated_payloads_name_the_field() {
+        let payload = sign_payload(&Keypair::from_secret(&[3u8; 32]), b"data");
+
+        assert!(reason(&payload[..5]).contains("too short for its header"));
+        assert!(reason(&payload[..HEADER_LEN + 2]).contains("declares 4 data bytes but only 2"));
+        assert!(reason(&payload[..HEADER_LEN + 4 + 10]).contains("public key truncated: 10 of 32"));
+        assert!(reason(&payload[..payload.len() - 1]).contains("signature truncated: 63 of 64"));
+
+        let mut longer = payload.clone();
+        longer.push(0);
+        assert!(reason(&longer).contains("1 trailing bytes"));
+    }
+
+    #[test]
+    fn test_bad_header_rejected() {
+        let payload = sign_payload(&Keypair::from_secret(&[3u8; 32]), b"data");
+
+        let mut bad_magic = payload.clone();
+        bad_magic[0] = b'X';
+        assert!(reason(&bad_magic).contains("bad magic"));
+
+        let mut bad_version = payload;
+        bad_version[3] = 2;
+        assert!(reason(&bad_version).contains("unsupported version 2"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Opt-in replay guard.
+//!
+//! TEV itself is stateless: verifying the same payload twice does the full
+//! work twice and succeeds twice, and replays are left to MARS nonces. A
+//! `ReplayGuard` remembers the `(signer, signature)` pairs it has verified,
+//! so an identical payload submitted again is refused with
+//! `ReplayDetected` before its signature is checked.
+//!
+//! Nothing uses a guard unless one is created and passed in. It holds at
+//! most `capacity` pairs, dropping the least recently seen first, so a
+//! replay of something old enough is verified again and caught by MARS.
+
+use crate::payload::decode_payload;
+use crate::signature::verify_signature;
+use crate::verified::VerifiedTransaction;
+use crate::ValidationError;
+use std::collections::{BTreeMap, HashMap};
+
+/// Identity of a verified payload.
+type SeenKey = ([u8; 32], [u8; 64]);
+
+/// Bounded least-recently-used set of verified `(signer, signature)` pairs.
+#[derive(Debug)]
+pub struct ReplayGuard {
+    /// Most pairs remembered
+    capacity: usize,
+
+    /// Pair to the tick it was last seen at
+    seen: HashMap<SeenKey, u64>,
+
+    /// Tick to pair, oldest first
+    by_tick: BTreeMap<u64, SeenKey>,
+
+    /// Incremented on every insert or hit
+    tick: u64,
+}
+
+impl ReplayGuard {
+    /// Create a guard remembering up to `capacity` payloads.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: HashMap::new(),
+            by_tick: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Verify a transaction payload like `verify_transaction`, refusing
+    /// one this guard has already verified.
+    ///
+    /// Only payloads that pass verification are remembered, so a forged
+    /// copy cannot block the genuine one.
+    pub fn verify_transaction(&mut self, payload: &[u8]) -> Result<VerifiedTransaction, ValidationError> {
+        let (data, signer, signature) = decode_payload("transaction", payload)?;
+        let key = (signer, signature);
+        if self.touch(&key) {
+            return Err(ValidationError::ReplayDetected);
+        }
+
+        verify_signature(&signer, data, &signature)?;
+        self.insert(key);
+
+        Ok(VerifiedTransaction {
+            data: data.to_vec(),
+            signer,
+            signature,
+        })
+    }
+
+    /// Number of payloads remembered.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Check if nothing is remembered.
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Mark `key` as just seen, returning whether it was already held.
+    fn touch(&mut self, key: &SeenKey) -> bool {
+        let Some(last) = self.seen.get_mut(key) else {
+            return false;
+        };
+        self.by_tick.remove(last);
+        self.tick += 1;
+        *last = self.tick;
+        self.by_tick.insert(self.tick, *key);
+        true
+    }
+
+    /// Remember `key`, evicting the least recently seen pair if full.
+    fn insert(&mut self, key: SeenKey) {
+        if self.seen.len() >= self.capacity {
+            if let Some((_, oldest)) = self.by_tick.pop_first() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.seen.insert(key, self.tick);
+        self.by_tick.insert(self.tick, key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign_payload, Keypair};
+
+    #[test]
+    fn test_identical_payload_is_replay() {
+        let keypair = Keypair::from_secret(&[4u8; 32]);
+        let mut guard = ReplayGuard::new(16);
+
+        let first = sign_payload(&keypair, b"transfer nonce 0");
+        guard.verify_transaction(&first).unwrap();
+        assert_eq!(guard.verify_transaction(&first).unwrap_err(), ValidationError::ReplayDetected);
+
+        // The next nonce is a different payload
+        let next = sign_payload(&keypair, b"transfer nonce 1");
+        guard.verify_transaction(&next).unwrap();
+        assert_eq!(guard.len(), 2);
+    }
+
+    #[test]
+    fn test_least_recently_seen_evicted() {
+        let keypair = Keypair::from_secret(&[4u8; 32]);
+        let mut guard = ReplayGuard::new(2);
+        let payloads: Vec<Vec<u8>> = (0..3u8).map(|i| sign_payload(&keypair, &[i])).collect();
+
+        guard.verify_transaction(&payloads[0]).unwrap();
+        guard.verify_transaction(&payloads[1]).unwrap();
+        // A replay of the first refreshes it, so the second is evicted next
+        assert!(guard.verify_transaction(&payloads[0]).is_err());
+        guard.verify_transaction(&payloads[2]).unwrap();
+
+        assert_eq!(guard.len(), 2);
+        assert!(guard.verify_transaction(&payloads[0]).is_err());
+        guard.verify_transaction(&payloads[1]).unwrap();
+    }
+
+    #[test]
+    fn test_forged_payload_not_remembered() {
+        let keypair = Keypair::from_secret(&[4u8; 32]);
+        let mut guard = ReplayGuard::new(16);
+        let genuine = sign_payload(&keypair, b"data");
+        let mut forged = genuine.clone();
+        forged[8] ^= 1;
+
+        assert_eq!(guard.verify_transaction(&forged).unwrap_err(), ValidationError::InvalidSignature);
+        guard.verify_transaction(&genuine).unwrap();
+    }
+}
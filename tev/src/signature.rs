@@ -2,9 +2,29 @@
 //!
 //! This module provides cryptographic signing and verification
 //! using the Ed25519 signature scheme.
+//!
+//! Transactions use plain Ed25519 over their full bytes. For large
+//! payloads such as blocks, the prehashed variant (Ed25519ph, RFC 8032)
+//! signs a SHA-512 digest of the message under the `PREHASH_CONTEXT`
+//! domain, so producers can sign the block hash instead of every byte.
+//!
+//! Public keys and signature `R` points with a small-order (torsion)
+//! component are rejected before any equation is checked. Honest keys and
+//! signatures never have one, and batch verification would accept such a
+//! signature only for some of its pseudorandom coefficients. Without them, the
+//! single and batch checks accept exactly the same signatures, so every
+//! node agrees on validity whichever path it takes.
 
 use crate::ValidationError;
+use curve25519_dalek::edwards::CompressedEdwardsY;
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use sha2::{Digest, Sha512};
+
+/// Domain separation context for prehashed (Ed25519ph) signatures.
+///
+/// Binds prehashed signatures to this chain's block signing, so they can
+/// never be replayed as signatures for any other purpose.
+pub const PREHASH_CONTEXT: &[u8] = b"unykorn-l1/block-prehash/v1";
 
 /// A keypair for signing operations.
 pub struct Keypair {
@@ -35,6 +55,39 @@ impl Keypair {
         let signature = self.signing_key.sign(message);
         signature.to_bytes()
     }
+
+    /// Sign a message with Ed25519ph under `PREHASH_CONTEXT`.
+    ///
+    /// Block producers pass the block hash as `message`.
+    pub fn sign_prehashed(&self, message: &[u8]) -> [u8; 64] {
+        let signature = self
+            .signing_key
+            .sign_prehashed(Sha512::new_with_prefix(message), Some(PREHASH_CONTEXT))
+            .expect("context is shorter than 256 bytes");
+        signature.to_bytes()
+    }
+}
+
+/// Parse a public key and signature, rejecting torsion components.
+pub(crate) fn parse_torsion_free(
+    public_key: &[u8; 32],
+    signature: &[u8; 64],
+) -> Result<(VerifyingKey, Signature), ValidationError> {
+    let verifying_key =
+        VerifyingKey::from_bytes(public_key).map_err(|_| ValidationError::InvalidPublicKey)?;
+    if !verifying_key.to_edwards().is_torsion_free() {
+        return Err(ValidationError::InvalidPublicKey);
+    }
+
+    let sig = Signature::from_bytes(signature);
+    let r = CompressedEdwardsY(*sig.r_bytes())
+        .decompress()
+        .ok_or(ValidationError::InvalidSignature)?;
+    if !r.is_torsion_free() {
+        return Err(ValidationError::InvalidSignature);
+    }
+
+    Ok((verifying_key, sig))
 }
 
 /// Verify an Ed25519 signature.
@@ -53,10 +106,7 @@ pub fn verify_signature(
     message: &[u8],
     signature: &[u8; 64],
 ) -> Result<(), ValidationError> {
-    let verifying_key =
-        VerifyingKey::from_bytes(public_key).map_err(|_| ValidationError::InvalidPublicKey)?;
-
-    let sig = Signature::from_bytes(signature);
+    let (verifying_key, sig) = parse_torsion_free(public_key, signature)?;
 
     verifying_key
         .verify(message, &sig)
@@ -65,6 +115,28 @@ pub fn verify_signature(
     Ok(())
 }
 
+/// Verify an Ed25519ph signature made with `sign_prehashed`.
+///
+/// # Returns
+///
+/// `Ok(())` if the signature is valid, `Err(ValidationError)` otherwise.
+pub fn verify_prehashed(
+    public_key: &[u8; 32],
+    message: &[u8],
+    signature: &[u8; 64],
+) -> Result<(), ValidationError> {
+    let (verifying_key, sig) = parse_torsion_free(public_key, signature)?;
+
+    verifying_key
+        .verify_prehashed(Sha512::new_with_prefix(message), Some(PREHASH_CONTEXT), &sig)
+        .map_err(|_| ValidationError::InvalidSignature)
+}
+
+/// Sign a message with a secret key using Ed25519ph (see `Keypair::sign_prehashed`).
+pub fn sign_prehashed(secret: &[u8; 32], message: &[u8]) -> [u8; 64] {
+    Keypair::from_secret(secret).sign_prehashed(message)
+}
+
 /// Sign a message with a secret key.
 ///
 /// # Arguments
@@ -114,6 +186,26 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_prehashed_sign_and_verify() {
+        let keypair = Keypair::from_secret(&[7u8; 32]);
+        let block_hash = [0xabu8; 32];
+        let signature = sign_prehashed(&[7u8; 32], &block_hash);
+        assert_eq!(signature, keypair.sign_prehashed(&block_hash));
+
+        assert!(verify_prehashed(&keypair.public_key(), &block_hash, &signature).is_ok());
+
+        let mut tampered = block_hash;
+        tampered[0] ^= 1;
+        assert_eq!(
+            verify_prehashed(&keypair.public_key(), &tampered, &signature),
+            Err(ValidationError::InvalidSignature)
+        );
+
+        // Not interchangeable with plain signatures
+        assert!(verify_signature(&keypair.public_key(), &block_hash, &signature).is_err());
+    }
+
     #[test]
     fn test_keypair_from_secret() {
         let secret = [42u8; 32];
@@ -6,8 +6,9 @@
 
 /// A transaction that has passed cryptographic verification.
 ///
-/// This type can only be created by `verify_transaction()`,
-/// guaranteeing that the signature has been checked.
+/// This type can only be created by `verify_transaction()` or
+/// `verify_transactions_batch()`, guaranteeing that the signature has
+/// been checked.
 #[derive(Clone, Debug)]
 pub struct VerifiedTransaction {
     /// The transaction data (excluding signature and pubkey)
@@ -60,6 +61,34 @@ impl VerifiedBlock {
     }
 }
 
+/// A transaction bundle whose envelope and every contained transaction
+/// have passed verification.
+///
+/// This type can only be created by `verify_bundle()`.
+#[derive(Clone, Debug)]
+pub struct VerifiedBundle {
+    /// The contained transactions, in bundle order
+    pub transactions: Vec<VerifiedTransaction>,
+
+    /// The verified public key that signed the bundle
+    pub signer: [u8; 32],
+
+    /// The verified bundle signature
+    pub signature: [u8; 64],
+}
+
+impl VerifiedBundle {
+    /// Get the contained transactions.
+    pub fn transactions(&self) -> &[VerifiedTransaction] {
+        &self.transactions
+    }
+
+    /// Get the bundle signer's public key.
+    pub fn signer(&self) -> &[u8; 32] {
+        &self.signer
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;